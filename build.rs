@@ -0,0 +1,13 @@
+fn main() {
+    println!("cargo:rerun-if-changed=proto/notifier.proto");
+    // gRPC代码只在启用grpc特性时生成，使用自带的protoc
+    #[cfg(feature = "grpc")]
+    {
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("bundled protoc");
+        std::env::set_var("PROTOC", protoc);
+        tonic_build::configure()
+            .build_client(false)
+            .compile_protos(&["proto/notifier.proto"], &["proto"])
+            .expect("compile proto/notifier.proto");
+    }
+}
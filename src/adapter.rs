@@ -0,0 +1,581 @@
+use crate::i18n;
+use crate::markdown;
+use crate::notification::{CardAction, Level, Notification};
+use crate::severity::Scale;
+use crate::timeutil;
+use anyhow::Result;
+use chrono::{DateTime, Duration};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::str::FromStr;
+
+/// 推送事件中最多列出的提交
+const MAX_COMMITS: usize = 10;
+
+/// `hook` 的输入格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputFormat {
+    /// 顶层的 `event`、`title`、`content`、`level` 等字段
+    Generic,
+    /// Claude Code传给hook的JSON
+    Claude,
+    /// GitHub webhook
+    Github,
+    /// GitLab webhook
+    Gitlab,
+    /// Jenkins Notification插件或流水线中的 `currentBuild`
+    Jenkins,
+}
+
+impl FromStr for InputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "generic" => Ok(InputFormat::Generic),
+            "claude" => Ok(InputFormat::Claude),
+            "github" => Ok(InputFormat::Github),
+            "gitlab" => Ok(InputFormat::Gitlab),
+            "jenkins" => Ok(InputFormat::Jenkins),
+            _ => Err(anyhow::anyhow!(
+                "Unknown input format '{}' (expected one of: generic, claude, github, gitlab, jenkins)",
+                s
+            )),
+        }
+    }
+}
+
+impl InputFormat {
+    /// 按webhook的原生格式生成通知；Generic和Claude不在这里处理
+    pub fn adapt(self, payload: &Value) -> Result<Option<Notification>> {
+        match self {
+            InputFormat::Generic | InputFormat::Claude => Ok(None),
+            InputFormat::Github => github(payload).map(Some),
+            InputFormat::Gitlab => gitlab(payload).map(Some),
+            InputFormat::Jenkins => jenkins(payload).map(Some),
+        }
+    }
+}
+
+fn text<'a>(value: &'a Value, pointer: &str) -> Option<&'a str> {
+    value.pointer(pointer)?.as_str().filter(|s| !s.is_empty())
+}
+
+/// 数字或字符串形式的编号
+fn number(value: &Value, pointer: &str) -> Option<String> {
+    match value.pointer(pointer)? {
+        Value::Number(n) => Some(n.to_string()),
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        _ => None,
+    }
+}
+
+fn short_sha(sha: &str) -> &str {
+    sha.get(..7).unwrap_or(sha)
+}
+
+fn branch(reference: &str) -> &str {
+    reference
+        .strip_prefix("refs/heads/")
+        .or_else(|| reference.strip_prefix("refs/tags/"))
+        .unwrap_or(reference)
+}
+
+fn elapsed(start: Option<&str>, end: Option<&str>) -> Option<String> {
+    let start = DateTime::parse_from_rfc3339(start?).ok()?;
+    let end = DateTime::parse_from_rfc3339(end?).ok()?;
+    Some(timeutil::format_duration(end - start))
+}
+
+/// 组装通知的各部分
+#[derive(Default)]
+struct Card {
+    facts: Vec<(&'static str, String)>,
+    body: Vec<String>,
+    url: Option<String>,
+}
+
+impl Card {
+    fn fact(&mut self, name: &'static str, value: Option<impl Into<String>>) {
+        if let Some(value) = value {
+            self.facts.push((name, value.into()));
+        }
+    }
+
+    fn build(self, event: &str, title: String, level: Level) -> Notification {
+        let mut parts = Vec::new();
+        if !self.facts.is_empty() {
+            parts.push(markdown::facts_table(&self.facts));
+        }
+        parts.extend(self.body);
+        let mut notification = Notification::new(event, title, parts.join("\n\n"), level);
+        if let Some(url) = self.url {
+            notification.actions.push(CardAction {
+                label: i18n::strings().ci_open.to_string(),
+                value: url,
+            });
+        }
+        notification
+    }
+}
+
+/// 推送的提交列表
+fn commit_list(commits: &[Value], total: usize) -> String {
+    let mut lines: Vec<String> = commits
+        .iter()
+        .take(MAX_COMMITS)
+        .map(|commit| {
+            let sha = text(commit, "/id").unwrap_or_default();
+            let message = text(commit, "/message").unwrap_or_default();
+            format!(
+                "- `{}` {}",
+                short_sha(sha),
+                message.lines().next().unwrap_or_default()
+            )
+        })
+        .collect();
+    let shown = lines.len();
+    if total > shown {
+        lines.push(i18n::fill(
+            i18n::strings().claude_more,
+            &[("count", &(total - shown))],
+        ));
+    }
+    lines.join("\n")
+}
+
+/// GitHub的运行状态：完成后按conclusion，否则为status
+fn github_state(status: Option<&str>, conclusion: Option<&str>) -> (String, Level) {
+    match (status, conclusion) {
+        (Some("completed"), Some(conclusion)) => {
+            let level = Scale::Github
+                .level(conclusion)
+                .and_then(|level| level.parse().ok())
+                .unwrap_or(Level::Info);
+            (conclusion.replace('_', " "), level)
+        }
+        (status, _) => (status.unwrap_or("unknown").replace('_', " "), Level::Info),
+    }
+}
+
+/// 按payload中的对象识别GitHub事件（webhook的事件名在请求头中，不在payload里）
+fn github(payload: &Value) -> Result<Notification> {
+    let strings = i18n::strings();
+    let repo = text(payload, "/repository/full_name").unwrap_or_default();
+    let mut card = Card::default();
+    card.fact(strings.ci_repository, Some(repo).filter(|r| !r.is_empty()));
+
+    let notification = if let Some(run) = payload.get("workflow_run") {
+        let (state, level) = github_state(text(run, "/status"), text(run, "/conclusion"));
+        let name = text(run, "/name").unwrap_or("Workflow");
+        let number = number(run, "/run_number").unwrap_or_default();
+        card.fact(strings.ci_branch, text(run, "/head_branch"));
+        card.fact(
+            strings.ci_commit,
+            text(run, "/head_sha").map(|sha| match text(run, "/display_title") {
+                Some(title) => format!("`{}` {}", short_sha(sha), title),
+                None => format!("`{}`", short_sha(sha)),
+            }),
+        );
+        card.fact(
+            strings.ci_actor,
+            text(run, "/triggering_actor/login").or(text(run, "/actor/login")),
+        );
+        if text(run, "/status") == Some("completed") {
+            card.fact(
+                strings.ci_duration,
+                elapsed(text(run, "/run_started_at"), text(run, "/updated_at")),
+            );
+        }
+        card.url = text(run, "/html_url").map(String::from);
+        let mut notification = card.build(
+            "github_workflow_run",
+            format!("{} #{}: {}", name, number, state),
+            level,
+        );
+        notification.thread_key = number_key(repo, "run", run, "/id");
+        notification
+    } else if let Some(job) = payload.get("workflow_job") {
+        let (state, level) = github_state(text(job, "/status"), text(job, "/conclusion"));
+        let name = match (text(job, "/workflow_name"), text(job, "/name")) {
+            (Some(workflow), Some(job)) => format!("{} / {}", workflow, job),
+            (workflow, job) => workflow.or(job).unwrap_or("Job").to_string(),
+        };
+        card.fact(strings.ci_branch, text(job, "/head_branch"));
+        card.fact(
+            strings.ci_commit,
+            text(job, "/head_sha").map(|sha| format!("`{}`", short_sha(sha))),
+        );
+        card.fact(
+            strings.ci_duration,
+            elapsed(text(job, "/started_at"), text(job, "/completed_at")),
+        );
+        card.url = text(job, "/html_url").map(String::from);
+        let mut notification =
+            card.build("github_workflow_job", format!("{}: {}", name, state), level);
+        notification.thread_key = number_key(repo, "run", job, "/run_id");
+        notification
+    } else if let Some(check) = payload.get("check_run") {
+        let (state, level) = github_state(text(check, "/status"), text(check, "/conclusion"));
+        let name = text(check, "/name").unwrap_or("Check");
+        card.fact(strings.ci_branch, text(check, "/check_suite/head_branch"));
+        card.fact(
+            strings.ci_commit,
+            text(check, "/head_sha").map(|sha| format!("`{}`", short_sha(sha))),
+        );
+        card.fact(
+            strings.ci_duration,
+            elapsed(text(check, "/started_at"), text(check, "/completed_at")),
+        );
+        card.url = text(check, "/html_url").map(String::from);
+        card.build("github_check_run", format!("{}: {}", name, state), level)
+    } else if let Some(pr) = payload.get("pull_request") {
+        let number = number(pr, "/number").unwrap_or_default();
+        let merged = pr["merged"].as_bool() == Some(true);
+        let action = match text(payload, "/action") {
+            Some("closed") if merged => "merged",
+            Some(action) => action,
+            None => "updated",
+        };
+        card.fact(
+            strings.ci_branch,
+            text(pr, "/head/ref").map(|head| match text(pr, "/base/ref") {
+                Some(base) => format!("{} → {}", head, base),
+                None => head.to_string(),
+            }),
+        );
+        card.fact(strings.ci_actor, text(payload, "/sender/login"));
+        if action == "opened" {
+            card.body.extend(text(pr, "/body").map(String::from));
+        }
+        card.url = text(pr, "/html_url").map(String::from);
+        let level = if merged { Level::Success } else { Level::Info };
+        let mut notification = card.build(
+            "github_pull_request",
+            format!(
+                "PR #{} {}: {}",
+                number,
+                action.replace('_', " "),
+                text(pr, "/title").unwrap_or_default()
+            ),
+            level,
+        );
+        notification.thread_key = Some(format!("github/{}#{}", repo, number));
+        notification
+    } else if let Some(release) = payload.get("release") {
+        let tag = text(release, "/tag_name").unwrap_or_default();
+        let action = text(payload, "/action").unwrap_or("updated");
+        card.fact(strings.ci_actor, text(release, "/author/login"));
+        card.body.extend(text(release, "/body").map(String::from));
+        card.url = text(release, "/html_url").map(String::from);
+        let level = if action == "published" {
+            Level::Success
+        } else {
+            Level::Info
+        };
+        let title = match text(release, "/name").filter(|name| *name != tag) {
+            Some(name) => format!("Release {} {}: {}", tag, action, name),
+            None => format!("Release {} {}", tag, action),
+        };
+        card.build("github_release", title, level)
+    } else if let Some(issue) = payload.get("issue") {
+        let number = number(issue, "/number").unwrap_or_default();
+        let issue_title = text(issue, "/title").unwrap_or_default();
+        let (event, title) = match payload.get("comment") {
+            Some(comment) => {
+                card.fact(strings.ci_actor, text(comment, "/user/login"));
+                card.body.extend(text(comment, "/body").map(String::from));
+                card.url = text(comment, "/html_url").map(String::from);
+                (
+                    "github_issue_comment",
+                    format!("Comment on #{}: {}", number, issue_title),
+                )
+            }
+            None => {
+                card.fact(strings.ci_actor, text(payload, "/sender/login"));
+                if text(payload, "/action") == Some("opened") {
+                    card.body.extend(text(issue, "/body").map(String::from));
+                }
+                card.url = text(issue, "/html_url").map(String::from);
+                (
+                    "github_issue",
+                    format!(
+                        "Issue #{} {}: {}",
+                        number,
+                        text(payload, "/action").unwrap_or("updated"),
+                        issue_title
+                    ),
+                )
+            }
+        };
+        let mut notification = card.build(event, title, Level::Info);
+        notification.thread_key = Some(format!("github/{}#{}", repo, number));
+        notification
+    } else if let (Some(reference), Some(commits)) =
+        (text(payload, "/ref"), payload["commits"].as_array())
+    {
+        let user = text(payload, "/pusher/name")
+            .or(text(payload, "/sender/login"))
+            .unwrap_or("someone");
+        card.body.push(commit_list(commits, commits.len()));
+        card.url = text(payload, "/compare").map(String::from);
+        let title = i18n::fill(
+            strings.ci_pushed,
+            &[
+                ("user", &user),
+                ("count", &commits.len()),
+                ("branch", &branch(reference)),
+            ],
+        );
+        card.build("github_push", title, Level::Info)
+    } else {
+        anyhow::bail!(
+            "Unrecognized GitHub payload (expected workflow_run, workflow_job, check_run, pull_request, release, issue or push)"
+        );
+    };
+    Ok(notification)
+}
+
+/// 同一次运行的通知放在同一会话中
+fn number_key(repo: &str, kind: &str, value: &Value, pointer: &str) -> Option<String> {
+    number(value, pointer).map(|id| format!("github/{}/{}/{}", repo, kind, id))
+}
+
+/// GitLab流水线、作业和部署的状态
+fn gitlab_level(status: &str) -> Level {
+    match status {
+        "success" => Level::Success,
+        "failed" => Level::Critical,
+        "canceled" | "manual" => Level::Warning,
+        _ => Level::Info,
+    }
+}
+
+fn gitlab(payload: &Value) -> Result<Notification> {
+    let strings = i18n::strings();
+    let project = text(payload, "/project/path_with_namespace")
+        .or(text(payload, "/project_name"))
+        .unwrap_or_default();
+    let web_url = text(payload, "/project/web_url");
+    let user = text(payload, "/user/name")
+        .or(text(payload, "/user/username"))
+        .or(text(payload, "/user_name"));
+    let mut card = Card::default();
+    card.fact(
+        strings.ci_repository,
+        Some(project).filter(|p| !p.is_empty()),
+    );
+
+    let notification = match text(payload, "/object_kind") {
+        Some("pipeline") => {
+            let attrs = &payload["object_attributes"];
+            let id = number(attrs, "/id").unwrap_or_default();
+            let status = text(attrs, "/status").unwrap_or("unknown");
+            card.fact(strings.ci_branch, text(attrs, "/ref"));
+            card.fact(
+                strings.ci_commit,
+                text(attrs, "/sha").map(|sha| match text(payload, "/commit/title") {
+                    Some(title) => format!("`{}` {}", short_sha(sha), title),
+                    None => format!("`{}`", short_sha(sha)),
+                }),
+            );
+            card.fact(strings.ci_actor, user);
+            card.fact(
+                strings.ci_duration,
+                attrs["duration"]
+                    .as_i64()
+                    .map(|secs| timeutil::format_duration(Duration::seconds(secs))),
+            );
+            card.url = text(attrs, "/url")
+                .map(String::from)
+                .or_else(|| web_url.map(|web| format!("{}/-/pipelines/{}", web, id)));
+            let name = text(attrs, "/name").unwrap_or("Pipeline");
+            let mut notification = card.build(
+                "gitlab_pipeline",
+                format!("{} #{}: {}", name, id, status),
+                gitlab_level(status),
+            );
+            notification.thread_key = Some(format!("gitlab/{}/pipeline/{}", project, id));
+            notification
+        }
+        Some("build") => {
+            let id = number(payload, "/build_id").unwrap_or_default();
+            let status = text(payload, "/build_status").unwrap_or("unknown");
+            card.fact(strings.ci_branch, text(payload, "/ref"));
+            card.fact(
+                strings.ci_commit,
+                text(payload, "/sha").map(|sha| format!("`{}`", short_sha(sha))),
+            );
+            card.fact(strings.ci_actor, user);
+            card.fact(
+                strings.ci_duration,
+                payload["build_duration"]
+                    .as_f64()
+                    .map(|secs| timeutil::format_duration(Duration::seconds(secs as i64))),
+            );
+            card.url = web_url.map(|web| format!("{}/-/jobs/{}", web, id));
+            let mut notification = card.build(
+                "gitlab_job",
+                format!(
+                    "{} #{}: {}",
+                    text(payload, "/build_name").unwrap_or("Job"),
+                    id,
+                    status
+                ),
+                gitlab_level(status),
+            );
+            notification.thread_key = number(payload, "/pipeline_id")
+                .map(|pipeline| format!("gitlab/{}/pipeline/{}", project, pipeline));
+            notification
+        }
+        Some("merge_request") => {
+            let attrs = &payload["object_attributes"];
+            let iid = number(attrs, "/iid").unwrap_or_default();
+            let action = text(attrs, "/action").unwrap_or("update");
+            card.fact(
+                strings.ci_branch,
+                text(attrs, "/source_branch").map(|source| match text(attrs, "/target_branch") {
+                    Some(target) => format!("{} → {}", source, target),
+                    None => source.to_string(),
+                }),
+            );
+            card.fact(strings.ci_actor, user);
+            if action == "open" {
+                card.body.extend(text(attrs, "/description").map(String::from));
+            }
+            card.url = text(attrs, "/url").map(String::from);
+            let level = if action == "merge" {
+                Level::Success
+            } else {
+                Level::Info
+            };
+            let mut notification = card.build(
+                "gitlab_merge_request",
+                format!(
+                    "MR !{} {}: {}",
+                    iid,
+                    action,
+                    text(attrs, "/title").unwrap_or_default()
+                ),
+                level,
+            );
+            notification.thread_key = Some(format!("gitlab/{}!{}", project, iid));
+            notification
+        }
+        Some(kind @ ("push" | "tag_push")) => {
+            let commits = payload["commits"].as_array().map(Vec::as_slice).unwrap_or_default();
+            let total = payload["total_commits_count"]
+                .as_u64()
+                .map(|n| n as usize)
+                .unwrap_or(commits.len());
+            let reference = text(payload, "/ref").unwrap_or_default();
+            if !commits.is_empty() {
+                card.body.push(commit_list(commits, total));
+            }
+            card.url = web_url.map(|web| format!("{}/-/tree/{}", web, branch(reference)));
+            let title = i18n::fill(
+                strings.ci_pushed,
+                &[
+                    ("user", &user.unwrap_or("someone")),
+                    ("count", &total),
+                    ("branch", &branch(reference)),
+                ],
+            );
+            card.build(&format!("gitlab_{}", kind), title, Level::Info)
+        }
+        Some("deployment") => {
+            let status = text(payload, "/status").unwrap_or("unknown");
+            let environment = text(payload, "/environment").unwrap_or_default();
+            card.fact(strings.ci_environment, Some(environment).filter(|e| !e.is_empty()));
+            card.fact(strings.ci_branch, text(payload, "/ref"));
+            card.fact(
+                strings.ci_commit,
+                text(payload, "/short_sha").map(|sha| format!("`{}`", sha)),
+            );
+            card.fact(strings.ci_actor, user);
+            card.url = text(payload, "/deployable_url").map(String::from);
+            let mut notification = card.build(
+                "gitlab_deployment",
+                format!("Deployment to {}: {}", environment, status),
+                gitlab_level(status),
+            );
+            notification.thread_key = number(payload, "/deployment_id")
+                .map(|id| format!("gitlab/{}/deployment/{}", project, id));
+            notification
+        }
+        kind => anyhow::bail!(
+            "Unsupported GitLab object_kind {} (expected pipeline, build, merge_request, push, tag_push or deployment)",
+            kind.map(|k| format!("'{}'", k)).unwrap_or_else(|| "(missing)".to_string())
+        ),
+    };
+    Ok(notification)
+}
+
+/// Jenkins构建结果
+pub(crate) fn jenkins_level(result: &str) -> Level {
+    match result.to_ascii_uppercase().as_str() {
+        "SUCCESS" => Level::Success,
+        "FAILURE" => Level::Critical,
+        "UNSTABLE" | "ABORTED" => Level::Warning,
+        _ => Level::Info,
+    }
+}
+
+/// Notification插件的 `{name, build: {...}}`，或流水线中 `currentBuild` 的扁平字段
+fn jenkins(payload: &Value) -> Result<Notification> {
+    let strings = i18n::strings();
+    let build = match payload.get("build") {
+        Some(build) => build,
+        None if payload.get("result").is_some() => payload,
+        None => anyhow::bail!(
+            "Unrecognized Jenkins payload (expected a Notification plugin `build` object or a `result` field)"
+        ),
+    };
+    let name = text(payload, "/name")
+        .or(text(payload, "/fullProjectName"))
+        .or(text(payload, "/projectName"));
+    let build_number = number(build, "/number").unwrap_or_default();
+    // `fullDisplayName` 已经带有构建号，例如 `app #12`
+    let display = match (name, text(build, "/fullDisplayName")) {
+        (Some(name), _) => format!("{} #{}", name, build_number),
+        (None, Some(display)) => display.to_string(),
+        (None, None) => format!("Build #{}", build_number),
+    };
+    // 开始阶段没有结果
+    let phase = text(build, "/phase").unwrap_or("COMPLETED");
+    let result = text(build, "/status").or(text(build, "/result"));
+    let (state, level) = match result {
+        Some(result) if phase != "STARTED" && phase != "QUEUED" => {
+            (result.to_ascii_lowercase(), jenkins_level(result))
+        }
+        _ => (phase.to_ascii_lowercase(), Level::Info),
+    };
+
+    let mut card = Card::default();
+    card.fact(
+        strings.ci_branch,
+        text(build, "/scm/branch").or(text(payload, "/branch")),
+    );
+    card.fact(
+        strings.ci_commit,
+        text(build, "/scm/commit")
+            .or(text(payload, "/commit"))
+            .map(|sha| format!("`{}`", short_sha(sha))),
+    );
+    card.fact(
+        strings.ci_duration,
+        build["duration"]
+            .as_i64()
+            .filter(|ms| *ms > 0)
+            .map(|ms| timeutil::format_duration(Duration::milliseconds(ms))),
+    );
+    card.url = text(build, "/full_url")
+        .or(text(build, "/absoluteUrl"))
+        .or(text(build, "/url"))
+        .filter(|url| url.starts_with("http"))
+        .map(String::from);
+    let mut notification = card.build("jenkins_build", format!("{}: {}", display, state), level);
+    notification.thread_key = Some(format!("jenkins/{}", display));
+    Ok(notification)
+}
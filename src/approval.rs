@@ -0,0 +1,94 @@
+use crate::callback::{self, Click};
+use crate::routing::glob_match;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+
+/// 审批按钮的回调值
+pub const APPROVE_ACTION: &str = "approve";
+pub const DENY_ACTION: &str = "deny";
+
+/// 轮询点击记录的间隔
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// PreToolUse的远程审批：发送带批准/拒绝按钮的卡片，等待回调服务收到点击
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ApprovalConfig {
+    /// 需要审批的工具名通配符，例如 `Bash`、`mcp__*`；为空时所有工具都需要
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<String>,
+    /// 等待点击的秒数，应小于Claude Code中该hook的 `timeout`（默认60秒）
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// 超时后的决定；不设置时不输出决定，由Claude Code照常询问
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_timeout: Option<Decision>,
+}
+
+fn default_timeout_secs() -> u64 {
+    50
+}
+
+impl ApprovalConfig {
+    pub fn matches(&self, tool: &str) -> bool {
+        self.tools.is_empty() || self.tools.iter().any(|pattern| glob_match(pattern, tool))
+    }
+}
+
+/// PreToolUse hook的权限决定
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Decision {
+    Allow,
+    Deny,
+    /// 交回Claude Code，在终端中询问
+    Ask,
+}
+
+impl Decision {
+    /// 写到stdout的hook输出，同时带上旧版本使用的 `decision` 字段
+    pub fn hook_output(self, reason: &str) -> Value {
+        let mut output = json!({
+            "hookSpecificOutput": {
+                "hookEventName": "PreToolUse",
+                "permissionDecision": self,
+                "permissionDecisionReason": reason,
+            }
+        });
+        let legacy = match self {
+            Decision::Allow => Some("approve"),
+            Decision::Deny => Some("block"),
+            Decision::Ask => None,
+        };
+        if let Some(legacy) = legacy {
+            output["decision"] = json!(legacy);
+            output["reason"] = json!(reason);
+        }
+        output
+    }
+}
+
+/// 等待通知 `id` 上的批准或拒绝点击，超时返回None
+pub fn wait(id: &str, timeout: Duration) -> Result<Option<(Decision, Click)>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let clicked = callback::clicks()?.into_iter().find_map(|click| {
+            let decision = match click.action.as_str() {
+                _ if click.id != id => return None,
+                APPROVE_ACTION => Decision::Allow,
+                DENY_ACTION => Decision::Deny,
+                _ => return None,
+            };
+            Some((decision, click))
+        });
+        if clicked.is_some() {
+            return Ok(clicked);
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(None);
+        }
+        std::thread::sleep(POLL_INTERVAL.min(deadline - now));
+    }
+}
@@ -0,0 +1,62 @@
+use crate::config::Config;
+use crate::error::NotifierError;
+use crate::redact;
+use anyhow::Result;
+use chrono::Local;
+use serde_json::{json, Value};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use tracing::warn;
+
+const AUDIT_FILE: &str = "audit.log";
+/// 超过该大小时轮转为 audit.log.1
+const MAX_SIZE: u64 = 10 * 1024 * 1024;
+
+/// 记录一次对外请求及其结果，URL和敏感字段已脱敏；写入失败只输出到stderr
+pub fn record(
+    url: &str,
+    request: &Value,
+    outcome: std::result::Result<&Value, &NotifierError>,
+    elapsed_ms: u128,
+) {
+    let mut entry = json!({
+        "at": Local::now().to_rfc3339(),
+        "url": redact::mask_url(url),
+        "request": redact::json(request),
+        "elapsed_ms": elapsed_ms,
+    });
+    match outcome {
+        Ok(response) => {
+            entry["success"] = json!(true);
+            entry["response"] = redact::json(response);
+        }
+        Err(e) => {
+            entry["success"] = json!(false);
+            entry["error"] = json!(redact::text(&e.to_string()));
+            entry["error_kind"] = json!(e.kind());
+        }
+    }
+
+    if let Err(e) = append(&entry) {
+        warn!("Failed to write audit log: {:#}", e);
+    }
+}
+
+fn append(entry: &Value) -> Result<()> {
+    let dir = Config::data_dir()?;
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(AUDIT_FILE);
+
+    if fs::metadata(&path).is_ok_and(|m| m.len() > MAX_SIZE) {
+        fs::rename(&path, dir.join(format!("{}.1", AUDIT_FILE)))?;
+    }
+
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(line.as_bytes())?;
+    Ok(())
+}
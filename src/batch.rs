@@ -0,0 +1,235 @@
+use crate::config::Config;
+use crate::notification::Notification;
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// 每行可以给出的字段；`channels` 和 `mentions` 在CSV中以逗号分隔
+pub const FIELDS: [&str; 8] = [
+    "event",
+    "title",
+    "content",
+    "level",
+    "channels",
+    "dedup_key",
+    "thread_key",
+    "mentions",
+];
+
+/// 文件中的一条通知
+pub struct Row {
+    /// 所在行号，从1开始（CSV的表头是第1行）
+    pub line: usize,
+    pub fields: Map<String, Value>,
+}
+
+/// 命令行给出的字段，行中没有时使用
+#[derive(Debug, Clone, Default)]
+pub struct Defaults {
+    pub event: Option<String>,
+    pub title: Option<String>,
+    pub content: Option<String>,
+    pub level: Option<String>,
+    pub channels: Option<Vec<String>>,
+    pub thread_key: Option<String>,
+    pub mentions: Vec<String>,
+}
+
+/// 校验通过、等待发送的一行
+pub struct Item {
+    pub line: usize,
+    pub notification: Notification,
+    pub channels: Option<Vec<String>>,
+}
+
+/// 解析 `--column FIELD=HEADER`
+pub fn parse_column(s: &str) -> Result<(String, String)> {
+    let Some((field, header)) = s.split_once('=') else {
+        anyhow::bail!("Invalid column mapping '{}' (expected FIELD=HEADER)", s);
+    };
+    let field = field.trim();
+    if !FIELDS.contains(&field) {
+        anyhow::bail!(
+            "Unknown field '{}' in column mapping (expected one of: {})",
+            field,
+            FIELDS.join(", ")
+        );
+    }
+    Ok((field.to_string(), header.trim().to_string()))
+}
+
+/// 读取文件：`.csv` 按表头取字段（`columns` 把字段映射到其他列名），其他每行一个JSON对象
+pub fn read(path: &Path, columns: &HashMap<String, String>) -> Result<Vec<Row>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let csv = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+    if csv {
+        read_csv(&text, columns)
+    } else if !columns.is_empty() {
+        anyhow::bail!("--column only applies to CSV files");
+    } else {
+        read_jsonl(&text)
+    }
+}
+
+fn read_jsonl(text: &str) -> Result<Vec<Row>> {
+    let mut rows = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields = match serde_json::from_str(line) {
+            Ok(Value::Object(fields)) => fields,
+            Ok(_) => anyhow::bail!("Line {}: expected a JSON object", index + 1),
+            Err(e) => anyhow::bail!("Line {}: {}", index + 1, e),
+        };
+        rows.push(Row {
+            line: index + 1,
+            fields,
+        });
+    }
+    Ok(rows)
+}
+
+fn read_csv(text: &str, columns: &HashMap<String, String>) -> Result<Vec<Row>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(text.as_bytes());
+    let headers = reader.headers()?.clone();
+    // 字段到列的位置；没有映射时取同名的列
+    let mut positions = Vec::new();
+    for field in FIELDS {
+        let header = columns.get(field).map_or(field, String::as_str);
+        match headers.iter().position(|h| h == header) {
+            Some(position) => positions.push((field, position)),
+            None if columns.contains_key(field) => {
+                anyhow::bail!("No column '{}' in the CSV header", header)
+            }
+            None => {}
+        }
+    }
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let line = record.position().map_or(0, |p| p.line() as usize);
+        let fields = positions
+            .iter()
+            .filter_map(|&(field, position)| {
+                let value = record.get(position).filter(|value| !value.is_empty())?;
+                Some((field.to_string(), Value::String(value.to_string())))
+            })
+            .collect();
+        rows.push(Row { line, fields });
+    }
+    Ok(rows)
+}
+
+/// JSON数组或逗号分隔的文本
+fn list(value: &Value) -> Option<Vec<String>> {
+    match value {
+        Value::Array(items) => Some(
+            items
+                .iter()
+                .filter_map(|item| item.as_str().map(String::from))
+                .collect(),
+        ),
+        Value::String(s) => Some(
+            s.split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(String::from)
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// 按行和默认值生成通知，检查必填字段、级别和渠道名
+pub fn item(config: &Config, channels: &[String], row: &Row, defaults: &Defaults) -> Result<Item> {
+    let text = |name: &str, default: &Option<String>| {
+        match row.fields.get(name) {
+            Some(Value::String(s)) => Some(s.clone()),
+            Some(Value::Null) | None => default.clone(),
+            // 数字等直接转为文本
+            Some(value) => Some(value.to_string()),
+        }
+        .filter(|s| !s.trim().is_empty())
+    };
+    let (Some(event), Some(title)) = (
+        text("event", &defaults.event),
+        text("title", &defaults.title),
+    ) else {
+        anyhow::bail!("'event' and 'title' are required");
+    };
+    let content = text("content", &defaults.content).unwrap_or_default();
+    let level = text("level", &defaults.level).unwrap_or_else(|| "info".to_string());
+    let (level, custom_level) = config.level(&level)?;
+
+    let mut notification = Notification::new(event, title, content, level);
+    notification.custom_level = custom_level;
+    notification.dedup_key = text("dedup_key", &None);
+    notification.thread_key = text("thread_key", &defaults.thread_key);
+    notification.mentions = match row.fields.get("mentions") {
+        Some(value) => list(value).context("'mentions' must be a list of user names")?,
+        None => defaults.mentions.clone(),
+    };
+    for name in &notification.mentions {
+        if !config.users.contains_key(name) {
+            anyhow::bail!("Unknown user '{}' in 'mentions'", name);
+        }
+    }
+    let selected = match row.fields.get("channels") {
+        Some(value) => Some(list(value).context("'channels' must be a list of channel names")?),
+        None => defaults.channels.clone(),
+    };
+    for name in selected.iter().flatten() {
+        if !channels.contains(name) {
+            anyhow::bail!("Unknown channel '{}'", name);
+        }
+    }
+    Ok(Item {
+        line: row.line,
+        notification,
+        channels: selected,
+    })
+}
+
+/// 同一批中视为重复的键：显式的去重键，否则为事件、标题和正文
+pub fn duplicate_key(notification: &Notification) -> String {
+    match &notification.dedup_key {
+        Some(key) => format!("key:{}", key),
+        None => format!(
+            "{}\0{}\0{}",
+            notification.event, notification.title, notification.content
+        ),
+    }
+}
+
+/// 把发送均匀分布在每分钟 `per_minute` 条以内
+pub struct Pacer {
+    interval: Duration,
+    next: Option<Instant>,
+}
+
+impl Pacer {
+    pub fn new(per_minute: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(60) / per_minute.max(1),
+            next: None,
+        }
+    }
+
+    /// 等到可以发送下一条
+    pub fn wait(&mut self) {
+        let now = Instant::now();
+        if let Some(next) = self.next.filter(|next| *next > now) {
+            thread::sleep(next - now);
+        }
+        self.next = Some(Instant::now() + self.interval);
+    }
+}
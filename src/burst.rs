@@ -0,0 +1,237 @@
+use crate::digest::{self, DigestEntry};
+use crate::i18n;
+use crate::notification::{Level, Notification};
+use crate::routing::glob_match;
+use crate::store;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const STATE_FILE: &str = "burst.json";
+
+/// 突发合并 - 同一事件在短时间内超过 `max` 条时，之后的先保存，这一阵结束后合并为一条发送
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BurstConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 参与合并的事件通配符，为空时所有事件
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// 窗口内照常发送的条数
+    #[serde(default = "default_max")]
+    pub max: usize,
+    /// 窗口（秒）：统计到达的条数，也是判断这一阵已结束的间隔
+    #[serde(default = "default_window_secs")]
+    pub window_secs: i64,
+    /// 持续不断时最多等待多少秒就发送已合并的
+    #[serde(default = "default_max_wait_secs")]
+    pub max_wait_secs: i64,
+}
+
+impl Default for BurstConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            events: Vec::new(),
+            max: default_max(),
+            window_secs: default_window_secs(),
+            max_wait_secs: default_max_wait_secs(),
+        }
+    }
+}
+
+fn default_max() -> usize {
+    3
+}
+
+fn default_window_secs() -> i64 {
+    10
+}
+
+fn default_max_wait_secs() -> i64 {
+    60
+}
+
+impl BurstConfig {
+    /// 紧急通知从不合并，和告警风暴一样照常发送
+    pub fn matches(&self, notification: &Notification) -> bool {
+        self.enabled
+            && notification.level != Level::Critical
+            && (self.events.is_empty()
+                || self
+                    .events
+                    .iter()
+                    .any(|pattern| glob_match(pattern, &notification.event)))
+    }
+}
+
+/// 同一事件最近的到达时间和本轮保存的通知
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Burst {
+    arrivals: Vec<i64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    held: Vec<DigestEntry>,
+}
+
+impl Burst {
+    /// 这一阵是否已结束（`window_secs` 内没有新的）或已等待太久
+    fn is_due(&self, config: &BurstConfig, now: i64) -> bool {
+        let (Some(first), Some(last)) = (self.held.first(), self.arrivals.last()) else {
+            return false;
+        };
+        now - last >= config.window_secs || now - first.at >= config.max_wait_secs
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BurstStore {
+    events: BTreeMap<String, Burst>,
+}
+
+impl BurstStore {
+    /// 持有期间完成读取、记录和写入，并发的子代理hook不会丢失到达记录
+    pub fn lock() -> Result<store::Lock> {
+        store::lock(STATE_FILE)
+    }
+
+    pub fn load() -> Result<Self> {
+        store::load(STATE_FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        store::save(STATE_FILE, self)
+    }
+
+    /// 记录一次到达：照常发送时返回 `None`，否则保存下来并返回本轮已合并的条数
+    pub fn arrive(
+        &mut self,
+        config: &BurstConfig,
+        notification: &Notification,
+        channels: &[String],
+        now: i64,
+    ) -> Option<usize> {
+        let burst = self.events.entry(notification.event.clone()).or_default();
+        burst.arrivals.retain(|at| now - at < config.window_secs);
+        burst.arrivals.push(now);
+        if burst.held.is_empty() && burst.arrivals.len() <= config.max {
+            return None;
+        }
+        burst.held.push(DigestEntry {
+            notification: notification.clone(),
+            channels: channels.to_vec(),
+            at: now,
+        });
+        Some(burst.held.len())
+    }
+
+    /// 取出可以发送的合并（`all` 时不论是否结束），按事件返回
+    pub fn take_due(
+        &mut self,
+        config: &BurstConfig,
+        now: i64,
+        all: bool,
+    ) -> BTreeMap<String, Vec<DigestEntry>> {
+        let mut due = BTreeMap::new();
+        for (event, burst) in self.events.iter_mut() {
+            if !burst.held.is_empty() && (all || burst.is_due(config, now)) {
+                due.insert(event.clone(), std::mem::take(&mut burst.held));
+            }
+        }
+        // 不再需要的记录不保留
+        self.events.retain(|_, burst| {
+            !burst.held.is_empty()
+                || burst
+                    .arrivals
+                    .iter()
+                    .any(|at| now - at < config.window_secs)
+        });
+        due
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.values().all(|burst| burst.held.is_empty())
+    }
+}
+
+/// 把同一事件的多条通知合并为一条，逐条列出标题
+pub fn combine(event: &str, entries: &[DigestEntry]) -> Notification {
+    let mut notification = digest::combine(entries);
+    notification.event = "burst".to_string();
+    notification.title = i18n::fill(
+        i18n::strings().burst_title,
+        &[("count", &entries.len()), ("event", &event)],
+    );
+    notification
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> BurstConfig {
+        BurstConfig {
+            enabled: true,
+            events: vec!["subagent_*".to_string()],
+            max: 2,
+            window_secs: 10,
+            max_wait_secs: 60,
+        }
+    }
+
+    fn notification(event: &str, level: Level) -> Notification {
+        Notification::new(event, "t", "c", level)
+    }
+
+    #[test]
+    fn matches_configured_events_but_never_critical() {
+        let config = config();
+        assert!(config.matches(&notification("subagent_stop", Level::Info)));
+        assert!(!config.matches(&notification("stop", Level::Info)));
+        assert!(!config.matches(&notification("subagent_stop", Level::Critical)));
+        let disabled = BurstConfig {
+            enabled: false,
+            ..config
+        };
+        assert!(!disabled.matches(&notification("subagent_stop", Level::Info)));
+    }
+
+    #[test]
+    fn holds_arrivals_beyond_max_until_burst_ends() {
+        let config = config();
+        let channels = vec!["teams".to_string()];
+        let n = notification("subagent_stop", Level::Info);
+        let mut store = BurstStore::default();
+        assert_eq!(store.arrive(&config, &n, &channels, 0), None);
+        assert_eq!(store.arrive(&config, &n, &channels, 1), None);
+        assert_eq!(store.arrive(&config, &n, &channels, 2), Some(1));
+        assert_eq!(store.arrive(&config, &n, &channels, 3), Some(2));
+
+        // 窗口内还有到达，尚未结束
+        assert!(store.take_due(&config, 12, false).is_empty());
+        let due = store.take_due(&config, 13, false);
+        assert_eq!(due["subagent_stop"].len(), 2);
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn continuous_burst_is_sent_after_max_wait() {
+        let config = config();
+        let channels = vec!["teams".to_string()];
+        let n = notification("subagent_stop", Level::Info);
+        let mut store = BurstStore::default();
+        for at in (0..=62).step_by(2) {
+            store.arrive(&config, &n, &channels, at);
+        }
+        // 第一条在4秒时被保存，60秒后即使仍在持续也发送
+        assert!(store.take_due(&config, 63, false).is_empty());
+        store.arrive(&config, &n, &channels, 64);
+        assert_eq!(
+            store.take_due(&config, 64, false)["subagent_stop"].len(),
+            31
+        );
+
+        // 仍在持续，新的一条继续保存；`all` 时不论是否结束都取出
+        assert_eq!(store.arrive(&config, &n, &channels, 66), Some(1));
+        assert_eq!(store.take_due(&config, 66, true)["subagent_stop"].len(), 1);
+    }
+}
@@ -0,0 +1,523 @@
+use crate::approval::{APPROVE_ACTION, DENY_ACTION};
+use crate::config::Config;
+use crate::escalation::{EscalationStore, ACK_ACTION};
+use crate::i18n;
+use anyhow::Result;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::Local;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+const CLICKS_FILE: &str = "callbacks.jsonl";
+
+/// 请求头和请求体的总上限，按钮回调都很小
+const MAX_REQUEST_BYTES: u64 = 64 * 1024;
+
+/// 读取一个请求的总时限，飞书要求3秒内应答，慢速连接不能一直占用
+const REQUEST_DEADLINE: Duration = Duration::from_secs(2);
+
+/// 同时处理的连接上限，超出时直接返回503
+const MAX_CONNECTIONS: usize = 64;
+
+/// 卡片按钮回调服务的设置
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CallbackConfig {
+    /// 回调服务的监听地址
+    #[serde(default = "default_listen")]
+    pub listen: String,
+    /// 聊天平台访问回调服务的外部地址，Teams等渠道的按钮向 `<public_url>/callback` 发送点击
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_url: Option<String>,
+    /// 签名按钮回调值的密钥，防止伪造点击
+    pub secret: String,
+    /// 飞书应用的Verification Token，校验请求来自飞书
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feishu_token: Option<String>,
+    /// 收到点击后运行的命令，点击记录以JSON写入stdin
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+}
+
+fn default_listen() -> String {
+    "127.0.0.1:8787".to_string()
+}
+
+impl CallbackConfig {
+    /// 按钮回调发送到的地址；没有 `public_url` 时为None
+    pub fn endpoint(&self) -> Option<String> {
+        self.public_url
+            .as_ref()
+            .map(|url| format!("{}/callback", url.trim_end_matches('/')))
+    }
+}
+
+/// 按钮携带的回调值，签名覆盖通知ID和动作
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CallbackValue {
+    /// 通知的历史ID
+    pub id: String,
+    pub action: String,
+    pub sig: String,
+}
+
+fn mac(secret: &str, id: &str, action: &str) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{}\n{}", id, action).as_bytes());
+    mac
+}
+
+impl CallbackValue {
+    pub fn new(secret: &str, id: &str, action: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            action: action.to_string(),
+            sig: URL_SAFE_NO_PAD.encode(mac(secret, id, action).finalize().into_bytes()),
+        }
+    }
+
+    /// 常量时间校验签名
+    pub fn verify(&self, secret: &str) -> bool {
+        URL_SAFE_NO_PAD.decode(&self.sig).is_ok_and(|sig| {
+            mac(secret, &self.id, &self.action)
+                .verify_slice(&sig)
+                .is_ok()
+        })
+    }
+}
+
+/// 常量时间比较，耗时不随第一个不同字节的位置变化
+fn constant_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
+
+/// 收到的一次按钮点击
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Click {
+    pub at: i64,
+    /// 回调来源：feishu，或 callback（Teams及其他渠道）
+    pub source: String,
+    /// 通知的历史ID
+    pub id: String,
+    pub action: String,
+    /// 点击者，飞书为open_id
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+/// 追加一条点击记录（JSONL，每行一条）
+pub fn append(click: &Click) -> Result<()> {
+    let dir = Config::data_dir()?;
+    fs::create_dir_all(&dir)?;
+
+    let mut line = serde_json::to_string(click)?;
+    line.push('\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(CLICKS_FILE))?
+        .write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// 读取所有点击记录，没有记录文件时为空
+pub fn clicks() -> Result<Vec<Click>> {
+    let path = Config::data_dir()?.join(CLICKS_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path)?;
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// 一次回调的处理结果
+enum Reply {
+    /// 飞书配置请求地址时的校验
+    Challenge(Value),
+    Click(Click),
+    Rejected(&'static str, String),
+}
+
+/// 解析并校验回调：飞书校验Verification Token，所有来源都校验回调值的签名
+fn handle(config: &CallbackConfig, path: &str, body: &[u8]) -> Reply {
+    let Ok(payload) = serde_json::from_slice::<Value>(body) else {
+        return Reply::Rejected("400 Bad Request", "body is not JSON".to_string());
+    };
+
+    let (source, value, user) = match path {
+        "/feishu" => {
+            if let Some(token) = &config.feishu_token {
+                let received = payload["token"]
+                    .as_str()
+                    .or_else(|| payload["header"]["token"].as_str());
+                if !received.is_some_and(|received| constant_eq(received, token)) {
+                    return Reply::Rejected(
+                        "401 Unauthorized",
+                        "invalid verification token".to_string(),
+                    );
+                }
+            }
+            if payload["type"] == "url_verification" {
+                return Reply::Challenge(json!({ "challenge": payload["challenge"] }));
+            }
+            // 新版卡片回调在 event 下，旧版在顶层
+            let event = if payload["event"].is_object() {
+                &payload["event"]
+            } else {
+                &payload
+            };
+            let user = event["operator"]["open_id"]
+                .as_str()
+                .or_else(|| event["open_id"].as_str())
+                .map(String::from);
+            ("feishu", event["action"]["value"].clone(), user)
+        }
+        // Teams的HttpPOST按钮原样发送回调值
+        "/callback" => ("callback", payload, None),
+        _ => return Reply::Rejected("404 Not Found", format!("no endpoint {}", path)),
+    };
+
+    match serde_json::from_value::<CallbackValue>(value) {
+        Ok(value) if value.verify(&config.secret) => Reply::Click(Click {
+            at: Local::now().timestamp(),
+            source: source.to_string(),
+            id: value.id,
+            action: value.action,
+            user,
+        }),
+        Ok(_) => Reply::Rejected("401 Unauthorized", "invalid signature".to_string()),
+        Err(e) => Reply::Rejected("400 Bad Request", format!("invalid callback value: {}", e)),
+    }
+}
+
+/// 请求声明的 `Content-Length` 超过上限，不分配也不读取请求体
+#[derive(Debug)]
+struct TooLarge;
+
+impl std::fmt::Display for TooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "request body larger than {} bytes", MAX_REQUEST_BYTES)
+    }
+}
+
+impl std::error::Error for TooLarge {}
+
+/// 每次读取前把读超时设为剩余时间，整个请求超过截止时间即失败
+struct DeadlineReader<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant,
+}
+
+impl Read for DeadlineReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(std::io::ErrorKind::TimedOut.into());
+        }
+        self.stream.set_read_timeout(Some(remaining))?;
+        let mut stream = self.stream;
+        stream.read(buf)
+    }
+}
+
+/// 读取请求行、请求头和请求体，返回 (方法, 路径, 请求体)
+fn read_request(stream: &TcpStream, deadline: Instant) -> Result<(String, String, Vec<u8>)> {
+    let reader = DeadlineReader { stream, deadline };
+    let mut reader = BufReader::new(reader.take(MAX_REQUEST_BYTES));
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut content_length: u64 = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            break;
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse()?;
+            }
+        }
+    }
+
+    if content_length > MAX_REQUEST_BYTES {
+        return Err(TooLarge.into());
+    }
+    let mut body = vec![0; content_length as usize];
+    reader.read_exact(&mut body)?;
+    Ok((method, path, body))
+}
+
+fn respond(mut stream: &TcpStream, status: &str, headers: &str, body: &Value) {
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        headers,
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes());
+}
+
+/// 把点击记录交给用户配置的命令，不等待命令结束
+fn run_command(config: &CallbackConfig, click: &Click) {
+    let Some(command) = &config.command else {
+        return;
+    };
+    let child = Command::new(command)
+        .args(&config.args)
+        .stdin(Stdio::piped())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            warn!("Failed to spawn {}: {}", command, e);
+            return;
+        }
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(json!(click).to_string().as_bytes());
+    }
+    let command = command.clone();
+    std::thread::spawn(move || match child.wait() {
+        Ok(status) if !status.success() => warn!("{} exited with {}", command, status),
+        Err(e) => warn!("{} failed: {}", command, e),
+        Ok(_) => {}
+    });
+}
+
+/// 确认按钮：取消该通知尚未触发的升级
+fn acknowledge(id: &str) -> String {
+    // 连接并发处理，升级记录的读改写要串行
+    static ACK: Mutex<()> = Mutex::new(());
+    let strings = i18n::strings();
+    let _guard = ACK.lock().unwrap_or_else(|e| e.into_inner());
+    let acked = EscalationStore::load().and_then(|mut store| {
+        let found = store.ack(id);
+        store.save()?;
+        Ok(found)
+    });
+    match acked {
+        Ok(true) => {
+            info!(id = %id, "acknowledged");
+            strings.acknowledged.to_string()
+        }
+        Ok(false) => strings.already_acknowledged.to_string(),
+        Err(e) => {
+            warn!("Failed to acknowledge {}: {:#}", id, e);
+            strings.acknowledge_failed.to_string()
+        }
+    }
+}
+
+/// 在前台提供按钮回调服务：`POST /feishu` 接收飞书卡片回调，`POST /callback` 接收其他渠道
+pub fn serve(config: &CallbackConfig) -> Result<()> {
+    let listener = TcpListener::bind(&config.listen)?;
+    info!(listen = %config.listen, "callback server listening");
+
+    // 每个连接一个线程，慢速连接不会挡住其他回调
+    let config = Arc::new(config.clone());
+    let active = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming().flatten() {
+        if active.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            active.fetch_sub(1, Ordering::SeqCst);
+            respond(
+                &stream,
+                "503 Service Unavailable",
+                "",
+                &json!({"error": "too many connections"}),
+            );
+            continue;
+        }
+        let config = Arc::clone(&config);
+        let active = Arc::clone(&active);
+        std::thread::spawn(move || {
+            serve_connection(&config, &stream);
+            active.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+    Ok(())
+}
+
+/// 读取并应答一个连接上的请求
+fn serve_connection(config: &CallbackConfig, stream: &TcpStream) {
+    let _ = stream.set_write_timeout(Some(REQUEST_DEADLINE));
+    let (method, path, body) = match read_request(stream, Instant::now() + REQUEST_DEADLINE) {
+        Ok(request) => request,
+        Err(e) if e.is::<TooLarge>() => {
+            debug!("{:#}", e);
+            respond(
+                stream,
+                "413 Payload Too Large",
+                "",
+                &json!({"error": "request too large"}),
+            );
+            return;
+        }
+        Err(e) => {
+            debug!("bad request: {:#}", e);
+            respond(
+                stream,
+                "400 Bad Request",
+                "",
+                &json!({"error": "bad request"}),
+            );
+            return;
+        }
+    };
+    if method != "POST" {
+        respond(
+            stream,
+            "405 Method Not Allowed",
+            "",
+            &json!({"error": "POST only"}),
+        );
+        return;
+    }
+
+    match handle(config, &path, &body) {
+        Reply::Challenge(challenge) => respond(stream, "200 OK", "", &challenge),
+        Reply::Click(click) => {
+            info!(id = %click.id, action = %click.action, source = %click.source, "button clicked");
+            if let Err(e) = append(&click) {
+                warn!("Failed to record click: {:#}", e);
+            }
+            run_command(config, &click);
+            let strings = i18n::strings();
+            let status = match click.action.as_str() {
+                ACK_ACTION => acknowledge(&click.id),
+                APPROVE_ACTION => strings.approved.to_string(),
+                DENY_ACTION => strings.denied.to_string(),
+                action => i18n::fill(strings.received, &[("action", &action)]),
+            };
+            if click.source == "feishu" {
+                let toast = json!({"toast": {"type": "success", "content": status}});
+                respond(stream, "200 OK", "", &toast);
+            } else {
+                // Teams在卡片上显示该响应头的内容
+                let header = format!("CARD-ACTION-STATUS: {}\r\n", status);
+                respond(stream, "200 OK", &header, &json!({"ok": true}));
+            }
+        }
+        Reply::Rejected(status, error) => {
+            warn!(path = %path, "callback rejected: {}", error);
+            respond(stream, status, "", &json!({ "error": error }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> CallbackConfig {
+        CallbackConfig {
+            listen: default_listen(),
+            public_url: None,
+            secret: "secret".to_string(),
+            feishu_token: Some("token".to_string()),
+            command: None,
+            args: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn signed_value_verifies_only_with_same_secret_and_fields() {
+        let value = CallbackValue::new("secret", "42", "approve");
+        assert!(value.verify("secret"));
+        assert!(!value.verify("other"));
+
+        let mut forged = value.clone();
+        forged.action = "deny".to_string();
+        assert!(!forged.verify("secret"));
+
+        let mut forged = value.clone();
+        forged.id = "43".to_string();
+        assert!(!forged.verify("secret"));
+
+        let mut garbled = value;
+        garbled.sig = "not base64!".to_string();
+        assert!(!garbled.verify("secret"));
+    }
+
+    #[test]
+    fn feishu_callback_requires_token_and_signature() {
+        let config = config();
+        let value = CallbackValue::new("secret", "42", "ack");
+        let body = |token: &str| {
+            json!({"token": token, "open_id": "ou_1", "action": {"value": value}}).to_string()
+        };
+
+        match handle(&config, "/feishu", body("token").as_bytes()) {
+            Reply::Click(click) => {
+                assert_eq!(click.source, "feishu");
+                assert_eq!(click.id, "42");
+                assert_eq!(click.action, "ack");
+                assert_eq!(click.user.as_deref(), Some("ou_1"));
+            }
+            _ => panic!("expected a click"),
+        }
+        for token in ["tokem", "token2", ""] {
+            assert!(matches!(
+                handle(&config, "/feishu", body(token).as_bytes()),
+                Reply::Rejected("401 Unauthorized", _)
+            ));
+        }
+
+        let forged = json!({"id": "42", "action": "approve", "sig": value.sig});
+        assert!(matches!(
+            handle(&config, "/callback", forged.to_string().as_bytes()),
+            Reply::Rejected("401 Unauthorized", _)
+        ));
+    }
+
+    #[test]
+    fn slow_request_fails_at_deadline() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+
+        // 请求头迟迟不结束，每次都在读超时之内发一点
+        let writer = std::thread::spawn(move || {
+            for _ in 0..10 {
+                if client.write_all(b"X-Slow: 1\r\n").is_err() {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(50));
+            }
+        });
+        let started = Instant::now();
+        let deadline = started + Duration::from_millis(200);
+        assert!(read_request(&server, deadline).is_err());
+        assert!(started.elapsed() < Duration::from_millis(400));
+        writer.join().unwrap();
+    }
+}
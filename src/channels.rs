@@ -0,0 +1,96 @@
+use crate::circuit_breaker::{CircuitBreaker, CircuitState};
+use crate::config::Config;
+use crate::history::HistoryEntry;
+use crate::manager::NotificationManager;
+use crate::redact;
+use crate::routing::parse_chain;
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+
+/// `channels` 命令中一个渠道的概况
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelInfo {
+    pub name: String,
+    /// 已启用且配置完整
+    pub enabled: bool,
+    /// 打码后的webhook或key，exec和插件渠道没有
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    pub circuit: CircuitState,
+    /// 最近一次成功发送的时间（Unix时间戳）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_success: Option<i64>,
+    /// 路由到该渠道的事件；规则的事件为通配符，没有事件条件的规则为 `*`
+    pub events: Vec<String>,
+}
+
+/// 汇总配置、熔断状态和历史记录中的所有渠道
+pub fn list(manager: &NotificationManager, history: &[HistoryEntry]) -> Result<Vec<ChannelInfo>> {
+    let config = manager.config();
+    let breaker = CircuitBreaker::load(config.circuit_breaker.clone())?;
+    Ok(manager
+        .channels()
+        .into_iter()
+        .map(|(name, ready)| ChannelInfo {
+            name: name.to_string(),
+            enabled: ready,
+            secret: secret(config, name),
+            circuit: breaker.state(name),
+            last_success: last_success(history, name),
+            events: events(config, name),
+        })
+        .collect())
+}
+
+fn secret(config: &Config, channel: &str) -> Option<String> {
+    let channels = &config.channels;
+    match channel {
+        "teams" => Some(redact::mask_url(&channels.teams.as_ref()?.webhook)),
+        "feishu" => Some(redact::mask_url(&channels.feishu.as_ref()?.webhook)),
+        "wechat" => Some(redact::mask_secret(&channels.wechat.as_ref()?.key)),
+        _ => None,
+    }
+}
+
+/// 备用链按实际送达的一跳计算
+fn last_success(history: &[HistoryEntry], channel: &str) -> Option<i64> {
+    let delivered = |key: &str, result: &Value| match result["hops"].as_array() {
+        Some(hops) => hops
+            .iter()
+            .any(|hop| hop["channel"] == channel && hop["success"] == true),
+        None => key == channel && result["success"] == true,
+    };
+    history
+        .iter()
+        .filter(|entry| {
+            entry
+                .results
+                .iter()
+                .any(|(key, result)| delivered(key, result))
+        })
+        .map(|entry| entry.at)
+        .max()
+}
+
+fn events(config: &Config, channel: &str) -> Vec<String> {
+    let routes = |specs: &[String]| {
+        specs
+            .iter()
+            .any(|spec| parse_chain(spec).iter().any(|c| c == channel))
+    };
+    let mut events: Vec<String> = config
+        .notifications
+        .iter()
+        .filter(|(_, specs)| routes(specs))
+        .map(|(event, _)| event.clone())
+        .collect();
+    events.sort();
+    for rule in config.rules.iter().filter(|rule| routes(&rule.channels)) {
+        let event = rule.event.clone().unwrap_or_else(|| "*".to_string());
+        if !events.contains(&event) {
+            events.push(event);
+        }
+    }
+    events
+}
@@ -0,0 +1,241 @@
+use crate::adapter;
+use crate::i18n;
+use crate::markdown;
+use crate::notification::{CardAction, Level, Notification};
+use anyhow::Result;
+use std::str::FromStr;
+
+/// `send --ci`：从哪个CI系统的环境变量补充构建信息
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CiMode {
+    /// 按环境变量识别当前的CI系统
+    Auto,
+    /// 不补充
+    Off,
+    Github,
+    Gitlab,
+    Jenkins,
+}
+
+impl FromStr for CiMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "auto" => Ok(CiMode::Auto),
+            "none" | "off" => Ok(CiMode::Off),
+            "github" => Ok(CiMode::Github),
+            "gitlab" => Ok(CiMode::Gitlab),
+            "jenkins" => Ok(CiMode::Jenkins),
+            _ => Err(anyhow::anyhow!(
+                "Unknown CI system '{}' (expected one of: auto, none, github, gitlab, jenkins)",
+                s
+            )),
+        }
+    }
+}
+
+/// 附加到通知的上下文：CI系统注入的构建信息，或本地仓库的状态（见 `git`）
+#[derive(Debug, Clone, Default)]
+pub struct CiContext {
+    /// 附在正文后的键值表
+    pub facts: Vec<(&'static str, String)>,
+    /// 查看这次运行的按钮
+    pub links: Vec<CardAction>,
+    /// 同一次运行的通知放在同一会话中，与 `hook --format` 的键一致
+    pub thread_key: Option<String>,
+    /// 按作业状态得出的级别，`send` 没有指定 `--level` 时使用
+    pub level: Option<Level>,
+}
+
+fn var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+/// 按 `mode` 读取构建信息；不在CI中时为None
+pub fn context(mode: CiMode) -> Option<CiContext> {
+    match mode {
+        CiMode::Off => None,
+        CiMode::Github => Some(github()),
+        CiMode::Gitlab => Some(gitlab()),
+        CiMode::Jenkins => Some(jenkins()),
+        CiMode::Auto => {
+            if var("GITHUB_ACTIONS").as_deref() == Some("true") {
+                Some(github())
+            } else if var("GITLAB_CI").is_some() {
+                Some(gitlab())
+            } else if var("JENKINS_URL").is_some() {
+                Some(jenkins())
+            } else {
+                None
+            }
+        }
+    }
+}
+
+fn short_sha(sha: String) -> String {
+    format!("`{}`", sha.get(..7).unwrap_or(&sha))
+}
+
+fn github() -> CiContext {
+    let strings = i18n::strings();
+    let repository = var("GITHUB_REPOSITORY");
+    let run_id = var("GITHUB_RUN_ID");
+    let mut context = CiContext::default();
+    context.fact(strings.ci_repository, repository.clone());
+    context.fact(
+        strings.ci_workflow,
+        var("GITHUB_WORKFLOW").map(|workflow| match var("GITHUB_RUN_NUMBER") {
+            Some(number) => format!("{} #{}", workflow, number),
+            None => workflow,
+        }),
+    );
+    context.fact(strings.ci_job, var("GITHUB_JOB"));
+    // 拉取请求的构建用源分支，GITHUB_REF_NAME为 `<number>/merge`
+    context.fact(
+        strings.ci_branch,
+        var("GITHUB_HEAD_REF").or_else(|| var("GITHUB_REF_NAME")),
+    );
+    context.fact(strings.ci_commit, var("GITHUB_SHA").map(short_sha));
+    context.fact(
+        strings.ci_actor,
+        var("GITHUB_TRIGGERING_ACTOR").or_else(|| var("GITHUB_ACTOR")),
+    );
+    if let (Some(repository), Some(run_id)) = (&repository, &run_id) {
+        let server = var("GITHUB_SERVER_URL").unwrap_or_else(|| "https://github.com".into());
+        let mut url = format!("{}/{}/actions/runs/{}", server, repository, run_id);
+        // 重新运行时指向这一次尝试
+        if let Some(attempt) = var("GITHUB_RUN_ATTEMPT").filter(|a| a != "1") {
+            url.push_str(&format!("/attempts/{}", attempt));
+        }
+        context.links.push(CardAction {
+            label: strings.ci_view_run.to_string(),
+            value: url,
+        });
+        context.thread_key = Some(format!("github/{}/run/{}", repository, run_id));
+    }
+    context
+}
+
+fn gitlab() -> CiContext {
+    let strings = i18n::strings();
+    let project = var("CI_PROJECT_PATH");
+    let pipeline_id = var("CI_PIPELINE_ID");
+    let mut context = CiContext::default();
+    context.fact(strings.ci_repository, project.clone());
+    context.fact(
+        strings.ci_pipeline,
+        pipeline_id.as_ref().map(|id| format!("#{}", id)),
+    );
+    context.fact(strings.ci_job, var("CI_JOB_NAME"));
+    // 合并请求流水线显示源分支
+    context.fact(
+        strings.ci_branch,
+        var("CI_MERGE_REQUEST_SOURCE_BRANCH_NAME").or_else(|| var("CI_COMMIT_REF_NAME")),
+    );
+    context.fact(
+        strings.ci_commit,
+        var("CI_COMMIT_SHA").map(|sha| match var("CI_COMMIT_TITLE") {
+            Some(title) => format!("{} {}", short_sha(sha), title),
+            None => short_sha(sha),
+        }),
+    );
+    context.fact(
+        strings.ci_actor,
+        var("GITLAB_USER_LOGIN").or_else(|| var("GITLAB_USER_NAME")),
+    );
+    context
+        .links
+        .extend(var("CI_PIPELINE_URL").map(|url| CardAction {
+            label: strings.ci_view_pipeline.to_string(),
+            value: url,
+        }));
+    if let (Some(project), Some(id)) = (&project, &pipeline_id) {
+        context.thread_key = Some(format!("gitlab/{}/pipeline/{}", project, id));
+    }
+    // 只在after_script中可用
+    context.level = var("CI_JOB_STATUS").map(|status| match status.as_str() {
+        "success" => Level::Success,
+        "failed" => Level::Critical,
+        "canceled" => Level::Warning,
+        _ => Level::Info,
+    });
+    context
+}
+
+/// Jenkins不提供构建结果的环境变量，流水线中用 `BUILD_RESULT=${currentBuild.currentResult}` 传入
+fn jenkins() -> CiContext {
+    let strings = i18n::strings();
+    let job = var("JOB_NAME");
+    let number = var("BUILD_NUMBER");
+    let mut context = CiContext::default();
+    context.fact(strings.ci_job, job.clone());
+    context.fact(
+        strings.ci_build,
+        var("BUILD_DISPLAY_NAME").or_else(|| number.as_ref().map(|n| format!("#{}", n))),
+    );
+    // 多分支流水线有BRANCH_NAME，Git插件的GIT_BRANCH带远端名
+    context.fact(
+        strings.ci_branch,
+        var("BRANCH_NAME").or_else(|| {
+            var("GIT_BRANCH").map(|branch| match branch.split_once('/') {
+                Some(("origin", branch)) => branch.to_string(),
+                _ => branch,
+            })
+        }),
+    );
+    context.fact(strings.ci_commit, var("GIT_COMMIT").map(short_sha));
+    context.fact(
+        strings.ci_actor,
+        var("BUILD_USER").or_else(|| var("CHANGE_AUTHOR")),
+    );
+    if let Some(url) = var("BUILD_URL") {
+        let console = format!("{}/console", url.trim_end_matches('/'));
+        context.links.push(CardAction {
+            label: strings.ci_view_build.to_string(),
+            value: url,
+        });
+        context.links.push(CardAction {
+            label: strings.ci_console.to_string(),
+            value: console,
+        });
+    }
+    if let (Some(job), Some(number)) = (&job, &number) {
+        context.thread_key = Some(format!("jenkins/{} #{}", job, number));
+    }
+    context.level = var("BUILD_RESULT")
+        .or_else(|| var("BUILD_STATUS"))
+        .map(|result| adapter::jenkins_level(&result));
+    context
+}
+
+impl CiContext {
+    fn fact(&mut self, name: &'static str, value: Option<String>) {
+        if let Some(value) = value {
+            self.facts.push((name, value));
+        }
+    }
+
+    /// 在正文后附上构建信息表格，添加查看运行的按钮；调用方指定的会话键优先
+    pub fn apply(&self, notification: &mut Notification) {
+        if !self.facts.is_empty() {
+            let table = markdown::facts_table(&self.facts);
+            notification.content = match notification.content.trim() {
+                "" => table,
+                content => format!("{}\n\n{}", content, table),
+            };
+        }
+        for link in &self.links {
+            if !notification
+                .actions
+                .iter()
+                .any(|action| action.value == link.value)
+            {
+                notification.actions.push(link.clone());
+            }
+        }
+        if notification.thread_key.is_none() {
+            notification.thread_key = self.thread_key.clone();
+        }
+    }
+}
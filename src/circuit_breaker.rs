@@ -0,0 +1,108 @@
+use crate::config::CircuitBreakerConfig;
+use crate::store;
+use anyhow::Result;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const STATE_FILE: &str = "circuit_breaker.json";
+
+/// 单个渠道的健康状态
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct ChannelHealth {
+    pub consecutive_failures: u32,
+    /// 熔断开始时间（Unix时间戳），None表示未熔断
+    pub opened_at: Option<i64>,
+}
+
+/// 渠道的熔断状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum CircuitState {
+    /// 未启用熔断
+    Disabled,
+    Closed {
+        consecutive_failures: u32,
+    },
+    Open {
+        opened_at: i64,
+        consecutive_failures: u32,
+    },
+    /// 冷却期已过，下一次发送作为试探
+    HalfOpen {
+        opened_at: i64,
+        consecutive_failures: u32,
+    },
+}
+
+/// 熔断器 - 连续失败的渠道暂停调用一段时间，状态跨进程持久化
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    states: HashMap<String, ChannelHealth>,
+}
+
+impl CircuitBreaker {
+    pub fn load(config: CircuitBreakerConfig) -> Result<Self> {
+        Ok(Self {
+            config,
+            states: store::load(STATE_FILE)?,
+        })
+    }
+
+    /// 渠道当前是否处于熔断状态（冷却期过后允许一次试探）
+    pub fn is_open(&self, channel: &str) -> bool {
+        if !self.config.enabled {
+            return false;
+        }
+
+        match self.states.get(channel).and_then(|s| s.opened_at) {
+            Some(opened_at) => Local::now().timestamp() - opened_at < self.config.cooldown_secs,
+            None => false,
+        }
+    }
+
+    pub fn state(&self, channel: &str) -> CircuitState {
+        if !self.config.enabled {
+            return CircuitState::Disabled;
+        }
+
+        let health = self.states.get(channel).cloned().unwrap_or_default();
+        let consecutive_failures = health.consecutive_failures;
+        match health.opened_at {
+            Some(opened_at) if self.is_open(channel) => CircuitState::Open {
+                opened_at,
+                consecutive_failures,
+            },
+            Some(opened_at) => CircuitState::HalfOpen {
+                opened_at,
+                consecutive_failures,
+            },
+            None => CircuitState::Closed {
+                consecutive_failures,
+            },
+        }
+    }
+
+    pub fn fallback_for(&self, channel: &str) -> Option<&String> {
+        self.config.fallback.get(channel)
+    }
+
+    pub fn record_success(&mut self, channel: &str) {
+        self.states.remove(channel);
+    }
+
+    pub fn record_failure(&mut self, channel: &str) {
+        let threshold = self.config.failure_threshold;
+        let health = self.states.entry(channel.to_string()).or_default();
+        health.consecutive_failures += 1;
+
+        if health.consecutive_failures >= threshold {
+            // 试探失败时重新计时
+            health.opened_at = Some(Local::now().timestamp());
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        store::save(STATE_FILE, &self.states)
+    }
+}
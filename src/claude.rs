@@ -0,0 +1,299 @@
+use crate::config::Config;
+use crate::i18n;
+use crate::notification::{Level, Notification};
+use crate::transcript;
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// Notification细分后的事件名
+pub const PERMISSION_REQUEST: &str = "permission_request";
+pub const AWAITING_INPUT: &str = "awaiting_input";
+pub const AGENT_ERROR: &str = "agent_error";
+
+/// 工具参数摘要的最大字符数
+const MAX_TOOL_SUMMARY_CHARS: usize = 300;
+
+/// Claude Code 传给hook的stdin输入，各事件共有字段加上事件特有字段
+#[derive(Debug, Deserialize, Clone)]
+pub struct HookInput {
+    #[serde(default)]
+    pub session_id: String,
+    #[serde(default)]
+    pub transcript_path: Option<PathBuf>,
+    #[serde(default)]
+    pub cwd: Option<String>,
+    pub hook_event_name: String,
+    /// Notification
+    #[serde(default)]
+    pub message: Option<String>,
+    /// Notification的类型（permission_prompt、idle_prompt等），旧版本没有
+    #[serde(default)]
+    pub notification_type: Option<String>,
+    /// PreToolUse / PostToolUse
+    #[serde(default)]
+    pub tool_name: Option<String>,
+    #[serde(default)]
+    pub tool_input: Option<Value>,
+    /// UserPromptSubmit
+    #[serde(default)]
+    pub prompt: Option<String>,
+    /// SessionStart的来源（startup、resume、clear、compact）
+    #[serde(default)]
+    pub source: Option<String>,
+    /// SessionEnd的原因
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// PreCompact的触发方式（manual、auto）
+    #[serde(default)]
+    pub trigger: Option<String>,
+    /// SubagentStop的子代理类型和会话记录
+    #[serde(default)]
+    pub agent_type: Option<String>,
+    #[serde(default)]
+    pub agent_transcript_path: Option<PathBuf>,
+    /// TaskCompleted的任务
+    #[serde(default)]
+    pub task_subject: Option<String>,
+    #[serde(default)]
+    pub task_description: Option<String>,
+}
+
+impl HookInput {
+    /// 有 `hook_event_name` 时按Claude Code的格式解析，否则为旧格式
+    pub fn parse(data: &Value) -> Option<Self> {
+        data.get("hook_event_name")?.as_str()?;
+        serde_json::from_value(data.clone()).ok()
+    }
+
+    /// 事件名转为蛇形，例如 `SubagentStop` → `subagent_stop`
+    pub fn event(&self) -> String {
+        let mut event = String::new();
+        for (i, c) in self.hook_event_name.chars().enumerate() {
+            if c.is_uppercase() {
+                if i > 0 {
+                    event.push('_');
+                }
+                event.extend(c.to_lowercase());
+            } else {
+                event.push(c);
+            }
+        }
+        event
+    }
+
+    /// 把Notification细分为权限请求、等待输入和错误，无法判断时为None
+    pub fn notification_kind(&self) -> Option<&'static str> {
+        match self.notification_type.as_deref() {
+            Some("permission_prompt") => return Some(PERMISSION_REQUEST),
+            Some("idle_prompt" | "elicitation_dialog") => return Some(AWAITING_INPUT),
+            _ => {}
+        }
+        // 旧版本只能按消息文字判断
+        let message = self.message.as_deref()?.to_lowercase();
+        if message.contains("permission") {
+            Some(PERMISSION_REQUEST)
+        } else if message.contains("waiting for your input") || message.contains("idle") {
+            Some(AWAITING_INPUT)
+        } else if message.contains("error") || message.contains("failed") {
+            Some(AGENT_ERROR)
+        } else {
+            None
+        }
+    }
+
+    /// 工作目录的最后一级，作为项目名
+    pub fn project(&self) -> Option<&str> {
+        Path::new(self.cwd.as_deref()?)
+            .file_name()
+            .and_then(|name| name.to_str())
+    }
+
+    /// 会话记录中最后一轮的回复和改动，以及整个会话的统计；读不到记录时为空
+    fn turn_summary(&self) -> String {
+        let Some(path) = self.transcript_path.as_deref() else {
+            return String::new();
+        };
+        let sections: Vec<String> = [
+            transcript::summarize(path)
+                .ok()
+                .map(|summary| transcript::format(&summary, self.cwd.as_deref())),
+            transcript::stats(path)
+                .ok()
+                .map(|stats| transcript::format_stats(&stats)),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|section| !section.is_empty())
+        .collect();
+        sections.join("\n\n")
+    }
+
+    /// 子代理最后的回复
+    fn agent_message(&self) -> Option<String> {
+        let path = self.agent_transcript_path.as_deref()?;
+        transcript::summarize(path).ok()?.last_message
+    }
+
+    /// 子代理类型加上回复
+    fn agent_summary(&self, message: Option<String>) -> String {
+        match (&self.agent_type, message) {
+            (Some(agent), Some(message)) => format!("**{}** {}", agent, message),
+            (Some(agent), None) => format!("**{}**", agent),
+            (None, message) => message.unwrap_or_default(),
+        }
+    }
+
+    /// 进度消息中的一行：任务名，或子代理类型和最后回复的第一行
+    pub fn progress_item(&self) -> String {
+        if let Some(subject) = &self.task_subject {
+            return subject.clone();
+        }
+        let message = self
+            .agent_message()
+            .and_then(|message| message.lines().next().map(String::from));
+        let item = self.agent_summary(message);
+        if item.is_empty() {
+            i18n::strings().claude_subagent_finished.to_string()
+        } else {
+            item
+        }
+    }
+
+    /// 会话的token用量和估算费用；读不到记录时为空
+    fn usage_report(&self, config: &Config) -> String {
+        self.transcript_path
+            .as_deref()
+            .and_then(|path| transcript::usage(path).ok())
+            .filter(|usage| !usage.is_empty())
+            .map(|usage| transcript::format_usage(&usage, &config.pricing))
+            .unwrap_or_default()
+    }
+
+    /// 按事件类型生成通知
+    pub fn notification(&self, config: &Config) -> Notification {
+        let strings = i18n::strings();
+        let tool = self.tool_name.as_deref().unwrap_or_default();
+        let tool_summary = || {
+            self.tool_input
+                .as_ref()
+                .map(|input| tool_summary(tool, input))
+                .unwrap_or_default()
+        };
+        let kind = self.notification_kind();
+        let (label, content, level) = match self.hook_event_name.as_str() {
+            "Notification" => {
+                let (label, level) = match kind {
+                    Some(PERMISSION_REQUEST) => (strings.claude_permission, Level::Warning),
+                    Some(AWAITING_INPUT) => (strings.claude_awaiting_input, Level::Info),
+                    Some(AGENT_ERROR) => (strings.claude_error, Level::Critical),
+                    _ => (strings.claude_attention, Level::Warning),
+                };
+                (
+                    label.to_string(),
+                    self.message.clone().unwrap_or_default(),
+                    level,
+                )
+            }
+            "Stop" => (
+                strings.claude_finished.to_string(),
+                self.turn_summary(),
+                Level::Success,
+            ),
+            "SubagentStop" => (
+                strings.claude_subagent_finished.to_string(),
+                self.agent_summary(self.agent_message()),
+                Level::Info,
+            ),
+            "TaskCompleted" => (
+                strings.claude_task_completed.to_string(),
+                [&self.task_subject, &self.task_description]
+                    .into_iter()
+                    .flatten()
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join("\n\n"),
+                Level::Success,
+            ),
+            "PreToolUse" => (
+                i18n::fill(strings.claude_tool, &[("tool", &tool)]),
+                tool_summary(),
+                Level::Info,
+            ),
+            "PostToolUse" => (
+                i18n::fill(strings.claude_tool_done, &[("tool", &tool)]),
+                tool_summary(),
+                Level::Info,
+            ),
+            "UserPromptSubmit" => (
+                strings.claude_prompt.to_string(),
+                self.prompt.clone().unwrap_or_default(),
+                Level::Info,
+            ),
+            "SessionStart" => (
+                with_detail(strings.claude_session_start, self.source.as_deref()),
+                String::new(),
+                Level::Info,
+            ),
+            "SessionEnd" => (
+                with_detail(strings.claude_session_end, self.reason.as_deref()),
+                self.usage_report(config),
+                Level::Info,
+            ),
+            "PreCompact" => (
+                with_detail(strings.claude_compact, self.trigger.as_deref()),
+                String::new(),
+                Level::Info,
+            ),
+            other => (
+                other.to_string(),
+                self.message.clone().unwrap_or_default(),
+                Level::Info,
+            ),
+        };
+
+        let title = match self.project() {
+            Some(project) => format!("{} · {}", label, project),
+            None => label,
+        };
+        // 没有正文时至少说明是哪个目录
+        let content = match (&self.cwd, content.is_empty()) {
+            (Some(cwd), true) => i18n::fill(strings.claude_cwd, &[("cwd", cwd)]),
+            _ => content,
+        };
+        let event = kind.map(String::from).unwrap_or_else(|| self.event());
+        Notification::new(event, title, content, level)
+    }
+}
+
+fn with_detail(label: &str, detail: Option<&str>) -> String {
+    match detail.filter(|d| !d.is_empty()) {
+        Some(detail) => format!("{} ({})", label, detail),
+        None => label.to_string(),
+    }
+}
+
+/// 工具参数的简短描述：命令、文件路径或紧凑的JSON
+pub fn tool_summary(tool: &str, input: &Value) -> String {
+    let field = |name: &str| input[name].as_str().map(String::from);
+    let summary = match tool {
+        "Bash" => field("command").map(|command| format!("```\n{}\n```", command)),
+        "Edit" | "MultiEdit" | "Write" | "Read" | "NotebookEdit" => field("file_path")
+            .or_else(|| field("notebook_path"))
+            .map(|path| format!("`{}`", path)),
+        "WebFetch" => field("url"),
+        "WebSearch" => field("query"),
+        "Grep" | "Glob" => field("pattern").map(|pattern| format!("`{}`", pattern)),
+        "Task" => field("description"),
+        _ => None,
+    };
+    summary.unwrap_or_else(|| {
+        let json = input.to_string();
+        if json.chars().count() > MAX_TOOL_SUMMARY_CHARS {
+            let kept: String = json.chars().take(MAX_TOOL_SUMMARY_CHARS).collect();
+            format!("`{}…`", kept)
+        } else {
+            format!("`{}`", json)
+        }
+    })
+}
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 飞书卡片标题栏可用的颜色模板
+pub const FEISHU_TEMPLATES: [&str; 13] = [
+    "blue",
+    "wathet",
+    "turquoise",
+    "green",
+    "yellow",
+    "orange",
+    "red",
+    "carmine",
+    "violet",
+    "purple",
+    "indigo",
+    "grey",
+    "default",
+];
+
+/// 各级别的卡片颜色，覆盖渠道的内置颜色；键为级别名，包括自定义级别
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ColorConfig {
+    /// 所有渠道通用的颜色（十六进制，不含#）
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub levels: HashMap<String, String>,
+    /// 按渠道覆盖，例如飞书使用模板名 `red`、`orange`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub channels: HashMap<String, HashMap<String, String>>,
+}
+
+impl ColorConfig {
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty() && self.channels.is_empty()
+    }
+
+    /// 配置中该渠道该级别的颜色；渠道覆盖优先于通用设置，都没有时为None
+    pub fn get(&self, channel: &str, level: &str) -> Option<&str> {
+        self.channels
+            .get(channel)
+            .and_then(|colors| colors.get(level))
+            .or_else(|| self.levels.get(level))
+            .map(String::as_str)
+    }
+}
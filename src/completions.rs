@@ -0,0 +1,84 @@
+use crate::config::Config;
+use crate::validate;
+use clap::builder::PossibleValuesParser;
+use clap::{Arg, Command};
+use clap_complete::Shell;
+use std::io::Write;
+
+/// 取值为事件名的参数：(子命令, 参数)
+const EVENT_ARGS: [(&str, &str); 3] = [("send", "event"), ("watch", "event"), ("history", "event")];
+
+/// 取值为渠道名的参数
+const CHANNEL_ARGS: [(&str, &str); 5] = [
+    ("send", "channels"),
+    ("watch", "channels"),
+    ("watch-session", "channels"),
+    ("test", "channel"),
+    ("health", "channels"),
+];
+
+/// 配置中出现的事件名：`notifications` 的键，以及规则、升级策略和周期任务中不含通配符的事件
+pub fn event_names(config: &Config) -> Vec<String> {
+    let patterns = config
+        .rules
+        .iter()
+        .filter_map(|rule| rule.event.as_ref())
+        .chain(config.escalations.iter().filter_map(|e| e.event.as_ref()))
+        .filter(|event| !event.contains(['*', '?']));
+    let mut events: Vec<String> = config
+        .notifications
+        .keys()
+        .chain(patterns)
+        .chain(config.recurring.iter().map(|job| &job.event))
+        .cloned()
+        .collect();
+    events.sort();
+    events.dedup();
+    events
+}
+
+/// 已配置的渠道名，包括未启用的
+pub fn channel_names(config: &Config) -> Vec<String> {
+    let mut channels: Vec<String> = validate::configured_channels(config)
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    channels.sort();
+    channels.dedup();
+    channels
+}
+
+/// 把候选值加到参数上，保留参数的默认值
+fn with_candidates(arg: Arg, names: &[String]) -> Arg {
+    let mut values: Vec<String> = names.to_vec();
+    for default in arg.get_default_values() {
+        let default = default.to_string_lossy().into_owned();
+        if !values.contains(&default) {
+            values.push(default);
+        }
+    }
+    arg.value_parser(PossibleValuesParser::new(values))
+}
+
+/// 生成 `shell` 的补全脚本；有 `config` 时事件和渠道参数补全其中的名称。
+/// 候选值只用于补全，命令行解析不受限制
+pub fn generate(shell: Shell, mut command: Command, config: Option<&Config>, out: &mut dyn Write) {
+    if let Some(config) = config {
+        let targets = [
+            (&EVENT_ARGS[..], event_names(config)),
+            (&CHANNEL_ARGS[..], channel_names(config)),
+        ];
+        for (args, names) in targets {
+            if names.is_empty() {
+                continue;
+            }
+            for (subcommand, arg) in args {
+                command = command.mut_subcommand(subcommand, |sub| {
+                    sub.mut_arg(arg, |arg| with_candidates(arg, &names))
+                });
+            }
+        }
+    }
+    let name = command.get_name().to_string();
+    clap_complete::generate(shell, &mut command, name, out);
+}
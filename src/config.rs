@@ -1,14 +1,149 @@
+use crate::approval::ApprovalConfig;
+use crate::burst::BurstConfig;
+use crate::callback::CallbackConfig;
+use crate::color::ColorConfig;
+use crate::dedup::DedupConfig;
+use crate::digest::DigestConfig;
+use crate::encryption::{self, Key};
+use crate::env;
+use crate::error::NotifierError;
+use crate::escalation::EscalationPolicy;
+use crate::extract::FieldMapping;
+use crate::git::GitConfig;
+use crate::grpc::GrpcConfig;
+use crate::http::HttpConfig;
+use crate::i18n::{self, Locale};
+use crate::level::{self, CustomLevel, LevelConfig};
+use crate::markdown::Dialect;
+use crate::metrics::MetricsConfig;
+use crate::migrate;
+use crate::notification::Level;
+use crate::offline::OfflineConfig;
+use crate::oncall::OnCall;
+use crate::pricing::ModelPrice;
+use crate::probe::ProbeConfig;
+use crate::progress::ProgressConfig;
+use crate::quiet_hours::QuietHours;
+use crate::recurring::RecurringJob;
+use crate::redact;
+use crate::routing::RoutingRule;
+use crate::secret;
+use crate::severity::SeverityMapping;
+use crate::storm::StormConfig;
+use crate::thread::ThreadingConfig;
+use crate::throttle::ThrottleRule;
+use crate::upload::UploadConfig;
+use crate::validate::{self, Severity};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::OnceLock;
+use tracing::{debug, warn};
+
+/// 随仓库提交的项目配置文件名，合并到用户配置之上
+const PROJECT_CONFIG_FILES: [&str; 4] = [
+    ".claude-notifier.json",
+    ".claude-notifier.toml",
+    ".claude-notifier.yaml",
+    ".claude-notifier.yml",
+];
+
+/// XDG/平台目录下的应用目录名
+const APP_DIR: &str = "claude-notifier";
+
+static CONFIG_FILE: OnceLock<PathBuf> = OnceLock::new();
+static PROFILE: OnceLock<String> = OnceLock::new();
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
+    /// 配置格式版本，旧版本由 `migrate` 升级
+    #[serde(default)]
+    pub version: u32,
     pub channels: ChannelConfig,
     pub notifications: HashMap<String, Vec<String>>,
+    /// 有序路由规则，优先于 `notifications`
+    #[serde(default)]
+    pub rules: Vec<RoutingRule>,
+    #[serde(default)]
+    pub routing_mode: RoutingMode,
     pub quiet_hours: QuietHours,
+    #[serde(default)]
+    pub dedup: DedupConfig,
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerConfig,
+    /// 未确认通知的升级策略，按顺序使用第一条匹配的
+    #[serde(default)]
+    pub escalations: Vec<EscalationPolicy>,
+    #[serde(default)]
+    pub digest: DigestConfig,
+    /// 同一事件短时间内过多时合并为一条
+    #[serde(default)]
+    pub burst: BurstConfig,
+    /// 全局发送速率过高时只发送紧急通知
+    #[serde(default)]
+    pub storm: StormConfig,
+    /// 网络不可达时排队，恢复后补发
+    #[serde(default)]
+    pub offline: OfflineConfig,
+    /// 发送前的联网探测，离线时不等待远程渠道超时
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub probe: Option<ProbeConfig>,
+    /// 按事件限制各渠道的发送频率，按顺序使用第一条匹配的
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub throttle: Vec<ThrottleRule>,
+    /// 按cron表达式周期发送的通知
+    #[serde(default)]
+    pub recurring: Vec<RecurringJob>,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    /// 全局HTTP设置（代理等），渠道的 `http` 设置优先
+    #[serde(default)]
+    pub http: HttpConfig,
+    /// 附件上传目标，供不能直接发送文件的渠道附上链接
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upload: Option<UploadConfig>,
+    /// 逻辑用户名到各平台身份的映射，供 `--mention` 使用
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub users: HashMap<String, UserIdentity>,
+    #[serde(default)]
+    pub threading: ThreadingConfig,
+    /// 卡片按钮回调服务（`serve`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub callbacks: Option<CallbackConfig>,
+    /// `serve` 的gRPC接口，需要以grpc特性编译
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grpc: Option<GrpcConfig>,
+    /// PreToolUse hook的远程审批，需要 `callbacks`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approvals: Option<ApprovalConfig>,
+    /// 子代理、任务完成等事件合并为一条持续更新的消息
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub progress: Option<ProgressConfig>,
+    /// 在git仓库中发送时附上分支、提交等信息
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git: Option<GitConfig>,
+    /// 会话费用估算的模型单价，优先于内置价目表
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pricing: Vec<ModelPrice>,
+    /// 自定义级别，键为级别名
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub levels: HashMap<String, LevelConfig>,
+    /// hook输入中通知字段的位置（jq风格表达式），按顺序使用第一组适用的
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extract: Vec<FieldMapping>,
+    /// hook payload中外部严重程度到级别的映射，按顺序使用第一个命中的
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub severity: Vec<SeverityMapping>,
+    /// 各级别的卡片颜色
+    #[serde(default, skip_serializing_if = "ColorConfig::is_empty")]
+    pub colors: ColorConfig,
+    /// 内置文字的语言（en、zh），不设置时按 `LANG` 推断
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<Locale>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -16,29 +151,111 @@ pub struct ChannelConfig {
     pub teams: Option<TeamConfig>,
     pub feishu: Option<FeishuConfig>,
     pub wechat: Option<WechatConfig>,
+    /// 外部命令渠道，键为可在路由中引用的渠道名
+    #[serde(default)]
+    pub exec: HashMap<String, ExecConfig>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TeamConfig {
+    #[serde(default = "default_true")]
     pub enabled: bool,
     pub webhook: String,
+    #[serde(default, skip_serializing_if = "HttpConfig::is_empty")]
+    pub http: HttpConfig,
     #[serde(default)]
     pub default_channel: String,
+    /// critical通知时@的值班人员
+    #[serde(default, skip_serializing_if = "OnCall::is_empty")]
+    pub on_call: OnCall,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct FeishuConfig {
+    #[serde(default = "default_true")]
     pub enabled: bool,
     pub webhook: String,
+    #[serde(default, skip_serializing_if = "HttpConfig::is_empty")]
+    pub http: HttpConfig,
+    /// 机器人开启签名校验时的密钥
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// 自建应用凭证，上传图片时需要
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_secret: Option<String>,
+    /// 应用机器人所在群，发送附件时需要
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chat_id: Option<String>,
     #[serde(default)]
     pub at_all_on_critical: bool,
+    /// critical通知时@的值班人员
+    #[serde(default, skip_serializing_if = "OnCall::is_empty")]
+    pub on_call: OnCall,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct WechatConfig {
+    #[serde(default = "default_true")]
     pub enabled: bool,
     pub service: WechatServiceType,
     pub key: String, // Server酱的key或PushPlus的token
+    #[serde(default, skip_serializing_if = "HttpConfig::is_empty")]
+    pub http: HttpConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExecConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default = "default_exec_timeout_secs")]
+    pub timeout_secs: u64,
+    /// 消息内容的字节数上限，超出时截断（例如短信为160）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_content_bytes: Option<usize>,
+    /// 命令接收的markdown方言（markdown、teams、lark、wecom、plain），默认原样传递
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub markdown: Option<Dialect>,
+    /// 命令需要联网（例如短信网关），联网探测失败时和webhook渠道一样跳过
+    #[serde(default)]
+    pub remote: bool,
+    /// critical通知时@的值班人员
+    #[serde(default, skip_serializing_if = "OnCall::is_empty")]
+    pub on_call: OnCall,
+}
+
+/// 用户在各平台上的身份，发送时按渠道选用
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct UserIdentity {
+    /// Teams用户主体名（UPN），通常是邮箱
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub teams: Option<String>,
+    /// 飞书open_id（ou_开头）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feishu: Option<String>,
+    /// 企业微信userid
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wecom: Option<String>,
+    /// 手机号，供短信等外部命令渠道使用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phone: Option<String>,
+}
+
+impl UserIdentity {
+    pub fn is_empty(&self) -> bool {
+        self.teams.is_none()
+            && self.feishu.is_none()
+            && self.wecom.is_none()
+            && self.phone.is_none()
+    }
+}
+
+fn default_exec_timeout_secs() -> u64 {
+    10
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -48,11 +265,53 @@ pub enum WechatServiceType {
     PushPlus,
 }
 
+/// 路由规则的匹配方式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RoutingMode {
+    /// 使用第一条命中的规则
+    #[default]
+    FirstMatch,
+    /// 合并所有命中规则的渠道
+    Accumulate,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct QuietHours {
+pub struct CircuitBreakerConfig {
+    #[serde(default = "default_true")]
     pub enabled: bool,
-    pub start: String,
-    pub end: String,
+    /// 连续失败多少次后熔断
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+    /// 熔断后多少秒再尝试恢复
+    #[serde(default = "default_cooldown_secs")]
+    pub cooldown_secs: i64,
+    /// 熔断时的备用渠道，例如 {"teams": "feishu"}
+    #[serde(default)]
+    pub fallback: HashMap<String, String>,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            failure_threshold: default_failure_threshold(),
+            cooldown_secs: default_cooldown_secs(),
+            fallback: HashMap::new(),
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_failure_threshold() -> u32 {
+    3
+}
+
+fn default_cooldown_secs() -> i64 {
+    300
 }
 
 impl Default for Config {
@@ -81,48 +340,637 @@ impl Default for Config {
         notifications.insert("daily_report".to_string(), vec!["feishu".to_string()]);
 
         Config {
+            version: migrate::CONFIG_VERSION,
             channels: ChannelConfig {
                 teams: None,
                 feishu: None,
                 wechat: None,
+                exec: HashMap::new(),
             },
             notifications,
+            rules: Vec::new(),
+            routing_mode: RoutingMode::default(),
             quiet_hours: QuietHours {
                 enabled: true,
                 start: "22:00".to_string(),
                 end: "08:00".to_string(),
+                channels: HashMap::new(),
+                exempt_events: Vec::new(),
+                suppress_events: Vec::new(),
+                days: Vec::new(),
+                holidays: Vec::new(),
+                holiday_calendar: None,
             },
+            dedup: DedupConfig::default(),
+            circuit_breaker: CircuitBreakerConfig::default(),
+            escalations: Vec::new(),
+            digest: DigestConfig::default(),
+            burst: BurstConfig::default(),
+            storm: StormConfig::default(),
+            offline: OfflineConfig::default(),
+            probe: None,
+            throttle: Vec::new(),
+            recurring: Vec::new(),
+            metrics: MetricsConfig::default(),
+            http: HttpConfig::default(),
+            upload: None,
+            users: HashMap::new(),
+            threading: ThreadingConfig::default(),
+            callbacks: None,
+            grpc: None,
+            approvals: None,
+            progress: None,
+            git: None,
+            pricing: Vec::new(),
+            levels: HashMap::new(),
+            extract: Vec::new(),
+            severity: Vec::new(),
+            colors: ColorConfig::default(),
+            locale: None,
+        }
+    }
+}
+
+/// 配置文件格式，按扩展名识别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl ConfigFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ConfigFormat::Json => "json",
+            ConfigFormat::Toml => "toml",
+            ConfigFormat::Yaml => "yaml",
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "json" => Some(ConfigFormat::Json),
+            "toml" => Some(ConfigFormat::Toml),
+            "yaml" | "yml" => Some(ConfigFormat::Yaml),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn parse(&self, content: &str) -> std::result::Result<Value, String> {
+        match self {
+            ConfigFormat::Json => serde_json::from_str(content).map_err(|e| e.to_string()),
+            ConfigFormat::Toml => toml::from_str(content).map_err(|e| e.to_string()),
+            ConfigFormat::Yaml => serde_yaml::from_str(content).map_err(|e| e.to_string()),
+        }
+    }
+
+    pub(crate) fn serialize<T: Serialize>(&self, value: &T) -> Result<String> {
+        Ok(match self {
+            ConfigFormat::Json => serde_json::to_string_pretty(value)?,
+            ConfigFormat::Toml => toml::to_string_pretty(value)?,
+            ConfigFormat::Yaml => serde_yaml::to_string(value)?,
+        })
+    }
+}
+
+impl FromStr for ConfigFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(ConfigFormat::Json),
+            "toml" => Ok(ConfigFormat::Toml),
+            "yaml" | "yml" => Ok(ConfigFormat::Yaml),
+            _ => Err(anyhow::anyhow!(
+                "Unknown config format '{}' (expected one of: json, toml, yaml)",
+                s
+            )),
         }
     }
 }
 
 impl Config {
+    /// 按名字解析级别，包括配置中的自定义级别
+    pub fn level(&self, name: &str) -> Result<(Level, Option<CustomLevel>)> {
+        level::resolve(&self.levels, name)
+    }
+
+    /// 读取配置文件（不存在时使用默认配置），合并项目配置，展开 `${VAR}`、应用 `CLAUDE_NOTIFIER_*` 覆盖，
+    /// 并解析密钥引用（`keyring:<alias>` 和 `{"command": ...}`）
     pub fn load() -> Result<Self> {
         let config_path = Self::config_path()?;
+        let config_error =
+            |e: String| NotifierError::ConfigError(format!("{}: {}", config_path.display(), e));
 
-        if config_path.exists() {
-            let content = fs::read_to_string(&config_path)?;
-            Ok(serde_json::from_str(&content)?)
+        let mut value = if config_path.exists() {
+            debug!(path = %config_path.display(), "loading configuration");
+            let content = Self::read_file(&config_path)?;
+            let format = ConfigFormat::from_path(&config_path).unwrap_or(ConfigFormat::Json);
+            format.parse(&content).map_err(config_error)?
+        } else if CONFIG_FILE.get().is_some() {
+            // 显式指定的文件不存在多半是路径写错，不静默使用默认配置
+            return Err(config_error("file not found".to_string()).into());
         } else {
-            Ok(Self::default())
+            debug!(path = %config_path.display(), "no configuration file, using defaults");
+            serde_json::to_value(Self::default())?
+        };
+        if !migrate::migrate(&mut value)
+            .map_err(|e| config_error(format!("{:#}", e)))?
+            .is_empty()
+        {
+            debug!("configuration upgraded in memory; run `claude-notifier migrate` to update the file");
         }
+
+        // 用户配置先展开变量、解析密钥，项目配置来自仓库，不能借此读取环境变量或密钥
+        env::expand_vars(&mut value)?;
+        secret::resolve(&mut value)?;
+
+        if let Some(project_path) = Self::project_config_path() {
+            debug!(path = %project_path.display(), "merging project configuration");
+            let content = fs::read_to_string(&project_path)?;
+            let format = ConfigFormat::from_path(&project_path).unwrap_or(ConfigFormat::Json);
+            let mut overlay = format.parse(&content).map_err(|e| {
+                NotifierError::ConfigError(format!("{}: {}", project_path.display(), e))
+            })?;
+            migrate::migrate(&mut overlay)?;
+            if let Some(map) = overlay.as_object_mut() {
+                map.remove("version");
+            }
+            restrict_project(&mut overlay, &project_path);
+            merge(&mut value, overlay);
+        }
+
+        env::apply_overrides(&mut value, std::env::vars());
+        // 环境变量覆盖的值也可以引用密钥
+        secret::resolve(&mut value)?;
+        let config = Self::from_value(
+            value,
+            |path| warn!(key = %path, "unknown configuration key ignored (see `config validate`)"),
+        )
+        .map_err(|e| config_error(e.to_string()))?;
+        i18n::set_locale(config.locale);
+        redact::register(&config);
+        Ok(config)
+    }
+
+    /// 反序列化配置，错误信息带字段路径；未知键交给 `on_unknown`
+    pub(crate) fn from_value(
+        value: Value,
+        mut on_unknown: impl FnMut(String),
+    ) -> std::result::Result<Self, serde_path_to_error::Error<serde_json::Error>> {
+        serde_path_to_error::deserialize(serde_ignored::Deserializer::new(
+            value,
+            &mut |path: serde_ignored::Path| on_unknown(path.to_string()),
+        ))
     }
 
+    /// 保存到当前使用的配置文件（保持其格式）
     pub fn save(&self) -> Result<()> {
         let config_path = Self::config_path()?;
+        let format = ConfigFormat::from_path(&config_path).unwrap_or(ConfigFormat::Json);
+        self.write(&config_path, format)
+    }
+
+    /// 以指定格式保存为 `config.<ext>`，返回文件路径；用 `--config` 指定文件时写入该文件
+    pub fn save_as(&self, format: ConfigFormat) -> Result<PathBuf> {
+        let (config_path, format) = Self::target(format)?;
+        self.write(&config_path, format)?;
+        Ok(config_path)
+    }
+
+    /// `save_as` 写入的文件；指定了 `--config` 时按其扩展名确定格式
+    pub fn target(format: ConfigFormat) -> Result<(PathBuf, ConfigFormat)> {
+        Ok(match CONFIG_FILE.get() {
+            Some(path) => (
+                path.clone(),
+                ConfigFormat::from_path(path).unwrap_or(format),
+            ),
+            None => (
+                Self::config_dir()?.join(format!("config.{}", format.extension())),
+                format,
+            ),
+        })
+    }
+
+    fn write(&self, config_path: &Path, format: ConfigFormat) -> Result<()> {
+        write_config(config_path, &format.serialize(self)?)
+    }
 
-        // 确保目录存在
-        if let Some(parent) = config_path.parent() {
-            fs::create_dir_all(parent)?;
+    /// 读取用户配置文件的文本，加密的文件先解密
+    pub fn read_file(path: &Path) -> Result<String> {
+        let content = fs::read(path)?;
+        if encryption::is_encrypted(&content) {
+            debug!(path = %path.display(), "decrypting configuration");
+            return encryption::decrypt(&content);
         }
+        String::from_utf8(content).map_err(|e| {
+            NotifierError::ConfigError(format!("{}: not UTF-8 text ({})", path.display(), e)).into()
+        })
+    }
 
-        let content = serde_json::to_string_pretty(self)?;
-        fs::write(&config_path, content)?;
+    /// 加密当前使用的配置文件，返回文件路径
+    pub fn encrypt_file(key: &Key) -> Result<PathBuf> {
+        let config_path = Self::existing_path()?;
+        let content = fs::read(&config_path)?;
+        if encryption::is_encrypted(&content) {
+            return Err(NotifierError::ConfigError(format!(
+                "{} is already encrypted",
+                config_path.display()
+            ))
+            .into());
+        }
+        let content = String::from_utf8(content).map_err(|e| {
+            NotifierError::ConfigError(format!("{}: not UTF-8 text ({})", config_path.display(), e))
+        })?;
+        write_atomic(&config_path, &encryption::encrypt(&content, key)?)?;
+        Ok(config_path)
+    }
+
+    /// 把当前使用的配置文件解密为明文，返回文件路径
+    pub fn decrypt_file() -> Result<PathBuf> {
+        let config_path = Self::existing_path()?;
+        if !encryption::is_encrypted(&fs::read(&config_path)?) {
+            return Err(NotifierError::ConfigError(format!(
+                "{} is not encrypted",
+                config_path.display()
+            ))
+            .into());
+        }
+        write_atomic(&config_path, &Self::read_file(&config_path)?)?;
+        Ok(config_path)
+    }
+
+    fn existing_path() -> Result<PathBuf> {
+        let config_path = Self::config_path()?;
+        if !config_path.exists() {
+            return Err(NotifierError::ConfigError(format!(
+                "{} does not exist",
+                config_path.display()
+            ))
+            .into());
+        }
+        Ok(config_path)
+    }
+
+    /// 读取配置文件原始内容（不展开环境变量和密钥），文件不存在时为默认配置
+    pub fn read_raw() -> Result<(PathBuf, ConfigFormat, Value)> {
+        let config_path = Self::config_path()?;
+        let format = ConfigFormat::from_path(&config_path).unwrap_or(ConfigFormat::Json);
+        let value = if config_path.exists() {
+            let content = Self::read_file(&config_path)?;
+            format.parse(&content).map_err(|e| {
+                NotifierError::ConfigError(format!("{}: {}", config_path.display(), e))
+            })?
+        } else {
+            serde_json::to_value(Self::default())?
+        };
+        Ok((config_path, format, value))
+    }
+
+    /// 原子写入配置文件原始内容
+    pub fn write_raw(path: &Path, format: ConfigFormat, value: &Value) -> Result<()> {
+        write_config(path, &format.serialize(value)?)
+    }
+
+    /// 修改配置文件中点分路径处的值，校验通过后原子写回，返回写入的文件
+    ///
+    /// 值按原有类型转换（见 `CLAUDE_NOTIFIER_*` 覆盖）；新增的值按JSON解析，失败时作为字符串。
+    /// 修改引入新的校验错误时不写入。
+    pub fn set_raw(path: &str, raw: &str) -> Result<PathBuf> {
+        let (config_path, format, mut value) = Self::read_raw()?;
+        let before = validate::validate_content(&format.serialize(&value)?, format);
+
+        set_path(&mut value, path, raw)?;
+        let content = format.serialize(&value)?;
+        let new_errors: Vec<String> = validate::validate_content(&content, format)
+            .into_iter()
+            .filter(|d| d.severity == Severity::Error)
+            .filter(|d| {
+                !before
+                    .iter()
+                    .any(|b| b.path == d.path && b.message == d.message)
+            })
+            .map(|d| d.to_string())
+            .collect();
+        if !new_errors.is_empty() {
+            return Err(NotifierError::ConfigError(format!(
+                "Not saved, the change makes the configuration invalid:\n{}",
+                new_errors.join("\n")
+            ))
+            .into());
+        }
+
+        write_config(&config_path, &content)?;
+        Ok(config_path)
+    }
+
+    /// 配置文件路径：`--config` 指定的文件，否则依次查找 config.json、config.toml、
+    /// config.yaml、config.yml，都不存在时为config.json
+    pub fn config_path() -> Result<PathBuf> {
+        if let Some(path) = CONFIG_FILE.get() {
+            return Ok(path.clone());
+        }
+        let dir = Self::config_dir()?;
+        Ok(["config.json", "config.toml", "config.yaml", "config.yml"]
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.exists())
+            .unwrap_or_else(|| dir.join("config.json")))
+    }
+
+    /// 影响配置的文件（用户配置和项目配置），守护进程据此判断是否需要重新加载
+    pub fn source_files() -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = Self::config_path().into_iter().collect();
+        files.extend(Self::project_config_path());
+        files
+    }
+
+    /// 从当前目录向上查找项目配置 `.claude-notifier.{json,toml,yaml,yml}`
+    pub fn project_config_path() -> Option<PathBuf> {
+        let cwd = std::env::current_dir().ok()?;
+        cwd.ancestors().find_map(|dir| {
+            PROJECT_CONFIG_FILES
+                .iter()
+                .map(|name| dir.join(name))
+                .find(|path| path.is_file())
+        })
+    }
+
+    /// 配置文件、模板和插件所在目录，使用profile时为 `profiles/<name>` 子目录
+    pub fn config_dir() -> Result<PathBuf> {
+        Ok(with_profile(base_dirs()?.0))
+    }
+
+    /// 运行状态文件（历史、去重、队列、审计日志等）所在目录
+    pub fn data_dir() -> Result<PathBuf> {
+        Ok(with_profile(base_dirs()?.1))
+    }
+
+    /// 使用指定的配置文件（`--config`），运行状态仍在数据目录中
+    pub fn use_config_file(path: PathBuf) {
+        let _ = CONFIG_FILE.set(path);
+    }
+
+    /// 使用独立的profile（`--profile`），配置和运行状态都与默认设置分开
+    pub fn use_profile(name: &str) -> Result<()> {
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(NotifierError::ConfigError(format!(
+                "Invalid profile name '{}' (use letters, digits, '-' and '_')",
+                name
+            ))
+            .into());
+        }
+        let _ = PROFILE.set(name.to_string());
         Ok(())
     }
 
-    fn config_path() -> Result<PathBuf> {
-        let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Cannot find home directory"))?;
-        Ok(home.join(".claude").join("notifiers").join("config.json"))
+    /// 渠道配置的值班人员
+    pub fn on_call(&self, channel: &str) -> Option<&OnCall> {
+        let on_call = match channel {
+            "teams" => &self.channels.teams.as_ref()?.on_call,
+            "feishu" => &self.channels.feishu.as_ref()?.on_call,
+            name => &self.channels.exec.get(name)?.on_call,
+        };
+        Some(on_call).filter(|on_call| !on_call.is_empty())
+    }
+}
+
+/// 配置目录和状态目录：旧的 `~/.claude/notifiers/` 存在时两者都用它；否则按平台约定，
+/// Linux为 `$XDG_CONFIG_HOME/claude-notifier` 和 `$XDG_STATE_HOME/claude-notifier`，
+/// Windows为 `%APPDATA%\claude-notifier` 和 `%LOCALAPPDATA%\claude-notifier`
+fn base_dirs() -> Result<(PathBuf, PathBuf)> {
+    let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Cannot find home directory"))?;
+    let legacy = home.join(".claude").join("notifiers");
+    if legacy.is_dir() {
+        return Ok((legacy.clone(), legacy));
+    }
+
+    // 显式设置的XDG变量在所有平台上都生效（macOS上dirs不读取它们）
+    let xdg = |name: &str| {
+        std::env::var_os(name)
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+    };
+    let config = xdg("XDG_CONFIG_HOME")
+        .or_else(dirs::config_dir)
+        .unwrap_or_else(|| home.join(".config"));
+    let state = xdg("XDG_STATE_HOME")
+        .or_else(dirs::state_dir)
+        .or_else(dirs::data_local_dir)
+        .unwrap_or_else(|| home.join(".local").join("state"));
+    Ok((config.join(APP_DIR), state.join(APP_DIR)))
+}
+
+fn with_profile(dir: PathBuf) -> PathBuf {
+    match PROFILE.get() {
+        Some(profile) => dir.join("profiles").join(profile),
+        None => dir,
+    }
+}
+
+/// 写入用户配置文件；原文件是加密的则以同样的方式加密
+fn write_config(path: &Path, content: &str) -> Result<()> {
+    let existing = fs::read(path).unwrap_or_default();
+    if encryption::is_encrypted(&existing) {
+        let key = encryption::key_of(&existing)?;
+        return write_atomic(path, &encryption::encrypt(content, &key)?);
+    }
+    write_atomic(path, content)
+}
+
+/// 先写同目录临时文件再重命名，中断时不会留下半个配置文件
+fn write_atomic(path: &Path, content: &str) -> Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(dir)?;
+
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("config");
+    let tmp = dir.join(format!(".{}.{}.tmp", name, std::process::id()));
+    fs::write(&tmp, content)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// 把项目配置合并到用户配置上：对象逐键合并，其他值（包括数组）整体替换
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) if existing.is_object() => merge(existing, value),
+                    _ => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// 项目配置可以设置的顶层键：事件映射、路由规则（含内联模板）、去重和限流。
+/// 渠道地址、凭证、HTTP代理、指标文件、回调等会把用户的密钥发往别处或写本地文件，只认用户配置
+const PROJECT_KEYS: &[&str] = &[
+    "notifications",
+    "rules",
+    "routing_mode",
+    "dedup",
+    "throttle",
+];
+
+/// 项目配置来自仓库，只保留 [`PROJECT_KEYS`]，其中也不能有密钥命令，
+/// 或引用环境变量（`${VAR}`、模板的 `get_env`）和密钥（`keyring:`）
+fn restrict_project(value: &mut Value, source: &Path) {
+    if let Some(map) = value.as_object_mut() {
+        map.retain(|key, _| {
+            let allowed = PROJECT_KEYS.contains(&key.as_str());
+            if !allowed {
+                warn!(path = %source.display(), key = %key, "setting is not allowed in project configuration; ignored");
+            }
+            allowed
+        });
+    }
+
+    fn strip(value: &mut Value, source: &Path) {
+        match value {
+            Value::Object(map) => {
+                map.retain(|key, item| {
+                    let is_command = secret::secret_command(item).is_some();
+                    if is_command {
+                        warn!(path = %source.display(), key = %key, "secret commands are not allowed in project configuration; ignored");
+                    }
+                    let is_reference = is_reference(item);
+                    if is_reference {
+                        warn!(path = %source.display(), key = %key, "environment variables and keyring secrets are not allowed in project configuration; ignored");
+                    }
+                    !is_command && !is_reference
+                });
+                map.values_mut().for_each(|item| strip(item, source));
+            }
+            Value::Array(items) => {
+                items.retain(|item| secret::secret_command(item).is_none() && !is_reference(item));
+                items.iter_mut().for_each(|item| strip(item, source));
+            }
+            _ => {}
+        }
+    }
+    strip(value, source);
+}
+
+/// 引用环境变量或密钥的字符串
+fn is_reference(value: &Value) -> bool {
+    value.as_str().is_some_and(|s| {
+        s.contains("${") || s.contains("get_env") || s.starts_with(secret::KEYRING_PREFIX)
+    })
+}
+
+fn path_segments(path: &str) -> Vec<&str> {
+    path.split('.').filter(|s| !s.is_empty()).collect()
+}
+
+/// 按点分路径（数组用下标，例如 `rules.0.channels`）取值
+pub fn get_path<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path_segments(path)
+        .into_iter()
+        .try_fold(value, |node, key| match node {
+            Value::Object(map) => map.get(key),
+            Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+            _ => None,
+        })
+}
+
+/// 按点分路径设置值，缺少的对象会被创建，数组下标等于长度时追加
+pub fn set_path(value: &mut Value, path: &str, raw: &str) -> Result<()> {
+    let segments = path_segments(path);
+    let Some((last, parents)) = segments.split_last() else {
+        return Err(anyhow::anyhow!("Empty config path"));
+    };
+    let invalid =
+        |key: &str| anyhow::anyhow!("Cannot set '{}': '{}' is not an object or array", path, key);
+
+    let mut node = value;
+    for key in parents {
+        if node.is_null() {
+            *node = Value::Object(Default::default());
+        }
+        node = match node {
+            Value::Object(map) => map
+                .entry(key.to_string())
+                .or_insert_with(|| Value::Object(Default::default())),
+            Value::Array(items) => key
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| items.get_mut(index))
+                .ok_or_else(|| invalid(key))?,
+            _ => return Err(invalid(key)),
+        };
+    }
+
+    if node.is_null() {
+        *node = Value::Object(Default::default());
+    }
+    match node {
+        Value::Object(map) => {
+            let converted = env::convert(raw, map.get(*last).unwrap_or(&Value::Null));
+            map.insert(last.to_string(), converted);
+        }
+        Value::Array(items) => {
+            let index: usize = last.parse().map_err(|_| invalid(last))?;
+            match index.cmp(&items.len()) {
+                std::cmp::Ordering::Less => items[index] = env::convert(raw, &items[index]),
+                std::cmp::Ordering::Equal => items.push(env::convert(raw, &Value::Null)),
+                std::cmp::Ordering::Greater => {
+                    return Err(anyhow::anyhow!(
+                        "Index {} out of range in '{}'",
+                        index,
+                        path
+                    ))
+                }
+            }
+        }
+        _ => return Err(invalid(last)),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn project_config_keeps_only_routing_settings() {
+        let mut overlay = json!({
+            "channels": { "feishu": { "webhook": "https://attacker.example/hook" } },
+            "http": { "proxy": "http://attacker.example:8080" },
+            "metrics": { "enabled": true, "textfile": "/home/user/.bashrc" },
+            "callbacks": { "command": "sh" },
+            "approvals": { "on_timeout": "allow" },
+            "notifications": { "deploy": ["teams"] },
+            "rules": [
+                { "event": "leak", "channels": ["teams"], "template": "{{ get_env(name=\"TOKEN\") }}" },
+                { "event": "ci_*", "channels": ["${TARGET}"] }
+            ],
+            "dedup": { "window_secs": 60 }
+        });
+        restrict_project(&mut overlay, Path::new(".claude-notifier.json"));
+        assert_eq!(
+            overlay,
+            json!({
+                "notifications": { "deploy": ["teams"] },
+                "rules": [
+                    { "event": "leak", "channels": ["teams"] },
+                    { "event": "ci_*", "channels": [] }
+                ],
+                "dedup": { "window_secs": 60 }
+            })
+        );
     }
 }
@@ -0,0 +1,224 @@
+use crate::notification::Notification;
+use crate::routing::glob_match;
+use crate::store;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const STATE_FILE: &str = "dedup.json";
+
+/// 去重设置
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DedupConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// 相同消息在多少秒内只发送一次
+    #[serde(default = "default_window_secs")]
+    pub window_secs: i64,
+    /// 组成去重键的字段
+    #[serde(default = "default_key")]
+    pub key: Vec<DedupKeyPart>,
+    /// 按事件覆盖，按顺序使用第一条匹配的
+    #[serde(default)]
+    pub overrides: Vec<DedupOverride>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DedupKeyPart {
+    Event,
+    Title,
+    /// 完整内容的哈希
+    Content,
+    Level,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DedupOverride {
+    /// 事件名通配符
+    pub event: String,
+    /// 为0时不去重
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window_secs: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<Vec<DedupKeyPart>>,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            window_secs: default_window_secs(),
+            key: default_key(),
+            overrides: Vec::new(),
+        }
+    }
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_window_secs() -> i64 {
+    300
+}
+
+fn default_key() -> Vec<DedupKeyPart> {
+    vec![
+        DedupKeyPart::Event,
+        DedupKeyPart::Title,
+        DedupKeyPart::Content,
+    ]
+}
+
+impl DedupConfig {
+    /// 计算通知的去重键和窗口；不需要去重时返回None
+    pub fn key_for(&self, notification: &Notification) -> Option<(String, i64)> {
+        if !self.enabled {
+            return None;
+        }
+
+        let rule = self
+            .overrides
+            .iter()
+            .find(|o| glob_match(&o.event, &notification.event));
+        let window = rule.and_then(|o| o.window_secs).unwrap_or(self.window_secs);
+        let parts = rule.and_then(|o| o.key.as_ref()).unwrap_or(&self.key);
+        if window <= 0 {
+            return None;
+        }
+
+        if let Some(key) = &notification.dedup_key {
+            return Some((format!("key:{}", key), window));
+        }
+
+        let key = parts
+            .iter()
+            .map(|part| match part {
+                DedupKeyPart::Event => notification.event.clone(),
+                DedupKeyPart::Title => notification.title.clone(),
+                DedupKeyPart::Content => format!("{:016x}", fnv1a(&notification.content)),
+                DedupKeyPart::Level => notification.level_name().to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(":");
+        Some((key, window))
+    }
+
+    /// 缓存需要保留的最长时间
+    pub fn max_window(&self) -> i64 {
+        self.overrides
+            .iter()
+            .filter_map(|o| o.window_secs)
+            .fold(self.window_secs, i64::max)
+    }
+}
+
+/// 跨进程稳定的字符串哈希
+fn fnv1a(s: &str) -> u64 {
+    s.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// 去重缓存，记录每个消息键最后发送的时间；持久化以便多次CLI调用之间生效
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DedupCache {
+    sent: HashMap<String, i64>,
+}
+
+impl DedupCache {
+    /// 并发的hook之间对缓存加锁，持有期间完成读取、检查和写入
+    pub fn lock() -> Result<store::Lock> {
+        store::lock(STATE_FILE)
+    }
+
+    /// 读取缓存并清理超过 `max_age` 秒的记录
+    pub fn load(now: i64, max_age: i64) -> Result<Self> {
+        let mut cache: Self = store::load(STATE_FILE)?;
+        cache.sent.retain(|_, &mut at| now - at < max_age);
+        Ok(cache)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        store::save(STATE_FILE, self)
+    }
+
+    /// 窗口内已发送过返回false，否则记录本次发送并返回true
+    pub fn check_and_record(&mut self, key: &str, now: i64, window: i64) -> bool {
+        if let Some(&last_sent) = self.sent.get(key) {
+            if now - last_sent < window {
+                return false;
+            }
+        }
+        self.sent.insert(key.to_string(), now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notification::Level;
+
+    #[test]
+    fn cache_suppresses_repeats_within_window() {
+        let mut cache = DedupCache::default();
+        assert!(cache.check_and_record("build:failed", 1000, 300));
+        assert!(!cache.check_and_record("build:failed", 1299, 300));
+        assert!(cache.check_and_record("build:other", 1299, 300));
+        assert!(cache.check_and_record("build:failed", 1300, 300));
+    }
+
+    #[test]
+    fn key_uses_configured_parts_and_overrides() {
+        let config = DedupConfig {
+            overrides: vec![
+                DedupOverride {
+                    event: "ci_*".to_string(),
+                    window_secs: Some(60),
+                    key: Some(vec![DedupKeyPart::Event, DedupKeyPart::Level]),
+                },
+                DedupOverride {
+                    event: "progress".to_string(),
+                    window_secs: Some(0),
+                    key: None,
+                },
+            ],
+            ..DedupConfig::default()
+        };
+        let a = Notification::new("build", "Build", "log a", Level::Info);
+        let b = Notification::new("build", "Build", "log b", Level::Info);
+        let (key_a, window) = config.key_for(&a).unwrap();
+        assert_eq!(window, 300);
+        assert_ne!(key_a, config.key_for(&b).unwrap().0);
+
+        let ci = Notification::new("ci_lint", "Lint", "anything", Level::Warning);
+        assert_eq!(
+            config.key_for(&ci),
+            Some(("ci_lint:warning".to_string(), 60))
+        );
+
+        let progress = Notification::new("progress", "t", "c", Level::Info);
+        assert_eq!(config.key_for(&progress), None);
+        assert_eq!(config.max_window(), 300);
+    }
+
+    #[test]
+    fn explicit_key_replaces_composed_key() {
+        let config = DedupConfig::default();
+        let mut first = Notification::new("ci", "Job 1 failed", "log", Level::Critical);
+        let mut retry = Notification::new("ci", "Job 1 failed again", "other log", Level::Critical);
+        first.dedup_key = Some("job-1".to_string());
+        retry.dedup_key = Some("job-1".to_string());
+        assert_eq!(config.key_for(&first), config.key_for(&retry));
+        assert_eq!(config.key_for(&first).unwrap().0, "key:job-1");
+
+        // 窗口为0的事件即使有去重键也不去重
+        let config = DedupConfig {
+            window_secs: 0,
+            ..DedupConfig::default()
+        };
+        assert_eq!(config.key_for(&first), None);
+    }
+}
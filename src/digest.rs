@@ -0,0 +1,170 @@
+use crate::i18n;
+use crate::notification::{Level, Notification};
+use crate::routing::glob_match;
+use crate::store;
+use anyhow::Result;
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const STATE_FILE: &str = "digest.json";
+
+/// 摘要模式 - 低优先级通知先累积，定期合并为一条发送
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DigestConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 参与汇总的级别
+    #[serde(default = "default_levels")]
+    pub levels: Vec<Level>,
+    /// 参与汇总的事件通配符，为空时所有事件
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// 最早一条累积超过多少分钟后发送
+    #[serde(default = "default_interval_minutes")]
+    pub interval_minutes: i64,
+    /// 累积到多少条时立即发送
+    #[serde(default = "default_max_messages")]
+    pub max_messages: usize,
+}
+
+impl Default for DigestConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            levels: default_levels(),
+            events: Vec::new(),
+            interval_minutes: default_interval_minutes(),
+            max_messages: default_max_messages(),
+        }
+    }
+}
+
+fn default_levels() -> Vec<Level> {
+    vec![Level::Info, Level::Success]
+}
+
+fn default_interval_minutes() -> i64 {
+    30
+}
+
+fn default_max_messages() -> usize {
+    20
+}
+
+impl DigestConfig {
+    pub fn matches(&self, notification: &Notification) -> bool {
+        self.enabled
+            && self.levels.contains(&notification.level)
+            && (self.events.is_empty()
+                || self
+                    .events
+                    .iter()
+                    .any(|pattern| glob_match(pattern, &notification.event)))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DigestEntry {
+    pub notification: Notification,
+    /// 路由得到的渠道（可能是备用链）
+    pub channels: Vec<String>,
+    pub at: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DigestStore {
+    entries: Vec<DigestEntry>,
+}
+
+impl DigestStore {
+    pub fn load() -> Result<Self> {
+        store::load(STATE_FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        store::save(STATE_FILE, self)
+    }
+
+    pub fn push(&mut self, entry: DigestEntry) {
+        self.entries.push(entry);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 是否达到发送条件
+    pub fn is_due(&self, config: &DigestConfig, now: i64) -> bool {
+        let oldest = self.entries.iter().map(|e| e.at).min();
+        self.entries.len() >= config.max_messages
+            || oldest.is_some_and(|at| now - at >= config.interval_minutes * 60)
+    }
+
+    /// 取出全部条目，按渠道分组
+    pub fn drain_by_channel(&mut self) -> BTreeMap<String, Vec<DigestEntry>> {
+        by_channel(std::mem::take(&mut self.entries))
+    }
+}
+
+/// 条目按渠道分组，发往多个渠道的条目在每组中各出现一次
+pub fn by_channel(entries: Vec<DigestEntry>) -> BTreeMap<String, Vec<DigestEntry>> {
+    let mut groups: BTreeMap<String, Vec<DigestEntry>> = BTreeMap::new();
+    for entry in entries {
+        for channel in &entry.channels {
+            groups
+                .entry(channel.clone())
+                .or_default()
+                .push(entry.clone());
+        }
+    }
+    groups
+}
+
+/// 把多条通知合并为一条摘要
+pub fn combine(entries: &[DigestEntry]) -> Notification {
+    let level = entries
+        .iter()
+        .map(|e| e.notification.level)
+        .max()
+        .unwrap_or(Level::Info);
+
+    let lines: Vec<String> = entries
+        .iter()
+        .map(|e| {
+            let time = Local
+                .timestamp_opt(e.at, 0)
+                .single()
+                .map(|t| t.format("%H:%M").to_string())
+                .unwrap_or_default();
+            let summary = e.notification.content.lines().next().unwrap_or_default();
+            if summary.is_empty() {
+                format!(
+                    "- `{}` {} **{}**",
+                    time,
+                    e.notification.emoji(),
+                    e.notification.title
+                )
+            } else {
+                format!(
+                    "- `{}` {} **{}**: {}",
+                    time,
+                    e.notification.emoji(),
+                    e.notification.title,
+                    summary
+                )
+            }
+        })
+        .collect();
+
+    Notification::new(
+        "digest",
+        i18n::fill(i18n::strings().digest_title, &[("count", &entries.len())]),
+        lines.join("\n"),
+        level,
+    )
+}
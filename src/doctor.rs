@@ -0,0 +1,613 @@
+use crate::config::{Config, WechatServiceType};
+use crate::http::{self, HttpConfig};
+use crate::notifiers;
+use crate::redact;
+use crate::validate::{self, Severity};
+use chrono::{DateTime, Local, Utc, Weekday};
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// 建立TCP连接的超时
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 飞书签名校验允许的时间偏差
+const FEISHU_MAX_SKEW_SECS: i64 = 3600;
+
+/// 超过此偏差时提醒校准时钟
+const SKEW_WARNING_SECS: i64 = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Status::Ok => "ok",
+            Status::Warning => "warning",
+            Status::Error => "error",
+        })
+    }
+}
+
+/// 一项检查的结论；有问题时 `fix` 给出处理办法
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix: Option<String>,
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.status, self.name, self.message)?;
+        if let Some(fix) = &self.fix {
+            write!(f, "\n    fix: {}", fix)?;
+        }
+        Ok(())
+    }
+}
+
+struct Checks(Vec<Check>);
+
+impl Checks {
+    fn push(&mut self, name: impl Into<String>, status: Status, message: impl Into<String>) {
+        self.0.push(Check {
+            name: name.into(),
+            status,
+            message: message.into(),
+            fix: None,
+        });
+    }
+
+    fn ok(&mut self, name: impl Into<String>, message: impl Into<String>) {
+        self.push(name, Status::Ok, message);
+    }
+
+    fn problem(
+        &mut self,
+        name: impl Into<String>,
+        status: Status,
+        message: impl Into<String>,
+        fix: impl Into<String>,
+    ) {
+        self.push(name, status, message);
+        if let Some(check) = self.0.last_mut() {
+            check.fix = Some(fix.into());
+        }
+    }
+}
+
+/// 检查配置、渠道的连通性、静默时段、时钟和Claude Code的hook注册；
+/// `offline` 时跳过需要网络的检查
+pub fn run(offline: bool) -> Vec<Check> {
+    let mut checks = Checks(Vec::new());
+    check_files(&mut checks);
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            checks.problem(
+                "config",
+                Status::Error,
+                redact::text(&format!("Cannot load the configuration: {:#}", e)),
+                "Fix the error above; nothing can be sent until the configuration loads",
+            );
+            return checks.0;
+        }
+    };
+    check_channels(&config, &mut checks, offline);
+    check_quiet_hours(&config, &mut checks);
+    if !offline {
+        check_clock(&config, &mut checks);
+    }
+    check_claude_hooks(&mut checks);
+    checks.0
+}
+
+fn check_files(checks: &mut Checks) {
+    let files = Config::source_files();
+    for path in &files {
+        let name = format!("config {}", path.display());
+        if !path.exists() {
+            checks.problem(
+                name,
+                Status::Warning,
+                "Does not exist; only environment overrides are used",
+                "Add a channel, e.g. `claude-notifier config set channels.teams.webhook <URL>`",
+            );
+            continue;
+        }
+        let diagnostics = match validate::validate(path) {
+            Ok(diagnostics) => diagnostics,
+            Err(e) => {
+                checks.problem(
+                    name,
+                    Status::Error,
+                    redact::text(&format!("Cannot read: {:#}", e)),
+                    "Check the file's permissions",
+                );
+                continue;
+            }
+        };
+        let errors: Vec<String> = diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .map(ToString::to_string)
+            .collect();
+        let warnings = diagnostics.len() - errors.len();
+        if !errors.is_empty() {
+            checks.problem(
+                name,
+                Status::Error,
+                errors.join("; "),
+                "Correct these entries, then re-run `claude-notifier config validate`",
+            );
+        } else if warnings > 0 {
+            checks.problem(
+                name,
+                Status::Warning,
+                format!("{} warning(s)", warnings),
+                "Run `claude-notifier config validate` to see them",
+            );
+        } else {
+            checks.ok(name, "Valid");
+        }
+    }
+}
+
+/// 不发送消息的渠道检查结果
+pub enum Probe {
+    /// `elapsed` 为建立连接的耗时，exec渠道没有
+    Ok {
+        message: String,
+        elapsed: Option<Duration>,
+    },
+    Failed {
+        message: String,
+        fix: String,
+    },
+}
+
+/// 已启用的渠道，内置渠道在前
+pub fn enabled_channels(config: &Config) -> Vec<String> {
+    let channels = &config.channels;
+    let mut exec: Vec<&String> = channels
+        .exec
+        .iter()
+        .filter(|(_, c)| c.enabled)
+        .map(|(name, _)| name)
+        .collect();
+    exec.sort();
+    [
+        channels
+            .teams
+            .as_ref()
+            .filter(|c| c.enabled)
+            .map(|_| "teams"),
+        channels
+            .feishu
+            .as_ref()
+            .filter(|c| c.enabled)
+            .map(|_| "feishu"),
+        channels
+            .wechat
+            .as_ref()
+            .filter(|c| c.enabled)
+            .map(|_| "wechat"),
+    ]
+    .into_iter()
+    .flatten()
+    .chain(exec.into_iter().map(String::as_str))
+    .map(String::from)
+    .collect()
+}
+
+/// HTTP渠道的请求地址和HTTP设置
+fn endpoint<'a>(config: &'a Config, channel: &str) -> Option<(String, &'a HttpConfig)> {
+    let channels = &config.channels;
+    match channel {
+        "teams" => {
+            let teams = channels.teams.as_ref()?;
+            Some((teams.webhook.clone(), &teams.http))
+        }
+        "feishu" => {
+            let feishu = channels.feishu.as_ref()?;
+            Some((feishu.webhook.clone(), &feishu.http))
+        }
+        "wechat" => {
+            let wechat = channels.wechat.as_ref()?;
+            let url = match wechat.service {
+                WechatServiceType::ServerChan => "https://sctapi.ftqq.com/",
+                WechatServiceType::PushPlus => "http://www.pushplus.plus/send",
+            };
+            Some((url.to_string(), &wechat.http))
+        }
+        _ => None,
+    }
+}
+
+/// 不发送消息地检查渠道：HTTP渠道解析并连接webhook的主机（有代理时连接代理），
+/// exec渠道检查命令是否存在；`offline` 时只检查webhook的格式。渠道未配置时为None
+pub fn probe(config: &Config, channel: &str, offline: bool) -> Option<Probe> {
+    if let Some(exec) = config.channels.exec.get(channel) {
+        return Some(match find_command(&exec.command) {
+            Some(_) => Probe::Ok {
+                message: format!("Command `{}` found", exec.command),
+                elapsed: None,
+            },
+            None => Probe::Failed {
+                message: format!("Command `{}` not found", exec.command),
+                fix: format!(
+                    "Install it or use an absolute path in channels.exec.{}.command",
+                    channel
+                ),
+            },
+        });
+    }
+    let (url, channel_http) = endpoint(config, channel)?;
+    let url = match reqwest::Url::parse(&url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") && url.has_host() => url,
+        _ => {
+            return Some(Probe::Failed {
+                message: "Webhook is not an http(s) URL".to_string(),
+                fix: format!(
+                    "Copy the webhook again from the {} bot settings into channels.{}.webhook",
+                    channel, channel
+                ),
+            })
+        }
+    };
+    if offline {
+        return Some(Probe::Ok {
+            message: "Webhook is a valid URL (reachability not checked)".to_string(),
+            elapsed: None,
+        });
+    }
+    let proxy = proxy(&channel_http.or(&config.http), url.scheme());
+    let target = proxy.as_ref().unwrap_or(&url);
+    let (Some(host), Some(port)) = (target.host_str(), target.port_or_known_default()) else {
+        return None;
+    };
+    let via = match &proxy {
+        Some(_) => format!(" (through proxy {}:{})", host, port),
+        None => String::new(),
+    };
+    Some(match reach(host, port) {
+        Ok(elapsed) => Probe::Ok {
+            message: format!(
+                "{} reachable{} in {} ms",
+                url.host_str().unwrap_or_default(),
+                via,
+                elapsed.as_millis()
+            ),
+            elapsed: Some(elapsed),
+        },
+        Err(e) => Probe::Failed {
+            message: format!("{}{}", e, via),
+            fix: match proxy {
+                Some(_) => "Check that the proxy is running, or set `http.proxy` to `direct`",
+                None => "Check DNS, the firewall or VPN; set `http.proxy` if the network requires a proxy",
+            }
+            .to_string(),
+        },
+    })
+}
+
+fn check_channels(config: &Config, checks: &mut Checks, offline: bool) {
+    let channels = enabled_channels(config);
+    if channels.is_empty() {
+        checks.problem(
+            "channels",
+            Status::Error,
+            "No channel is enabled, so notifications go nowhere",
+            "Configure one, e.g. `claude-notifier config set channels.teams.webhook <URL>`",
+        );
+        return;
+    }
+    for channel in channels {
+        let name = if config.channels.exec.contains_key(&channel) {
+            format!("channels.exec.{}", channel)
+        } else {
+            format!("channels.{}", channel)
+        };
+        match probe(config, &channel, offline) {
+            Some(Probe::Ok { message, .. }) => checks.ok(name, message),
+            Some(Probe::Failed { message, fix }) => {
+                checks.problem(name, Status::Error, message, fix)
+            }
+            None => {}
+        }
+    }
+}
+
+/// 请求实际连接的代理：配置优先，其次是代理环境变量；`direct` 表示直连
+fn proxy(http_config: &HttpConfig, scheme: &str) -> Option<reqwest::Url> {
+    let configured = match http_config.proxy.as_deref() {
+        Some(http::DIRECT) => return None,
+        Some(proxy) => Some(proxy.to_string()),
+        None => None,
+    };
+    let variables: &[&str] = match scheme {
+        "https" => &["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"],
+        _ => &["HTTP_PROXY", "http_proxy", "ALL_PROXY", "all_proxy"],
+    };
+    configured
+        .or_else(|| {
+            variables
+                .iter()
+                .find_map(|name| std::env::var(name).ok().filter(|v| !v.is_empty()))
+        })
+        .and_then(|proxy| reqwest::Url::parse(&proxy).ok())
+}
+
+/// 解析主机名并建立TCP连接，返回连接耗时
+fn reach(host: &str, port: u16) -> Result<Duration, String> {
+    let addrs: Vec<_> = (host, port)
+        .to_socket_addrs()
+        .map_err(|e| format!("Cannot resolve {}: {}", host, e))?
+        .collect();
+    let mut last_error = None;
+    for addr in addrs {
+        let started = Instant::now();
+        match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+            Ok(_) => return Ok(started.elapsed()),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(match last_error {
+        Some(e) => format!("Cannot connect to {}:{}: {}", host, port, e),
+        None => format!("{} has no addresses", host),
+    })
+}
+
+/// 按PATH查找命令；含路径分隔符时直接检查文件
+fn find_command(command: &str) -> Option<PathBuf> {
+    let path = Path::new(command);
+    if path.components().count() > 1 {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+    let extensions: &[&str] = if cfg!(windows) {
+        &["", ".exe", ".cmd", ".bat"]
+    } else {
+        &[""]
+    };
+    std::env::split_paths(&std::env::var_os("PATH")?).find_map(|dir| {
+        extensions
+            .iter()
+            .map(|ext| dir.join(format!("{}{}", command, ext)))
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+fn check_quiet_hours(config: &Config, checks: &mut Checks) {
+    let quiet = &config.quiet_hours;
+    if !quiet.enabled && quiet.channels.values().all(|window| !window.enabled) {
+        return;
+    }
+    if quiet.enabled && quiet.start == quiet.end {
+        checks.problem(
+            "quiet_hours",
+            Status::Warning,
+            format!(
+                "start and end are both {}, so quiet hours last all day",
+                quiet.start
+            ),
+            "Set quiet_hours.end to when notifications should resume",
+        );
+    }
+    let mut weekdays: Vec<u32> = quiet
+        .days
+        .iter()
+        .filter_map(|day| day.parse::<Weekday>().ok())
+        .map(|day| day.num_days_from_monday())
+        .collect();
+    weekdays.sort_unstable();
+    weekdays.dedup();
+    if quiet.enabled && weekdays.len() == 7 {
+        checks.problem(
+            "quiet_hours",
+            Status::Warning,
+            "Every day of the week is listed in quiet_hours.days",
+            "Remove the working days from quiet_hours.days",
+        );
+    }
+    let now = Local::now();
+    let channels = &config.channels;
+    let mut quiet_now: Vec<&str> = [
+        channels.teams.as_ref().map(|_| "teams"),
+        channels.feishu.as_ref().map(|_| "feishu"),
+        channels.wechat.as_ref().map(|_| "wechat"),
+    ]
+    .into_iter()
+    .flatten()
+    .chain(channels.exec.keys().map(String::as_str))
+    .filter(|channel| quiet.is_quiet(channel, &now))
+    .collect();
+    quiet_now.sort_unstable();
+    if quiet_now.is_empty() {
+        checks.ok("quiet_hours", "Not in effect right now");
+    } else {
+        checks.problem(
+            "quiet_hours",
+            Status::Warning,
+            format!(
+                "In effect now for {}; notifications below critical are not sent",
+                quiet_now.join(", ")
+            ),
+            "Test with `send -f`, or turn them off with `claude-notifier config set quiet_hours.enabled false`",
+        );
+    }
+}
+
+/// 飞书校验签名中的时间戳，本机时钟偏差过大时签名的请求会被拒绝
+fn check_clock(config: &Config, checks: &mut Checks) {
+    let Some(feishu) =
+        config.channels.feishu.as_ref().filter(|feishu| {
+            feishu.enabled && feishu.secret.as_ref().is_some_and(|s| !s.is_empty())
+        })
+    else {
+        return;
+    };
+    let Ok(url) = reqwest::Url::parse(&feishu.webhook) else {
+        return;
+    };
+    let client = match feishu.http.or(&config.http).client() {
+        Ok(client) => client,
+        Err(e) => {
+            checks.problem(
+                "clock",
+                Status::Warning,
+                redact::text(&format!("Cannot check: {:#}", e)),
+                "Fix the channels.feishu.http settings",
+            );
+            return;
+        }
+    };
+    let response = notifiers::blocking::runtime()
+        .block_on(async { client.inner.head(url.clone()).send().await });
+    let server_time = response.ok().and_then(|response| {
+        let date = response
+            .headers()
+            .get(reqwest::header::DATE)?
+            .to_str()
+            .ok()?;
+        DateTime::parse_from_rfc2822(date).ok()
+    });
+    let Some(server_time) = server_time else {
+        checks.problem(
+            "clock",
+            Status::Warning,
+            format!(
+                "Cannot read the time from {}",
+                url.host_str().unwrap_or_default()
+            ),
+            "Make sure the system clock is synchronized (e.g. `timedatectl set-ntp true`)",
+        );
+        return;
+    };
+    let skew = Utc::now().timestamp() - server_time.timestamp();
+    let message = format!(
+        "Local clock is {}s {} {}",
+        skew.abs(),
+        if skew >= 0 { "ahead of" } else { "behind" },
+        url.host_str().unwrap_or_default()
+    );
+    let fix = "Synchronize the system clock (e.g. `timedatectl set-ntp true`)";
+    if skew.abs() > FEISHU_MAX_SKEW_SECS {
+        checks.problem(
+            "clock",
+            Status::Error,
+            format!(
+                "{}; Feishu rejects signed requests off by more than an hour",
+                message
+            ),
+            fix,
+        );
+    } else if skew.abs() > SKEW_WARNING_SECS {
+        checks.problem("clock", Status::Warning, message, fix);
+    } else {
+        checks.ok("clock", message);
+    }
+}
+
+/// Claude Code的设置文件：用户级和当前项目的
+fn claude_settings() -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = dirs::home_dir()
+        .map(|home| home.join(".claude").join("settings.json"))
+        .into_iter()
+        .collect();
+    if let Ok(cwd) = std::env::current_dir() {
+        let project = cwd.join(".claude");
+        files.push(project.join("settings.json"));
+        files.push(project.join("settings.local.json"));
+    }
+    files
+}
+
+fn check_claude_hooks(checks: &mut Checks) {
+    let mut registered = Vec::new();
+    for path in claude_settings() {
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let settings: Value = match serde_json::from_str(&content) {
+            Ok(settings) => settings,
+            Err(e) => {
+                checks.problem(
+                    "claude_hooks",
+                    Status::Error,
+                    format!("{} is not valid JSON: {}", path.display(), e),
+                    "Fix the file; Claude Code ignores settings it cannot parse",
+                );
+                continue;
+            }
+        };
+        let Some(hooks) = settings["hooks"].as_object() else {
+            continue;
+        };
+        for (event, matchers) in hooks {
+            let commands = matchers
+                .as_array()
+                .into_iter()
+                .flatten()
+                .flat_map(|matcher| matcher["hooks"].as_array().into_iter().flatten())
+                .filter_map(|hook| hook["command"].as_str())
+                .filter(|command| command.contains("claude-notifier"));
+            for command in commands {
+                registered.push((path.clone(), event.clone(), command.to_string()));
+            }
+        }
+    }
+
+    if registered.is_empty() {
+        checks.problem(
+            "claude_hooks",
+            Status::Warning,
+            "claude-notifier is not registered as a Claude Code hook",
+            "Add `claude-notifier hook` to the Notification and Stop hooks in ~/.claude/settings.json (see the README)",
+        );
+        return;
+    }
+    // 命令中的程序需要能在Claude Code的PATH中找到
+    let mut missing = false;
+    for (path, event, command) in &registered {
+        let program = command.split_whitespace().next().unwrap_or_default();
+        if find_command(program).is_none() {
+            missing = true;
+            checks.problem(
+                "claude_hooks",
+                Status::Error,
+                format!(
+                    "The {} hook in {} runs `{}`, which is not found",
+                    event,
+                    path.display(),
+                    program
+                ),
+                "Use the absolute path of the claude-notifier binary in the hook command",
+            );
+        }
+    }
+    if missing {
+        return;
+    }
+    let mut events: Vec<&str> = registered
+        .iter()
+        .map(|(_, event, _)| event.as_str())
+        .collect();
+    events.sort_unstable();
+    events.dedup();
+    checks.ok(
+        "claude_hooks",
+        format!("Registered for {}", events.join(", ")),
+    );
+}
@@ -0,0 +1,229 @@
+use crate::config::Config;
+use anyhow::Result;
+use std::path::PathBuf;
+
+/// 密钥文件路径，默认为配置目录下的 `config.key`
+pub const KEY_FILE_VAR: &str = "CLAUDE_NOTIFIER_KEY_FILE";
+
+/// 口令加密的配置不在终端中使用时（hook、守护进程）从该变量读取口令
+pub const PASSPHRASE_VAR: &str = "CLAUDE_NOTIFIER_PASSPHRASE";
+
+const KEY_FILE: &str = "config.key";
+
+/// age格式的ASCII armor首行
+const ARMOR_HEADER: &str = "-----BEGIN AGE ENCRYPTED FILE-----";
+
+/// 二进制age文件的开头
+const BINARY_HEADER: &str = "age-encryption.org/v1";
+
+/// 加密配置的方式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Key {
+    /// age（X25519）身份文件，不存在时 `config encrypt` 生成
+    File(PathBuf),
+    /// 口令（scrypt），使用时输入或从 `CLAUDE_NOTIFIER_PASSPHRASE` 读取
+    Passphrase,
+}
+
+/// 内容是否为age加密的文件
+pub fn is_encrypted(content: &[u8]) -> bool {
+    let start = content
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(content.len());
+    let content = &content[start..];
+    content.starts_with(ARMOR_HEADER.as_bytes()) || content.starts_with(BINARY_HEADER.as_bytes())
+}
+
+/// 当前使用的密钥文件
+pub fn key_file() -> Result<PathBuf> {
+    match std::env::var_os(KEY_FILE_VAR) {
+        Some(path) if !path.is_empty() => Ok(PathBuf::from(path)),
+        _ => Ok(Config::config_dir()?.join(KEY_FILE)),
+    }
+}
+
+#[cfg(not(feature = "encryption"))]
+fn unsupported() -> anyhow::Error {
+    crate::error::NotifierError::ConfigError(
+        "Encrypted configuration is not supported by this build (rebuild with --features encryption)"
+            .to_string(),
+    )
+    .into()
+}
+
+#[cfg(not(feature = "encryption"))]
+pub fn decrypt(_ciphertext: &[u8]) -> Result<String> {
+    Err(unsupported())
+}
+
+#[cfg(not(feature = "encryption"))]
+pub fn encrypt(_plaintext: &str, _key: &Key) -> Result<String> {
+    Err(unsupported())
+}
+
+#[cfg(not(feature = "encryption"))]
+pub fn key_of(_ciphertext: &[u8]) -> Result<Key> {
+    Err(unsupported())
+}
+
+#[cfg(not(feature = "encryption"))]
+pub fn generate_key_file(_path: &std::path::Path) -> Result<String> {
+    Err(unsupported())
+}
+
+#[cfg(feature = "encryption")]
+pub use imp::{decrypt, encrypt, generate_key_file, key_of};
+
+#[cfg(feature = "encryption")]
+mod imp {
+    use super::{key_file, Key, PASSPHRASE_VAR};
+    use crate::error::NotifierError;
+    use age::armor::{ArmoredReader, ArmoredWriter, Format};
+    use age::secrecy::{ExposeSecret, SecretString};
+    use age::{Decryptor, Encryptor, Identity, IdentityFile, NoCallbacks, Recipient};
+    use anyhow::{Context, Result};
+    use std::fs;
+    use std::io::{self, IsTerminal, Read, Write};
+    use std::iter;
+    use std::path::Path;
+    use std::sync::OnceLock;
+
+    /// 同一进程中只输入一次口令（例如 `config set` 先解密再加密）
+    static PASSPHRASE: OnceLock<SecretString> = OnceLock::new();
+
+    fn config_error(message: String) -> anyhow::Error {
+        NotifierError::ConfigError(message).into()
+    }
+
+    /// 读取口令：环境变量优先，其次在终端中提示；`confirm` 时输入两次
+    fn passphrase(confirm: bool) -> Result<SecretString> {
+        if let Some(passphrase) = PASSPHRASE.get() {
+            return Ok(passphrase.clone());
+        }
+        let passphrase = match std::env::var(PASSPHRASE_VAR) {
+            Ok(passphrase) if !passphrase.is_empty() => passphrase,
+            // hook等非交互场景不能等待输入
+            _ if !io::stdin().is_terminal() => {
+                return Err(config_error(format!(
+                    "The configuration is encrypted with a passphrase; set {} when not running in a terminal",
+                    PASSPHRASE_VAR
+                )))
+            }
+            _ => {
+                let passphrase = rpassword::prompt_password("Configuration passphrase: ")?;
+                if confirm && rpassword::prompt_password("Repeat the passphrase: ")? != passphrase {
+                    return Err(config_error("The passphrases do not match".to_string()));
+                }
+                if passphrase.is_empty() {
+                    return Err(config_error("The passphrase is empty".to_string()));
+                }
+                passphrase
+            }
+        };
+        let passphrase = SecretString::from(passphrase);
+        let _ = PASSPHRASE.set(passphrase.clone());
+        Ok(passphrase)
+    }
+
+    fn read_key_file(path: &Path) -> Result<IdentityFile<NoCallbacks>> {
+        IdentityFile::from_file(path.to_string_lossy().into_owned()).map_err(|e| {
+            config_error(format!(
+                "Cannot read the key file {}: {} (set {} if it is elsewhere)",
+                path.display(),
+                e,
+                super::KEY_FILE_VAR
+            ))
+        })
+    }
+
+    /// 解密配置文件内容
+    pub fn decrypt(ciphertext: &[u8]) -> Result<String> {
+        let decryptor = Decryptor::new_buffered(ArmoredReader::new(ciphertext))
+            .map_err(|e| config_error(format!("Cannot read the encrypted configuration: {}", e)))?;
+        let mut plaintext = String::new();
+        if decryptor.is_scrypt() {
+            let identity = age::scrypt::Identity::new(passphrase(false)?);
+            decryptor
+                .decrypt(iter::once(&identity as &dyn Identity))
+                .map_err(|e| config_error(format!("Cannot decrypt the configuration: {}", e)))?
+                .read_to_string(&mut plaintext)?;
+        } else {
+            let path = key_file()?;
+            let identities = read_key_file(&path)?
+                .into_identities()
+                .map_err(|e| config_error(format!("{}: {}", path.display(), e)))?;
+            decryptor
+                .decrypt(identities.iter().map(|i| i.as_ref() as &dyn Identity))
+                .map_err(|e| {
+                    config_error(format!(
+                        "Cannot decrypt the configuration with {}: {}",
+                        path.display(),
+                        e
+                    ))
+                })?
+                .read_to_string(&mut plaintext)?;
+        }
+        Ok(plaintext)
+    }
+
+    /// 加密为ASCII armor文本
+    pub fn encrypt(plaintext: &str, key: &Key) -> Result<String> {
+        let encryptor = match key {
+            Key::Passphrase => Encryptor::with_user_passphrase(passphrase(true)?),
+            Key::File(path) => {
+                let recipients = read_key_file(path)?
+                    .to_recipients()
+                    .map_err(|e| config_error(format!("{}: {}", path.display(), e)))?;
+                Encryptor::with_recipients(recipients.iter().map(|r| r.as_ref() as &dyn Recipient))
+                    .map_err(|e| config_error(format!("{}: {}", path.display(), e)))?
+            }
+        };
+        let mut ciphertext = Vec::new();
+        let mut writer = encryptor.wrap_output(ArmoredWriter::wrap_output(
+            &mut ciphertext,
+            Format::AsciiArmor,
+        )?)?;
+        writer.write_all(plaintext.as_bytes())?;
+        writer.finish()?.finish()?;
+        Ok(String::from_utf8(ciphertext).expect("armored output is ASCII"))
+    }
+
+    /// 加密文件使用的方式，重新保存时沿用
+    pub fn key_of(ciphertext: &[u8]) -> Result<Key> {
+        let decryptor = Decryptor::new_buffered(ArmoredReader::new(ciphertext))
+            .map_err(|e| config_error(format!("Cannot read the encrypted configuration: {}", e)))?;
+        Ok(if decryptor.is_scrypt() {
+            Key::Passphrase
+        } else {
+            Key::File(key_file()?)
+        })
+    }
+
+    /// 生成age身份文件（仅所有者可读），返回公钥
+    pub fn generate_key_file(path: &Path) -> Result<String> {
+        let identity = age::x25519::Identity::generate();
+        let recipient = identity.to_public().to_string();
+        let content = format!(
+            "# created: {}\n# public key: {}\n{}\n",
+            chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            recipient,
+            identity.to_string().expose_secret()
+        );
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options
+            .open(path)
+            .and_then(|mut file| file.write_all(content.as_bytes()))
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(recipient)
+    }
+}
@@ -0,0 +1,164 @@
+use crate::encryption;
+use crate::error::NotifierError;
+use anyhow::Result;
+use serde_json::{Map, Value};
+
+/// 环境变量覆盖配置的前缀，例如 `CLAUDE_NOTIFIER_TEAMS_WEBHOOK`
+pub const OVERRIDE_PREFIX: &str = "CLAUDE_NOTIFIER_";
+
+const CHANNEL_NAMES: [&str; 4] = ["teams", "feishu", "wechat", "exec"];
+
+/// 展开配置中所有字符串里的 `${VAR}` 和 `${VAR:-default}`；`$${` 输出字面量 `${`
+pub fn expand_vars(value: &mut Value) -> Result<()> {
+    match value {
+        Value::String(s) if s.contains("${") => *s = expand_str(s)?,
+        Value::Array(items) => {
+            for item in items {
+                expand_vars(item)?;
+            }
+        }
+        Value::Object(map) => {
+            for item in map.values_mut() {
+                expand_vars(item)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn expand_str(s: &str) -> Result<String> {
+    let mut out = String::new();
+    let mut rest = s;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            out.push_str(&rest[..start - 1]);
+            out.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        out.push_str(&rest[..start]);
+
+        let end = rest[start..].find('}').ok_or_else(|| {
+            NotifierError::ConfigError(format!("Unterminated ${{...}} in '{}'", s))
+        })? + start;
+        let expr = &rest[start + 2..end];
+        let (name, default) = match expr.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (expr, None),
+        };
+        // 与shell一致：`:-` 在变量未设置或为空时使用默认值
+        match (std::env::var(name), default) {
+            (Ok(value), Some(default)) if value.is_empty() => out.push_str(default),
+            (Ok(value), _) => out.push_str(&value),
+            (Err(_), Some(default)) => out.push_str(default),
+            (Err(_), None) => {
+                return Err(NotifierError::ConfigError(format!(
+                    "Environment variable {} is not set",
+                    name
+                ))
+                .into())
+            }
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// 应用 `CLAUDE_NOTIFIER_*` 环境变量覆盖
+///
+/// 变量名去掉前缀后转为小写，按配置键逐级匹配（键名本身可以包含下划线），
+/// 例如 `CLAUDE_NOTIFIER_QUIET_HOURS_ENABLED=false`。缺少的对象会被创建，
+/// 值按原有类型转换（数组用逗号分隔，其他非字符串按JSON解析）。
+pub fn apply_overrides(value: &mut Value, vars: impl IntoIterator<Item = (String, String)>) {
+    for (name, raw) in vars {
+        let Some(path) = name.strip_prefix(OVERRIDE_PREFIX) else {
+            continue;
+        };
+        // 解密配置用的变量，不是配置项
+        if name == encryption::KEY_FILE_VAR || name == encryption::PASSPHRASE_VAR {
+            continue;
+        }
+        if path.is_empty() {
+            continue;
+        }
+        let mut path = path.to_lowercase();
+        // 渠道设置可省略 `CHANNELS_`，例如 `CLAUDE_NOTIFIER_TEAMS_WEBHOOK`；
+        // 配置文件中没有的内置渠道先补上空对象，键名才能正确匹配
+        if let Some(channel) = CHANNEL_NAMES
+            .iter()
+            .find(|channel| path.starts_with(&format!("{}_", channel)))
+        {
+            ensure_object(value, &["channels", channel]);
+            // `http` 段为空时不写入文件，同样先补上
+            if path[channel.len() + 1..].starts_with("http_") {
+                ensure_object(value, &["channels", channel, "http"]);
+            }
+            path = format!("channels_{}", path);
+        } else if path.starts_with("http_") {
+            ensure_object(value, &["http"]);
+        }
+        set_path(value, &path, &raw);
+    }
+}
+
+fn ensure_object(value: &mut Value, keys: &[&str]) {
+    let mut node = value;
+    for key in keys {
+        if !node.is_object() {
+            *node = Value::Object(Map::new());
+        }
+        node = node
+            .as_object_mut()
+            .expect("object")
+            .entry(key.to_string())
+            .or_insert(Value::Null);
+    }
+    if !node.is_object() {
+        *node = Value::Object(Map::new());
+    }
+}
+
+fn set_path(value: &mut Value, path: &str, raw: &str) {
+    if !value.is_object() {
+        *value = Value::Object(Map::new());
+    }
+    let Value::Object(map) = value else {
+        return;
+    };
+
+    // 优先匹配最长的已有键
+    let key = map
+        .keys()
+        .filter(|key| path == key.as_str() || path.starts_with(&format!("{}_", key)))
+        .max_by_key(|key| key.len())
+        .cloned();
+
+    match key {
+        Some(key) if key == path => {
+            let target = map.get_mut(&key).expect("key exists");
+            *target = convert(raw, target);
+        }
+        Some(key) => {
+            let rest = &path[key.len() + 1..];
+            set_path(map.get_mut(&key).expect("key exists"), rest, raw);
+        }
+        None => {
+            map.insert(path.to_string(), convert(raw, &Value::Null));
+        }
+    }
+}
+
+/// 按原值的类型转换字符串形式的值（环境变量、`config set`）
+pub(crate) fn convert(raw: &str, current: &Value) -> Value {
+    match current {
+        Value::String(_) => Value::String(raw.to_string()),
+        Value::Array(_) if !raw.trim_start().starts_with('[') => Value::Array(
+            raw.split(',')
+                .map(|item| Value::String(item.trim().to_string()))
+                .collect(),
+        ),
+        _ => serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string())),
+    }
+}
@@ -0,0 +1,93 @@
+use crate::redact;
+use thiserror::Error;
+
+pub type Result<T, E = NotifierError> = std::result::Result<T, E>;
+
+/// 通知发送过程中的错误分类
+#[derive(Debug, Error)]
+pub enum NotifierError {
+    #[error("Config error: {0}")]
+    ConfigError(String),
+
+    #[error("Channel unavailable: {0}")]
+    ChannelUnavailable(String),
+
+    /// DNS解析或连接失败，通常是本机离线
+    #[error("Network unreachable: {0}")]
+    Unreachable(String),
+
+    #[error("HTTP status {status}: {body}")]
+    HttpStatus { status: u16, body: String },
+
+    #[error("Provider rejected request (code {code}): {msg}")]
+    ProviderRejected { code: i64, msg: String },
+
+    #[error("Request timed out")]
+    Timeout,
+
+    #[error("Rate limited{}", retry_after.map(|s| format!(", retry after {}s", s)).unwrap_or_default())]
+    RateLimited { retry_after: Option<u64> },
+
+    #[error("Network error: {0}")]
+    Network(String),
+}
+
+impl NotifierError {
+    /// 写入JSON结果的错误类型标识
+    pub fn kind(&self) -> &'static str {
+        match self {
+            NotifierError::ConfigError(_) => "config_error",
+            NotifierError::ChannelUnavailable(_) => "channel_unavailable",
+            NotifierError::Unreachable(_) => "unreachable",
+            NotifierError::HttpStatus { .. } => "http_status",
+            NotifierError::ProviderRejected { .. } => "provider_rejected",
+            NotifierError::Timeout => "timeout",
+            NotifierError::RateLimited { .. } => "rate_limited",
+            NotifierError::Network(_) => "network",
+        }
+    }
+
+    /// 稍后重试是否可能成功
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            NotifierError::Timeout
+            | NotifierError::RateLimited { .. }
+            | NotifierError::Network(_)
+            | NotifierError::ChannelUnavailable(_)
+            | NotifierError::Unreachable(_) => true,
+            NotifierError::HttpStatus { status, .. } => *status >= 500,
+            NotifierError::ConfigError(_) | NotifierError::ProviderRejected { .. } => false,
+        }
+    }
+}
+
+impl From<reqwest::Error> for NotifierError {
+    fn from(e: reqwest::Error) -> Self {
+        // reqwest的错误信息中带有完整URL
+        if e.is_timeout() {
+            NotifierError::Timeout
+        } else if e.is_connect() {
+            NotifierError::Unreachable(redact::text(&e.to_string()))
+        } else {
+            NotifierError::Network(redact::text(&e.to_string()))
+        }
+    }
+}
+
+/// 进程退出码
+pub mod exit_code {
+    /// 全部送达
+    pub const OK: u8 = 0;
+    /// 命令执行失败
+    pub const FAILURE: u8 = 1;
+    /// 部分渠道失败
+    pub const PARTIAL: u8 = 2;
+    /// 所有渠道都失败
+    pub const ALL_FAILED: u8 = 3;
+    /// 因静音、静默时段、去重、限流或告警风暴未发送，暂存待合并或进入摘要，或全部进入离线队列
+    pub const SUPPRESSED: u8 = 4;
+    /// 命令因可重试的错误失败（EX_TEMPFAIL）
+    pub const TEMPFAIL: u8 = 75;
+    /// 配置错误（EX_CONFIG）
+    pub const CONFIG: u8 = 78;
+}
@@ -0,0 +1,135 @@
+use crate::notification::{Level, Notification};
+use crate::routing::glob_match;
+use crate::store;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+const STATE_FILE: &str = "escalations.json";
+
+/// 确认按钮的回调值
+pub const ACK_ACTION: &str = "ack";
+
+/// 升级策略 - 未在规定时间内确认的通知发送到第二组渠道
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EscalationPolicy {
+    /// 事件名通配符，不设置时匹配所有事件
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<String>,
+    #[serde(default = "default_min_level")]
+    pub min_level: Level,
+    /// 每一步等待确认的分钟数
+    pub after_minutes: i64,
+    pub channels: Vec<String>,
+    /// 升级前先按原路由重发的次数
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub repeat: u32,
+    /// 升级后仍未确认时依次发送到的渠道，每项为一级（可以是备用链）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub then: Vec<String>,
+    /// 视为确认的表情类型（如 THUMBSUP），为空时任意表情回复都算确认
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ack_reactions: Vec<String>,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+fn default_min_level() -> Level {
+    Level::Critical
+}
+
+impl EscalationPolicy {
+    pub fn matches(&self, notification: &Notification) -> bool {
+        notification.level >= self.min_level
+            && self
+                .event
+                .as_deref()
+                .is_none_or(|pattern| glob_match(pattern, &notification.event))
+    }
+
+    /// 依次执行的各步渠道；空列表表示按原路由重发
+    pub fn stages(&self) -> Vec<Vec<String>> {
+        let mut stages = vec![Vec::new(); self.repeat as usize];
+        stages.push(self.channels.clone());
+        stages.extend(self.then.iter().map(|spec| vec![spec.clone()]));
+        stages
+    }
+}
+
+/// 等待确认的升级
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PendingEscalation {
+    pub id: String,
+    pub notification: Notification,
+    /// 下一步的渠道，空列表表示按原路由重发
+    pub channels: Vec<String>,
+    pub created_at: i64,
+    pub due_at: i64,
+    /// 之后各步的渠道
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stages: Vec<Vec<String>>,
+    #[serde(default)]
+    pub after_minutes: i64,
+    /// 原通知及已发出的升级通知的历史ID，都可用于确认
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history_ids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ack_reactions: Vec<String>,
+}
+
+impl PendingEscalation {
+    /// 进入下一步；没有后续步骤时返回None
+    pub fn next_stage(mut self, now: i64) -> Option<Self> {
+        if self.stages.is_empty() {
+            return None;
+        }
+        self.channels = self.stages.remove(0);
+        self.due_at = now + self.after_minutes * 60;
+        Some(self)
+    }
+
+    pub fn is_acknowledged_by(&self, reaction: &str) -> bool {
+        self.ack_reactions.is_empty() || self.ack_reactions.iter().any(|r| r == reaction)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct EscalationStore {
+    pending: Vec<PendingEscalation>,
+}
+
+impl EscalationStore {
+    pub fn load() -> Result<Self> {
+        store::load(STATE_FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        store::save(STATE_FILE, self)
+    }
+
+    pub fn schedule(&mut self, escalation: PendingEscalation) {
+        self.pending.push(escalation);
+    }
+
+    /// 按升级ID或通知的历史ID确认（或取消）升级，返回是否存在该ID
+    pub fn ack(&mut self, id: &str) -> bool {
+        let before = self.pending.len();
+        self.pending
+            .retain(|e| e.id != id && !e.history_ids.iter().any(|h| h == id));
+        self.pending.len() != before
+    }
+
+    /// 取出所有已到期的升级
+    pub fn take_due(&mut self, now: i64) -> Vec<PendingEscalation> {
+        let (due, pending) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|e| e.due_at <= now);
+        self.pending = pending;
+        due
+    }
+
+    pub fn pending(&self) -> &[PendingEscalation] {
+        &self.pending
+    }
+}
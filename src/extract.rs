@@ -0,0 +1,190 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// hook输入中通知字段的位置，用于没有固定格式的工具（GitHub webhook、监控告警等）
+///
+/// 值为jq风格的表达式：`.pull_request.title`、`.alerts[0].labels["alert name"]`；
+/// `a // b` 取第一个有值的，`"text"` 为字面量
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct FieldMapping {
+    /// 只在该表达式有值时使用这组映射，用于区分不同来源；不设置时总是使用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dedup_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread_key: Option<String>,
+}
+
+impl FieldMapping {
+    /// 字段名和表达式
+    pub fn expressions(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("when", &self.when),
+            ("event", &self.event),
+            ("title", &self.title),
+            ("content", &self.content),
+            ("level", &self.level),
+            ("dedup_key", &self.dedup_key),
+            ("thread_key", &self.thread_key),
+        ]
+        .into_iter()
+        .filter_map(|(name, expr)| expr.as_deref().map(|expr| (name, expr)))
+        .collect()
+    }
+}
+
+/// 使用第一组适用的映射，返回取到值的字段
+pub fn fields(mappings: &[FieldMapping], payload: &Value) -> Result<Vec<(&'static str, String)>> {
+    for mapping in mappings {
+        if let Some(when) = &mapping.when {
+            if eval(when, payload)?.is_none() {
+                continue;
+            }
+        }
+        let mut fields = Vec::new();
+        for (name, expr) in mapping.expressions() {
+            if name == "when" {
+                continue;
+            }
+            if let Some(value) = eval(expr, payload)? {
+                fields.push((name, value));
+            }
+        }
+        return Ok(fields);
+    }
+    Ok(Vec::new())
+}
+
+enum Alternative {
+    Literal(String),
+    Path(Vec<Segment>),
+}
+
+/// 按引号外的 `//` 拆分并逐个解析
+fn parse(expr: &str) -> Result<Vec<Alternative>> {
+    let mut parts = Vec::new();
+    let (mut start, mut in_quotes) = (0, false);
+    let bytes = expr.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => in_quotes = !in_quotes,
+            b'/' if !in_quotes && bytes.get(i + 1) == Some(&b'/') => {
+                parts.push(&expr[start..i]);
+                i += 1;
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    parts.push(&expr[start..]);
+
+    parts
+        .into_iter()
+        .map(|part| {
+            let part = part.trim();
+            match part.strip_prefix('"') {
+                Some(literal) => match literal.strip_suffix('"') {
+                    Some(literal) => Ok(Alternative::Literal(literal.to_string())),
+                    None => anyhow::bail!("Unterminated string in '{}'", expr),
+                },
+                None => parse_path(part).map(Alternative::Path),
+            }
+        })
+        .collect()
+}
+
+/// 求值为字符串：数字和布尔值转为文字，对象和数组为紧凑JSON；不存在、null或空字符串为None
+pub fn eval(expr: &str, payload: &Value) -> Result<Option<String>> {
+    for alternative in parse(expr)? {
+        let value = match alternative {
+            Alternative::Literal(literal) => Some(Value::String(literal)),
+            Alternative::Path(path) => lookup(&path, payload).cloned(),
+        };
+        let text = match value {
+            None | Some(Value::Null) => None,
+            Some(Value::String(s)) => Some(s),
+            Some(other) => Some(other.to_string()),
+        };
+        if let Some(text) = text.filter(|t| !t.is_empty()) {
+            return Ok(Some(text));
+        }
+    }
+    Ok(None)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    /// 负数从末尾算起
+    Index(i64),
+}
+
+/// 解析 `.a.b[0]["c d"]`；单独的 `.` 为整个输入
+fn parse_path(path: &str) -> Result<Vec<Segment>> {
+    let invalid = |reason: &str| anyhow::anyhow!("Invalid path '{}': {}", path, reason);
+    let Some(mut rest) = path.strip_prefix('.') else {
+        return Err(invalid("must start with '.', e.g. .pull_request.title"));
+    };
+    let mut segments = Vec::new();
+    while !rest.is_empty() {
+        if let Some(bracket) = rest.strip_prefix('[') {
+            let end = bracket.find(']').ok_or_else(|| invalid("missing ']'"))?;
+            let inner = bracket[..end].trim();
+            let segment = match inner.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+                Some(key) => Segment::Key(key.to_string()),
+                None => Segment::Index(
+                    inner
+                        .parse()
+                        .map_err(|_| invalid("expected an index or a quoted key in [...]"))?,
+                ),
+            };
+            segments.push(segment);
+            rest = &bracket[end + 1..];
+        } else {
+            // `.a.b` 中除第一个外的键前面有点
+            rest = rest.strip_prefix('.').unwrap_or(rest);
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            let key = &rest[..end];
+            if key.is_empty() {
+                return Err(invalid("empty key"));
+            }
+            segments.push(Segment::Key(key.to_string()));
+            rest = &rest[end..];
+        }
+    }
+    Ok(segments)
+}
+
+fn lookup<'a>(segments: &[Segment], payload: &'a Value) -> Option<&'a Value> {
+    segments
+        .iter()
+        .try_fold(payload, |value, segment| match segment {
+            Segment::Key(key) => value.get(key),
+            Segment::Index(index) => {
+                let items = value.as_array()?;
+                let index = if *index < 0 {
+                    items.len().checked_sub(index.unsigned_abs() as usize)?
+                } else {
+                    *index as usize
+                };
+                items.get(index)
+            }
+        })
+}
+
+/// 检查表达式能否解析
+pub fn check(expr: &str) -> Result<()> {
+    parse(expr).map(|_| ())
+}
@@ -0,0 +1,85 @@
+use crate::ipc::{Pending, Request};
+use anyhow::Result;
+use std::path::Path;
+use std::sync::mpsc::Sender;
+
+/// 在后台线程中读取FIFO（不存在时创建），每行一条JSON通知，按 `hook` 的通用格式处理
+#[cfg(unix)]
+pub fn listen(path: &Path, requests: Sender<Pending>) -> Result<()> {
+    use serde_json::Value;
+    use std::fs::OpenOptions;
+    use std::io::{BufRead, BufReader, ErrorKind};
+    use tracing::{error, warn};
+
+    create(path)?;
+    // 同时以写方式打开，所有写入方关闭后也不会读到EOF
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| anyhow::anyhow!("Failed to open {}: {}", path.display(), e))?;
+    let cwd = std::env::current_dir()?;
+    let path = path.to_path_buf();
+    std::thread::spawn(move || {
+        for line in BufReader::new(file).lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) if e.kind() == ErrorKind::InvalidData => {
+                    warn!(fifo = %path.display(), "ignoring a line that is not UTF-8");
+                    continue;
+                }
+                Err(e) => {
+                    error!(fifo = %path.display(), "Failed to read: {}", e);
+                    return;
+                }
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let input = match serde_json::from_str::<Value>(&line) {
+                Ok(input) if input.is_object() => input,
+                _ => {
+                    warn!(fifo = %path.display(), line, "ignoring a line that is not a JSON object");
+                    continue;
+                }
+            };
+            let request = Request::Hook {
+                input,
+                format: None,
+                cwd: cwd.clone(),
+            };
+            if requests.send(Pending::unanswered(request)).is_err() {
+                return;
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn listen(_path: &Path, _requests: Sender<Pending>) -> Result<()> {
+    anyhow::bail!("FIFOs are only supported on Unix")
+}
+
+/// 只有当前用户可以写入
+#[cfg(unix)]
+fn create(path: &Path) -> Result<()> {
+    use anyhow::Context;
+    use std::os::unix::fs::FileTypeExt;
+
+    match std::fs::metadata(path) {
+        Ok(metadata) if metadata.file_type().is_fifo() => Ok(()),
+        Ok(_) => anyhow::bail!("{} exists and is not a FIFO", path.display()),
+        Err(_) => {
+            let status = std::process::Command::new("mkfifo")
+                .args(["-m", "600"])
+                .arg(path)
+                .status()
+                .context("Failed to run mkfifo")?;
+            if !status.success() {
+                anyhow::bail!("mkfifo {} failed ({})", path.display(), status);
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use crate::ci::CiContext;
+use crate::i18n;
+use crate::notification::CardAction;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+
+/// 在git仓库中发送时附上分支、提交、作者和是否有未提交的改动
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct GitConfig {
+    /// 提交链接的模板，可用 `{host}`、`{repo}`（origin的路径，如 `owner/name`）、`{sha}`、`{branch}`，
+    /// 例如 `https://github.com/{repo}/commit/{sha}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+}
+
+/// 链接模板中可用的占位符
+pub const PLACEHOLDERS: [&str; 4] = ["host", "repo", "sha", "branch"];
+
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// 从origin的地址取出主机和仓库路径，支持 `git@host:owner/name.git` 和 `https://host/owner/name`
+fn parse_remote(url: &str) -> Option<(String, String)> {
+    let rest = match url.split_once("://") {
+        Some((_, rest)) => rest,
+        None => url,
+    };
+    // 去掉用户名；scp形式的 `host:path` 转为 `host/path`
+    let rest = rest.rsplit_once('@').map_or(rest, |(_, rest)| rest);
+    let (host, path) = match rest.split_once(['/', ':']) {
+        Some((host, path)) => (host, path),
+        None => return None,
+    };
+    // ssh://host:22/path 中的端口
+    let path = match path.split_once('/') {
+        Some((port, path)) if port.chars().all(|c| c.is_ascii_digit()) => path,
+        _ => path,
+    };
+    let path = path.trim_end_matches('/').trim_end_matches(".git");
+    (!path.is_empty()).then(|| (host.to_string(), path.to_string()))
+}
+
+/// `dir` 所在仓库的状态；不在git仓库中或没有git时为None
+pub fn context(dir: &Path, config: &GitConfig) -> Option<CiContext> {
+    let strings = i18n::strings();
+    let sha = git(dir, &["rev-parse", "HEAD"])?;
+    // 分离HEAD时没有分支
+    let branch = git(dir, &["rev-parse", "--abbrev-ref", "HEAD"]).filter(|b| b != "HEAD");
+    let dirty = git(dir, &["status", "--porcelain", "--untracked-files=no"])
+        .is_some_and(|status| !status.is_empty());
+
+    let mut context = CiContext::default();
+    if let Some(branch) = &branch {
+        context.facts.push((strings.ci_branch, branch.clone()));
+    }
+    let mut commit = format!("`{}`", sha.get(..7).unwrap_or(&sha));
+    if let Some(subject) = git(dir, &["log", "-1", "--format=%s"]).filter(|s| !s.is_empty()) {
+        commit = format!("{} {}", commit, subject);
+    }
+    if dirty {
+        commit = format!("{} ({})", commit, strings.git_dirty);
+    }
+    context.facts.push((strings.ci_commit, commit));
+    if let Some(author) = git(dir, &["log", "-1", "--format=%an"]).filter(|a| !a.is_empty()) {
+        context.facts.push((strings.git_author, author));
+    }
+
+    if let Some(template) = &config.link {
+        let remote = git(dir, &["remote", "get-url", "origin"]).and_then(|url| parse_remote(&url));
+        // 模板用到远端信息而仓库没有origin时不加链接
+        let needs_remote = template.contains("{host}") || template.contains("{repo}");
+        if remote.is_some() || !needs_remote {
+            let (host, repo) = remote.unwrap_or_default();
+            let url = template
+                .replace("{host}", &host)
+                .replace("{repo}", &repo)
+                .replace("{sha}", &sha)
+                .replace("{branch}", branch.as_deref().unwrap_or_default());
+            context.links.push(CardAction {
+                label: strings.git_view_commit.to_string(),
+                value: url,
+            });
+        }
+    }
+    Some(context)
+}
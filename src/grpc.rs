@@ -0,0 +1,335 @@
+use serde::{Deserialize, Serialize};
+
+/// `serve` 的gRPC接口（`proto/notifier.proto`），供其他语言的服务作为sidecar调用
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GrpcConfig {
+    /// gRPC服务的监听地址
+    #[serde(default = "default_listen")]
+    pub listen: String,
+    /// 设置后客户端需在 `authorization` 元数据中携带 `Bearer <token>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+fn default_listen() -> String {
+    "127.0.0.1:50051".to_string()
+}
+
+impl Default for GrpcConfig {
+    fn default() -> Self {
+        Self {
+            listen: default_listen(),
+            token: None,
+        }
+    }
+}
+
+/// 在前台提供gRPC服务，直到出错
+#[cfg(feature = "grpc")]
+pub fn serve(config: &GrpcConfig) -> anyhow::Result<()> {
+    server::serve(config)
+}
+
+#[cfg(not(feature = "grpc"))]
+pub fn serve(_config: &GrpcConfig) -> anyhow::Result<()> {
+    Err(crate::error::NotifierError::ConfigError(
+        "gRPC support is not enabled (rebuild with --features grpc)".to_string(),
+    )
+    .into())
+}
+
+// tonic的接口以Status作为错误类型
+#[cfg(feature = "grpc")]
+#[allow(clippy::result_large_err)]
+mod server {
+    use super::GrpcConfig;
+    use crate::history::{self, DeliveryState, HistoryEntry};
+    use crate::manager::NotificationManager;
+    use crate::notification::{CardAction, Notification};
+    use crate::redact;
+    use crate::routing::glob_match;
+    use crate::watch::FileTail;
+    use anyhow::{Context, Result};
+    use serde_json::Value;
+    use std::fs::OpenOptions;
+    use std::net::SocketAddr;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+    use tokio_stream::wrappers::ReceiverStream;
+    use tonic::{Request, Response, Status};
+    use tracing::info;
+
+    pub mod proto {
+        tonic::include_proto!("claude_notifier.v1");
+    }
+
+    use proto::notifier_server::{Notifier, NotifierServer};
+
+    /// StreamResults检查历史文件的间隔
+    const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+    struct Service {
+        /// 发送会修改熔断、会话等状态，逐个处理
+        manager: Arc<Mutex<NotificationManager>>,
+    }
+
+    fn internal(e: anyhow::Error) -> Status {
+        Status::internal(redact::text(&format!("{:#}", e)))
+    }
+
+    /// 渠道以阻塞方式发送，放到阻塞线程池中执行
+    async fn blocking<T, F>(f: F) -> Result<T, Status>
+    where
+        F: FnOnce() -> Result<T, Status> + Send + 'static,
+        T: Send + 'static,
+    {
+        tokio::task::spawn_blocking(f)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+    }
+
+    fn channel_result(channel: &str, result: &Value) -> proto::ChannelResult {
+        let state = match DeliveryState::of(result) {
+            DeliveryState::Delivered => proto::DeliveryState::Delivered,
+            DeliveryState::Retryable => proto::DeliveryState::Retryable,
+            DeliveryState::Failed => proto::DeliveryState::Failed,
+            DeliveryState::Skipped => proto::DeliveryState::Skipped,
+        };
+        let hops = result["hops"].as_array().map(Vec::as_slice).unwrap_or(&[]);
+        // 备用链的错误在最后一跳中；未发送时为原因
+        let error = result["error"]
+            .as_str()
+            .or_else(|| hops.last()?["error"].as_str())
+            .or_else(|| result["status"].as_str())
+            .or_else(|| result["skipped"].as_str())
+            .filter(|_| state != proto::DeliveryState::Delivered);
+        let message_id = result["message_id"]
+            .as_str()
+            .or_else(|| hops.iter().rev().find_map(|hop| hop["message_id"].as_str()));
+        proto::ChannelResult {
+            channel: channel.to_string(),
+            state: state.into(),
+            error: error.unwrap_or_default().to_string(),
+            delivered_via: result["delivered_via"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            message_id: message_id.unwrap_or_default().to_string(),
+        }
+    }
+
+    fn notification_result<'a>(
+        entry: &HistoryEntry,
+        results: impl IntoIterator<Item = (&'a String, &'a Value)>,
+    ) -> proto::NotificationResult {
+        let mut results: Vec<proto::ChannelResult> = results
+            .into_iter()
+            .map(|(channel, result)| channel_result(channel, result))
+            .collect();
+        results.sort_by(|a, b| a.channel.cmp(&b.channel));
+        proto::NotificationResult {
+            id: entry.id.clone(),
+            at: entry.at,
+            event: entry.notification.event.clone(),
+            title: entry.notification.title.clone(),
+            level: entry.notification.level_name().to_string(),
+            status: entry.status.clone().unwrap_or_default(),
+            results,
+            escalation_id: String::new(),
+        }
+    }
+
+    fn send(
+        manager: &mut NotificationManager,
+        request: proto::SendRequest,
+    ) -> Result<proto::NotificationResult, Status> {
+        if request.event.is_empty() || request.title.is_empty() {
+            return Err(Status::invalid_argument("'event' and 'title' are required"));
+        }
+        let level = match request.level.as_str() {
+            "" => "info",
+            level => level,
+        };
+        let (level, custom_level) = manager
+            .config()
+            .level(level)
+            .map_err(|e| Status::invalid_argument(format!("{:#}", e)))?;
+        let mut notification =
+            Notification::new(request.event, request.title, request.content, level);
+        notification.custom_level = custom_level;
+        notification.dedup_key = Some(request.dedup_key).filter(|key| !key.is_empty());
+        notification.thread_key = Some(request.thread_key).filter(|key| !key.is_empty());
+        notification.mentions = request.mentions;
+        notification.actions = request
+            .actions
+            .into_iter()
+            .map(|action| CardAction {
+                label: action.label,
+                value: action.value,
+            })
+            .collect();
+        let channels = Some(request.channels).filter(|channels| !channels.is_empty());
+
+        let results = manager
+            .send_notification(&notification, channels, request.force)
+            .map_err(internal)?;
+        let text = |key: &str| results.get(key).and_then(Value::as_str).map(String::from);
+        let entry = HistoryEntry {
+            id: text("id").unwrap_or_default(),
+            at: chrono::Local::now().timestamp(),
+            status: text("status"),
+            notification,
+            results: Default::default(),
+            resent_from: None,
+        };
+        // 其余键（id、summary等）不是渠道结果
+        let channels = results.iter().filter(|(_, result)| {
+            result.get("success").is_some() || result.get("status").is_some()
+        });
+        let mut result = notification_result(&entry, channels);
+        result.escalation_id = text("escalation_id").unwrap_or_default();
+        Ok(result)
+    }
+
+    #[tonic::async_trait]
+    impl Notifier for Service {
+        async fn send(
+            &self,
+            request: Request<proto::SendRequest>,
+        ) -> Result<Response<proto::NotificationResult>, Status> {
+            let manager = self.manager.clone();
+            let request = request.into_inner();
+            blocking(move || {
+                let mut manager = manager.lock().expect("manager lock poisoned");
+                send(&mut manager, request)
+            })
+            .await
+            .map(Response::new)
+        }
+
+        async fn get_status(
+            &self,
+            request: Request<proto::GetStatusRequest>,
+        ) -> Result<Response<proto::NotificationResult>, Status> {
+            let id = request.into_inner().id;
+            blocking(move || {
+                let entries = history::read().map_err(internal)?;
+                let (Some(entry), Some(results)) = (
+                    entries.iter().find(|e| e.id == id),
+                    history::latest_results(&entries, &id),
+                ) else {
+                    return Err(Status::not_found(format!("No notification with id {}", id)));
+                };
+                Ok(notification_result(entry, &results))
+            })
+            .await
+            .map(Response::new)
+        }
+
+        async fn list_channels(
+            &self,
+            _request: Request<proto::ListChannelsRequest>,
+        ) -> Result<Response<proto::ListChannelsResponse>, Status> {
+            let manager = self.manager.clone();
+            // 发送进行中时等待锁，不占用异步工作线程
+            blocking(move || {
+                let manager = manager.lock().expect("manager lock poisoned");
+                let channels = manager
+                    .channels()
+                    .into_iter()
+                    .map(|(name, ready)| proto::Channel {
+                        name: name.to_string(),
+                        ready,
+                    })
+                    .collect();
+                Ok(proto::ListChannelsResponse { channels })
+            })
+            .await
+            .map(Response::new)
+        }
+
+        type StreamResultsStream = ReceiverStream<Result<proto::NotificationResult, Status>>;
+
+        /// 跟踪历史文件，其他进程（hook、send）的发送也会推送
+        async fn stream_results(
+            &self,
+            request: Request<proto::StreamResultsRequest>,
+        ) -> Result<Response<Self::StreamResultsStream>, Status> {
+            let pattern = request.into_inner().event;
+            let mut tail = history_tail().map_err(internal)?;
+            let (tx, rx) = mpsc::channel(16);
+            thread::spawn(move || {
+                // 客户端断开后结束
+                while !tx.is_closed() {
+                    let lines = match tail.read_lines() {
+                        Ok(lines) => lines,
+                        Err(e) => {
+                            let _ = tx.blocking_send(Err(internal(e)));
+                            return;
+                        }
+                    };
+                    for line in lines {
+                        let Ok(entry) = serde_json::from_str::<HistoryEntry>(&line) else {
+                            continue;
+                        };
+                        if !pattern.is_empty() && !glob_match(&pattern, &entry.notification.event) {
+                            continue;
+                        }
+                        let result = notification_result(&entry, &entry.results);
+                        if tx.blocking_send(Ok(result)).is_err() {
+                            return;
+                        }
+                    }
+                    thread::sleep(POLL_INTERVAL);
+                }
+            });
+            Ok(Response::new(ReceiverStream::new(rx)))
+        }
+    }
+
+    /// 从历史文件的当前末尾开始跟踪；还没有历史时先创建
+    fn history_tail() -> Result<FileTail> {
+        let path = history::path()?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        OpenOptions::new().create(true).append(true).open(&path)?;
+        FileTail::open(&path, false)
+    }
+
+    pub fn serve(config: &GrpcConfig) -> Result<()> {
+        let addr: SocketAddr = config
+            .listen
+            .parse()
+            .with_context(|| format!("Invalid gRPC listen address '{}'", config.listen))?;
+        let service = Service {
+            manager: Arc::new(Mutex::new(NotificationManager::new()?)),
+        };
+        let expected = config
+            .token
+            .as_ref()
+            .map(|token| format!("Bearer {}", token));
+        let authorize = move |request: Request<()>| -> Result<Request<()>, Status> {
+            let Some(expected) = &expected else {
+                return Ok(request);
+            };
+            match request.metadata().get("authorization") {
+                Some(value) if value.as_bytes() == expected.as_bytes() => Ok(request),
+                _ => Err(Status::unauthenticated("Missing or invalid bearer token")),
+            }
+        };
+
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()?;
+        info!(listen = %addr, "gRPC server listening");
+        runtime.block_on(
+            tonic::transport::Server::builder()
+                .add_service(NotifierServer::with_interceptor(service, authorize))
+                .serve(addr),
+        )?;
+        Ok(())
+    }
+}
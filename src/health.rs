@@ -0,0 +1,171 @@
+use crate::doctor::{self, Probe};
+use crate::manager::NotificationManager;
+use serde::Serialize;
+use std::fmt;
+
+/// 按Nagios插件的约定，状态的数值即退出码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum State {
+    Ok = 0,
+    Critical = 2,
+    Unknown = 3,
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            State::Ok => "OK",
+            State::Critical => "CRITICAL",
+            State::Unknown => "UNKNOWN",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelHealth {
+    pub channel: String,
+    pub state: State,
+    pub message: String,
+    /// 建立连接或发送测试消息的耗时
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elapsed_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub state: State,
+    pub channels: Vec<ChannelHealth>,
+}
+
+/// 检查 `channels`（为空时检查所有已启用的渠道）；`send` 时向渠道发送测试消息，
+/// 否则只检查能否连上服务商，不发送消息
+pub fn check(manager: &NotificationManager, channels: &[String], send: bool) -> Report {
+    let configured = manager.channels();
+    let names: Vec<String> = if channels.is_empty() {
+        configured
+            .iter()
+            .filter(|(_, ready)| *ready)
+            .map(|(name, _)| name.to_string())
+            .collect()
+    } else {
+        channels.to_vec()
+    };
+
+    let mut results = Vec::new();
+    let mut ready = Vec::new();
+    for name in names {
+        match configured.iter().find(|(channel, _)| *channel == name) {
+            None => results.push(ChannelHealth {
+                channel: name,
+                state: State::Unknown,
+                message: "Not configured".to_string(),
+                elapsed_ms: None,
+            }),
+            Some((_, false)) => results.push(ChannelHealth {
+                channel: name,
+                state: State::Critical,
+                message: "Disabled or incompletely configured".to_string(),
+                elapsed_ms: None,
+            }),
+            Some((_, true)) => ready.push(name),
+        }
+    }
+
+    if send {
+        let sent = manager.test_channels(Some(&ready), true);
+        for name in ready {
+            let result = sent.get(&name).unwrap_or(&serde_json::Value::Null);
+            let delivered = result["success"].as_bool() == Some(true);
+            results.push(ChannelHealth {
+                state: if delivered {
+                    State::Ok
+                } else {
+                    State::Critical
+                },
+                message: match result["error"].as_str() {
+                    Some(error) => error.to_string(),
+                    None if delivered => "Test message delivered".to_string(),
+                    None => "Test message not delivered".to_string(),
+                },
+                elapsed_ms: result["elapsed_ms"].as_u64(),
+                channel: name,
+            });
+        }
+    } else {
+        for name in ready {
+            let health = match doctor::probe(manager.config(), &name, false) {
+                Some(Probe::Ok { message, elapsed }) => ChannelHealth {
+                    channel: name,
+                    state: State::Ok,
+                    message,
+                    elapsed_ms: elapsed.map(|elapsed| elapsed.as_millis() as u64),
+                },
+                Some(Probe::Failed { message, .. }) => ChannelHealth {
+                    channel: name,
+                    state: State::Critical,
+                    message,
+                    elapsed_ms: None,
+                },
+                // 插件渠道无法在不发送的情况下检查
+                None => ChannelHealth {
+                    channel: name,
+                    state: State::Ok,
+                    message: "Loaded (use --send to check delivery)".to_string(),
+                    elapsed_ms: None,
+                },
+            };
+            results.push(health);
+        }
+    }
+    results.sort_by(|a, b| a.channel.cmp(&b.channel));
+
+    let state = if results.iter().any(|r| r.state == State::Critical) {
+        State::Critical
+    } else if results.is_empty() || results.iter().any(|r| r.state == State::Unknown) {
+        State::Unknown
+    } else {
+        State::Ok
+    };
+    Report {
+        state,
+        channels: results,
+    }
+}
+
+impl fmt::Display for Report {
+    /// 插件输出：首行为状态、摘要和性能数据，其后每个渠道一行
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let problems: Vec<String> = self
+            .channels
+            .iter()
+            .filter(|r| r.state != State::Ok)
+            .map(|r| format!("{}: {}", r.channel, r.message))
+            .collect();
+        let summary = match (self.channels.is_empty(), problems.is_empty()) {
+            (true, _) => "No channels are enabled".to_string(),
+            (false, true) => format!(
+                "{} healthy",
+                self.channels
+                    .iter()
+                    .map(|r| r.channel.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            (false, false) => problems.join("; "),
+        };
+        write!(f, "CLAUDE-NOTIFIER {} - {}", self.state, summary)?;
+        let perfdata: Vec<String> = self
+            .channels
+            .iter()
+            .filter_map(|r| Some(format!("'{}'={}ms", r.channel, r.elapsed_ms?)))
+            .collect();
+        if !perfdata.is_empty() {
+            write!(f, " | {}", perfdata.join(" "))?;
+        }
+        for r in &self.channels {
+            write!(f, "\n{}: {} - {}", r.channel, r.state, r.message)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,198 @@
+use crate::config::Config;
+use crate::notification::Notification;
+use crate::redact;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+const HISTORY_FILE: &str = "history.jsonl";
+
+/// 一次发送尝试的记录
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HistoryEntry {
+    pub id: String,
+    pub at: i64,
+    pub notification: Notification,
+    /// 未发送时的原因，例如 quiet_hours、duplicate、digested、collapsed、storm、silenced
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    /// 各渠道的发送结果
+    #[serde(default)]
+    pub results: HashMap<String, Value>,
+    /// 重发时对应的原记录ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resent_from: Option<String>,
+}
+
+impl HistoryEntry {
+    /// 发送失败的渠道
+    pub fn failed_channels(&self) -> Vec<&str> {
+        let mut channels: Vec<&str> = self
+            .results
+            .iter()
+            .filter(|(_, r)| r["success"] == false)
+            .map(|(channel, _)| channel.as_str())
+            .collect();
+        channels.sort();
+        channels
+    }
+
+    pub fn is_failed(&self) -> bool {
+        !self.failed_channels().is_empty()
+    }
+}
+
+/// 渠道的投递状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryState {
+    Delivered,
+    /// 失败但可以重试（resend）
+    Retryable,
+    /// 失败且重试也不会成功，例如被服务商拒绝
+    Failed,
+    /// 未发送，例如处于渠道的静默时段
+    Skipped,
+}
+
+impl DeliveryState {
+    pub fn of(result: &Value) -> Self {
+        match result["success"].as_bool() {
+            Some(true) => DeliveryState::Delivered,
+            Some(false) if result["retryable"] == true => DeliveryState::Retryable,
+            Some(false) => DeliveryState::Failed,
+            None => DeliveryState::Skipped,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryState::Delivered => "delivered",
+            DeliveryState::Retryable => "retryable",
+            DeliveryState::Failed => "failed",
+            DeliveryState::Skipped => "skipped",
+        }
+    }
+}
+
+impl fmt::Display for DeliveryState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 记录及其后续的重发记录，按重发顺序
+fn resend_chain<'a>(entries: &'a [HistoryEntry], entry: &'a HistoryEntry) -> Vec<&'a HistoryEntry> {
+    let mut chain = vec![entry];
+    let mut current = entry.id.as_str();
+    while let Some(resend) = entries
+        .iter()
+        .find(|e| e.resent_from.as_deref() == Some(current))
+    {
+        chain.push(resend);
+        current = resend.id.as_str();
+    }
+    chain
+}
+
+/// 通知各渠道的最终结果，重发的结果覆盖原结果；找不到记录时返回None
+pub fn latest_results(entries: &[HistoryEntry], id: &str) -> Option<BTreeMap<String, Value>> {
+    let entry = entries.iter().find(|e| e.id == id)?;
+    let mut results = BTreeMap::new();
+    for entry in resend_chain(entries, entry) {
+        for (channel, result) in &entry.results {
+            results.insert(channel.clone(), result.clone());
+        }
+    }
+    Some(results)
+}
+
+/// 通知各渠道的最终状态；找不到记录时返回None
+pub fn delivery_states(
+    entries: &[HistoryEntry],
+    id: &str,
+) -> Option<BTreeMap<String, DeliveryState>> {
+    let results = latest_results(entries, id)?;
+    Some(
+        results
+            .into_iter()
+            .map(|(channel, result)| (channel, DeliveryState::of(&result)))
+            .collect(),
+    )
+}
+
+/// 通知在各渠道上已发送消息的ID，备用链按实际送达的渠道记录；找不到记录时返回None
+pub fn message_ids(entries: &[HistoryEntry], id: &str) -> Option<BTreeMap<String, String>> {
+    let entry = entries.iter().find(|e| e.id == id)?;
+    let mut ids = BTreeMap::new();
+    for entry in resend_chain(entries, entry) {
+        for (channel, result) in &entry.results {
+            if let Some(message_id) = result["message_id"].as_str() {
+                ids.insert(channel.clone(), message_id.to_string());
+            }
+            for hop in result["hops"].as_array().into_iter().flatten() {
+                if let (Some(channel), Some(message_id)) =
+                    (hop["channel"].as_str(), hop["message_id"].as_str())
+                {
+                    ids.insert(channel.to_string(), message_id.to_string());
+                }
+            }
+        }
+    }
+    Some(ids)
+}
+
+/// 有失败渠道且尚未重发过的记录
+pub fn unresolved_failures(entries: &[HistoryEntry]) -> Vec<&HistoryEntry> {
+    entries
+        .iter()
+        .filter(|e| e.is_failed())
+        .filter(|e| {
+            !entries
+                .iter()
+                .any(|other| other.resent_from.as_deref() == Some(e.id.as_str()))
+        })
+        .collect()
+}
+
+/// 历史文件（JSONL，每行一条）
+pub fn path() -> Result<PathBuf> {
+    Ok(Config::data_dir()?.join(HISTORY_FILE))
+}
+
+/// 追加一条记录
+pub fn append(entry: &HistoryEntry) -> Result<()> {
+    let path = path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut value = serde_json::to_value(entry)?;
+    // 错误信息和服务商响应中可能带有webhook地址或key
+    redact::strings(&mut value["results"]);
+    let mut line = serde_json::to_string(&value)?;
+    line.push('\n');
+    // 单次write_all追加整行，并发调用时行不会交错
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// 读取全部记录（按时间顺序），跳过损坏的行
+pub fn read() -> Result<Vec<HistoryEntry>> {
+    let Ok(content) = fs::read_to_string(path()?) else {
+        return Ok(Vec::new());
+    };
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
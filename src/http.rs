@@ -0,0 +1,230 @@
+use crate::config::Config;
+use crate::error::NotifierError;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// 表示不使用代理（包括 `HTTPS_PROXY` 等环境变量）的代理设置
+pub const DIRECT: &str = "direct";
+
+/// 未设置 `timeout_secs` 时单次请求的超时
+pub const DEFAULT_TIMEOUT_SECS: f64 = 10.0;
+
+/// 重试等待的上限
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+const USER_AGENT: &str = concat!("claude-notifier/", env!("CARGO_PKG_VERSION"));
+
+/// HTTP客户端设置，可全局设置（顶层 `http`）也可按渠道设置，渠道设置优先
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct HttpConfig {
+    /// 代理地址（`http://`、`https://`、`socks5://`），`direct` 表示直连；
+    /// 不设置时使用 `HTTPS_PROXY`/`HTTP_PROXY`/`ALL_PROXY` 和 `NO_PROXY`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// 额外信任的根证书（PEM，可包含多个），相对路径基于配置目录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_cert: Option<PathBuf>,
+    /// 双向TLS的客户端证书（PEM）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_cert: Option<PathBuf>,
+    /// 客户端证书的私钥（PKCS#8 PEM）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_key: Option<PathBuf>,
+    /// 单次请求的总超时（秒，可为小数），默认10秒
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<f64>,
+    /// 建立连接的超时（秒，可为小数），默认只受总超时限制
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connect_timeout_secs: Option<f64>,
+    /// 可重试的错误（超时、连接失败、限流、5xx）的重试次数，默认0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u32>,
+}
+
+/// 渠道使用的HTTP客户端及其重试设置
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    pub(crate) inner: reqwest::Client,
+    pub(crate) retries: u32,
+}
+
+/// 默认设置的客户端在进程内共用，连接池和TLS会话可以复用
+impl Default for HttpClient {
+    fn default() -> Self {
+        static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+        Self {
+            inner: CLIENT
+                .get_or_init(|| {
+                    builder()
+                        .timeout(Duration::from_secs_f64(DEFAULT_TIMEOUT_SECS))
+                        .build()
+                        .unwrap_or_default()
+                })
+                .clone(),
+            retries: 0,
+        }
+    }
+}
+
+/// 按设置缓存客户端，设置相同的渠道共用一个客户端（及其连接池）
+#[derive(Default)]
+pub struct ClientCache {
+    clients: HashMap<String, reqwest::Client>,
+}
+
+impl ClientCache {
+    pub fn get(&mut self, config: &HttpConfig) -> Result<HttpClient> {
+        // 重试次数不影响客户端本身
+        let key = serde_json::to_string(&HttpConfig {
+            retries: None,
+            ..config.clone()
+        })?;
+        let inner = match self.clients.get(&key) {
+            Some(client) => client.clone(),
+            None => {
+                let client = config.client()?.inner;
+                self.clients.insert(key, client.clone());
+                client
+            }
+        };
+        Ok(HttpClient {
+            inner,
+            retries: config.retries.unwrap_or(0),
+        })
+    }
+}
+
+impl HttpClient {
+    /// 第 `attempt` 次重试前的等待时间：限流时按 `Retry-After`，否则指数退避
+    pub(crate) fn backoff(attempt: u32, retry_after: Option<u64>) -> Duration {
+        retry_after
+            .map(Duration::from_secs)
+            .unwrap_or_else(|| Duration::from_millis(500) * 2u32.saturating_pow(attempt))
+            .min(MAX_BACKOFF)
+    }
+}
+
+impl HttpConfig {
+    pub fn is_empty(&self) -> bool {
+        self.proxy.is_none()
+            && self.ca_cert.is_none()
+            && self.client_cert.is_none()
+            && self.client_key.is_none()
+            && self.timeout_secs.is_none()
+            && self.connect_timeout_secs.is_none()
+            && self.retries.is_none()
+    }
+
+    /// 渠道设置中未设置的项使用全局设置
+    pub fn or(&self, global: &HttpConfig) -> HttpConfig {
+        HttpConfig {
+            proxy: self.proxy.clone().or_else(|| global.proxy.clone()),
+            ca_cert: self.ca_cert.clone().or_else(|| global.ca_cert.clone()),
+            // 证书和私钥成对使用，不分别继承
+            client_cert: self.client_cert.clone().or_else(|| {
+                global
+                    .client_cert
+                    .clone()
+                    .filter(|_| self.client_key.is_none())
+            }),
+            client_key: self.client_key.clone().or_else(|| {
+                global
+                    .client_key
+                    .clone()
+                    .filter(|_| self.client_cert.is_none())
+            }),
+            timeout_secs: self.timeout_secs.or(global.timeout_secs),
+            connect_timeout_secs: self.connect_timeout_secs.or(global.connect_timeout_secs),
+            retries: self.retries.or(global.retries),
+        }
+    }
+
+    pub fn client(&self) -> Result<HttpClient> {
+        let mut builder = builder().timeout(seconds(
+            "timeout_secs",
+            self.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS),
+        )?);
+        if let Some(secs) = self.connect_timeout_secs {
+            builder = builder.connect_timeout(seconds("connect_timeout_secs", secs)?);
+        }
+
+        if let Some(path) = &self.ca_cert {
+            let pem = read_pem(path)?;
+            let certs = reqwest::Certificate::from_pem_bundle(&pem).map_err(|e| {
+                NotifierError::ConfigError(format!("CA bundle {}: {}", path.display(), e))
+            })?;
+            for cert in certs {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+
+        match (&self.client_cert, &self.client_key) {
+            (Some(cert), Some(key)) => {
+                let identity = reqwest::Identity::from_pkcs8_pem(&read_pem(cert)?, &read_pem(key)?)
+                    .map_err(|e| {
+                        NotifierError::ConfigError(format!(
+                            "Client certificate {}: {}",
+                            cert.display(),
+                            e
+                        ))
+                    })?;
+                builder = builder.identity(identity);
+            }
+            (None, None) => {}
+            _ => {
+                return Err(NotifierError::ConfigError(
+                    "client_cert and client_key must be set together".to_string(),
+                )
+                .into())
+            }
+        }
+
+        match self.proxy.as_deref() {
+            Some(DIRECT) => builder = builder.no_proxy(),
+            Some(url) => {
+                let proxy = reqwest::Proxy::all(url)
+                    .map_err(|e| {
+                        NotifierError::ConfigError(format!("Invalid proxy '{}': {}", url, e))
+                    })?
+                    // 显式代理同样遵守 NO_PROXY
+                    .no_proxy(reqwest::NoProxy::from_env());
+                builder = builder.proxy(proxy);
+            }
+            None => {}
+        }
+        let inner = builder
+            .build()
+            .map_err(|e| NotifierError::ConfigError(format!("HTTP client: {}", e)))?;
+        Ok(HttpClient {
+            inner,
+            retries: self.retries.unwrap_or(0),
+        })
+    }
+}
+
+fn builder() -> reqwest::ClientBuilder {
+    reqwest::Client::builder().user_agent(USER_AGENT)
+}
+
+fn seconds(key: &str, secs: f64) -> Result<Duration> {
+    Duration::try_from_secs_f64(secs)
+        .ok()
+        .filter(|d| !d.is_zero())
+        .ok_or_else(|| {
+            NotifierError::ConfigError(format!("{} must be a positive number, got {}", key, secs))
+                .into()
+        })
+}
+
+/// 读取证书文件，相对路径基于配置目录
+fn read_pem(path: &Path) -> Result<Vec<u8>> {
+    let path = Config::config_dir()?.join(path);
+    fs::read(&path).map_err(|e| {
+        NotifierError::ConfigError(format!("Cannot read {}: {}", path.display(), e)).into()
+    })
+}
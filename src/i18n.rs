@@ -0,0 +1,369 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::sync::RwLock;
+
+/// 内置文字（测试消息、升级标题、状态输出等）的语言
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    #[serde(alias = "zh-CN", alias = "zh_CN")]
+    Zh,
+}
+
+impl Locale {
+    /// 按 `LC_ALL`、`LC_MESSAGES`、`LANG` 推断，中文环境为zh，其余为en
+    pub fn from_env() -> Self {
+        let lang = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        if lang.starts_with("zh") {
+            Locale::Zh
+        } else {
+            Locale::En
+        }
+    }
+
+    pub fn strings(self) -> &'static Strings {
+        match self {
+            Locale::En => &EN,
+            Locale::Zh => &ZH,
+        }
+    }
+}
+
+/// 当前语言，读取配置时设置；未设置时按环境变量推断
+static CURRENT: RwLock<Option<Locale>> = RwLock::new(None);
+
+pub fn set_locale(locale: Option<Locale>) {
+    if let Ok(mut current) = CURRENT.write() {
+        *current = locale;
+    }
+}
+
+pub fn locale() -> Locale {
+    CURRENT
+        .read()
+        .ok()
+        .and_then(|current| *current)
+        .unwrap_or_else(Locale::from_env)
+}
+
+/// 当前语言的文字表
+pub fn strings() -> &'static Strings {
+    locale().strings()
+}
+
+/// 替换模板中的 `{name}` 占位符
+pub fn fill(template: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut text = template.to_string();
+    for (name, value) in args {
+        text = text.replace(&format!("{{{}}}", name), &value.to_string());
+    }
+    text
+}
+
+/// 内置文字表，带 `{name}` 的条目用 `fill` 填充
+pub struct Strings {
+    /// 渠道需要标题而通知没有时使用
+    pub default_title: &'static str,
+    pub test_title: &'static str,
+    /// `{channel}`
+    pub test_content: &'static str,
+    pub escalated: &'static str,
+    pub reminder: &'static str,
+    /// `{minutes}`、`{id}`
+    pub not_acknowledged: &'static str,
+    pub acknowledge: &'static str,
+    pub acknowledged: &'static str,
+    pub already_acknowledged: &'static str,
+    pub acknowledge_failed: &'static str,
+    /// `{action}`
+    pub received: &'static str,
+    pub approve: &'static str,
+    pub deny: &'static str,
+    pub approved: &'static str,
+    pub denied: &'static str,
+    /// `{source}`
+    pub decided_remotely: &'static str,
+    /// `{seconds}`
+    pub no_response: &'static str,
+    /// `{count}`
+    pub digest_title: &'static str,
+    /// `{count}`、`{event}`
+    pub burst_title: &'static str,
+    /// `{max}`
+    pub storm_started: &'static str,
+    /// `{count}`、`{minutes}`
+    pub storm_summary: &'static str,
+    pub storm_ended: &'static str,
+    /// `{time}`
+    pub queued_offline: &'static str,
+    /// `{count}`
+    pub throttled: &'static str,
+    /// `{omitted}`
+    pub truncated: &'static str,
+    pub delivered: &'static str,
+    /// `{via}`
+    pub delivered_via: &'static str,
+    /// `{error}`
+    pub failed: &'static str,
+    pub not_delivered: &'static str,
+    /// `{total}`、`{succeeded}`、`{failed}`、`{suppressed}`
+    pub summary: &'static str,
+    /// `{row}`
+    pub batch_row: &'static str,
+    /// `{row}`
+    pub batch_duplicate: &'static str,
+    /// `{rows}`、`{sent}`、`{suppressed}`、`{failed}`、`{duplicates}`
+    pub batch_summary: &'static str,
+    /// `{id}`
+    pub acknowledged_id: &'static str,
+    /// `{id}`
+    pub no_pending_escalation: &'static str,
+    pub claude_attention: &'static str,
+    pub claude_permission: &'static str,
+    pub claude_awaiting_input: &'static str,
+    pub claude_error: &'static str,
+    pub claude_finished: &'static str,
+    pub claude_subagent_finished: &'static str,
+    pub claude_task_completed: &'static str,
+    /// `{tool}`
+    pub claude_tool: &'static str,
+    /// `{tool}`
+    pub claude_tool_done: &'static str,
+    pub claude_prompt: &'static str,
+    pub claude_session_start: &'static str,
+    pub claude_session_end: &'static str,
+    pub claude_compact: &'static str,
+    /// `{cwd}`
+    pub claude_cwd: &'static str,
+    pub claude_files_changed: &'static str,
+    pub claude_commands_run: &'static str,
+    /// `{count}`
+    pub claude_more: &'static str,
+    /// `{count}`
+    pub progress_earlier: &'static str,
+    pub stats_duration: &'static str,
+    pub stats_tool_calls: &'static str,
+    /// `{count}`
+    pub stats_others: &'static str,
+    pub stats_files_edited: &'static str,
+    pub stats_commands: &'static str,
+    pub usage_tokens: &'static str,
+    /// `{input}`、`{output}`、`{cache_read}`、`{cache_write}`
+    pub usage_breakdown: &'static str,
+    pub usage_cache_hits: &'static str,
+    pub usage_cost: &'static str,
+    /// `{models}`
+    pub usage_unpriced: &'static str,
+    pub ci_repository: &'static str,
+    pub ci_branch: &'static str,
+    pub ci_commit: &'static str,
+    pub ci_actor: &'static str,
+    pub ci_workflow: &'static str,
+    pub ci_job: &'static str,
+    pub ci_view_run: &'static str,
+    pub ci_pipeline: &'static str,
+    pub ci_view_pipeline: &'static str,
+    pub ci_build: &'static str,
+    pub ci_view_build: &'static str,
+    pub ci_console: &'static str,
+    pub git_author: &'static str,
+    pub git_dirty: &'static str,
+    pub git_view_commit: &'static str,
+    pub ci_environment: &'static str,
+    pub ci_duration: &'static str,
+    pub ci_open: &'static str,
+    /// `{user}`、`{count}`、`{branch}`
+    pub ci_pushed: &'static str,
+    /// `{file}`
+    pub watch_title: &'static str,
+    /// `{duration}`
+    pub live_waiting: &'static str,
+    /// `{duration}`
+    pub live_stalled: &'static str,
+}
+
+static EN: Strings = Strings {
+    default_title: "Notification",
+    test_title: "Test Notification",
+    test_content: "This is a test message from Claude Notifier to {channel}",
+    escalated: "[Escalated]",
+    reminder: "[Reminder]",
+    not_acknowledged: "Not acknowledged within {minutes} minutes (id: {id}).",
+    acknowledge: "Acknowledge",
+    acknowledged: "Acknowledged",
+    already_acknowledged: "Already acknowledged",
+    acknowledge_failed: "Failed to acknowledge",
+    received: "Received: {action}",
+    approve: "Approve",
+    deny: "Deny",
+    approved: "Approved",
+    denied: "Denied",
+    decided_remotely: "Decided remotely via {source}",
+    no_response: "No response within {seconds} seconds",
+    digest_title: "Digest: {count} notifications",
+    burst_title: "{count} × {event}",
+    storm_started: "🌩 Alert storm: over {max} notifications per minute, only critical ones are sent until it calms down",
+    storm_summary: "🌩 Suppressed {count} notifications in the last {minutes} minutes",
+    storm_ended: "The rate is back to normal; notifications are sent again.",
+    queued_offline: "⏳ Queued while offline, originally sent at {time}",
+    throttled: "… and {count} more like this suppressed",
+    truncated: "… truncated, {omitted} chars omitted",
+    delivered: "delivered",
+    delivered_via: "delivered via {via}",
+    failed: "failed: {error}",
+    not_delivered: "not delivered",
+    summary:
+        "summary: {total} total, {succeeded} succeeded, {failed} failed, {suppressed} suppressed",
+    batch_row: "row {row}",
+    batch_duplicate: "duplicate of row {row}, not sent",
+    batch_summary: "{rows} rows: {sent} sent, {suppressed} suppressed, {failed} failed, {duplicates} duplicates",
+    acknowledged_id: "Acknowledged {id}",
+    no_pending_escalation: "No pending escalation with id {id}",
+    claude_attention: "Claude needs your attention",
+    claude_permission: "Claude needs your permission",
+    claude_awaiting_input: "Claude is waiting for your input",
+    claude_error: "Claude ran into an error",
+    claude_finished: "Claude finished",
+    claude_subagent_finished: "Subagent finished",
+    claude_task_completed: "Task completed",
+    claude_tool: "Claude wants to use {tool}",
+    claude_tool_done: "Claude used {tool}",
+    claude_prompt: "Prompt submitted",
+    claude_session_start: "Session started",
+    claude_session_end: "Session ended",
+    claude_compact: "Compacting context",
+    claude_cwd: "Directory: `{cwd}`",
+    claude_files_changed: "Files changed:",
+    claude_commands_run: "Commands run:",
+    claude_more: "and {count} more",
+    progress_earlier: "… {count} earlier",
+    stats_duration: "Session duration",
+    stats_tool_calls: "Tool calls",
+    stats_others: "others {count}",
+    stats_files_edited: "Files edited",
+    stats_commands: "Commands run",
+    usage_tokens: "Tokens:",
+    usage_breakdown:
+        "{input} in · {output} out · {cache_read} cache read · {cache_write} cache write",
+    usage_cache_hits: "Cache hits:",
+    usage_cost: "Estimated cost:",
+    usage_unpriced: "No price for {models}; add it under `pricing`",
+    ci_repository: "Repository",
+    ci_branch: "Branch",
+    ci_commit: "Commit",
+    ci_actor: "Triggered by",
+    ci_workflow: "Workflow",
+    ci_job: "Job",
+    ci_view_run: "View run",
+    ci_pipeline: "Pipeline",
+    ci_view_pipeline: "View pipeline",
+    ci_build: "Build",
+    ci_view_build: "View build",
+    ci_console: "Console output",
+    git_author: "Author",
+    git_dirty: "uncommitted changes",
+    git_view_commit: "View commit",
+    ci_environment: "Environment",
+    ci_duration: "Duration",
+    ci_open: "Open",
+    ci_pushed: "{user} pushed {count} commit(s) to {branch}",
+    watch_title: "Match in {file}",
+    live_waiting: "Waiting for {duration}; it may need your permission in the terminal",
+    live_stalled: "No output from Claude for {duration}",
+};
+
+static ZH: Strings = Strings {
+    default_title: "通知",
+    test_title: "测试通知",
+    test_content: "这是 Claude Notifier 发送到 {channel} 的测试消息",
+    escalated: "[升级]",
+    reminder: "[提醒]",
+    not_acknowledged: "{minutes} 分钟内未确认（ID：{id}）。",
+    acknowledge: "确认",
+    acknowledged: "已确认",
+    already_acknowledged: "已经确认过了",
+    acknowledge_failed: "确认失败",
+    received: "已收到：{action}",
+    approve: "批准",
+    deny: "拒绝",
+    approved: "已批准",
+    denied: "已拒绝",
+    decided_remotely: "通过 {source} 远程决定",
+    no_response: "{seconds} 秒内没有回应",
+    digest_title: "摘要：{count} 条通知",
+    burst_title: "{count} 条 {event}",
+    storm_started: "🌩 告警风暴：每分钟超过 {max} 条通知，平息前只发送紧急通知",
+    storm_summary: "🌩 过去 {minutes} 分钟抑制了 {count} 条通知",
+    storm_ended: "发送速率已恢复正常，恢复发送通知。",
+    queued_offline: "⏳ 离线期间排队，原始发送时间 {time}",
+    throttled: "… 另有 {count} 条同类通知被限流",
+    truncated: "… 已截断，省略 {omitted} 个字符",
+    delivered: "已送达",
+    delivered_via: "经 {via} 送达",
+    failed: "失败：{error}",
+    not_delivered: "未送达",
+    summary: "汇总：共 {total} 个，成功 {succeeded} 个，失败 {failed} 个，抑制 {suppressed} 个",
+    batch_row: "第 {row} 行",
+    batch_duplicate: "与第 {row} 行重复，未发送",
+    batch_summary:
+        "共 {rows} 行：发送 {sent} 行，抑制 {suppressed} 行，失败 {failed} 行，重复 {duplicates} 行",
+    acknowledged_id: "已确认 {id}",
+    no_pending_escalation: "没有ID为 {id} 的待处理升级",
+    claude_attention: "Claude 需要你的处理",
+    claude_permission: "Claude 需要你的授权",
+    claude_awaiting_input: "Claude 正在等待你的输入",
+    claude_error: "Claude 遇到错误",
+    claude_finished: "Claude 已完成",
+    claude_subagent_finished: "子代理已完成",
+    claude_task_completed: "任务已完成",
+    claude_tool: "Claude 请求使用 {tool}",
+    claude_tool_done: "Claude 已使用 {tool}",
+    claude_prompt: "已提交提示词",
+    claude_session_start: "会话开始",
+    claude_session_end: "会话结束",
+    claude_compact: "正在压缩上下文",
+    claude_cwd: "目录：`{cwd}`",
+    claude_files_changed: "改动的文件：",
+    claude_commands_run: "执行的命令：",
+    claude_more: "等 {count} 项",
+    progress_earlier: "… 更早的 {count} 项",
+    stats_duration: "会话时长",
+    stats_tool_calls: "工具调用",
+    stats_others: "其他 {count}",
+    stats_files_edited: "编辑的文件",
+    stats_commands: "执行的命令",
+    usage_tokens: "Token：",
+    usage_breakdown:
+        "输入 {input} · 输出 {output} · 缓存读取 {cache_read} · 缓存写入 {cache_write}",
+    usage_cache_hits: "缓存命中：",
+    usage_cost: "估算费用：",
+    usage_unpriced: "{models} 没有单价，请在 `pricing` 中添加",
+    ci_repository: "仓库",
+    ci_branch: "分支",
+    ci_commit: "提交",
+    ci_actor: "触发者",
+    ci_workflow: "工作流",
+    ci_job: "作业",
+    ci_view_run: "查看运行",
+    ci_pipeline: "流水线",
+    ci_view_pipeline: "查看流水线",
+    ci_build: "构建",
+    ci_view_build: "查看构建",
+    ci_console: "控制台输出",
+    git_author: "作者",
+    git_dirty: "有未提交的改动",
+    git_view_commit: "查看提交",
+    ci_environment: "环境",
+    ci_duration: "耗时",
+    ci_open: "打开",
+    ci_pushed: "{user} 向 {branch} 推送了 {count} 个提交",
+    watch_title: "{file} 中出现匹配",
+    live_waiting: "已等待 {duration}，可能需要在终端中授权",
+    live_stalled: "Claude 已 {duration} 没有输出",
+};
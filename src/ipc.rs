@@ -0,0 +1,218 @@
+use crate::adapter::InputFormat;
+use crate::approval::ApprovalConfig;
+use crate::config::Config;
+use crate::notification::Notification;
+use crate::redact;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tracing::{debug, error};
+
+/// 守护进程的socket文件名，在数据目录中，每个配置档各一个
+const SOCKET_FILE: &str = "daemon.sock";
+
+/// 等待守护进程回复的最长时间，与Claude Code的hook超时一致
+#[cfg(unix)]
+const REPLY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// `send` 和 `hook` 转发给守护进程的请求，路径都是绝对路径
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    /// 已构造好的通知，级别和仓库状态由守护进程按配置补充
+    Send {
+        notification: Box<Notification>,
+        level: String,
+        channels: Option<Vec<String>>,
+        force: bool,
+        /// 附上该目录所在仓库的状态；CI中为None
+        git_dir: Option<PathBuf>,
+    },
+    /// hook的原始输入
+    Hook {
+        input: Value,
+        format: Option<InputFormat>,
+        cwd: PathBuf,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope {
+    /// 客户端所用的配置文件，与守护进程不同（例如有项目配置）时不转发
+    config: Vec<PathBuf>,
+    request: Request,
+}
+
+/// 守护进程处理请求的结果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Outcome {
+    pub results: HashMap<String, Value>,
+    /// 需要等待审批时由客户端等待按钮点击
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval: Option<ApprovalConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum Reply {
+    Done(Outcome),
+    /// 配置不同，由客户端自己发送
+    Mismatch,
+    Error {
+        message: String,
+    },
+}
+
+pub fn socket_path() -> Result<PathBuf> {
+    Ok(Config::data_dir()?.join(SOCKET_FILE))
+}
+
+/// 守护进程在运行时由它处理请求并返回结果；没有运行或配置不同时返回None，由调用方自己处理
+#[cfg(unix)]
+pub fn forward(request: Request) -> Result<Option<Outcome>> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+
+    let Ok(mut stream) = UnixStream::connect(socket_path()?) else {
+        return Ok(None);
+    };
+    let envelope = Envelope {
+        config: Config::source_files(),
+        request,
+    };
+    let mut line = serde_json::to_string(&envelope)?;
+    line.push('\n');
+    // 请求发出后出错不再自己发送，以免重复
+    stream.set_read_timeout(Some(REPLY_TIMEOUT))?;
+    stream.write_all(line.as_bytes())?;
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    let reply: Reply = serde_json::from_str(&reply)
+        .map_err(|e| anyhow::anyhow!("Invalid reply from the daemon: {}", e))?;
+    match reply {
+        Reply::Done(outcome) => Ok(Some(outcome)),
+        Reply::Mismatch => Ok(None),
+        Reply::Error { message } => Err(anyhow::anyhow!(message)),
+    }
+}
+
+#[cfg(not(unix))]
+pub fn forward(_request: Request) -> Result<Option<Outcome>> {
+    Ok(None)
+}
+
+/// 守护进程收到的一个请求
+pub struct Pending {
+    request: Request,
+    /// 等待回复的客户端；FIFO等单向来源没有
+    #[cfg(unix)]
+    stream: Option<std::os::unix::net::UnixStream>,
+}
+
+impl Pending {
+    /// 不需要回复的请求，结果只写入日志
+    pub fn unanswered(request: Request) -> Self {
+        Self {
+            request,
+            #[cfg(unix)]
+            stream: None,
+        }
+    }
+
+    pub fn request(&self) -> &Request {
+        &self.request
+    }
+
+    /// 处理请求并把结果回复给客户端
+    pub fn respond(self, handler: impl FnOnce(Request) -> Result<Outcome>) {
+        let reply = match handler(self.request) {
+            Ok(outcome) => Reply::Done(outcome),
+            Err(e) => Reply::Error {
+                message: redact::text(&format!("{:#}", e)),
+            },
+        };
+        #[cfg(unix)]
+        if let Some(mut stream) = self.stream {
+            send_reply(&mut stream, &reply);
+            return;
+        }
+        match reply {
+            Reply::Done(outcome) => debug!(results = ?outcome.results, "request processed"),
+            Reply::Error { message } => error!("Failed to process request: {}", message),
+            Reply::Mismatch => {}
+        }
+    }
+}
+
+#[cfg(unix)]
+fn send_reply(stream: &mut std::os::unix::net::UnixStream, reply: &Reply) {
+    use std::io::Write;
+
+    if let Ok(mut line) = serde_json::to_string(reply) {
+        line.push('\n');
+        // 客户端超时退出后写入失败，忽略
+        let _ = stream.write_all(line.as_bytes());
+    }
+}
+
+/// 在后台线程中接受转发的请求并交给 `requests`；已有守护进程在监听时返回错误
+#[cfg(unix)]
+pub fn listen(requests: std::sync::mpsc::Sender<Pending>) -> Result<PathBuf> {
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    let path = socket_path()?;
+    if UnixStream::connect(&path).is_ok() {
+        anyhow::bail!("Another daemon is listening on {}", path.display());
+    }
+    // 上次异常退出留下的socket文件
+    let _ = std::fs::remove_file(&path);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let listener = UnixListener::bind(&path)?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let _ = stream.set_read_timeout(Some(REPLY_TIMEOUT));
+            let mut line = String::new();
+            let read = stream
+                .try_clone()
+                .and_then(|clone| BufReader::new(clone).read_line(&mut line));
+            let envelope = match read
+                .map_err(anyhow::Error::from)
+                .and_then(|_| Ok(serde_json::from_str::<Envelope>(&line)?))
+            {
+                Ok(envelope) => envelope,
+                Err(e) => {
+                    debug!("bad request on the daemon socket: {:#}", e);
+                    let message = format!("Bad request: {:#}", e);
+                    send_reply(&mut stream, &Reply::Error { message });
+                    continue;
+                }
+            };
+            if envelope.config != Config::source_files() {
+                debug!(config = ?envelope.config, "client uses other configuration files");
+                send_reply(&mut stream, &Reply::Mismatch);
+                continue;
+            }
+            let pending = Pending {
+                request: envelope.request,
+                stream: Some(stream),
+            };
+            if requests.send(pending).is_err() {
+                return;
+            }
+        }
+    });
+    Ok(path)
+}
+
+#[cfg(not(unix))]
+pub fn listen(_requests: std::sync::mpsc::Sender<Pending>) -> Result<PathBuf> {
+    anyhow::bail!("Forwarding requests needs Unix sockets")
+}
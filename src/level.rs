@@ -0,0 +1,63 @@
+use crate::notification::Level;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 配置中定义的级别，例如 `debug`、`page`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct LevelConfig {
+    /// 基础级别：决定排序（`min_level` 比较）、默认颜色和未设置项的行为
+    #[serde(default = "default_base")]
+    pub base: Level,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emoji: Option<String>,
+    /// 是否无视静默时段，不设置时与基础级别相同（只有critical无视）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bypass_quiet_hours: Option<bool>,
+    /// 是否像critical一样@值班人员（没有值班人员时@所有人），不设置时与基础级别相同
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mention_on_call: Option<bool>,
+}
+
+fn default_base() -> Level {
+    Level::Info
+}
+
+/// 通知使用的自定义级别，连同定义随通知保存，定时发送和重发时不受配置变化影响
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct CustomLevel {
+    pub name: String,
+    #[serde(flatten)]
+    pub config: LevelConfig,
+}
+
+/// 解析级别名：先查内置级别，再查配置中的自定义级别
+pub fn resolve(
+    levels: &HashMap<String, LevelConfig>,
+    name: &str,
+) -> Result<(Level, Option<CustomLevel>)> {
+    if let Ok(level) = name.parse::<Level>() {
+        return Ok((level, None));
+    }
+    let name = name.trim();
+    match levels.get(name) {
+        Some(config) => Ok((
+            config.base,
+            Some(CustomLevel {
+                name: name.to_string(),
+                config: config.clone(),
+            }),
+        )),
+        None => {
+            let mut names: Vec<&str> = Level::ALL.iter().map(|l| l.as_str()).collect();
+            let mut custom: Vec<&str> = levels.keys().map(String::as_str).collect();
+            custom.sort();
+            names.extend(custom);
+            anyhow::bail!(
+                "Unknown level '{}' (expected one of: {})",
+                name,
+                names.join(", ")
+            )
+        }
+    }
+}
@@ -0,0 +1,66 @@
+pub mod adapter;
+pub mod approval;
+pub mod audit;
+pub mod batch;
+pub mod burst;
+pub mod callback;
+pub mod channels;
+pub mod ci;
+pub mod circuit_breaker;
+pub mod claude;
+pub mod color;
+pub mod completions;
+pub mod config;
+pub mod dedup;
+pub mod digest;
+pub mod doctor;
+pub mod encryption;
+pub mod env;
+pub mod error;
+pub mod escalation;
+pub mod extract;
+pub mod fifo;
+pub mod git;
+pub mod grpc;
+pub mod health;
+pub mod history;
+pub mod http;
+pub mod i18n;
+pub mod ipc;
+pub mod level;
+pub mod live;
+pub mod manager;
+pub mod markdown;
+pub mod mcp;
+pub mod metrics;
+pub mod migrate;
+pub mod notification;
+pub mod notifiers;
+pub mod offline;
+pub mod oncall;
+pub mod preset;
+pub mod pricing;
+pub mod probe;
+pub mod progress;
+pub mod provider;
+pub mod quiet_hours;
+pub mod recurring;
+pub mod redact;
+pub mod replay;
+pub mod routing;
+pub mod schedule;
+pub mod secret;
+pub mod severity;
+pub mod silence;
+pub mod store;
+pub mod storm;
+pub mod systemd;
+pub mod template;
+pub mod thread;
+pub mod throttle;
+pub mod timeutil;
+pub mod transcript;
+pub mod upload;
+pub mod validate;
+pub mod watch;
+pub mod wizard;
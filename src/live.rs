@@ -0,0 +1,316 @@
+use crate::claude::{self, AGENT_ERROR, PERMISSION_REQUEST};
+use crate::i18n;
+use crate::notification::{Level, Notification};
+use crate::timeutil;
+use crate::watch::FileTail;
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// 会话长时间没有输出的事件名
+pub const SESSION_STALLED: &str = "session_stalled";
+
+/// 错误信息的最大字符数
+const MAX_ERROR_CHARS: usize = 500;
+
+/// 本身就会运行很久的工具，不当作在等待授权
+const LONG_RUNNING_TOOLS: [&str; 2] = ["Task", "Agent"];
+
+/// 用户拒绝授权时工具结果中的文字，不算错误
+const REJECTED: &str = "doesn't want to proceed";
+
+/// 跟踪进行中会话的阈值
+#[derive(Debug, Clone)]
+pub struct LiveOptions {
+    /// 工具调用等待结果超过该时长时认为在等待授权
+    pub permission_after: Duration,
+    /// 等待模型回复超过该时长时认为会话卡住
+    pub idle_after: Duration,
+    /// 工具调用返回的错误也通知，默认只通知API错误
+    pub tool_errors: bool,
+}
+
+/// 会话记录中值得通知的情况
+#[derive(Debug, Clone)]
+pub enum Alert {
+    Error {
+        tool: Option<String>,
+        message: String,
+    },
+    Permission {
+        tool: String,
+        summary: String,
+        waited: Duration,
+    },
+    Stalled {
+        idle: Duration,
+    },
+}
+
+struct PendingTool {
+    name: String,
+    summary: String,
+    since: Instant,
+    alerted: bool,
+}
+
+/// 跟踪正在写入的会话记录（JSONL）
+pub struct LiveTranscript {
+    tail: FileTail,
+    options: LiveOptions,
+    /// 尚无结果的工具调用，按tool_use id
+    pending: HashMap<String, PendingTool>,
+    /// 最近一条用户输入或工具结果之后还没有回复
+    waiting_since: Option<Instant>,
+    stalled_alerted: bool,
+    cwd: Option<String>,
+}
+
+impl LiveTranscript {
+    /// 已有内容只用来恢复状态，不产生通知
+    pub fn open(path: &Path, options: LiveOptions) -> Result<Self> {
+        let mut live = Self {
+            tail: FileTail::open(path, true)?,
+            options,
+            pending: HashMap::new(),
+            waiting_since: None,
+            stalled_alerted: false,
+            cwd: None,
+        };
+        for line in live.tail.read_lines()? {
+            if let Ok(entry) = serde_json::from_str(&line) {
+                live.process(&entry, &mut Vec::new());
+            }
+        }
+        // 启动前已经卡住的会话不再通知
+        live.stalled_alerted = live.waiting_since.is_some();
+        Ok(live)
+    }
+
+    /// 读取新增的记录并检查等待时长
+    pub fn poll(&mut self) -> Result<Vec<Alert>> {
+        let mut alerts = Vec::new();
+        for line in self.tail.read_lines()? {
+            // 写入中途的行在下次读取时才完整，这里只会是无法解析的行
+            if let Ok(entry) = serde_json::from_str(&line) {
+                self.process(&entry, &mut alerts);
+            }
+        }
+
+        for tool in self.pending.values_mut() {
+            let waited = tool.since.elapsed();
+            if !tool.alerted && waited >= self.options.permission_after {
+                tool.alerted = true;
+                alerts.push(Alert::Permission {
+                    tool: tool.name.clone(),
+                    summary: tool.summary.clone(),
+                    waited,
+                });
+            }
+        }
+        if let Some(since) = self.waiting_since.filter(|_| self.pending.is_empty()) {
+            let idle = since.elapsed();
+            if !self.stalled_alerted && idle >= self.options.idle_after {
+                self.stalled_alerted = true;
+                alerts.push(Alert::Stalled { idle });
+            }
+        }
+        Ok(alerts)
+    }
+
+    fn process(&mut self, entry: &Value, alerts: &mut Vec<Alert>) {
+        if let Some(cwd) = entry["cwd"].as_str() {
+            self.cwd = Some(cwd.to_string());
+        }
+        let now = Instant::now();
+        match entry["type"].as_str() {
+            Some("assistant") => {
+                self.waiting_since = None;
+                self.stalled_alerted = false;
+                if entry["isApiErrorMessage"].as_bool() == Some(true) {
+                    alerts.push(Alert::Error {
+                        tool: None,
+                        message: content_text(&entry["message"]["content"]),
+                    });
+                }
+                let blocks = entry["message"]["content"].as_array();
+                for block in blocks.into_iter().flatten() {
+                    let (Some("tool_use"), Some(id), Some(name)) = (
+                        block["type"].as_str(),
+                        block["id"].as_str(),
+                        block["name"].as_str(),
+                    ) else {
+                        continue;
+                    };
+                    if LONG_RUNNING_TOOLS.contains(&name) {
+                        continue;
+                    }
+                    self.pending.insert(
+                        id.to_string(),
+                        PendingTool {
+                            name: name.to_string(),
+                            summary: claude::tool_summary(name, &block["input"]),
+                            since: now,
+                            alerted: false,
+                        },
+                    );
+                }
+            }
+            Some("user") => {
+                let content = &entry["message"]["content"];
+                let text = content_text(content);
+                if text.starts_with("[Request interrupted by user") {
+                    self.pending.clear();
+                    self.waiting_since = None;
+                    return;
+                }
+                // 本地斜杠命令、压缩摘要等不会得到回复
+                let silent = ["isMeta", "isCompactSummary", "isVisibleInTranscriptOnly"]
+                    .iter()
+                    .any(|flag| entry[*flag].as_bool() == Some(true))
+                    || text.starts_with("<command-name>")
+                    || text.starts_with("<local-command");
+                if silent {
+                    return;
+                }
+                for block in content.as_array().into_iter().flatten() {
+                    if block["type"].as_str() != Some("tool_result") {
+                        continue;
+                    }
+                    let tool = block["tool_use_id"]
+                        .as_str()
+                        .and_then(|id| self.pending.remove(id))
+                        .map(|tool| tool.name);
+                    let message = content_text(&block["content"]);
+                    if block["is_error"].as_bool() == Some(true)
+                        && self.options.tool_errors
+                        && !message.contains(REJECTED)
+                    {
+                        alerts.push(Alert::Error { tool, message });
+                    }
+                }
+                self.waiting_since = Some(now);
+                self.stalled_alerted = false;
+            }
+            Some("system") if entry["level"].as_str() == Some("error") => {
+                alerts.push(Alert::Error {
+                    tool: None,
+                    message: content_text(&entry["content"]),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    pub fn notification(&self, alert: &Alert) -> Notification {
+        let strings = i18n::strings();
+        let (event, label, content, level) = match alert {
+            Alert::Error { tool, message } => {
+                let message = truncate(message);
+                let content = match tool {
+                    Some(tool) => format!("{}\n```\n{}\n```", tool, message),
+                    None => message,
+                };
+                let level = if tool.is_some() {
+                    Level::Warning
+                } else {
+                    Level::Critical
+                };
+                (
+                    AGENT_ERROR,
+                    strings.claude_error.to_string(),
+                    content,
+                    level,
+                )
+            }
+            Alert::Permission {
+                tool,
+                summary,
+                waited,
+            } => (
+                PERMISSION_REQUEST,
+                i18n::fill(strings.claude_tool, &[("tool", tool)]),
+                format!(
+                    "{}\n\n{}",
+                    summary,
+                    i18n::fill(
+                        strings.live_waiting,
+                        &[("duration", &format_duration(*waited))]
+                    )
+                ),
+                Level::Warning,
+            ),
+            Alert::Stalled { idle } => (
+                SESSION_STALLED,
+                i18n::fill(
+                    strings.live_stalled,
+                    &[("duration", &format_duration(*idle))],
+                ),
+                String::new(),
+                Level::Warning,
+            ),
+        };
+        let project = self
+            .cwd
+            .as_deref()
+            .and_then(|cwd| Path::new(cwd).file_name())
+            .and_then(|name| name.to_str());
+        let title = match project {
+            Some(project) => format!("{} · {}", label, project),
+            None => label,
+        };
+        let content = match (&self.cwd, content.is_empty()) {
+            (Some(cwd), true) => i18n::fill(strings.claude_cwd, &[("cwd", cwd)]),
+            _ => content,
+        };
+        Notification::new(event, title, content, level)
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    timeutil::format_duration(chrono::Duration::seconds(duration.as_secs() as i64))
+}
+
+/// 字符串或文本块数组中的文字
+fn content_text(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.trim().to_string(),
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter_map(|block| block["text"].as_str())
+            .map(str::trim)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+fn truncate(text: &str) -> String {
+    if text.chars().count() > MAX_ERROR_CHARS {
+        let kept: String = text.chars().take(MAX_ERROR_CHARS).collect();
+        format!("{}…", kept)
+    } else {
+        text.to_string()
+    }
+}
+
+/// Claude Code所有项目中最近写入的会话记录
+pub fn latest_transcript() -> Option<PathBuf> {
+    let projects = dirs::home_dir()?.join(".claude").join("projects");
+    std::fs::read_dir(projects)
+        .ok()?
+        .flatten()
+        .filter_map(|project| std::fs::read_dir(project.path()).ok())
+        .flatten()
+        .flatten()
+        .map(|file| file.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+        .filter_map(|path| {
+            let modified = path.metadata().ok()?.modified().ok()?;
+            Some((modified, path))
+        })
+        .max()
+        .map(|(_, path)| path)
+}
@@ -1,256 +1,1349 @@
-mod config;
-mod notifiers;
-
 use anyhow::Result;
 use chrono::Local;
-use clap::{Parser, Subcommand, ValueEnum};
-use notifiers::Notifier;
-use serde_json::json;
+use clap::{ArgGroup, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use claude_notifier::adapter::InputFormat;
+use claude_notifier::approval::{self, ApprovalConfig};
+use claude_notifier::batch;
+use claude_notifier::callback;
+use claude_notifier::channels::{self, ChannelInfo};
+use claude_notifier::ci::{self, CiMode};
+use claude_notifier::circuit_breaker::CircuitState;
+use claude_notifier::claude;
+use claude_notifier::completions;
+use claude_notifier::config::{self, ConfigFormat};
+use claude_notifier::doctor;
+use claude_notifier::encryption::{self, Key};
+use claude_notifier::error::{exit_code, NotifierError};
+use claude_notifier::extract;
+use claude_notifier::fifo;
+use claude_notifier::git;
+use claude_notifier::grpc::{self, GrpcConfig};
+use claude_notifier::health;
+use claude_notifier::history::{self, DeliveryState, HistoryEntry};
+use claude_notifier::i18n;
+use claude_notifier::ipc;
+use claude_notifier::live::{self, LiveOptions, LiveTranscript};
+use claude_notifier::manager::NotificationManager;
+use claude_notifier::mcp;
+use claude_notifier::metrics;
+use claude_notifier::migrate;
+use claude_notifier::notification::{CardAction, Level, Notification};
+use claude_notifier::notifiers::Image;
+use claude_notifier::preset::{self, Preset};
+use claude_notifier::redact;
+use claude_notifier::replay;
+use claude_notifier::routing::{self, glob_match};
+use claude_notifier::schedule::ScheduleStore;
+use claude_notifier::secret;
+use claude_notifier::severity;
+use claude_notifier::silence::{Silence, SilenceStore};
+use claude_notifier::systemd;
+use claude_notifier::timeutil;
+use claude_notifier::validate::{self, Severity};
+use claude_notifier::watch::{self, FileTail};
+use claude_notifier::wizard::Wizard;
+use regex::Regex;
+use serde_json::{json, Value};
+use std::cmp::Reverse;
 use std::collections::HashMap;
-use std::io::{self, Read};
-use std::sync::Arc;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
 
 #[derive(Parser)]
 #[command(name = "claude-notifier")]
 #[command(about = "High-performance notification manager for Teams, Feishu, and WeChat")]
 #[command(version)]
 struct Cli {
+    /// Output format for delivery results
+    #[arg(short, long, global = true, value_enum, default_value_t = OutputFormat::Json)]
+    output: OutputFormat,
+
+    /// Log level or filter (error, warn, info, debug, trace); overrides RUST_LOG
+    #[arg(long, global = true)]
+    log_level: Option<String>,
+
+    /// Append logs to this file instead of stderr
+    #[arg(long, global = true)]
+    log_file: Option<PathBuf>,
+
+    /// Use this configuration file instead of config.* in the config directory
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Use a separate profile (config and state under profiles/<name> in the usual directories)
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
+    /// Record every webhook request and response to fixtures in this directory
+    #[arg(long, global = true, value_name = "DIR", conflicts_with = "replay")]
+    record: Option<PathBuf>,
+
+    /// Answer webhook requests from fixtures recorded with --record instead of the network
+    #[arg(long, global = true, value_name = "DIR")]
+    replay: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Commands,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// JSON object with per-channel results
+    Json,
+    /// One human-readable line per channel
+    Text,
+    /// No output, only the exit code
+    Quiet,
+}
+
+// 命令只解析一次，变体大小差异无关紧要
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum Commands {
     /// Send a notification
     Send {
         /// Event type (e.g., build_success, build_failure, security_alert)
-        #[arg(short, long)]
-        event: String,
+        #[arg(short, long, required_unless_present = "batch")]
+        event: Option<String>,
 
         /// Notification title
-        #[arg(short = 't', long)]
-        title: String,
+        #[arg(short = 't', long, required_unless_present = "batch")]
+        title: Option<String>,
 
         /// Notification content
-        #[arg(short, long)]
-        content: String,
+        #[arg(short, long, required_unless_present = "batch")]
+        content: Option<String>,
 
-        /// Notification level (info, warning, critical, success)
-        #[arg(short = 'l', long, default_value = "info")]
-        level: String,
+        /// Notification level (info, warning, critical, success or a level defined under `levels`);
+        /// defaults to info, or to the job status where the CI system provides one
+        #[arg(short = 'l', long)]
+        level: Option<String>,
 
-        /// Specific channels to send to (overrides config)
-        #[arg(short = 'c', long, value_delimiter = ',')]
-        channels: Option<Vec<Channel>>,
+        /// Specific channels to send to (overrides config), e.g. teams,feishu or a named exec channel
+        #[arg(short = 'C', long, value_delimiter = ',')]
+        channels: Option<Vec<String>>,
 
-        /// Force send even during quiet hours
+        /// Force send even during quiet hours or a silence, if recently sent or throttled
         #[arg(short = 'f', long)]
         force: bool,
+
+        /// Deliver at a local time instead of now (e.g. 2024-06-01T09:00 or 09:00)
+        #[arg(long, conflicts_with = "delay")]
+        at: Option<String>,
+
+        /// Deliver after a delay instead of now (e.g. 30m, 2h)
+        #[arg(long = "in")]
+        delay: Option<String>,
+
+        /// Explicit deduplication key, e.g. a CI job ID; notifications sharing it are sent once
+        #[arg(long)]
+        dedup_key: Option<String>,
+
+        /// Attach an image (local file or http(s) URL), e.g. a screenshot
+        #[arg(long)]
+        image: Option<String>,
+
+        /// Attach a file, e.g. a build log (repeatable)
+        #[arg(long = "attach", value_name = "FILE")]
+        attachments: Vec<PathBuf>,
+
+        /// @-mention a user defined under `users` in the config (repeatable or comma-separated)
+        #[arg(long = "mention", value_name = "USER", value_delimiter = ',')]
+        mentions: Vec<String>,
+
+        /// Post as a reply in the thread of earlier notifications with the same key, e.g. a CI run ID
+        #[arg(long)]
+        thread_key: Option<String>,
+
+        /// Add a card button (repeatable): a URL opens a link, anything else is posted
+        /// back to the `serve` callback server, e.g. "Approve=approve"
+        #[arg(long = "action", value_name = "LABEL=VALUE")]
+        actions: Vec<CardAction>,
+
+        /// Add build details and a "View run" button from the CI environment:
+        /// auto (detect, the default), none, github, gitlab or jenkins
+        #[arg(long, default_value = "auto")]
+        ci: CiMode,
+
+        /// Send every notification in a file: JSON Lines, or CSV with a header row (by the .csv
+        /// extension). --event, --title, --content, --level, -C, --thread-key and --mention
+        /// fill in fields a row leaves out
+        #[arg(
+            long,
+            value_name = "FILE",
+            conflicts_with_all = ["at", "delay", "dedup_key", "image", "attachments", "actions"]
+        )]
+        batch: Option<PathBuf>,
+
+        /// Read a field from a differently named CSV column (repeatable), e.g. title=Subject
+        #[arg(long = "column", value_name = "FIELD=HEADER", value_parser = batch::parse_column, requires = "batch")]
+        columns: Vec<(String, String)>,
+
+        /// Send at most this many notifications per minute with --batch (critical rows are not limited)
+        #[arg(long, default_value_t = 60, value_parser = clap::value_parser!(u32).range(1..), requires = "batch")]
+        rate: u32,
+
+        /// Show what each channel would receive without sending anything
+        #[arg(long, conflicts_with_all = ["at", "delay"])]
+        dry_run: bool,
     },
 
     /// Process notification from stdin (for hook integration)
-    Hook,
+    Hook {
+        /// Input format: generic, claude, github, gitlab or jenkins (default: Claude Code input if recognized, otherwise generic)
+        #[arg(long)]
+        format: Option<InputFormat>,
+
+        /// Show what each channel would receive without sending anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Read one JSON notification per stdin line until EOF, sending each as it arrives
+    /// (e.g. `tail -f app.log | jq -c --unbuffered ... | claude-notifier stream`)
+    Stream {
+        /// Input format of every line: generic, claude, github, gitlab or jenkins (default: as for `hook`)
+        #[arg(long)]
+        format: Option<InputFormat>,
+
+        /// Show what each channel would receive without sending anything
+        #[arg(long)]
+        dry_run: bool,
+    },
 
-    /// Initialize configuration
-    Init,
+    /// Follow a log file and notify whenever a line matches a pattern
+    Watch {
+        /// File to follow; rotation and truncation are handled
+        file: PathBuf,
+
+        /// Regular expression matched against each new line
+        #[arg(short, long)]
+        pattern: String,
+
+        /// Event type of the notifications
+        #[arg(short, long, default_value = "log_match")]
+        event: String,
+
+        /// Notification title (default: "Match in <file>")
+        #[arg(short = 't', long)]
+        title: Option<String>,
+
+        /// Notification level (info, warning, critical, success or a level defined under `levels`)
+        #[arg(short = 'l', long, default_value = "warning")]
+        level: String,
+
+        /// Specific channels to send to (overrides config)
+        #[arg(short = 'C', long, value_delimiter = ',')]
+        channels: Option<Vec<String>>,
+
+        /// Also match the lines already in the file
+        #[arg(long)]
+        from_start: bool,
+
+        /// Exit after the first notification
+        #[arg(long)]
+        once: bool,
+
+        /// Seconds between checks; matches found in one check are sent together
+        #[arg(long, default_value_t = 1)]
+        interval: u64,
+
+        /// Show what each channel would receive without sending anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Follow a running Claude Code session and notify on errors, likely permission
+    /// prompts and long silences (e.g. when Claude runs on a remote server)
+    WatchSession {
+        /// Transcript (.jsonl) to follow (default: the most recently written one under ~/.claude/projects)
+        transcript: Option<PathBuf>,
+
+        /// Seconds a tool call may wait for its result before it is reported as a likely permission prompt
+        #[arg(long, default_value_t = 30)]
+        permission_secs: u64,
+
+        /// Minutes without a response before the session is reported as stalled
+        #[arg(long, default_value_t = 10)]
+        idle_minutes: u64,
+
+        /// Also report failed tool calls, not only API errors
+        #[arg(long)]
+        tool_errors: bool,
+
+        /// Specific channels to send to (overrides config)
+        #[arg(short = 'C', long, value_delimiter = ',')]
+        channels: Option<Vec<String>>,
+
+        /// Seconds between checks
+        #[arg(long, default_value_t = 2)]
+        interval: u64,
+
+        /// Show what each channel would receive without sending anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Run a Model Context Protocol server on stdio so Claude can notify you mid-task
+    /// (tools: send_notification, list_channels)
+    Mcp {
+        /// Show what each channel would receive without sending anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Set up the configuration: in a terminal, asks for channels, webhooks and quiet hours and
+    /// sends a test message to each channel; otherwise writes a skeleton with no channels
+    Init {
+        /// Configuration file format (json, toml, yaml)
+        #[arg(long, default_value = "json")]
+        format: ConfigFormat,
+
+        /// Write the skeleton without asking, even in a terminal
+        #[arg(long, conflicts_with_all = ["preset", "from_env"])]
+        skeleton: bool,
+
+        /// Start from a routing preset instead of asking: ci (build, test and deploy results,
+        /// no quiet hours), personal (Claude Code sessions, quiet at night) or oncall (alerts
+        /// through a fallback chain, unacknowledged critical notifications escalated)
+        #[arg(long)]
+        preset: Option<Preset>,
+
+        /// Take channels and other settings from CLAUDE_NOTIFIER_* environment variables
+        /// (e.g. CLAUDE_NOTIFIER_FEISHU_WEBHOOK) instead of asking
+        #[arg(long)]
+        from_env: bool,
+    },
+
+    /// Upgrade the configuration file to the current format (the original is backed up)
+    Migrate {
+        /// Only show what would change
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Inspect and edit the configuration file
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
 
     /// Test notification to specific channel
     Test {
-        /// Channel to test
-        #[arg(value_enum)]
-        channel: Channel,
+        /// Channel to test (teams, feishu, wechat or a named exec channel)
+        #[arg(required_unless_present = "all")]
+        channel: Option<String>,
+
+        /// Test every configured channel and report the result and latency of each
+        #[arg(long, conflicts_with = "channel")]
+        all: bool,
+
+        /// With --all, send to all channels at the same time
+        #[arg(long, requires = "all")]
+        parallel: bool,
+    },
+
+    /// Find out why notifications do not arrive: checks the configuration, channel reachability,
+    /// quiet hours, the clock and the Claude Code hook registration, with a fix for each problem
+    Doctor {
+        /// Print the checks as a JSON array
+        #[arg(long)]
+        json: bool,
+
+        /// Skip the checks that need the network (reachability, clock)
+        #[arg(long)]
+        offline: bool,
+    },
+
+    /// Check channels for monitoring systems such as Nagios or cron: prints a plugin-style status
+    /// line and exits 0 when healthy, 2 when a channel fails and 3 when the state is unknown
+    Health {
+        /// Channel to check (repeatable or comma-separated; default: every enabled channel)
+        #[arg(short = 'C', long = "channel", value_delimiter = ',')]
+        channels: Vec<String>,
+
+        /// Send a test message instead of only checking that the provider is reachable
+        #[arg(long)]
+        send: bool,
+
+        /// Print the result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show each configured channel: enabled or not, masked webhook or key, circuit breaker state,
+    /// last successful send and the events routed to it
+    Channels {
+        /// Print the channels as a JSON array
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Edit a sent notification in place, e.g. turn "Deploying…" into "Deploy succeeded"
+    #[command(group = ArgGroup::new("changes").required(true).multiple(true))]
+    Update {
+        /// History ID returned by send/hook
+        id: String,
+
+        /// New title
+        #[arg(short = 't', long, group = "changes")]
+        title: Option<String>,
+
+        /// New content
+        #[arg(short, long, group = "changes")]
+        content: Option<String>,
+
+        /// New level, which also changes the card color
+        #[arg(short = 'l', long, group = "changes")]
+        level: Option<String>,
+
+        /// Show the edited cards without sending anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Acknowledge a notification, cancelling its pending escalation
+    Ack {
+        /// Escalation ID or history ID returned by send/hook
+        id: String,
+    },
+
+    /// Deliver due scheduled sends, recurring notifications, escalations, digests, bursts
+    /// and alert storm summaries
+    /// (run periodically from cron, or use `daemon`)
+    RunScheduled,
+
+    /// Run in the foreground and process scheduled tasks periodically
+    Daemon {
+        /// Seconds between checks
+        #[arg(long, default_value_t = 30)]
+        interval: u64,
+
+        /// Serve Prometheus metrics on this address, e.g. 127.0.0.1:9464
+        #[arg(long)]
+        metrics_addr: Option<String>,
+
+        /// Install, enable and start a systemd user unit running the daemon with these options
+        #[arg(long)]
+        install_systemd: bool,
+
+        /// Don't accept `send` and `hook` requests on the daemon socket
+        #[arg(long)]
+        no_socket: bool,
+
+        /// Also read newline-delimited JSON notifications (the `hook` input format) from this FIFO,
+        /// created if missing
+        #[arg(long)]
+        fifo: Option<PathBuf>,
+    },
+
+    /// Run the callback server that receives card button clicks
+    Serve {
+        /// Listen address, overriding `callbacks.listen`
+        #[arg(long)]
+        listen: Option<String>,
+
+        /// Also serve the gRPC API on this address, overriding `grpc.listen` (needs the grpc feature)
+        #[arg(long)]
+        grpc_listen: Option<String>,
+    },
+
+    /// Send all accumulated digest notifications now
+    FlushDigest,
+
+    /// Show previously sent notifications
+    History {
+        /// Only entries newer than this (e.g. 1h, 2d or 2024-06-01T09:00)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Only entries whose event matches this glob
+        #[arg(long)]
+        event: Option<String>,
+
+        /// Only entries where at least one channel failed
+        #[arg(long)]
+        failed: bool,
+
+        /// Maximum number of entries to show (most recent)
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+
+        /// Print entries as JSON lines
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show the delivery state of each channel for a sent notification
+    Status {
+        /// Notification ID returned by send/hook
+        id: String,
     },
+
+    /// Re-send previously failed notifications to the channels that failed
+    Resend {
+        /// History ID to re-send
+        #[arg(required_unless_present = "failed", conflicts_with = "failed")]
+        id: Option<String>,
+
+        /// Re-send every failed notification that hasn't been re-sent yet
+        #[arg(long)]
+        failed: bool,
+
+        /// With --failed, only notifications newer than this (e.g. 1h, 2d)
+        #[arg(long, requires = "failed")]
+        since: Option<String>,
+    },
+
+    /// Manage notifications scheduled with send --at/--in
+    Schedule {
+        #[command(subcommand)]
+        action: ScheduleAction,
+    },
+
+    /// Suppress notifications for a while, e.g. during maintenance (`silence 2h --event deploy_*`)
+    #[command(args_conflicts_with_subcommands = true)]
+    Silence {
+        #[command(subcommand)]
+        action: Option<SilenceAction>,
+
+        /// How long to silence, e.g. 30m, 2h or 1d
+        duration: Option<String>,
+
+        /// Only events matching this glob (default: all events)
+        #[arg(long)]
+        event: Option<String>,
+
+        /// Only this channel, e.g. wechat or a named exec channel (default: all channels)
+        #[arg(long)]
+        channel: Option<String>,
+    },
+
+    /// Stop re-alerting for one known issue: notifications with this dedup key (or event name)
+    Snooze {
+        /// Dedup key (send --dedup-key, or `dedup_key` in the hook JSON) or event name
+        target: String,
+
+        /// How long to snooze, e.g. 30m, 1h or 1d
+        #[arg(long = "for", value_name = "DURATION")]
+        duration: String,
+    },
+
+    /// Manage webhook URLs and tokens stored in the system keyring
+    Secret {
+        #[command(subcommand)]
+        action: SecretAction,
+    },
+
+    /// Print a shell completion script; event and channel names are completed from the
+    /// current configuration (generate again after adding events or channels)
+    Completions {
+        /// Shell to generate the script for
+        shell: Shell,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Check the configuration for syntax errors, unknown keys, bad URLs and unknown channels
+    Validate {
+        /// Print diagnostics as a JSON array
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Print a value by dotted path (e.g. quiet_hours, channels.feishu.enabled); whole file when omitted
+    Get { path: Option<String> },
+
+    /// Set a value by dotted path, e.g. `config set channels.feishu.enabled true`
+    Set {
+        path: String,
+        /// New value; parsed as JSON unless the current value is a string
+        value: String,
+    },
+
+    /// Encrypt the configuration file with an age key file (generated when missing) or a
+    /// passphrase; it is decrypted transparently on load (requires --features encryption)
+    Encrypt {
+        /// Encrypt with a passphrase instead of a key file; hooks read it from
+        /// CLAUDE_NOTIFIER_PASSPHRASE
+        #[arg(long, conflicts_with = "key_file")]
+        passphrase: bool,
+        /// Key file to use (default: config.key next to the configuration; set
+        /// CLAUDE_NOTIFIER_KEY_FILE when it is elsewhere)
+        #[arg(long, value_name = "PATH")]
+        key_file: Option<PathBuf>,
+    },
+
+    /// Decrypt the configuration file back to plain text
+    Decrypt,
 }
 
-#[derive(Debug, Clone, ValueEnum)]
-enum Channel {
-    Teams,
-    Feishu,
-    Wechat,
+#[derive(Subcommand)]
+enum ScheduleAction {
+    /// List pending scheduled notifications
+    List,
+
+    /// Cancel a scheduled notification
+    Cancel {
+        /// Schedule ID returned by send --at/--in
+        id: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum SilenceAction {
+    /// List active silences
+    List,
+
+    /// Remove a silence, or all of them
+    Clear {
+        /// Silence ID shown by `silence list` (default: all)
+        id: Option<String>,
+    },
 }
 
-impl std::fmt::Display for Channel {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Channel::Teams => write!(f, "teams"),
-            Channel::Feishu => write!(f, "feishu"),
-            Channel::Wechat => write!(f, "wechat"),
+#[derive(Subcommand)]
+enum SecretAction {
+    /// Store a secret; reference it from config as "keyring:<alias>"
+    Set {
+        alias: String,
+        /// Secret value; read from stdin when omitted (keeps it out of shell history)
+        value: Option<String>,
+    },
+
+    /// Print a stored secret
+    Get { alias: String },
+
+    /// Remove a stored secret
+    Delete { alias: String },
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(code) => ExitCode::from(code),
+        Err(e) => {
+            eprintln!("Error: {}", redact::text(&format!("{:#}", e)));
+            let code = match e.downcast_ref::<NotifierError>() {
+                Some(NotifierError::ConfigError(_)) => exit_code::CONFIG,
+                Some(err) if err.is_retryable() => exit_code::TEMPFAIL,
+                _ => exit_code::FAILURE,
+            };
+            ExitCode::from(code)
         }
     }
 }
 
-struct NotificationManager {
-    config: config::Config,
-    notifiers: HashMap<String, Arc<dyn Notifier>>,
-    message_cache: HashMap<String, i64>,
+/// 配置文件及其修改时间，用于守护进程检测配置变化
+fn config_fingerprint() -> Vec<(PathBuf, Option<SystemTime>)> {
+    config::Config::source_files()
+        .into_iter()
+        .map(|path| {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+            (path, modified)
+        })
+        .collect()
 }
 
-impl NotificationManager {
-    fn new() -> Result<Self> {
-        let config = config::Config::load()?;
-        let mut notifiers = HashMap::new();
-
-        // 初始化Teams
-        if let Some(teams_config) = &config.channels.teams {
-            if teams_config.enabled && !teams_config.webhook.is_empty() {
-                notifiers.insert(
-                    "teams".to_string(),
-                    Arc::new(notifiers::teams::TeamsNotifier::new(
-                        teams_config.webhook.clone(),
-                    )) as Arc<dyn Notifier>,
-                );
+/// 收到SIGHUP时置位，守护进程在下一轮重新加载配置
+#[cfg(unix)]
+fn watch_sighup(flag: Arc<AtomicBool>) -> Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let mut hangup = runtime.block_on(async { signal(SignalKind::hangup()) })?;
+    thread::spawn(move || {
+        runtime.block_on(async {
+            while hangup.recv().await.is_some() {
+                flag.store(true, Ordering::SeqCst);
             }
-        }
+        })
+    });
+    Ok(())
+}
 
-        // 初始化飞书
-        if let Some(feishu_config) = &config.channels.feishu {
-            if feishu_config.enabled && !feishu_config.webhook.is_empty() {
-                notifiers.insert(
-                    "feishu".to_string(),
-                    Arc::new(notifiers::feishu::FeishuNotifier::new(
-                        feishu_config.webhook.clone(),
-                        feishu_config.at_all_on_critical,
-                    )) as Arc<dyn Notifier>,
-                );
+/// 解析静音时长（秒），必须为正
+fn positive_duration(s: &str) -> Result<i64> {
+    let duration = timeutil::parse_duration(s)?;
+    if duration <= chrono::Duration::zero() {
+        anyhow::bail!("The duration must be positive");
+    }
+    Ok(duration.num_seconds())
+}
+
+/// 根据各渠道的发送结果确定退出码
+fn results_exit_code(results: &HashMap<String, Value>) -> u8 {
+    if let Some("quiet_hours" | "duplicate" | "storm" | "silenced" | "collapsed" | "digested") =
+        results.get("status").and_then(|s| s.as_str())
+    {
+        return exit_code::SUPPRESSED;
+    }
+
+    let outcomes: Vec<bool> = results
+        .values()
+        .filter_map(|r| r["success"].as_bool())
+        .collect();
+    // 所有渠道都进入离线队列稍后补发，或被限流
+    if outcomes.is_empty()
+        && results
+            .values()
+            .any(|r| matches!(r["status"].as_str(), Some("queued" | "throttled")))
+    {
+        return exit_code::SUPPRESSED;
+    }
+    if outcomes.iter().all(|&ok| ok) {
+        exit_code::OK
+    } else if outcomes.iter().any(|&ok| ok) {
+        exit_code::PARTIAL
+    } else {
+        exit_code::ALL_FAILED
+    }
+}
+
+/// `test --all` 的退出码：未启用而跳过的渠道不算失败
+fn test_exit_code(results: &HashMap<String, Value>) -> u8 {
+    let tested: HashMap<String, Value> = results
+        .iter()
+        .filter(|(_, result)| !result["skipped"].is_string())
+        .map(|(name, result)| (name.clone(), result.clone()))
+        .collect();
+    results_exit_code(&tested)
+}
+
+/// 等待审批按钮的点击，把决定写到stdout；超时且没有 `on_timeout` 时不输出，由Claude Code照常询问
+fn await_approval(approval: &ApprovalConfig, id: &str) -> Result<()> {
+    let strings = i18n::strings();
+    let timeout = Duration::from_secs(approval.timeout_secs);
+    let (decision, reason) = match approval::wait(id, timeout)? {
+        Some((decision, click)) => {
+            let mut reason = i18n::fill(strings.decided_remotely, &[("source", &click.source)]);
+            if let Some(user) = &click.user {
+                reason.push_str(&format!(" ({})", user));
             }
+            (decision, reason)
         }
+        None => match approval.on_timeout {
+            Some(decision) => (
+                decision,
+                i18n::fill(strings.no_response, &[("seconds", &approval.timeout_secs)]),
+            ),
+            None => return Ok(()),
+        },
+    };
+    println!("{}", decision.hook_output(&reason));
+    Ok(())
+}
 
-        // 初始化微信
-        if let Some(wechat_config) = &config.channels.wechat {
-            if wechat_config.enabled && !wechat_config.key.is_empty() {
-                let notifier = match wechat_config.service {
-                    config::WechatServiceType::ServerChan => {
-                        notifiers::wechat::WechatNotifier::new_serverchan(wechat_config.key.clone())
-                    }
-                    config::WechatServiceType::PushPlus => {
-                        notifiers::wechat::WechatNotifier::new_pushplus(wechat_config.key.clone())
-                    }
-                };
-                notifiers.insert(
-                    "wechat".to_string(),
-                    Arc::new(notifier) as Arc<dyn Notifier>,
+/// 按输出格式打印发送结果
+fn print_results(
+    results: &HashMap<String, Value>,
+    output: OutputFormat,
+    pretty: bool,
+) -> Result<()> {
+    match output {
+        OutputFormat::Json if pretty => println!("{}", serde_json::to_string_pretty(results)?),
+        OutputFormat::Json => println!("{}", serde_json::to_string(results)?),
+        OutputFormat::Text => {
+            let mut keys: Vec<&String> = results.keys().filter(|k| *k != "summary").collect();
+            keys.sort();
+            for key in keys {
+                println!("{}: {}", key, describe_result(&results[key]));
+            }
+            // 汇总放在最后
+            if let Some(summary) = results.get("summary") {
+                println!(
+                    "{}",
+                    i18n::fill(
+                        i18n::strings().summary,
+                        &[
+                            ("total", &summary["total"]),
+                            ("succeeded", &summary["succeeded"]),
+                            ("failed", &summary["failed"]),
+                            ("suppressed", &summary["suppressed"]),
+                        ]
+                    )
                 );
             }
         }
+        OutputFormat::Quiet => {}
+    }
+    Ok(())
+}
 
-        Ok(Self {
-            config,
-            notifiers,
-            message_cache: HashMap::new(),
-        })
+/// `test --all` 的表格：每个渠道的结果和耗时
+fn print_test_table(results: &HashMap<String, Value>) {
+    let mut channels: Vec<&String> = results.keys().filter(|k| *k != "summary").collect();
+    channels.sort();
+    let width = channels.iter().map(|c| c.len()).max().unwrap_or(0).max(7);
+    println!("{:<width$}  {:>8}  RESULT", "CHANNEL", "LATENCY");
+    for channel in channels {
+        let result = &results[channel];
+        let latency = match result["elapsed_ms"].as_u64() {
+            Some(ms) => format!("{} ms", ms),
+            None => "-".to_string(),
+        };
+        let outcome = match result["skipped"].as_str() {
+            Some(reason) => format!("skipped ({})", reason),
+            None => describe_result(result),
+        };
+        println!("{:<width$}  {:>8}  {}", channel, latency, outcome);
+    }
+    if let Some(summary) = results.get("summary") {
+        println!(
+            "{}",
+            i18n::fill(
+                i18n::strings().summary,
+                &[
+                    ("total", &summary["total"]),
+                    ("succeeded", &summary["succeeded"]),
+                    ("failed", &summary["failed"]),
+                    ("suppressed", &summary["suppressed"]),
+                ]
+            )
+        );
     }
+}
 
-    fn is_quiet_hours(&self) -> bool {
-        if !self.config.quiet_hours.enabled {
-            return false;
+fn print_channels_table(list: &[ChannelInfo]) {
+    if list.is_empty() {
+        println!("No channels are configured");
+        return;
+    }
+    let rows: Vec<[String; 6]> = list
+        .iter()
+        .map(|channel| {
+            let circuit = match channel.circuit {
+                CircuitState::Disabled => "-".to_string(),
+                CircuitState::Closed {
+                    consecutive_failures: 0,
+                } => "closed".to_string(),
+                CircuitState::Closed {
+                    consecutive_failures,
+                } => format!("closed ({} failures)", consecutive_failures),
+                CircuitState::Open { opened_at, .. } => {
+                    format!("open since {}", timeutil::format_timestamp(opened_at))
+                }
+                CircuitState::HalfOpen { .. } => "half-open".to_string(),
+            };
+            [
+                channel.name.clone(),
+                if channel.enabled { "yes" } else { "no" }.to_string(),
+                channel.secret.clone().unwrap_or_else(|| "-".to_string()),
+                circuit,
+                channel
+                    .last_success
+                    .map_or_else(|| "never".to_string(), timeutil::format_timestamp),
+                if channel.events.is_empty() {
+                    "-".to_string()
+                } else {
+                    channel.events.join(", ")
+                },
+            ]
+        })
+        .collect();
+    let headers = [
+        "CHANNEL",
+        "ENABLED",
+        "SECRET",
+        "CIRCUIT",
+        "LAST SUCCESS",
+        "EVENTS",
+    ];
+    let mut widths = headers.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
         }
+    }
+    let print_row = |cells: [&str; 6]| {
+        let line: Vec<String> = cells
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    };
+    print_row(headers);
+    for row in &rows {
+        print_row(row.each_ref().map(String::as_str));
+    }
+}
 
-        let now = Local::now().format("%H:%M").to_string();
-        let start = &self.config.quiet_hours.start;
-        let end = &self.config.quiet_hours.end;
+/// 单个渠道结果的简短描述
+fn describe_result(result: &Value) -> String {
+    let strings = i18n::strings();
+    match result {
+        Value::String(s) => s.clone(),
+        _ => match result["success"].as_bool() {
+            Some(true) => match result["delivered_via"].as_str() {
+                Some(via) => i18n::fill(strings.delivered_via, &[("via", &via)]),
+                None => strings.delivered.to_string(),
+            },
+            Some(false) => {
+                // 备用链的错误在最后一跳中
+                let error = result["error"]
+                    .as_str()
+                    .or_else(|| result["hops"].as_array()?.last()?["error"].as_str())
+                    .unwrap_or(strings.not_delivered);
+                i18n::fill(strings.failed, &[("error", &error)])
+            }
+            None => match result["status"].as_str() {
+                Some(status) => status.to_string(),
+                None => result.to_string(),
+            },
+        },
+    }
+}
 
-        if start < end {
-            now >= *start && now <= *end
-        } else {
-            now >= *start || now <= *end
+/// 本地图片转为绝对路径，定时或延后发送时不依赖当前目录
+fn image_location(image: &str, base: &Path) -> Result<String> {
+    match Image::parse(image) {
+        Image::Url(url) => Ok(url),
+        Image::File(path) => {
+            let path = base.join(path);
+            if !path.is_file() {
+                anyhow::bail!("Image {} does not exist", path.display());
+            }
+            Ok(std::path::absolute(&path)?.display().to_string())
         }
     }
+}
 
-    fn should_send(&mut self, message_key: &str) -> bool {
-        let now = Local::now().timestamp();
+/// 附件转为绝对路径，相对路径相对于 `base`
+fn attachment_location(path: &Path, base: &Path) -> Result<String> {
+    let path = base.join(path);
+    if !path.is_file() {
+        anyhow::bail!("Attachment {} does not exist", path.display());
+    }
+    Ok(std::path::absolute(&path)?.display().to_string())
+}
 
-        if let Some(&last_sent) = self.message_cache.get(message_key) {
-            if now - last_sent < 300 {
-                // 5分钟去重
-                return false;
+/// 按配置确定级别并附上仓库状态；本进程发送和守护进程代为发送共用
+fn prepare_send(
+    manager: &NotificationManager,
+    notification: &mut Notification,
+    level: &str,
+    git_dir: Option<&Path>,
+) -> Result<()> {
+    let (level, custom_level) = manager.config().level(level)?;
+    notification.level = level;
+    notification.custom_level = custom_level;
+    let context = manager
+        .config()
+        .git
+        .as_ref()
+        .zip(git_dir)
+        .and_then(|(git, dir)| git::context(dir, git));
+    if let Some(context) = context {
+        context.apply(notification);
+    }
+    Ok(())
+}
+
+/// `send --batch`：先校验所有行，有无效的行时一条也不发送；同一批中重复的行只发送第一条
+fn send_batch(
+    path: &Path,
+    columns: &HashMap<String, String>,
+    defaults: &batch::Defaults,
+    per_minute: u32,
+    force: bool,
+    dry_run: bool,
+    output: OutputFormat,
+) -> Result<u8> {
+    let rows = batch::read(path, columns)?;
+    let mut manager = NotificationManager::new()?;
+    manager.set_dry_run(dry_run);
+    let names: Vec<String> = manager
+        .channels()
+        .into_iter()
+        .map(|(name, _)| name.to_string())
+        .collect();
+    let mut items = Vec::new();
+    let mut invalid = 0;
+    for row in &rows {
+        match batch::item(manager.config(), &names, row, defaults) {
+            Ok(item) => items.push(item),
+            Err(e) => {
+                error!("Row {}: {:#}", row.line, e);
+                invalid += 1;
             }
         }
+    }
+    if invalid > 0 {
+        anyhow::bail!(
+            "{} of {} rows are invalid; nothing was sent",
+            invalid,
+            rows.len()
+        );
+    }
+    // 先发送级别高的，同级别保持文件中的顺序
+    items.sort_by_key(|item| Reverse(item.notification.level));
 
-        self.message_cache.insert(message_key.to_string(), now);
+    let strings = i18n::strings();
+    let mut first_rows: HashMap<String, usize> = HashMap::new();
+    let mut pacer = batch::Pacer::new(per_minute);
+    let (mut sent, mut suppressed, mut failed, mut duplicates) = (0, 0, 0, 0);
+    for item in items {
+        let row = i18n::fill(strings.batch_row, &[("row", &item.line)]);
+        let key = batch::duplicate_key(&item.notification);
+        if let Some(first) = first_rows.get(&key) {
+            duplicates += 1;
+            let results = HashMap::from([
+                ("status".to_string(), json!("duplicate")),
+                ("duplicate_of".to_string(), json!(first)),
+            ]);
+            match output {
+                OutputFormat::Text => println!(
+                    "{}: {}",
+                    row,
+                    i18n::fill(strings.batch_duplicate, &[("row", first)])
+                ),
+                _ => print_batch_row(item.line, results, output)?,
+            }
+            continue;
+        }
+        first_rows.insert(key, item.line);
+        // critical不受每分钟条数的限制，也不占用其他级别的配额
+        if !dry_run && item.notification.level != Level::Critical {
+            pacer.wait();
+        }
+        let results = match manager.send_notification(&item.notification, item.channels, force) {
+            Ok(results) => {
+                match results_exit_code(&results) {
+                    exit_code::SUPPRESSED => suppressed += 1,
+                    exit_code::PARTIAL | exit_code::ALL_FAILED => failed += 1,
+                    _ => sent += 1,
+                }
+                results
+            }
+            Err(e) => {
+                failed += 1;
+                HashMap::from([(
+                    "error".to_string(),
+                    json!(redact::text(&format!("{:#}", e))),
+                )])
+            }
+        };
+        if output == OutputFormat::Text {
+            println!("{}:", row);
+        }
+        print_batch_row(item.line, results, output)?;
+    }
+    if output == OutputFormat::Text {
+        println!(
+            "{}",
+            i18n::fill(
+                strings.batch_summary,
+                &[
+                    ("rows", &rows.len()),
+                    ("sent", &sent),
+                    ("suppressed", &suppressed),
+                    ("failed", &failed),
+                    ("duplicates", &duplicates),
+                ]
+            )
+        );
+    }
+    if !dry_run {
+        run_due_tasks(&mut manager);
+    }
+    Ok(match failed {
+        0 => exit_code::OK,
+        _ if sent > 0 => exit_code::PARTIAL,
+        _ => exit_code::ALL_FAILED,
+    })
+}
 
-        // 清理过期缓存
-        self.message_cache.retain(|_, &mut v| now - v < 600);
+/// 每行一个JSON对象，带上行号；文本格式下逐个渠道列出
+fn print_batch_row(
+    line: usize,
+    mut results: HashMap<String, Value>,
+    output: OutputFormat,
+) -> Result<()> {
+    if output == OutputFormat::Json {
+        results.insert("row".to_string(), json!(line));
+    }
+    print_results(&results, output, false)
+}
 
-        true
+/// 按hook输入生成通知并发送，返回发送结果和需要等待的审批
+fn run_hook(
+    manager: &mut NotificationManager,
+    data: &Value,
+    format: Option<InputFormat>,
+    cwd: &Path,
+) -> Result<(HashMap<String, Value>, Option<ApprovalConfig>)> {
+    // Claude Code的原生输入按事件生成通知，旧格式的字段仍然优先
+    let input = match format {
+        None | Some(InputFormat::Claude) => claude::HookInput::parse(data),
+        Some(_) => None,
+    };
+    if format == Some(InputFormat::Claude) && input.is_none() {
+        anyhow::bail!("Input is not a Claude Code hook payload (no hook_event_name)");
     }
+    let derived = match format {
+        // CI等webhook的原生payload由适配器生成通知
+        Some(format) if input.is_none() => format.adapt(data)?,
+        _ => input.as_ref().map(|input| {
+            let mut notification = input.notification(manager.config());
+            // 细分的事件（例如 `permission_request`）没有配置路由时退回原事件
+            if notification.event != input.event()
+                && !routing::is_routed(manager.config(), &notification.event)
+            {
+                notification.event = input.event();
+            }
+            notification
+        }),
+    };
+    // 按 `extract` 从任意工具的JSON中取出通知字段，覆盖同名的顶层字段
+    let mut fields = data.clone();
+    if let Some(object) = fields.as_object_mut() {
+        for (name, value) in extract::fields(&manager.config().extract, data)? {
+            object.insert(name.to_string(), serde_json::Value::String(value));
+        }
+    }
+    let field = |name: &str, derived: Option<&str>, default: &'static str| {
+        fields[name]
+            .as_str()
+            .or(derived)
+            .unwrap_or(default)
+            .to_string()
+    };
+    let event = field(
+        "event",
+        derived.as_ref().map(|n| n.event.as_str()),
+        "notification",
+    );
+    let title = field(
+        "title",
+        derived.as_ref().map(|n| n.title.as_str()),
+        "Notification",
+    );
+    let content = field("content", derived.as_ref().map(|n| n.content.as_str()), "");
+    // 已知的级别名直接使用，否则按 `severity` 映射外部工具的严重程度
+    let config = manager.config();
+    let level = match fields["level"].as_str() {
+        Some(level) if config.level(level).is_ok() => level.to_string(),
+        explicit => severity::level(&config.severity, data)
+            .or_else(|| explicit.map(String::from))
+            .or_else(|| derived.as_ref().map(|n| n.level.to_string()))
+            .unwrap_or_else(|| "info".to_string()),
+    };
+    let (level, custom_level) = config.level(&level)?;
 
-    fn send_notification(
-        &mut self,
-        event_type: &str,
-        title: &str,
-        content: &str,
-        level: &str,
-        override_channels: Option<Vec<Channel>>,
-        force: bool,
-    ) -> Result<HashMap<String, serde_json::Value>> {
-        // 检查静默时段
-        if !force && self.is_quiet_hours() && level != "critical" {
-            return Ok(HashMap::from([(
-                "status".to_string(),
-                json!("quiet_hours"),
-            )]));
-        }
-
-        // 消息去重
-        // 使用chars()处理Unicode字符边界
-        let content_preview: String = content.chars().take(50).collect();
-        let message_key = format!("{}:{}:{}", event_type, title, content_preview);
-        if !self.should_send(&message_key) {
-            return Ok(HashMap::from([("status".to_string(), json!("duplicate"))]));
-        }
-
-        // 确定发送渠道
-        let channels = if let Some(override_channels) = override_channels {
-            override_channels.iter().map(|c| c.to_string()).collect()
-        } else {
-            self.config
-                .notifications
-                .get(event_type)
-                .cloned()
+    let mut notification = Notification::new(event, title, content, level);
+    notification.custom_level = custom_level;
+    notification.dedup_key = fields["dedup_key"]
+        .as_str()
+        .map(String::from)
+        .or_else(|| derived.as_ref().and_then(|n| n.dedup_key.clone()));
+    notification.thread_key = fields["thread_key"]
+        .as_str()
+        .map(String::from)
+        .or_else(|| derived.as_ref().and_then(|n| n.thread_key.clone()));
+    notification.image = data["image"]
+        .as_str()
+        .map(|image| image_location(image, cwd))
+        .transpose()?;
+    notification.attachments = data["attachments"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|path| path.as_str())
+        .map(|path| attachment_location(Path::new(path), cwd))
+        .collect::<Result<_>>()?;
+    notification.mentions = data["mentions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|name| name.as_str().map(String::from))
+        .collect();
+    match data.get("actions") {
+        Some(actions) => notification.actions = serde_json::from_value(actions.clone())?,
+        None => {
+            notification.actions = derived
+                .as_ref()
+                .map(|n| n.actions.clone())
                 .unwrap_or_default()
-        };
+        }
+    }
+    // 需要审批的工具调用带上批准/拒绝按钮
+    let approval = input
+        .as_ref()
+        .filter(|input| input.hook_event_name == "PreToolUse")
+        .and_then(|input| {
+            let approvals = manager.config().approvals.as_ref()?;
+            approvals
+                .matches(input.tool_name.as_deref().unwrap_or_default())
+                .then(|| approvals.clone())
+        });
+    if approval.is_some() {
+        let strings = i18n::strings();
+        for (label, value) in [
+            (strings.approve, approval::APPROVE_ACTION),
+            (strings.deny, approval::DENY_ACTION),
+        ] {
+            notification.actions.push(CardAction {
+                label: label.to_string(),
+                value: value.to_string(),
+            });
+        }
+    }
+    // 仓库状态取自Claude Code的工作目录；webhook的payload与本地仓库无关
+    let webhook = matches!(
+        format,
+        Some(InputFormat::Github | InputFormat::Gitlab | InputFormat::Jenkins)
+    );
+    if let Some(git) = manager.config().git.as_ref().filter(|_| !webhook) {
+        let dir = input
+            .as_ref()
+            .and_then(|input| input.cwd.as_deref())
+            .map_or(cwd, Path::new);
+        if let Some(context) = git::context(dir, git) {
+            context.apply(&mut notification);
+        }
+    }
+    notification.payload = Some(data.clone());
+    // 进度模式下同一会话的完成事件合并为一条消息；审批卡片单独发送，保留按钮
+    let progress = input.as_ref().filter(|input| {
+        approval.is_none()
+            && !input.session_id.is_empty()
+            && manager
+                .config()
+                .progress
+                .as_ref()
+                .is_some_and(|progress| progress.matches(&notification.event))
+    });
+    let results = match progress {
+        Some(input) => {
+            let key = format!("{}/{}", input.session_id, notification.event);
+            manager.send_progress(&notification, &key, input.progress_item())?
+        }
+        // 审批卡片有人在等，不受去重、摘要和静默时段影响
+        None => manager.send_notification(&notification, None, approval.is_some())?,
+    };
+    Ok((results, approval))
+}
 
-        // 颜色映射
-        let color = match level {
-            "info" => "0078D4",
-            "warning" => "FFA500",
-            "critical" => "DC3545",
-            "success" => "28A745",
-            _ => "0078D4",
-        };
+/// 转发来的请求的级别，用于同时到达的请求排序；判断方式与 `run_hook` 相同，但不生成整条通知
+fn request_priority(config: &config::Config, request: &ipc::Request) -> Level {
+    let level = match request {
+        ipc::Request::Send { level, .. } => Some(level.clone()),
+        ipc::Request::Hook { input, format, .. } => {
+            let extracted = extract::fields(&config.extract, input)
+                .ok()
+                .and_then(|fields| fields.into_iter().find(|(name, _)| *name == "level"))
+                .map(|(_, value)| value);
+            match extracted.as_deref().or(input["level"].as_str()) {
+                Some(level) if config.level(level).is_ok() => Some(level.to_string()),
+                _ => severity::level(&config.severity, input).or_else(|| {
+                    let derived = match format {
+                        None | Some(InputFormat::Claude) => {
+                            claude::HookInput::parse(input).map(|input| input.notification(config))
+                        }
+                        Some(format) => format.adapt(input).ok().flatten(),
+                    };
+                    derived.map(|n| n.level.to_string())
+                }),
+            }
+        }
+    };
+    level
+        .and_then(|level| config.level(&level).ok())
+        .map_or(Level::Info, |(level, _)| level)
+}
 
-        let mut results = HashMap::new();
+/// 守护进程处理 `send`/`hook` 转发来的请求
+fn handle_forwarded(
+    manager: &mut NotificationManager,
+    request: ipc::Request,
+) -> Result<ipc::Outcome> {
+    match request {
+        ipc::Request::Send {
+            mut notification,
+            level,
+            channels,
+            force,
+            git_dir,
+        } => {
+            prepare_send(manager, &mut notification, &level, git_dir.as_deref())?;
+            let results = manager.send_notification(&notification, channels, force)?;
+            Ok(ipc::Outcome {
+                results,
+                approval: None,
+            })
+        }
+        ipc::Request::Hook { input, format, cwd } => {
+            let (results, approval) = run_hook(manager, &input, format, &cwd)?;
+            Ok(ipc::Outcome { results, approval })
+        }
+    }
+}
 
-        // 发送到各个渠道
-        for channel in channels {
-            if let Some(notifier) = self.notifiers.get(&channel) {
-                // 对于critical级别的飞书消息，添加@all
-                let final_content = if level == "critical" && channel == "feishu" {
-                    format!("{}\n<at user_id='all'></at>", content)
-                } else {
-                    content.to_string()
-                };
+/// send/hook时顺带处理到期任务，失败不影响本次发送结果
+fn run_due_tasks(manager: &mut NotificationManager) {
+    if let Err(e) = manager.run_scheduled() {
+        error!("Failed to process scheduled tasks: {:#}", e);
+    }
+}
 
-                let result = notifier.send_card(title, &final_content, color, vec![]);
+/// 日志输出到stderr（或文件），stdout保留给结果JSON；默认只输出警告
+fn init_logging(level: Option<&str>, file: Option<&Path>) -> Result<()> {
+    let filter = match level {
+        Some(level) => EnvFilter::try_new(level)?,
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn")),
+    };
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
 
-                results.insert(
-                    channel.clone(),
-                    match result {
-                        Ok(val) => json!({"success": true, "response": val}),
-                        Err(e) => json!({"success": false, "error": e.to_string()}),
-                    },
-                );
-            }
+    match file {
+        Some(path) => {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            builder
+                .with_ansi(false)
+                .with_writer(redact::Writer(Mutex::new(file)))
+                .init();
         }
-
-        Ok(results)
+        // journald自带时间戳
+        None if systemd::under_journald() => builder
+            .with_ansi(false)
+            .without_time()
+            .with_writer(redact::Writer(io::stderr))
+            .init(),
+        None => builder
+            .with_ansi(io::stderr().is_terminal())
+            .with_writer(redact::Writer(io::stderr))
+            .init(),
     }
+    Ok(())
 }
 
-fn main() -> Result<()> {
+fn run() -> Result<u8> {
     let cli = Cli::parse();
+    init_logging(cli.log_level.as_deref(), cli.log_file.as_deref())?;
+    if let Some(profile) = &cli.profile {
+        config::Config::use_profile(profile)?;
+    }
+    if let Some(path) = &cli.config {
+        config::Config::use_config_file(path.clone());
+    }
+    if let Some(dir) = &cli.record {
+        replay::set_mode(replay::Mode::Record(dir.clone()))?;
+    } else if let Some(dir) = &cli.replay {
+        replay::set_mode(replay::Mode::Replay(dir.clone()))?;
+    }
 
-    match cli.command {
+    let results = match cli.command {
         Commands::Send {
             event,
             title,
@@ -258,50 +1351,1123 @@ fn main() -> Result<()> {
             level,
             channels,
             force,
+            at,
+            delay,
+            dedup_key,
+            image,
+            attachments,
+            mentions,
+            thread_key,
+            actions,
+            ci,
+            batch,
+            columns,
+            rate,
+            dry_run,
         } => {
+            if let Some(path) = batch {
+                let defaults = batch::Defaults {
+                    event,
+                    title,
+                    content,
+                    level,
+                    channels,
+                    thread_key,
+                    mentions,
+                };
+                let columns = columns.into_iter().collect();
+                return send_batch(&path, &columns, &defaults, rate, force, dry_run, cli.output);
+            }
+            let (Some(event), Some(title), Some(content)) = (event, title, content) else {
+                anyhow::bail!("--event, --title and --content are required");
+            };
+            let context = ci::context(ci);
+            let level = level
+                .or_else(|| context.as_ref()?.level.map(|level| level.to_string()))
+                .unwrap_or_else(|| "info".to_string());
+            let cwd = std::env::current_dir()?;
+            // 级别在确定配置后设置
+            let mut notification = Notification::new(event, title, content, Level::Info);
+            notification.dedup_key = dedup_key;
+            notification.image = image
+                .map(|image| image_location(&image, &cwd))
+                .transpose()?;
+            notification.attachments = attachments
+                .iter()
+                .map(|path| attachment_location(path, &cwd))
+                .collect::<Result<_>>()?;
+            notification.mentions = mentions;
+            notification.thread_key = thread_key;
+            notification.actions = actions;
+            // CI中已有分支和提交，不再重复仓库状态
+            let git_dir = match context {
+                Some(context) => {
+                    context.apply(&mut notification);
+                    None
+                }
+                None => Some(cwd),
+            };
+
+            let due_at = match (at, delay) {
+                (Some(at), _) => Some(timeutil::parse_datetime(&at)?),
+                (None, Some(delay)) => Some(Local::now() + timeutil::parse_duration(&delay)?),
+                (None, None) => None,
+            };
+            // 守护进程在运行时由它发送，省去加载配置和建立连接
+            if due_at.is_none() && !dry_run {
+                let request = ipc::Request::Send {
+                    notification: Box::new(notification.clone()),
+                    level: level.clone(),
+                    channels: channels.clone(),
+                    force,
+                    git_dir: git_dir.clone(),
+                };
+                if let Some(outcome) = ipc::forward(request)? {
+                    print_results(&outcome.results, cli.output, true)?;
+                    return Ok(results_exit_code(&outcome.results));
+                }
+            }
+
             let mut manager = NotificationManager::new()?;
-            let results =
-                manager.send_notification(&event, &title, &content, &level, channels, force)?;
-            println!("{}", serde_json::to_string_pretty(&results)?);
+            prepare_send(&manager, &mut notification, &level, git_dir.as_deref())?;
+            if let Some(due_at) = due_at {
+                let id = manager.schedule(&notification, channels, force, due_at.timestamp())?;
+                let result = HashMap::from([
+                    ("status".to_string(), json!("scheduled")),
+                    ("id".to_string(), json!(id)),
+                    ("due_at".to_string(), json!(due_at.to_rfc3339())),
+                ]);
+                print_results(&result, cli.output, true)?;
+                return Ok(exit_code::OK);
+            }
+
+            manager.set_dry_run(dry_run);
+            let results = manager.send_notification(&notification, channels, force)?;
+            print_results(&results, cli.output, true)?;
+            if !dry_run {
+                run_due_tasks(&mut manager);
+            }
+            results
         }
 
-        Commands::Hook => {
+        Commands::Hook { format, dry_run } => {
             // 从stdin读取JSON
             let mut input = String::new();
             io::stdin().read_to_string(&mut input)?;
 
             let data: serde_json::Value = serde_json::from_str(&input)?;
+            let cwd = std::env::current_dir()?;
 
-            let event = data["event"].as_str().unwrap_or("notification");
-            let title = data["title"].as_str().unwrap_or("Notification");
-            let content = data["content"].as_str().unwrap_or("");
-            let level = data["level"].as_str().unwrap_or("info");
+            // Claude Code等待hook结束，守护进程在运行时由它处理以缩短耗时
+            let forwarded = if dry_run {
+                None
+            } else {
+                ipc::forward(ipc::Request::Hook {
+                    input: data.clone(),
+                    format,
+                    cwd: cwd.clone(),
+                })?
+            };
+            let (results, approval) = match forwarded {
+                Some(outcome) => (outcome.results, outcome.approval),
+                None => {
+                    // 先读取配置，生成的文字使用配置的语言
+                    let mut manager = NotificationManager::new()?;
+                    manager.set_dry_run(dry_run);
+                    let outcome = run_hook(&mut manager, &data, format, &cwd)?;
+                    if !dry_run {
+                        run_due_tasks(&mut manager);
+                    }
+                    outcome
+                }
+            };
+            // 等待审批时stdout只输出hook决定
+            let approval = approval.filter(|_| !dry_run);
+            if approval.is_none() {
+                print_results(&results, cli.output, false)?;
+            }
+            if let Some(approval) = &approval {
+                // 没有渠道送达时无从点击，交回Claude Code询问
+                let delivered = results
+                    .get("summary")
+                    .and_then(|summary| summary["succeeded"].as_u64())
+                    .unwrap_or(0)
+                    > 0;
+                if let Some(id) = results
+                    .get("id")
+                    .and_then(Value::as_str)
+                    .filter(|_| delivered)
+                {
+                    await_approval(approval, id)?;
+                }
+                return Ok(exit_code::OK);
+            }
+            // Claude Code把hook的退出码2视为阻塞错误，部分失败按普通错误退出
+            return Ok(match results_exit_code(&results) {
+                exit_code::PARTIAL => exit_code::FAILURE,
+                code => code,
+            });
+        }
 
+        Commands::Stream { format, dry_run } => {
             let mut manager = NotificationManager::new()?;
-            let results = manager.send_notification(event, title, content, level, None, false)?;
-            println!("{}", serde_json::to_string(&results)?);
+            manager.set_dry_run(dry_run);
+            let cwd = std::env::current_dir()?;
+            // 单行出错不中断，结束时以失败退出
+            let mut failed = false;
+            for (number, line) in io::stdin().lock().lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let outcome = serde_json::from_str::<Value>(&line)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|data| run_hook(&mut manager, &data, format, &cwd));
+                match outcome {
+                    Ok((results, _)) => {
+                        print_results(&results, cli.output, false)?;
+                        // 静默和去重不算失败
+                        failed |= matches!(
+                            results_exit_code(&results),
+                            exit_code::PARTIAL | exit_code::ALL_FAILED
+                        );
+                    }
+                    Err(e) => {
+                        error!("Line {}: {:#}", number + 1, e);
+                        failed = true;
+                    }
+                }
+                if !dry_run {
+                    run_due_tasks(&mut manager);
+                }
+            }
+            return Ok(if failed {
+                exit_code::FAILURE
+            } else {
+                exit_code::OK
+            });
         }
 
-        Commands::Init => {
-            let config = config::Config::default();
-            config.save()?;
-            println!("Configuration initialized at: ~/.claude/notifiers/config.json");
-            println!("Please edit the configuration file to add your webhook URLs.");
+        Commands::Watch {
+            file,
+            pattern,
+            event,
+            title,
+            level,
+            channels,
+            from_start,
+            once,
+            interval,
+            dry_run,
+        } => {
+            let regex = Regex::new(&pattern)
+                .map_err(|e| anyhow::anyhow!("Invalid --pattern '{}': {}", pattern, e))?;
+            let mut manager = NotificationManager::new()?;
+            manager.set_dry_run(dry_run);
+            let (level, custom_level) = manager.config().level(&level)?;
+            let title = title.unwrap_or_else(|| {
+                i18n::fill(i18n::strings().watch_title, &[("file", &file.display())])
+            });
+            let mut tail = FileTail::open(&file, from_start)?;
+            info!(file = %file.display(), pattern, "watching");
+            loop {
+                let matched: Vec<String> = tail
+                    .read_lines()?
+                    .into_iter()
+                    .filter(|line| regex.is_match(line))
+                    .collect();
+                if !matched.is_empty() {
+                    let mut notification =
+                        Notification::new(&event, &title, watch::content(&matched), level);
+                    notification.custom_level = custom_level.clone();
+                    // 发送失败不中断跟踪
+                    match manager.send_notification(&notification, channels.clone(), false) {
+                        Ok(results) => {
+                            print_results(&results, cli.output, false)?;
+                            if once {
+                                break results;
+                            }
+                        }
+                        Err(e) => error!("Failed to send notification: {:#}", e),
+                    }
+                }
+                if !dry_run {
+                    run_due_tasks(&mut manager);
+                }
+                thread::sleep(Duration::from_secs(interval.max(1)));
+            }
+        }
+
+        Commands::WatchSession {
+            transcript,
+            permission_secs,
+            idle_minutes,
+            tool_errors,
+            channels,
+            interval,
+            dry_run,
+        } => {
+            let Some(transcript) = transcript.or_else(live::latest_transcript) else {
+                anyhow::bail!("No transcript found under ~/.claude/projects; pass its path");
+            };
+            let mut manager = NotificationManager::new()?;
+            manager.set_dry_run(dry_run);
+            let options = LiveOptions {
+                permission_after: Duration::from_secs(permission_secs),
+                idle_after: Duration::from_secs(idle_minutes * 60),
+                tool_errors,
+            };
+            let mut session = LiveTranscript::open(&transcript, options)?;
+            info!(transcript = %transcript.display(), "watching session");
+            loop {
+                for alert in session.poll()? {
+                    let notification = session.notification(&alert);
+                    // 发送失败不中断跟踪
+                    match manager.send_notification(&notification, channels.clone(), false) {
+                        Ok(results) => print_results(&results, cli.output, false)?,
+                        Err(e) => error!("Failed to send notification: {:#}", e),
+                    }
+                }
+                if !dry_run {
+                    run_due_tasks(&mut manager);
+                }
+                thread::sleep(Duration::from_secs(interval.max(1)));
+            }
         }
 
-        Commands::Test { channel } => {
+        Commands::Mcp { dry_run } => {
             let mut manager = NotificationManager::new()?;
-            let results = manager.send_notification(
+            manager.set_dry_run(dry_run);
+            info!("MCP server listening on stdio");
+            mcp::serve(&mut manager, io::stdin().lock(), io::stdout().lock())?;
+            return Ok(exit_code::OK);
+        }
+
+        Commands::Init {
+            format,
+            skeleton,
+            preset,
+            from_env,
+        } => {
+            let path = if preset.is_some() || from_env {
+                let mut config = config::Config::default();
+                if from_env {
+                    config = preset::from_env(&config, std::env::vars())?;
+                    let channels = doctor::enabled_channels(&config);
+                    preset::route_to(&mut config, &channels);
+                }
+                if let Some(preset) = preset {
+                    preset.apply(&mut config);
+                }
+                let (path, warnings) = validate::save_checked(&config, format)?;
+                println!("Configuration initialized at: {}", path.display());
+                if doctor::enabled_channels(&config).is_empty() {
+                    // 预设路由到尚未配置的内置渠道，警告都是意料之中的
+                    println!("Please edit the configuration file to add your webhook URLs.");
+                } else {
+                    for warning in &warnings {
+                        eprintln!("{}", warning);
+                    }
+                }
+                path
+            } else if skeleton || !io::stdin().is_terminal() {
+                let path = config::Config::default().save_as(format)?;
+                println!("Configuration initialized at: {}", path.display());
+                println!("Please edit the configuration file to add your webhook URLs.");
+                path
+            } else {
+                match Wizard::new(io::stdin().lock(), io::stdout()).run(format)? {
+                    Some(path) => path,
+                    None => return Ok(exit_code::OK),
+                }
+            };
+            let active = config::Config::config_path()?;
+            if active != path {
+                eprintln!(
+                    "Note: {} takes precedence; remove it to use the new file.",
+                    active.display()
+                );
+            }
+            HashMap::new()
+        }
+
+        Commands::Migrate { dry_run } => {
+            let path = config::Config::config_path()?;
+            if !path.exists() {
+                eprintln!("No configuration file at {}", path.display());
+                return Ok(exit_code::FAILURE);
+            }
+            let (_, format, mut value) = config::Config::read_raw()?;
+            let from = migrate::version_of(&value);
+            let changes = migrate::migrate(&mut value)?;
+            if changes.is_empty() {
+                println!(
+                    "{} is already at version {}",
+                    path.display(),
+                    migrate::CONFIG_VERSION
+                );
+                return Ok(exit_code::OK);
+            }
+            for change in &changes {
+                println!("{}", change);
+            }
+            if dry_run {
+                return Ok(exit_code::OK);
+            }
+
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let mut backup = path.with_file_name(format!("{}.v{}.bak", name, from));
+            if backup.exists() {
+                backup = path.with_file_name(format!(
+                    "{}.v{}.{}.bak",
+                    name,
+                    from,
+                    Local::now().timestamp()
+                ));
+            }
+            fs::copy(&path, &backup)?;
+            config::Config::write_raw(&path, format, &value)?;
+            println!(
+                "Migrated {} to version {} (backup: {})",
+                path.display(),
+                migrate::CONFIG_VERSION,
+                backup.display()
+            );
+            return Ok(exit_code::OK);
+        }
+
+        Commands::Config { action } => match action {
+            ConfigAction::Validate { json } => {
+                let path = config::Config::config_path()?;
+                let diagnostics = validate::validate(&path)?;
+                let errors = diagnostics
+                    .iter()
+                    .filter(|d| d.severity == Severity::Error)
+                    .count();
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&diagnostics)?);
+                } else if cli.output != OutputFormat::Quiet {
+                    for diagnostic in &diagnostics {
+                        println!("{}", diagnostic);
+                    }
+                    println!(
+                        "{}: {} error(s), {} warning(s)",
+                        path.display(),
+                        errors,
+                        diagnostics.len() - errors
+                    );
+                }
+                return Ok(if errors > 0 {
+                    exit_code::CONFIG
+                } else {
+                    exit_code::OK
+                });
+            }
+            ConfigAction::Get { path } => {
+                let (_, _, value) = config::Config::read_raw()?;
+                let Some(value) = config::get_path(&value, path.as_deref().unwrap_or_default())
+                else {
+                    eprintln!("No config value at {}", path.unwrap_or_default());
+                    return Ok(exit_code::FAILURE);
+                };
+                match value {
+                    Value::String(s) => println!("{}", s),
+                    other => println!("{}", serde_json::to_string_pretty(other)?),
+                }
+                return Ok(exit_code::OK);
+            }
+            ConfigAction::Set { path, value } => {
+                let file = config::Config::set_raw(&path, &value)?;
+                println!("Set {} in {}", path, file.display());
+                return Ok(exit_code::OK);
+            }
+            ConfigAction::Encrypt {
+                passphrase,
+                key_file,
+            } => {
+                let key = if passphrase {
+                    Key::Passphrase
+                } else {
+                    let default = encryption::key_file()?;
+                    let path = key_file.unwrap_or_else(|| default.clone());
+                    if !path.exists() {
+                        let recipient = encryption::generate_key_file(&path)?;
+                        println!("Generated key file {} ({})", path.display(), recipient);
+                        println!("Back it up: the configuration cannot be decrypted without it.");
+                    }
+                    if path != default {
+                        println!(
+                            "Set {}={} wherever the configuration is loaded.",
+                            encryption::KEY_FILE_VAR,
+                            path.display()
+                        );
+                    }
+                    Key::File(path)
+                };
+                let file = config::Config::encrypt_file(&key)?;
+                println!("Encrypted {}", file.display());
+                return Ok(exit_code::OK);
+            }
+            ConfigAction::Decrypt => {
+                let file = config::Config::decrypt_file()?;
+                println!("Decrypted {}", file.display());
+                return Ok(exit_code::OK);
+            }
+        },
+
+        Commands::Test {
+            channel,
+            all,
+            parallel,
+        } => {
+            let mut manager = NotificationManager::new()?;
+            let Some(channel) = channel.filter(|_| !all) else {
+                let results = manager.test_channels(None, parallel);
+                match cli.output {
+                    OutputFormat::Text => print_test_table(&results),
+                    output => print_results(&results, output, true)?,
+                }
+                return Ok(test_exit_code(&results));
+            };
+            let strings = i18n::strings();
+            let notification = Notification::new(
                 "test",
-                "Test Notification",
-                &format!("This is a test message from Claude Notifier to {}", channel),
-                "info",
-                Some(vec![channel]),
-                true,
-            )?;
-            println!("{}", serde_json::to_string_pretty(&results)?);
+                strings.test_title,
+                i18n::fill(strings.test_content, &[("channel", &channel)]),
+                Level::Info,
+            );
+            let results = manager.send_notification(&notification, Some(vec![channel]), true)?;
+            print_results(&results, cli.output, true)?;
+            results
         }
+
+        Commands::Doctor { json, offline } => {
+            let checks = doctor::run(offline);
+            let errors = checks
+                .iter()
+                .filter(|c| c.status == doctor::Status::Error)
+                .count();
+            let warnings = checks
+                .iter()
+                .filter(|c| c.status == doctor::Status::Warning)
+                .count();
+            if json {
+                println!("{}", serde_json::to_string_pretty(&checks)?);
+            } else if cli.output != OutputFormat::Quiet {
+                for check in &checks {
+                    println!("{}", check);
+                }
+                println!("{} error(s), {} warning(s)", errors, warnings);
+            }
+            return Ok(if errors > 0 {
+                exit_code::FAILURE
+            } else {
+                exit_code::OK
+            });
+        }
+
+        Commands::Health {
+            channels,
+            send,
+            json,
+        } => {
+            let report = match NotificationManager::new() {
+                Ok(manager) => health::check(&manager, &channels, send),
+                // 监控系统把无法检查视为UNKNOWN
+                Err(e) => {
+                    println!(
+                        "CLAUDE-NOTIFIER UNKNOWN - {}",
+                        redact::text(&format!("{:#}", e))
+                    );
+                    return Ok(health::State::Unknown as u8);
+                }
+            };
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else if cli.output != OutputFormat::Quiet {
+                println!("{}", report);
+            }
+            return Ok(report.state as u8);
+        }
+
+        Commands::Channels { json } => {
+            let manager = NotificationManager::new()?;
+            let list = channels::list(&manager, &history::read()?)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&list)?);
+            } else if cli.output != OutputFormat::Quiet {
+                print_channels_table(&list);
+            }
+            return Ok(exit_code::OK);
+        }
+
+        Commands::Update {
+            id,
+            title,
+            content,
+            level,
+            dry_run,
+        } => {
+            let entries = history::read()?;
+            let Some(entry) = entries.iter().find(|e| e.id == id) else {
+                eprintln!("No history entry with id {}", id);
+                return Ok(exit_code::FAILURE);
+            };
+            let message_ids = history::message_ids(&entries, &id).unwrap_or_default();
+            if message_ids.is_empty() {
+                eprintln!(
+                    "Notification {} has no editable messages (editing needs Feishu with app_id, app_secret and chat_id)",
+                    id
+                );
+                return Ok(exit_code::FAILURE);
+            }
+
+            let mut notification = entry.notification.clone();
+            notification.title = title.unwrap_or(notification.title);
+            notification.content = content.unwrap_or(notification.content);
+            let mut manager = NotificationManager::new()?;
+            if let Some(level) = level {
+                (notification.level, notification.custom_level) = manager.config().level(&level)?;
+            }
+            manager.set_dry_run(dry_run);
+            let results = manager.update(&notification, &message_ids)?;
+            print_results(&results, cli.output, true)?;
+            results
+        }
+
+        Commands::Ack { id } => {
+            let manager = NotificationManager::new()?;
+            if manager.ack(&id)? {
+                println!(
+                    "{}",
+                    i18n::fill(i18n::strings().acknowledged_id, &[("id", &id)])
+                );
+                return Ok(exit_code::OK);
+            }
+            eprintln!(
+                "{}",
+                i18n::fill(i18n::strings().no_pending_escalation, &[("id", &id)])
+            );
+            return Ok(exit_code::FAILURE);
+        }
+
+        Commands::RunScheduled => {
+            let mut manager = NotificationManager::new()?;
+            let results = manager.run_scheduled()?;
+            print_results(&results, cli.output, true)?;
+            HashMap::new()
+        }
+
+        Commands::Daemon {
+            interval,
+            metrics_addr,
+            install_systemd,
+            no_socket,
+            fifo,
+        } => {
+            if install_systemd {
+                let mut args = vec![std::env::current_exe()?.display().to_string()];
+                // 单元中的路径都用绝对路径
+                if let Some(path) = &cli.config {
+                    args.extend([
+                        "--config".into(),
+                        fs::canonicalize(path)?.display().to_string(),
+                    ]);
+                }
+                if let Some(profile) = &cli.profile {
+                    args.extend(["--profile".into(), profile.clone()]);
+                }
+                if let Some(level) = &cli.log_level {
+                    args.extend(["--log-level".into(), level.clone()]);
+                }
+                args.extend(["daemon".into(), "--interval".into(), interval.to_string()]);
+                if let Some(addr) = &metrics_addr {
+                    args.extend(["--metrics-addr".into(), addr.clone()]);
+                }
+                if no_socket {
+                    args.push("--no-socket".into());
+                }
+                if let Some(path) = &fifo {
+                    args.extend([
+                        "--fifo".into(),
+                        std::path::absolute(path)?.display().to_string(),
+                    ]);
+                }
+                let name = systemd::unit_name(cli.profile.as_deref());
+                let path = systemd::install(&name, &systemd::unit(&args))?;
+                println!("Installed and started {}", path.display());
+                println!("Logs: journalctl --user -u {} -f", name);
+                println!(
+                    "To keep it running after logout and start it at boot: loginctl enable-linger"
+                );
+                return Ok(exit_code::OK);
+            }
+            let mut manager = NotificationManager::new()?;
+            if let Some(addr) = &metrics_addr {
+                metrics::serve(addr)?;
+                info!("serving metrics on http://{}/metrics", addr);
+            }
+            let hangup = Arc::new(AtomicBool::new(false));
+            #[cfg(unix)]
+            watch_sighup(hangup.clone())?;
+
+            // send和hook通过socket把请求交给守护进程
+            let (requests, pending) = mpsc::channel();
+            if let Some(path) = &fifo {
+                fifo::listen(path, requests.clone())?;
+                info!(fifo = %path.display(), "reading notifications");
+            }
+            if !no_socket {
+                match ipc::listen(requests) {
+                    Ok(path) => info!(socket = %path.display(), "accepting forwarded requests"),
+                    Err(e) => warn!("Not accepting forwarded requests: {:#}", e),
+                }
+            }
+
+            info!(interval, "daemon started");
+            systemd::notify("READY=1")?;
+            let mut fingerprint = config_fingerprint();
+            loop {
+                // 配置文件变化或收到SIGHUP时重建渠道；队列都在磁盘上，不会丢失
+                let current = config_fingerprint();
+                let signalled = hangup.swap(false, Ordering::SeqCst);
+                if signalled || current != fingerprint {
+                    fingerprint = current;
+                    systemd::notify("RELOADING=1")?;
+                    match NotificationManager::new() {
+                        Ok(reloaded) => {
+                            manager = reloaded;
+                            info!(signalled, "configuration reloaded");
+                        }
+                        Err(e) => error!(
+                            "Failed to reload configuration, keeping the previous one: {:#}",
+                            e
+                        ),
+                    }
+                    systemd::notify("READY=1")?;
+                }
+
+                match manager.run_scheduled() {
+                    Ok(results) => {
+                        // 只输出有实际发送的任务
+                        let sent: HashMap<_, _> = results
+                            .into_iter()
+                            .filter(|(_, v)| v.as_object().is_some_and(|o| !o.is_empty()))
+                            .collect();
+                        if !sent.is_empty() {
+                            println!("{}", serde_json::to_string(&sent)?);
+                        }
+                    }
+                    Err(e) => error!("Failed to process scheduled tasks: {:#}", e),
+                }
+
+                // 等待下一轮时处理转发来的请求
+                let next = Instant::now() + Duration::from_secs(interval.max(1));
+                loop {
+                    let wait = next.saturating_duration_since(Instant::now());
+                    match pending.recv_timeout(wait) {
+                        Ok(request) => {
+                            // 同时到达的请求（例如子代理并发结束）先处理级别高的
+                            let mut requests = vec![request];
+                            requests.extend(pending.try_iter());
+                            requests.sort_by_cached_key(|request| {
+                                Reverse(request_priority(manager.config(), request.request()))
+                            });
+                            for request in requests {
+                                request.respond(|request| handle_forwarded(&mut manager, request))
+                            }
+                        }
+                        Err(mpsc::RecvTimeoutError::Timeout) => break,
+                        Err(mpsc::RecvTimeoutError::Disconnected) => {
+                            thread::sleep(wait);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        Commands::Schedule { action } => {
+            let mut store = ScheduleStore::load()?;
+            match action {
+                ScheduleAction::List => {
+                    for scheduled in store.pending() {
+                        println!(
+                            "{}  {}  [{}] {}: {}",
+                            scheduled.id,
+                            timeutil::format_timestamp(scheduled.due_at),
+                            scheduled.notification.level,
+                            scheduled.notification.event,
+                            scheduled.notification.title
+                        );
+                    }
+                }
+                ScheduleAction::Cancel { id } => {
+                    if !store.cancel(&id) {
+                        eprintln!("No scheduled notification with id {}", id);
+                        return Ok(exit_code::FAILURE);
+                    }
+                    store.save()?;
+                    println!("Cancelled {}", id);
+                }
+            }
+            HashMap::new()
+        }
+
+        Commands::History {
+            since,
+            event,
+            failed,
+            limit,
+            json,
+        } => {
+            let since = since.map(|s| timeutil::parse_since(&s)).transpose()?;
+            let entries: Vec<HistoryEntry> = history::read()?
+                .into_iter()
+                .filter(|e| since.is_none_or(|since| e.at >= since))
+                .filter(|e| {
+                    event
+                        .as_deref()
+                        .is_none_or(|pattern| glob_match(pattern, &e.notification.event))
+                })
+                .filter(|e| !failed || e.is_failed())
+                .collect();
+
+            for entry in &entries[entries.len().saturating_sub(limit)..] {
+                if json {
+                    println!("{}", serde_json::to_string(entry)?);
+                    continue;
+                }
+                let outcome = match &entry.status {
+                    Some(status) => status.clone(),
+                    None if entry.results.is_empty() => "no channels".to_string(),
+                    None => {
+                        let mut channels: Vec<String> = entry
+                            .results
+                            .iter()
+                            .map(|(channel, r)| match r["success"].as_bool() {
+                                Some(true) => format!("{} ok", channel),
+                                Some(false) => format!("{} FAILED", channel),
+                                None => {
+                                    format!("{} {}", channel, r["status"].as_str().unwrap_or("-"))
+                                }
+                            })
+                            .collect();
+                        channels.sort();
+                        channels.join(", ")
+                    }
+                };
+                println!(
+                    "{}  {}  [{}] {}: {}  ({})",
+                    entry.id,
+                    timeutil::format_timestamp(entry.at),
+                    entry.notification.level,
+                    entry.notification.event,
+                    entry.notification.title,
+                    outcome
+                );
+            }
+            HashMap::new()
+        }
+
+        Commands::Status { id } => {
+            let entries = history::read()?;
+            let (Some(entry), Some(states)) = (
+                entries.iter().find(|e| e.id == id),
+                history::delivery_states(&entries, &id),
+            ) else {
+                eprintln!("No notification with id {}", id);
+                return Ok(exit_code::FAILURE);
+            };
+
+            println!(
+                "{}  {}  [{}] {}: {}",
+                entry.id,
+                timeutil::format_timestamp(entry.at),
+                entry.notification.level,
+                entry.notification.event,
+                entry.notification.title
+            );
+            if let Some(status) = &entry.status {
+                println!("  not sent: {}", status);
+            }
+            for (channel, state) in &states {
+                println!("  {:<12} {}", channel, state);
+            }
+            return Ok(
+                if states
+                    .values()
+                    .all(|s| *s != DeliveryState::Retryable && *s != DeliveryState::Failed)
+                {
+                    exit_code::OK
+                } else {
+                    exit_code::FAILURE
+                },
+            );
+        }
+
+        Commands::Resend { id, failed, since } => {
+            let entries = history::read()?;
+            let targets: Vec<&HistoryEntry> = if failed {
+                let since = since.map(|s| timeutil::parse_since(&s)).transpose()?;
+                history::unresolved_failures(&entries)
+                    .into_iter()
+                    .filter(|e| since.is_none_or(|since| e.at >= since))
+                    .collect()
+            } else {
+                let id = id.unwrap_or_default();
+                let Some(entry) = entries.iter().find(|e| e.id == id) else {
+                    eprintln!("No history entry with id {}", id);
+                    return Ok(exit_code::FAILURE);
+                };
+                if !entry.is_failed() {
+                    eprintln!("Notification {} has no failed channels", id);
+                    return Ok(exit_code::FAILURE);
+                }
+                vec![entry]
+            };
+
+            let mut manager = NotificationManager::new()?;
+            let mut all_results = HashMap::new();
+            let mut channel_results = HashMap::new();
+            for entry in targets {
+                let results = manager.resend(entry)?;
+                for (channel, result) in &results {
+                    channel_results.insert(format!("{}/{}", entry.id, channel), result.clone());
+                }
+                all_results.insert(entry.id.clone(), json!(results));
+            }
+            print_results(&all_results, cli.output, true)?;
+            channel_results
+        }
+
+        Commands::Serve {
+            listen,
+            grpc_listen,
+        } => {
+            let config = config::Config::load()?;
+            let mut grpc = config.grpc;
+            if let Some(listen) = grpc_listen {
+                grpc.get_or_insert_with(GrpcConfig::default).listen = listen;
+            }
+            let Some(mut callbacks) = config.callbacks else {
+                let Some(grpc) = grpc else {
+                    eprintln!("No `callbacks` or `grpc` section in the configuration");
+                    return Ok(exit_code::FAILURE);
+                };
+                grpc::serve(&grpc)?;
+                return Ok(exit_code::OK);
+            };
+            if let Some(listen) = listen {
+                callbacks.listen = listen;
+            }
+            // 两个服务同时运行时gRPC在后台线程中，出错时结束整个进程
+            if let Some(grpc) = grpc {
+                thread::spawn(move || {
+                    if let Err(e) = grpc::serve(&grpc) {
+                        eprintln!("Error: {}", redact::text(&format!("{:#}", e)));
+                        std::process::exit(exit_code::FAILURE.into());
+                    }
+                });
+            }
+            callback::serve(&callbacks)?;
+            return Ok(exit_code::OK);
+        }
+
+        Commands::FlushDigest => {
+            let mut manager = NotificationManager::new()?;
+            let results = manager.flush_digest(true)?;
+            print_results(&results, cli.output, true)?;
+            HashMap::new()
+        }
+
+        Commands::Silence {
+            action,
+            duration,
+            event,
+            channel,
+        } => {
+            let mut store = SilenceStore::load()?;
+            let now = Local::now().timestamp();
+            match action {
+                Some(SilenceAction::List) => {
+                    for silence in store.active(now) {
+                        println!(
+                            "{}  until {} ({} left)  {}",
+                            silence.id,
+                            timeutil::format_timestamp(silence.until),
+                            timeutil::format_duration(chrono::Duration::seconds(
+                                silence.until - now
+                            )),
+                            silence.scope()
+                        );
+                    }
+                }
+                Some(SilenceAction::Clear { id }) => {
+                    let removed = store.clear(id.as_deref());
+                    if let (Some(id), 0) = (&id, removed) {
+                        eprintln!("No silence with id {}", id);
+                        return Ok(exit_code::FAILURE);
+                    }
+                    store.save()?;
+                    println!("Removed {} silence(s)", removed);
+                }
+                None => {
+                    let Some(duration) = duration else {
+                        anyhow::bail!(
+                            "Specify a duration (e.g. `silence 2h`), or use `silence list`"
+                        );
+                    };
+                    let until = now + positive_duration(&duration)?;
+                    let silence = Silence::new(event, channel, now, until);
+                    println!(
+                        "Silenced {} until {} ({})",
+                        silence.scope(),
+                        timeutil::format_timestamp(silence.until),
+                        silence.id
+                    );
+                    store.add(silence);
+                    store.save()?;
+                }
+            }
+            HashMap::new()
+        }
+
+        Commands::Snooze { target, duration } => {
+            let now = Local::now().timestamp();
+            let silence = Silence::snooze(target.clone(), now, now + positive_duration(&duration)?);
+            println!(
+                "Snoozed {} until {} ({}); `silence clear {}` ends it early",
+                target,
+                timeutil::format_timestamp(silence.until),
+                silence.id,
+                silence.id
+            );
+            let mut store = SilenceStore::load()?;
+            store.add(silence);
+            store.save()?;
+            HashMap::new()
+        }
+
+        Commands::Secret { action } => {
+            match action {
+                SecretAction::Set { alias, value } => {
+                    let value = match value {
+                        Some(value) => value,
+                        None => {
+                            let mut input = String::new();
+                            io::stdin().read_to_string(&mut input)?;
+                            input.trim_end_matches(['\r', '\n']).to_string()
+                        }
+                    };
+                    secret::set(&alias, &value)?;
+                    println!(
+                        "Stored {} (use \"{}{}\" in config)",
+                        alias,
+                        secret::KEYRING_PREFIX,
+                        alias
+                    );
+                }
+                SecretAction::Get { alias } => println!("{}", secret::get(&alias)?),
+                SecretAction::Delete { alias } => {
+                    secret::delete(&alias)?;
+                    println!("Deleted {}", alias);
+                }
+            }
+            HashMap::new()
+        }
+
+        Commands::Completions { shell } => {
+            // 配置有误时仍生成脚本，只是不补全事件和渠道名
+            let config = config::Config::load()
+                .map_err(|e| warn!("Completing without configured names: {:#}", e))
+                .ok();
+            completions::generate(shell, Cli::command(), config.as_ref(), &mut io::stdout());
+            return Ok(exit_code::OK);
+        }
+    };
+
+    Ok(results_exit_code(&results))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn results(entries: &[(&str, Value)]) -> HashMap<String, Value> {
+        entries
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect()
     }
 
-    Ok(())
+    #[test]
+    fn suppressed_statuses_exit_with_suppressed() {
+        for status in [
+            "quiet_hours",
+            "duplicate",
+            "storm",
+            "silenced",
+            "collapsed",
+            "digested",
+        ] {
+            let results = results(&[("status", json!(status))]);
+            assert_eq!(
+                results_exit_code(&results),
+                exit_code::SUPPRESSED,
+                "{}",
+                status
+            );
+        }
+    }
+
+    #[test]
+    fn channel_outcomes_decide_exit_code() {
+        let ok = json!({"success": true});
+        let failed = json!({"success": false});
+        assert_eq!(
+            results_exit_code(&results(&[("teams", ok.clone())])),
+            exit_code::OK
+        );
+        assert_eq!(
+            results_exit_code(&results(&[("teams", ok), ("feishu", failed.clone())])),
+            exit_code::PARTIAL
+        );
+        assert_eq!(
+            results_exit_code(&results(&[("teams", failed)])),
+            exit_code::ALL_FAILED
+        );
+    }
+
+    #[test]
+    fn queued_or_throttled_channels_exit_with_suppressed() {
+        let results = results(&[
+            ("teams", json!({"status": "queued"})),
+            ("feishu", json!({"status": "throttled"})),
+        ]);
+        assert_eq!(results_exit_code(&results), exit_code::SUPPRESSED);
+    }
+
+    #[test]
+    fn disabled_channels_do_not_fail_test_all() {
+        let skipped = json!({"success": false, "skipped": "not_enabled"});
+        let passed = results(&[
+            ("teams", json!({"success": true, "elapsed_ms": 120})),
+            ("wechat", skipped.clone()),
+        ]);
+        assert_eq!(test_exit_code(&passed), exit_code::OK);
+
+        let failed = results(&[
+            ("teams", json!({"success": false, "elapsed_ms": 120})),
+            ("wechat", skipped),
+        ]);
+        assert_eq!(test_exit_code(&failed), exit_code::ALL_FAILED);
+    }
+
+    #[test]
+    fn scheduled_notification_exits_ok() {
+        let results = results(&[("status", json!("scheduled"))]);
+        assert_eq!(results_exit_code(&results), exit_code::OK);
+    }
 }
@@ -0,0 +1,1698 @@
+use crate::burst::{self, BurstStore};
+use crate::callback::CallbackValue;
+use crate::circuit_breaker::CircuitBreaker;
+use crate::config::{Config, WechatServiceType};
+use crate::dedup::DedupCache;
+use crate::digest::{self, DigestEntry, DigestStore};
+use crate::error::NotifierError;
+use crate::escalation::{EscalationStore, PendingEscalation, ACK_ACTION};
+use crate::history::{self, HistoryEntry};
+use crate::http::ClientCache;
+use crate::i18n;
+use crate::markdown;
+use crate::metrics::Metrics;
+use crate::notification::{new_id, CardAction, Level, Notification};
+use crate::notifiers::{self, Action, BlockingNotifier, Image, Notifier};
+use crate::offline::{self, OfflineQueue, QueuedNotification};
+use crate::probe;
+use crate::progress::ProgressStore;
+use crate::provider;
+use crate::recurring::RecurringStore;
+use crate::redact;
+use crate::replay;
+use crate::routing::{self, Route};
+use crate::schedule::{ScheduleStore, ScheduledNotification};
+use crate::silence::SilenceStore;
+use crate::storm::{self, StormState, StormStore};
+use crate::template::Templates;
+use crate::thread::ThreadStore;
+use crate::throttle::ThrottleStore;
+use anyhow::Result;
+use chrono::{Local, TimeZone};
+use serde::Serialize;
+use serde_json::json;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, info_span, warn, Span};
+
+/// 一次发送在各渠道上的汇总
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct DeliverySummary {
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    /// 因静默时段、去重、摘要、熔断未发送或离线排队
+    pub suppressed: usize,
+}
+
+impl DeliverySummary {
+    /// 所有渠道都未发送，例如整条通知处于静默时段
+    fn suppressed(channels: usize) -> Self {
+        Self {
+            total: channels,
+            suppressed: channels,
+            ..Self::default()
+        }
+    }
+
+    fn of(results: &HashMap<String, serde_json::Value>) -> Self {
+        let mut summary = Self::default();
+        for result in results.values() {
+            summary.total += 1;
+            match result["success"].as_bool() {
+                Some(true) => summary.succeeded += 1,
+                Some(false) if result["skipped"].is_string() => summary.suppressed += 1,
+                Some(false) => summary.failed += 1,
+                None => summary.suppressed += 1,
+            }
+        }
+        summary
+    }
+}
+
+/// 本次发送的附件；`link` 为上传目标返回的链接
+struct Attachment {
+    path: PathBuf,
+    link: Option<String>,
+}
+
+impl Attachment {
+    fn name(&self) -> String {
+        self.path.file_name().map_or_else(
+            || self.path.display().to_string(),
+            |n| n.to_string_lossy().into_owned(),
+        )
+    }
+}
+
+pub struct NotificationManager {
+    config: Config,
+    notifiers: HashMap<String, Arc<dyn Notifier>>,
+    /// 各渠道并行发送，熔断状态和指标需要加锁
+    circuit_breaker: Mutex<CircuitBreaker>,
+    templates: Templates,
+    metrics: Mutex<Metrics>,
+    threads: Mutex<ThreadStore>,
+    /// 只演练路由、静默、去重和模板，不发送也不修改任何状态
+    dry_run: bool,
+}
+
+impl NotificationManager {
+    pub fn new() -> Result<Self> {
+        Self::with_config(Config::load()?)
+    }
+
+    /// 使用给定的配置而不是配置文件，例如 `init` 验证刚输入的渠道
+    pub fn with_config(config: Config) -> Result<Self> {
+        // webhook或key的格式不对时发送必然失败，不等到发送时才报HTTP错误
+        provider::verify(&config)?;
+        let mut notifiers = HashMap::new();
+        let mut clients = ClientCache::default();
+
+        // 初始化Teams
+        if let Some(teams_config) = &config.channels.teams {
+            if teams_config.enabled && !teams_config.webhook.is_empty() {
+                let client = clients.get(&teams_config.http.or(&config.http))?;
+                notifiers.insert(
+                    "teams".to_string(),
+                    Arc::new(
+                        notifiers::teams::TeamsNotifier::new(teams_config.webhook.clone())
+                            .with_client(client),
+                    ) as Arc<dyn Notifier>,
+                );
+            }
+        }
+
+        // 初始化飞书
+        if let Some(feishu_config) = &config.channels.feishu {
+            if feishu_config.enabled && !feishu_config.webhook.is_empty() {
+                let client = clients.get(&feishu_config.http.or(&config.http))?;
+                notifiers.insert(
+                    "feishu".to_string(),
+                    Arc::new(
+                        notifiers::feishu::FeishuNotifier::new(
+                            feishu_config.webhook.clone(),
+                            feishu_config.at_all_on_critical,
+                        )
+                        .with_secret(feishu_config.secret.clone())
+                        .with_app(
+                            feishu_config.app_id.clone(),
+                            feishu_config.app_secret.clone(),
+                        )
+                        .with_chat_id(feishu_config.chat_id.clone())
+                        .with_client(client),
+                    ) as Arc<dyn Notifier>,
+                );
+            }
+        }
+
+        // 初始化微信
+        if let Some(wechat_config) = &config.channels.wechat {
+            if wechat_config.enabled && !wechat_config.key.is_empty() {
+                let notifier = match wechat_config.service {
+                    WechatServiceType::ServerChan => {
+                        notifiers::wechat::WechatNotifier::new_serverchan(wechat_config.key.clone())
+                    }
+                    WechatServiceType::PushPlus => {
+                        notifiers::wechat::WechatNotifier::new_pushplus(wechat_config.key.clone())
+                    }
+                };
+                let client = clients.get(&wechat_config.http.or(&config.http))?;
+                notifiers.insert(
+                    "wechat".to_string(),
+                    Arc::new(notifier.with_client(client)) as Arc<dyn Notifier>,
+                );
+            }
+        }
+
+        // 初始化外部命令渠道
+        for (name, exec_config) in &config.channels.exec {
+            if exec_config.enabled && !exec_config.command.is_empty() {
+                notifiers.insert(
+                    name.clone(),
+                    Arc::new(
+                        notifiers::exec::ExecNotifier::new(
+                            exec_config.command.clone(),
+                            exec_config.args.clone(),
+                            exec_config.timeout_secs,
+                        )
+                        .with_max_content_bytes(exec_config.max_content_bytes)
+                        .with_markdown(exec_config.markdown.unwrap_or_default())
+                        .with_remote(exec_config.remote),
+                    ) as Arc<dyn Notifier>,
+                );
+            }
+        }
+
+        // 加载WASM插件，渠道名为插件文件名，不覆盖内置渠道
+        #[cfg(feature = "wasm-plugins")]
+        for plugin in notifiers::wasm::discover(&Config::config_dir()?.join("plugins")) {
+            if !notifiers.contains_key(plugin.name()) {
+                let client = clients.get(&config.http)?;
+                notifiers.insert(
+                    plugin.name().to_string(),
+                    Arc::new(plugin.with_client(client)) as Arc<dyn Notifier>,
+                );
+            }
+        }
+
+        let circuit_breaker = CircuitBreaker::load(config.circuit_breaker.clone())?;
+        let threads = ThreadStore::load(&config.threading)?;
+
+        Ok(Self {
+            config,
+            notifiers,
+            circuit_breaker: Mutex::new(circuit_breaker),
+            templates: Templates::load()?,
+            metrics: Mutex::new(Metrics::default()),
+            threads: Mutex::new(threads),
+            dry_run: false,
+        })
+    }
+
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run;
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// 配置中的所有渠道（含插件）及是否已启用并配置完整，按名称排序
+    pub fn channels(&self) -> Vec<(&str, bool)> {
+        let configured = &self.config.channels;
+        let mut names: Vec<&str> = [
+            configured.teams.as_ref().map(|_| "teams"),
+            configured.feishu.as_ref().map(|_| "feishu"),
+            configured.wechat.as_ref().map(|_| "wechat"),
+        ]
+        .into_iter()
+        .flatten()
+        .chain(configured.exec.keys().map(String::as_str))
+        .chain(self.notifiers.keys().map(String::as_str))
+        .collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+            .into_iter()
+            .map(|name| (name, self.notifiers.contains_key(name)))
+            .collect()
+    }
+
+    /// 直接向每个渠道（或 `only` 中的渠道）发送测试卡片并计时，不经路由、备用链、去重和历史记录；
+    /// `parallel` 为true时同时发送，未就绪的渠道标为跳过
+    pub fn test_channels(
+        &self,
+        only: Option<&[String]>,
+        parallel: bool,
+    ) -> HashMap<String, serde_json::Value> {
+        let strings = i18n::strings();
+        let mut results = HashMap::new();
+        let mut sends = Vec::new();
+        let selected = |name: &str| only.is_none_or(|only| only.iter().any(|c| c == name));
+        for (name, ready) in self
+            .channels()
+            .into_iter()
+            .filter(|(name, _)| selected(name))
+        {
+            let Some(notifier) = self.notifiers.get(name).filter(|_| ready).cloned() else {
+                results.insert(
+                    name.to_string(),
+                    json!({"success": false, "skipped": "not_enabled"}),
+                );
+                continue;
+            };
+            let notification = Notification::new(
+                "test",
+                strings.test_title,
+                i18n::fill(strings.test_content, &[("channel", &name)]),
+                Level::Info,
+            );
+            let color = self.color(notifier.as_ref(), name, &notification);
+            let name = name.to_string();
+            sends.push(async move {
+                let started = Instant::now();
+                let result = notifier
+                    .send_card(
+                        &notification.title,
+                        &notification.content,
+                        &color,
+                        Vec::new(),
+                    )
+                    .await;
+                (name, started.elapsed(), result)
+            });
+        }
+
+        let outcomes = notifiers::blocking::runtime().block_on(async {
+            let mut outcomes = Vec::new();
+            if parallel {
+                let mut tasks = tokio::task::JoinSet::new();
+                for send in sends {
+                    tasks.spawn(send);
+                }
+                while let Some(outcome) = tasks.join_next().await {
+                    match outcome {
+                        Ok(outcome) => outcomes.push(outcome),
+                        Err(e) => warn!("test send panicked: {}", e),
+                    }
+                }
+            } else {
+                for send in sends {
+                    outcomes.push(send.await);
+                }
+            }
+            outcomes
+        });
+        for (name, elapsed, result) in outcomes {
+            let elapsed_ms = elapsed.as_millis() as u64;
+            let result = match result {
+                Ok(response) => {
+                    json!({"success": true, "elapsed_ms": elapsed_ms, "response": response})
+                }
+                Err(e) => json!({
+                    "success": false,
+                    "elapsed_ms": elapsed_ms,
+                    "error": redact::text(&e.to_string()),
+                    "error_kind": e.kind()
+                }),
+            };
+            results.insert(name, result);
+        }
+        let summary = DeliverySummary::of(&results);
+        results.insert("summary".to_string(), json!(summary));
+        results
+    }
+
+    fn circuit_breaker(&self) -> MutexGuard<'_, CircuitBreaker> {
+        self.circuit_breaker
+            .lock()
+            .expect("circuit breaker lock poisoned")
+    }
+
+    fn metrics(&self) -> MutexGuard<'_, Metrics> {
+        self.metrics.lock().expect("metrics lock poisoned")
+    }
+
+    fn threads(&self) -> MutexGuard<'_, ThreadStore> {
+        self.threads.lock().expect("thread store lock poisoned")
+    }
+
+    fn should_send(&self, notification: &Notification) -> Result<bool> {
+        let Some((message_key, window)) = self.config.dedup.key_for(notification) else {
+            return Ok(true);
+        };
+        let now = Local::now().timestamp();
+
+        let _lock = DedupCache::lock()?;
+        let mut cache = DedupCache::load(now, self.config.dedup.max_window())?;
+        if !cache.check_and_record(&message_key, now, window) {
+            return Ok(false);
+        }
+        if !self.dry_run {
+            cache.save()?;
+        }
+
+        Ok(true)
+    }
+
+    pub fn send_notification(
+        &mut self,
+        notification: &Notification,
+        override_channels: Option<Vec<String>>,
+        force: bool,
+    ) -> Result<HashMap<String, serde_json::Value>> {
+        let policy = self
+            .config
+            .escalations
+            .iter()
+            .find(|p| p.matches(notification))
+            .cloned();
+
+        // 需要确认的通知带上确认按钮，点击后由回调服务取消升级
+        let mut notification = notification.clone();
+        if policy.is_some()
+            && self.config.callbacks.is_some()
+            && !notification.actions.iter().any(|a| a.value == ACK_ACTION)
+        {
+            notification.actions.push(CardAction {
+                label: i18n::strings().acknowledge.to_string(),
+                value: ACK_ACTION.to_string(),
+            });
+        }
+        // 先补发离线期间排队的通知，保持发送顺序
+        if let Err(e) = self.flush_offline() {
+            warn!("Failed to flush the offline queue: {:#}", e);
+        }
+        let mut results = self.dispatch(&notification, override_channels, force)?;
+
+        // 已发出的通知按策略安排升级
+        let dispatched = !results.contains_key("status") && !self.dry_run;
+        if let Some(policy) = policy.filter(|_| dispatched) {
+            let now = Local::now().timestamp();
+            let mut stages = policy.stages();
+            let escalation = PendingEscalation {
+                id: new_id(),
+                notification,
+                channels: stages.remove(0),
+                created_at: now,
+                due_at: now + policy.after_minutes * 60,
+                stages,
+                after_minutes: policy.after_minutes,
+                history_ids: results
+                    .get("id")
+                    .and_then(|id| id.as_str())
+                    .map(String::from)
+                    .into_iter()
+                    .collect(),
+                ack_reactions: policy.ack_reactions,
+            };
+            results.insert("escalation_id".to_string(), json!(escalation.id));
+
+            let mut store = EscalationStore::load()?;
+            store.schedule(escalation);
+            store.save()?;
+        }
+
+        Ok(results)
+    }
+
+    /// 进度模式：`key` 已有进度消息时就地更新并追加 `item`，否则发送新消息；
+    /// 没有渠道能编辑消息时每次都发送累计后的新消息
+    pub fn send_progress(
+        &mut self,
+        notification: &Notification,
+        key: &str,
+        item: String,
+    ) -> Result<HashMap<String, serde_json::Value>> {
+        let Some(config) = self.config.progress.clone() else {
+            return self.send_notification(notification, None, false);
+        };
+        let mut store = ProgressStore::load(&config)?;
+        let entry = store.push(key, item);
+
+        let mut notification = notification.clone();
+        if entry.count() > 1 {
+            notification.title = format!("{} (×{})", notification.title, entry.count());
+        }
+        notification.content = entry.content(config.max_items);
+
+        if !entry.message_ids.is_empty() {
+            let results = self.update(&notification, &entry.message_ids)?;
+            if DeliverySummary::of(&results).succeeded > 0 {
+                if !self.dry_run {
+                    store.save()?;
+                }
+                return Ok(results);
+            }
+            // 消息已被删除等原因无法编辑时另发一条
+            debug!(key = %key, "progress message not editable, sending a new one");
+        }
+
+        let results = self.send_notification(&notification, None, false)?;
+        if let Some(id) = results.get("id").and_then(|id| id.as_str()) {
+            entry.message_ids = history::message_ids(&history::read()?, id).unwrap_or_default();
+        }
+        if !self.dry_run {
+            store.save()?;
+        }
+        Ok(results)
+    }
+
+    /// 确认通知，取消尚未触发的升级
+    pub fn ack(&self, id: &str) -> Result<bool> {
+        let mut store = EscalationStore::load()?;
+        let found = store.ack(id);
+        store.save()?;
+        Ok(found)
+    }
+
+    /// 发送所有到期且未确认的升级通知
+    pub fn run_escalations(&mut self) -> Result<HashMap<String, serde_json::Value>> {
+        let mut store = EscalationStore::load()?;
+        let mut due = store.take_due(Local::now().timestamp());
+        if due.is_empty() {
+            return Ok(HashMap::new());
+        }
+        store.save()?;
+        // 同时到期的先发送级别高的
+        due.sort_by_key(|e| Reverse(e.notification.level));
+
+        // 表情回复也算确认，只有飞书应用机器人发送的消息能读取
+        let entries = if due.iter().any(|e| !e.history_ids.is_empty()) {
+            history::read().unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+
+        let mut all_results = HashMap::new();
+        let mut next_stages = Vec::new();
+        for mut escalation in due {
+            if self.acknowledged_by_reaction(&escalation, &entries) {
+                info!(id = %escalation.id, "acknowledged by reaction");
+                all_results.insert(escalation.id, json!({"status": "acknowledged"}));
+                continue;
+            }
+            let original = &escalation.notification;
+            let minutes = (escalation.due_at - escalation.created_at) / 60;
+            // 空渠道列表表示先提醒原渠道，之后才升级
+            let strings = i18n::strings();
+            let (prefix, channels) = if escalation.channels.is_empty() {
+                (strings.reminder, None)
+            } else {
+                (strings.escalated, Some(escalation.channels.clone()))
+            };
+            let mut notification = Notification::new(
+                original.event.clone(),
+                format!("{} {}", prefix, original.title),
+                format!(
+                    "{}\n\n{}",
+                    original.content,
+                    i18n::fill(
+                        strings.not_acknowledged,
+                        &[("minutes", &minutes), ("id", &escalation.id)]
+                    )
+                ),
+                original.level,
+            );
+            notification.image = original.image.clone();
+            notification.attachments = original.attachments.clone();
+            notification.mentions = original.mentions.clone();
+            notification.thread_key = original.thread_key.clone();
+            notification.actions = original.actions.clone();
+            notification.custom_level = original.custom_level.clone();
+            let results = self.dispatch(&notification, channels, true)?;
+            if let Some(id) = results.get("id").and_then(|id| id.as_str()) {
+                escalation.history_ids.push(id.to_string());
+            }
+            all_results.insert(escalation.id.clone(), json!(results));
+            next_stages.extend(escalation.next_stage(Local::now().timestamp()));
+        }
+
+        // 仍未确认的升级继续等待下一步
+        if !next_stages.is_empty() {
+            let mut store = EscalationStore::load()?;
+            for escalation in next_stages {
+                store.schedule(escalation);
+            }
+            store.save()?;
+        }
+
+        Ok(all_results)
+    }
+
+    /// 升级相关的任一消息收到了视为确认的表情回复
+    fn acknowledged_by_reaction(
+        &self,
+        escalation: &PendingEscalation,
+        entries: &[HistoryEntry],
+    ) -> bool {
+        for history_id in &escalation.history_ids {
+            let message_ids = history::message_ids(entries, history_id).unwrap_or_default();
+            for (channel, message_id) in message_ids {
+                let Some(notifier) = self.notifiers.get(&channel) else {
+                    continue;
+                };
+                match notifier.reactions_blocking(&message_id) {
+                    Ok(reactions) => {
+                        if reactions.iter().any(|r| escalation.is_acknowledged_by(r)) {
+                            return true;
+                        }
+                    }
+                    Err(e) => debug!(channel = %channel, "cannot read reactions: {}", e),
+                }
+            }
+        }
+        false
+    }
+
+    /// 发送摘要；`all` 为false时只在达到条数或时间条件时发送
+    pub fn flush_digest(&mut self, all: bool) -> Result<HashMap<String, serde_json::Value>> {
+        let mut store = DigestStore::load()?;
+        if store.is_empty()
+            || (!all && !store.is_due(&self.config.digest, Local::now().timestamp()))
+        {
+            return Ok(HashMap::new());
+        }
+
+        let groups = store.drain_by_channel();
+        store.save()?;
+
+        let mut all_results = HashMap::new();
+        for (channel, entries) in groups {
+            let notification = digest::combine(&entries);
+            let results = self.dispatch(&notification, Some(vec![channel.clone()]), true)?;
+            all_results.insert(channel, json!(results));
+        }
+
+        Ok(all_results)
+    }
+
+    /// 发送已结束的突发合并（`all` 时全部发送），按事件和渠道返回结果
+    pub fn flush_bursts(&mut self, all: bool) -> Result<HashMap<String, serde_json::Value>> {
+        let lock = BurstStore::lock()?;
+        let mut store = BurstStore::load()?;
+        if store.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let due = store.take_due(&self.config.burst, Local::now().timestamp(), all);
+        if due.is_empty() {
+            return Ok(HashMap::new());
+        }
+        store.save()?;
+        // 发送合并时不再持有锁
+        drop(lock);
+
+        let mut all_results = HashMap::new();
+        for (event, entries) in due {
+            let mut event_results = HashMap::new();
+            for (channel, entries) in digest::by_channel(entries) {
+                let notification = burst::combine(&event, &entries);
+                let results = self.dispatch(&notification, Some(vec![channel.clone()]), true)?;
+                event_results.insert(channel, json!(results));
+            }
+            all_results.insert(event, json!(event_results));
+        }
+
+        Ok(all_results)
+    }
+
+    /// 风暴期间到时发送被抑制条数的汇总；没有新通知时也据此发现风暴已结束
+    pub fn flush_storm(&mut self) -> Result<HashMap<String, serde_json::Value>> {
+        if !self.config.storm.enabled {
+            return Ok(HashMap::new());
+        }
+        let lock = StormStore::lock()?;
+        let mut store = StormStore::load()?;
+        if store.is_idle() {
+            return Ok(HashMap::new());
+        }
+        let now = Local::now().timestamp();
+        let state = store.tick(&self.config.storm, now, false);
+        let summary = store.take_summary(&self.config.storm, now, state == StormState::Ended);
+        store.save()?;
+        drop(lock);
+        if state == StormState::Ended {
+            info!("alert storm is over");
+        }
+        match summary {
+            Some((summary, channels)) => self.dispatch(&summary, Some(channels), true),
+            None => Ok(HashMap::new()),
+        }
+    }
+
+    /// 安排在指定时间发送，返回定时任务ID
+    pub fn schedule(
+        &self,
+        notification: &Notification,
+        override_channels: Option<Vec<String>>,
+        force: bool,
+        due_at: i64,
+    ) -> Result<String> {
+        let scheduled = ScheduledNotification {
+            id: new_id(),
+            notification: notification.clone(),
+            channels: override_channels,
+            force,
+            created_at: Local::now().timestamp(),
+            due_at,
+        };
+        let id = scheduled.id.clone();
+
+        let mut store = ScheduleStore::load()?;
+        store.add(scheduled);
+        store.save()?;
+        Ok(id)
+    }
+
+    /// 发送所有到期的定时通知
+    pub fn run_scheduled_sends(&mut self) -> Result<HashMap<String, serde_json::Value>> {
+        let mut store = ScheduleStore::load()?;
+        let mut due = store.take_due(Local::now().timestamp());
+        if due.is_empty() {
+            return Ok(HashMap::new());
+        }
+        store.save()?;
+        // 同时到期的先发送级别高的，同级别按到期时间
+        due.sort_by_key(|s| Reverse(s.notification.level));
+
+        let mut all_results = HashMap::new();
+        for scheduled in due {
+            let results = self.send_notification(
+                &scheduled.notification,
+                scheduled.channels,
+                scheduled.force,
+            )?;
+            all_results.insert(scheduled.id, json!(results));
+        }
+
+        Ok(all_results)
+    }
+
+    /// 发送到达触发时间的周期通知；错过的多次触发只补发一次
+    pub fn run_recurring(&mut self) -> Result<HashMap<String, serde_json::Value>> {
+        let mut jobs = self.config.recurring.clone();
+        if jobs.is_empty() {
+            return Ok(HashMap::new());
+        }
+        // 同一轮触发的先发送级别高的
+        jobs.sort_by_key(|job| Reverse(job.level));
+
+        let now = Local::now().timestamp();
+        let mut store = RecurringStore::load()?;
+        store.retain(&jobs);
+
+        let mut all_results = HashMap::new();
+        for job in jobs.iter().filter(|job| job.enabled) {
+            // 首次见到的任务从现在开始计时，不补发历史触发
+            let Some(last_run) = store.last_run(&job.name) else {
+                store.set_last_run(&job.name, now);
+                continue;
+            };
+            let next = match job.next_after(last_run) {
+                Ok(next) => next,
+                Err(e) => {
+                    all_results.insert(
+                        job.name.clone(),
+                        json!({"success": false, "error": redact::text(&e.to_string())}),
+                    );
+                    continue;
+                }
+            };
+            if next.is_none_or(|next| next > now) {
+                continue;
+            }
+
+            store.set_last_run(&job.name, now);
+            let mut notification =
+                Notification::new(&job.event, &job.title, &job.content, job.level);
+            notification.payload = Some(json!({
+                "recurring": job.name,
+                "scheduled_at": next.and_then(|ts| Local.timestamp_opt(ts, 0).single()).map(|t| t.to_rfc3339()),
+            }));
+            let results = self.send_notification(&notification, job.channels.clone(), false)?;
+            all_results.insert(job.name.clone(), json!(results));
+        }
+        store.save()?;
+
+        Ok(all_results)
+    }
+
+    /// 按顺序补发离线队列中的通知；仍然不可达时停止，剩余的留待下次
+    pub fn flush_offline(&mut self) -> Result<HashMap<String, serde_json::Value>> {
+        if self.dry_run {
+            return Ok(HashMap::new());
+        }
+        let mut queue = OfflineQueue::load()?;
+        if queue.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let expired = queue.expire(Local::now().timestamp(), &self.config.offline);
+        if expired > 0 {
+            warn!(
+                expired,
+                "dropped queued notifications older than max_age_hours"
+            );
+        }
+        if !self.is_online() {
+            debug!(
+                pending = queue.len(),
+                "offline, keeping queued notifications"
+            );
+            queue.save()?;
+            return Ok(HashMap::new());
+        }
+
+        let mut all_results = HashMap::new();
+        while let Some(queued) = queue.front_mut() {
+            let notification = queued.notification();
+            let id = new_id();
+            let (mut results, _) =
+                self.route_and_deliver(&notification, &id, Some(queued.channels.clone()), true)?;
+            let delivered = results.values().any(|r| r["success"] == true);
+            let unreachable = offline::mark_queued(&queued.id, &mut results);
+            if !delivered && !unreachable.is_empty() {
+                debug!(pending = queue.len(), "network still unreachable");
+                break;
+            }
+
+            // 补发记录为原记录的重发，历史中的最终状态随之更新
+            self.record(&id, &notification, &results, Some(&queued.history_id));
+            let summary = DeliverySummary::of(&results);
+            results.insert("id".to_string(), json!(id));
+            results.insert("summary".to_string(), json!(summary));
+            all_results.insert(queued.id.clone(), json!(results));
+            if unreachable.is_empty() {
+                queue.pop_front();
+            } else {
+                // 部分渠道仍不可达，留在队首
+                queued.channels = unreachable;
+                queued.history_id = id;
+                break;
+            }
+        }
+        queue.save()?;
+
+        Ok(all_results)
+    }
+
+    /// 处理所有到期任务：离线补发、升级、定时发送、周期通知、摘要；
+    /// 离线队列保持原有顺序，升级是未确认的重要通知，排在其他任务之前
+    pub fn run_scheduled(&mut self) -> Result<HashMap<String, serde_json::Value>> {
+        let offline = self.flush_offline()?;
+        let escalations = self.run_escalations()?;
+        let scheduled = self.run_scheduled_sends()?;
+        let recurring = self.run_recurring()?;
+        let digest = self.flush_digest(false)?;
+        let burst = self.flush_bursts(false)?;
+        let storm = self.flush_storm()?;
+
+        Ok(HashMap::from([
+            ("storm".to_string(), json!(storm)),
+            ("burst".to_string(), json!(burst)),
+            ("offline".to_string(), json!(offline)),
+            ("scheduled".to_string(), json!(scheduled)),
+            ("recurring".to_string(), json!(recurring)),
+            ("escalations".to_string(), json!(escalations)),
+            ("digest".to_string(), json!(digest)),
+        ]))
+    }
+
+    /// 发送并记录到历史
+    fn dispatch(
+        &mut self,
+        notification: &Notification,
+        override_channels: Option<Vec<String>>,
+        force: bool,
+    ) -> Result<HashMap<String, serde_json::Value>> {
+        let _span =
+            info_span!("dispatch", event = %notification.event, level = %notification.level)
+                .entered();
+        // 先分配记录ID，按钮回调据此关联到通知
+        let id = new_id();
+        let (mut results, mut summary) =
+            self.route_and_deliver(notification, &id, override_channels, force)?;
+        if !self.dry_run {
+            if self.queue_unreachable(&id, notification, &mut results)? {
+                summary = DeliverySummary::of(&results);
+            }
+            self.record(&id, notification, &results, None);
+            results.insert("id".to_string(), json!(id));
+        }
+        results.insert("summary".to_string(), json!(summary));
+        Ok(results)
+    }
+
+    /// 因网络不可达失败的渠道进入离线队列，结果改为 `queued`；返回是否有渠道排队
+    fn queue_unreachable(
+        &self,
+        history_id: &str,
+        notification: &Notification,
+        results: &mut HashMap<String, serde_json::Value>,
+    ) -> Result<bool> {
+        if !self.config.offline.enabled {
+            return Ok(false);
+        }
+        let id = new_id();
+        let channels = offline::mark_queued(&id, results);
+        if channels.is_empty() {
+            return Ok(false);
+        }
+
+        let mut queue = OfflineQueue::load()?;
+        let dropped = queue.push(
+            QueuedNotification {
+                id,
+                notification: notification.clone(),
+                channels,
+                history_id: history_id.to_string(),
+                queued_at: Local::now().timestamp(),
+            },
+            &self.config.offline,
+        );
+        if dropped > 0 {
+            warn!(
+                dropped,
+                "offline queue is full, dropped the oldest notifications"
+            );
+        }
+        queue.save()?;
+        info!(
+            pending = queue.len(),
+            "network unreachable, queued for later"
+        );
+        Ok(true)
+    }
+
+    /// 把失败的历史记录重新发送到失败的渠道
+    pub fn resend(&mut self, entry: &HistoryEntry) -> Result<HashMap<String, serde_json::Value>> {
+        let channels: Vec<String> = entry
+            .failed_channels()
+            .into_iter()
+            .map(String::from)
+            .collect();
+        for channel in &channels {
+            self.metrics().record_resend(channel);
+        }
+        let id = new_id();
+        let (mut results, summary) =
+            self.route_and_deliver(&entry.notification, &id, Some(channels), true)?;
+        self.record(&id, &entry.notification, &results, Some(&entry.id));
+        results.insert("id".to_string(), json!(id));
+        results.insert("summary".to_string(), json!(summary));
+        Ok(results)
+    }
+
+    /// 就地编辑已发送的消息，`message_ids` 为各渠道的消息ID；不重新@用户，也不附带附件
+    pub fn update(
+        &self,
+        notification: &Notification,
+        message_ids: &BTreeMap<String, String>,
+    ) -> Result<HashMap<String, serde_json::Value>> {
+        let route = routing::resolve(&self.config, notification)?;
+        let mut results = HashMap::new();
+        for (channel, message_id) in message_ids {
+            let Some(notifier) = self.notifiers.get(channel) else {
+                results.insert(
+                    channel.clone(),
+                    json!({"success": false, "skipped": "not_configured"}),
+                );
+                continue;
+            };
+            let content = self.render_content(
+                notifier.as_ref(),
+                channel,
+                notification,
+                route.template.as_deref(),
+            );
+            let content = match notifier.max_content_bytes() {
+                Some(max) => notifiers::truncate(&content, max).into_owned(),
+                None => content,
+            };
+
+            if self.dry_run {
+                let payload = notifier.card_request(
+                    &notification.title,
+                    &content,
+                    &self.color(notifier.as_ref(), channel, notification),
+                    vec![],
+                );
+                results.insert(
+                    channel.clone(),
+                    json!({
+                        "success": true,
+                        "dry_run": true,
+                        "message_id": message_id,
+                        "payload": redact::json(&payload)
+                    }),
+                );
+                continue;
+            }
+
+            let result = match notifier.update_card_blocking(
+                message_id,
+                &notification.title,
+                &content,
+                &self.color(notifier.as_ref(), channel, notification),
+                vec![],
+            ) {
+                Ok(response) => {
+                    debug!(channel = %channel, "updated");
+                    json!({"success": true, "message_id": message_id, "response": response})
+                }
+                Err(e) => {
+                    info!(channel = %channel, "update failed: {}", e);
+                    json!({
+                        "success": false,
+                        "message_id": message_id,
+                        "error": redact::text(&e.to_string()),
+                        "error_kind": e.kind(),
+                        "retryable": e.is_retryable()
+                    })
+                }
+            };
+            results.insert(channel.clone(), result);
+        }
+
+        let summary = DeliverySummary::of(&results);
+        results.insert("summary".to_string(), json!(summary));
+        Ok(results)
+    }
+
+    /// 联网探测的结果；未配置探测或回放录制的请求时视为在线
+    fn is_online(&self) -> bool {
+        let Some(config) = &self.config.probe else {
+            return true;
+        };
+        if matches!(replay::mode(), Some(replay::Mode::Replay(_))) {
+            return true;
+        }
+        probe::is_online(config, &probe::targets(&self.config))
+    }
+
+    /// 写入历史记录，写入失败不影响发送结果
+    fn record(
+        &self,
+        id: &str,
+        notification: &Notification,
+        results: &HashMap<String, serde_json::Value>,
+        resent_from: Option<&str>,
+    ) {
+        let status = results
+            .get("status")
+            .and_then(|s| s.as_str())
+            .map(String::from);
+        let entry = HistoryEntry {
+            id: id.to_string(),
+            at: Local::now().timestamp(),
+            notification: notification.clone(),
+            results: if status.is_some() {
+                HashMap::new()
+            } else {
+                results.clone()
+            },
+            status,
+            resent_from: resent_from.map(String::from),
+        };
+        if let Err(e) = history::append(&entry) {
+            warn!("Failed to write history: {:#}", e);
+        }
+    }
+
+    fn route_and_deliver(
+        &mut self,
+        notification: &Notification,
+        id: &str,
+        override_channels: Option<Vec<String>>,
+        force: bool,
+    ) -> Result<(HashMap<String, serde_json::Value>, DeliverySummary)> {
+        // 确定发送渠道
+        let mut route = routing::resolve(&self.config, notification)?;
+        if let Some(override_channels) = override_channels {
+            route.channels = override_channels;
+        }
+        let channels = std::mem::take(&mut route.channels);
+
+        // 手动静音（`silence` 命令），所有渠道都已静音时不发送
+        let now = Local::now();
+        let silences = if force {
+            Vec::new()
+        } else {
+            SilenceStore::load()?.matching(notification, now.timestamp())
+        };
+        let silenced = |channel: &str| silences.iter().any(|s| s.covers(channel));
+        let all_silenced = !channels.is_empty()
+            && channels
+                .iter()
+                .all(|c| routing::parse_chain(c).iter().all(|hop| silenced(hop)));
+        if all_silenced {
+            let ids: Vec<&str> = silences.iter().map(|s| s.id.as_str()).collect();
+            debug!(silences = ?ids, "silenced");
+            return Ok((
+                HashMap::from([
+                    ("status".to_string(), json!("silenced")),
+                    ("silences".to_string(), json!(ids)),
+                ]),
+                DeliverySummary::suppressed(channels.len()),
+            ));
+        }
+
+        // 检查静默时段，所有渠道都在静默时段内时不发送
+        let quiet_hours = self.config.quiet_hours.clone();
+        let applies = !force && quiet_hours.applies_to(notification);
+        let quiet = |channel: &str| applies && quiet_hours.is_quiet(channel, &now);
+        let all_quiet = !channels.is_empty()
+            && channels
+                .iter()
+                .all(|c| routing::parse_chain(c).iter().all(|hop| quiet(hop)));
+        if all_quiet {
+            debug!("all channels are in quiet hours");
+            return Ok((
+                HashMap::from([("status".to_string(), json!("quiet_hours"))]),
+                DeliverySummary::suppressed(channels.len()),
+            ));
+        }
+
+        // 消息去重，强制发送时跳过
+        if !force && !self.should_send(notification)? {
+            debug!("suppressed as duplicate");
+            return Ok((
+                HashMap::from([("status".to_string(), json!("duplicate"))]),
+                DeliverySummary::suppressed(channels.len()),
+            ));
+        }
+
+        // 全局速率过高时进入风暴模式，只发送紧急通知，其余的计数后定期汇总
+        if !force && self.config.storm.enabled {
+            let now = Local::now().timestamp();
+            let lock = StormStore::lock()?;
+            let mut store = StormStore::load()?;
+            let state = store.tick(&self.config.storm, now, true);
+            let suppress = state.is_storming() && notification.level != Level::Critical;
+            if suppress {
+                store.suppress(notification, &channels);
+            }
+            let summary = store.take_summary(&self.config.storm, now, state == StormState::Ended);
+            if !self.dry_run {
+                store.save()?;
+                // 发送提示和汇总时不再持有锁
+                drop(lock);
+                if state == StormState::Started {
+                    warn!("alert storm, only critical notifications are sent");
+                    self.dispatch(
+                        &storm::started(&self.config.storm),
+                        Some(channels.clone()),
+                        true,
+                    )?;
+                }
+                if let Some((summary, summary_channels)) = summary {
+                    self.dispatch(&summary, Some(summary_channels), true)?;
+                }
+            }
+            if suppress {
+                debug!("suppressed by alert storm");
+                return Ok((
+                    HashMap::from([("status".to_string(), json!("storm"))]),
+                    DeliverySummary::suppressed(channels.len()),
+                ));
+            }
+        }
+
+        // 低优先级通知进入摘要，达到条件时合并发送
+        if !force && self.config.digest.matches(notification) {
+            let summary = DeliverySummary::suppressed(channels.len());
+            if self.dry_run {
+                return Ok((
+                    HashMap::from([
+                        ("status".to_string(), json!("digested")),
+                        ("channels".to_string(), json!(channels)),
+                    ]),
+                    summary,
+                ));
+            }
+            let now = Local::now().timestamp();
+            let mut store = DigestStore::load()?;
+            store.push(DigestEntry {
+                notification: notification.clone(),
+                channels,
+                at: now,
+            });
+            let pending = store.len();
+            store.save()?;
+            debug!(pending, "added to digest");
+
+            let mut results = HashMap::from([
+                ("status".to_string(), json!("digested")),
+                ("pending".to_string(), json!(pending)),
+            ]);
+            let flushed = self.flush_digest(false)?;
+            if !flushed.is_empty() {
+                results.insert("digest".to_string(), json!(flushed));
+            }
+            return Ok((results, summary));
+        }
+
+        // 同一事件短时间内过多时，之后的先保存，这一阵结束后合并为一条发送
+        if !force && self.config.burst.matches(notification) {
+            // 上一阵已结束的先发出，保持顺序
+            let flushed = if self.dry_run {
+                HashMap::new()
+            } else {
+                self.flush_bursts(false)?
+            };
+            let _lock = BurstStore::lock()?;
+            let mut store = BurstStore::load()?;
+            let held = store.arrive(
+                &self.config.burst,
+                notification,
+                &channels,
+                Local::now().timestamp(),
+            );
+            if !self.dry_run {
+                store.save()?;
+            }
+            if let Some(pending) = held {
+                debug!(pending, "collapsed into burst");
+                let mut results = HashMap::from([
+                    ("status".to_string(), json!("collapsed")),
+                    ("pending".to_string(), json!(pending)),
+                ]);
+                if !flushed.is_empty() {
+                    results.insert("burst".to_string(), json!(flushed));
+                }
+                return Ok((results, DeliverySummary::suppressed(channels.len())));
+            }
+        }
+
+        // 每个渠道项可以是备用链，例如 "feishu -> wechat -> sms"
+        let chains: Vec<Vec<String>> = channels.iter().map(|c| routing::parse_chain(c)).collect();
+        let primaries: Vec<String> = chains.iter().filter_map(|c| c.first().cloned()).collect();
+        let mut results = HashMap::new();
+        let mut pending: Vec<(String, Vec<String>)> = Vec::new();
+        for chain in chains {
+            let Some(primary) = chain.first().cloned() else {
+                continue;
+            };
+            if results.contains_key(&primary) || pending.iter().any(|(p, _)| *p == primary) {
+                continue;
+            }
+
+            // 其他链的首选渠道本身会发送，不再作为备用渠道重复发送；
+            // 跳过已静音或处于自身静默时段的渠道
+            let all_silenced = chain.iter().all(|hop| silenced(hop));
+            let chain: Vec<String> = chain
+                .into_iter()
+                .enumerate()
+                .filter(|(i, hop)| *i == 0 || !primaries.contains(hop))
+                .map(|(_, hop)| hop)
+                .filter(|hop| !quiet(hop) && !silenced(hop))
+                .collect();
+            if all_silenced {
+                debug!(channel = %primary, "channel is silenced");
+                results.insert(primary, json!({"status": "silenced"}));
+                continue;
+            }
+            if chain.is_empty() {
+                debug!(channel = %primary, "channel is in quiet hours");
+                results.insert(primary, json!({"status": "quiet_hours"}));
+                continue;
+            }
+            pending.push((primary, chain));
+        }
+
+        // 按事件限流，超出限额的渠道不发送，被限流的条数在下一条中注明
+        let mut notes: HashMap<String, String> = HashMap::new();
+        let rule = self
+            .config
+            .throttle
+            .iter()
+            .find(|r| r.matches(notification));
+        if let Some(rule) = rule.filter(|_| !force && !pending.is_empty()) {
+            let now = now.timestamp();
+            let _lock = ThrottleStore::lock()?;
+            let mut store = ThrottleStore::load()?;
+            pending.retain(|(primary, _)| match store.admit(rule, primary, now) {
+                Some(0) => true,
+                Some(count) => {
+                    let note =
+                        i18n::fill(i18n::strings().throttled, &[("count", &count.to_string())]);
+                    notes.insert(primary.clone(), note);
+                    true
+                }
+                None => {
+                    debug!(channel = %primary, event = %notification.event, "throttled");
+                    results.insert(primary.clone(), json!({"status": "throttled"}));
+                    false
+                }
+            });
+            if !self.dry_run {
+                store.save()?;
+            }
+        }
+
+        // 联网探测失败时远程渠道直接按不可达处理，另外发送到本地的备用渠道
+        let offline = !pending.is_empty() && !self.dry_run && !self.is_online();
+        if offline {
+            info!("connectivity probe failed, skipping remote channels");
+            let fallback = self.config.probe.as_ref().map(|p| p.fallback.clone());
+            for channel in fallback.unwrap_or_default() {
+                if !quiet(&channel)
+                    && !silenced(&channel)
+                    && !results.contains_key(&channel)
+                    && !pending.iter().any(|(_, chain)| chain.contains(&channel))
+                {
+                    pending.push((channel.clone(), vec![channel]));
+                }
+            }
+        }
+
+        for name in &notification.mentions {
+            if !self.config.users.contains_key(name) {
+                warn!(user = %name, "unknown user, define it under `users` in the config");
+            }
+        }
+        let attachments = self.prepare_attachments(notification, &pending, offline);
+        let actions = self.card_actions(id, notification);
+
+        // 各链并行发送，链内按顺序尝试
+        let span = Span::current();
+        let (this, route, attachments, actions) = (&*self, &route, &attachments, &actions);
+        let delivered: Vec<(String, Option<serde_json::Value>)> = thread::scope(|scope| {
+            let handles: Vec<_> = pending
+                .into_iter()
+                .map(|(primary, chain)| {
+                    let span = span.clone();
+                    let noted = notes
+                        .remove(&primary)
+                        .map(|note| notification.with_note(&note));
+                    scope.spawn(move || {
+                        let notification = noted.as_ref().unwrap_or(notification);
+                        let result = span.in_scope(|| {
+                            this.deliver_chain(
+                                chain,
+                                notification,
+                                route,
+                                attachments,
+                                actions,
+                                offline,
+                            )
+                        });
+                        (primary, result)
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("delivery thread panicked"))
+                .collect()
+        });
+        for (primary, result) in delivered {
+            if let Some(result) = result {
+                results.insert(primary, result);
+            }
+        }
+
+        if !self.dry_run {
+            self.circuit_breaker().save()?;
+            self.threads().save()?;
+            self.metrics().flush(&self.config.metrics)?;
+        }
+
+        let summary = DeliverySummary::of(&results);
+        Ok((results, summary))
+    }
+
+    /// 卡片颜色：配置的 `colors` 优先（自定义级别先按其名字查找），否则用渠道的默认颜色
+    fn color(&self, notifier: &dyn Notifier, channel: &str, notification: &Notification) -> String {
+        let colors = &self.config.colors;
+        let level = notification.level;
+        colors
+            .get(channel, notification.level_name())
+            .or_else(|| colors.get(channel, level.as_str()))
+            .unwrap_or_else(|| notifier.level_color(level))
+            .to_string()
+    }
+
+    /// 套用模板并转换为渠道的markdown方言；模板出错时退回原始内容，不影响通知送达
+    fn render_content(
+        &self,
+        notifier: &dyn Notifier,
+        channel: &str,
+        notification: &Notification,
+        template: Option<&str>,
+    ) -> String {
+        let content = self
+            .templates
+            .render(notification, template, channel)
+            .unwrap_or_else(|e| {
+                warn!(channel = %channel, "{:#}", e);
+                notification.content.clone()
+            });
+        markdown::render(&content, notifier.markdown_dialect())
+    }
+
+    /// 通知的卡片按钮；回调按钮需要 `callbacks` 配置，否则略过
+    fn card_actions(&self, id: &str, notification: &Notification) -> Vec<Action> {
+        notification
+            .actions
+            .iter()
+            .filter_map(|action| {
+                if action.is_link() {
+                    return Some(Action {
+                        text: action.label.clone(),
+                        url: action.value.clone(),
+                        callback: None,
+                    });
+                }
+                let Some(callbacks) = &self.config.callbacks else {
+                    warn!(action = %action.label, "no `callbacks` configured, skipping button");
+                    return None;
+                };
+                Some(Action {
+                    text: action.label.clone(),
+                    url: callbacks.endpoint().unwrap_or_default(),
+                    callback: Some(json!(CallbackValue::new(
+                        &callbacks.secret,
+                        id,
+                        &action.value
+                    ))),
+                })
+            })
+            .collect()
+    }
+
+    /// 有不能直接发送文件的渠道时，把附件上传到 `upload` 目标以便附上链接
+    fn prepare_attachments(
+        &self,
+        notification: &Notification,
+        pending: &[(String, Vec<String>)],
+        offline: bool,
+    ) -> Vec<Attachment> {
+        let needs_links = !self.dry_run
+            && !offline
+            && pending.iter().flat_map(|(_, chain)| chain).any(|channel| {
+                self.notifiers
+                    .get(channel)
+                    .is_some_and(|n| !n.supports_attachments())
+            });
+        let client = match (&self.config.upload, needs_links) {
+            (Some(_), true) => match self.config.http.client() {
+                Ok(client) => Some(client),
+                Err(e) => {
+                    warn!("Cannot upload attachments: {:#}", e);
+                    None
+                }
+            },
+            (None, true) if !notification.attachments.is_empty() => {
+                warn!("no upload target configured, attachments are only listed by name");
+                None
+            }
+            _ => None,
+        };
+
+        notification
+            .attachments
+            .iter()
+            .map(|path| {
+                let path = PathBuf::from(path);
+                let link = self.config.upload.as_ref().zip(client.as_ref()).and_then(
+                    |(upload, client)| {
+                        notifiers::blocking::runtime()
+                            .block_on(upload.upload(client, &path))
+                            .inspect_err(|e| warn!(file = %path.display(), "upload failed: {}", e))
+                            .ok()
+                    },
+                );
+                Attachment { path, link }
+            })
+            .collect()
+    }
+
+    /// 直接发送附件，失败不影响通知本身的结果
+    fn send_attachment(
+        &self,
+        notifier: &dyn Notifier,
+        channel: &str,
+        attachment: &Attachment,
+    ) -> serde_json::Value {
+        match notifier.send_attachment_blocking(&attachment.path) {
+            Ok(_) => json!({"file": attachment.name(), "success": true}),
+            Err(e) => {
+                warn!(channel = %channel, file = %attachment.name(), "attachment failed: {}", e);
+                json!({"file": attachment.name(), "success": false, "error": redact::text(&e.to_string())})
+            }
+        }
+    }
+
+    /// 依次尝试链上的渠道直到成功；熔断的渠道跳过，并追加其配置的熔断备用渠道
+    fn deliver_chain(
+        &self,
+        chain: Vec<String>,
+        notification: &Notification,
+        route: &Route,
+        attachments: &[Attachment],
+        actions: &[Action],
+        offline: bool,
+    ) -> Option<serde_json::Value> {
+        let thread_key = self.config.threading.key(notification);
+        let single = chain.len() == 1;
+        let mut queue: VecDeque<String> = chain.into();
+        let mut visited: Vec<String> = Vec::new();
+        let mut hops: Vec<serde_json::Value> = Vec::new();
+        let mut delivered_via = None;
+
+        while let Some(channel) = queue.pop_front() {
+            visited.push(channel.clone());
+
+            let (open, fallback) = {
+                let breaker = self.circuit_breaker();
+                (
+                    breaker.is_open(&channel),
+                    breaker.fallback_for(&channel).cloned(),
+                )
+            };
+            if open {
+                if let Some(fallback) = fallback {
+                    if !visited.contains(&fallback) && !queue.contains(&fallback) {
+                        queue.push_back(fallback);
+                    }
+                }
+                self.metrics().record_send(
+                    &channel,
+                    notification.level_name(),
+                    "skipped",
+                    Duration::ZERO,
+                );
+                debug!(channel = %channel, "circuit open, skipping");
+                hops.push(json!({"channel": channel, "success": false, "skipped": "circuit_open"}));
+                continue;
+            }
+
+            let Some(notifier) = self.notifiers.get(&channel) else {
+                debug!(channel = %channel, "channel not configured");
+                hops.push(
+                    json!({"channel": channel, "success": false, "skipped": "not_configured"}),
+                );
+                continue;
+            };
+            if offline && !notifier.is_local() {
+                debug!(channel = %channel, "offline, skipping");
+                let e = NotifierError::Unreachable("connectivity probe failed".to_string());
+                hops.push(json!({
+                    "channel": channel,
+                    "success": false,
+                    "error": e.to_string(),
+                    "error_kind": e.kind(),
+                    "retryable": e.is_retryable()
+                }));
+                continue;
+            }
+
+            let content = self.render_content(
+                notifier.as_ref(),
+                &channel,
+                notification,
+                route.template.as_deref(),
+            );
+
+            // 不能直接发送文件的渠道附上附件链接
+            let native_attachments = notifier.supports_attachments();
+            let mut suffix = String::new();
+            if !native_attachments && !attachments.is_empty() {
+                let links: Vec<String> = attachments
+                    .iter()
+                    .map(|a| match &a.link {
+                        Some(link) => format!("- 📎 [{}]({})", a.name(), link),
+                        None => format!("- 📎 {}", a.name()),
+                    })
+                    .collect();
+                suffix.push_str("\n\n");
+                suffix.push_str(&markdown::render(
+                    &links.join("\n"),
+                    notifier.markdown_dialect(),
+                ));
+            }
+
+            // critical通知@渠道的值班人员；配置了值班人员时默认不再@所有人
+            let page = notification.mentions_on_call();
+            let on_call = match self.config.on_call(&channel) {
+                Some(on_call) if page => on_call.current(Local::now().date_naive()),
+                _ => Vec::new(),
+            };
+            let mention_all = route.mention_all.unwrap_or(page && on_call.is_empty());
+            let mut mentions = notification.mentions.clone();
+            for user in on_call {
+                if !mentions.contains(&user) {
+                    mentions.push(user);
+                }
+            }
+
+            // 按渠道选用用户身份，没有对应身份时显示名字
+            if !mentions.is_empty() {
+                let mentions: Vec<String> = mentions
+                    .iter()
+                    .map(|name| {
+                        self.config
+                            .users
+                            .get(name)
+                            .and_then(|user| notifier.mention(name, user))
+                            .unwrap_or_else(|| format!("@{}", name))
+                    })
+                    .collect();
+                suffix.push_str("\n\n");
+                suffix.push_str(&mentions.join(" "));
+            }
+
+            if mention_all && channel == "feishu" {
+                suffix.push_str("\n<at user_id='all'></at>");
+            }
+            // 超出渠道长度上限时截断，附件链接和@标记保留在末尾；它们本身就超出上限时整体截断
+            let final_content = match notifier.max_content_bytes() {
+                Some(max) if suffix.len() <= max => format!(
+                    "{}{}",
+                    notifiers::truncate(&content, max - suffix.len()),
+                    suffix
+                ),
+                Some(max) => {
+                    notifiers::truncate(&format!("{}{}", content, suffix), max).into_owned()
+                }
+                None => format!("{}{}", content, suffix),
+            };
+
+            if self.dry_run {
+                let payload = notifier.card_request(
+                    &notification.title,
+                    &final_content,
+                    &self.color(notifier.as_ref(), &channel, notification),
+                    actions.to_vec(),
+                );
+                let mut hop = json!({
+                    "channel": channel,
+                    "success": true,
+                    "dry_run": true,
+                    "payload": redact::json(&payload)
+                });
+                if let Some(image) = &notification.image {
+                    hop["image"] = json!(image);
+                }
+                if !notification.attachments.is_empty() {
+                    hop["attachments"] = json!(notification.attachments);
+                }
+                if let Some(key) = thread_key.as_ref().filter(|_| notifier.supports_threads()) {
+                    hop["thread"] = json!(key);
+                }
+                hops.push(hop);
+                delivered_via = Some(channel);
+                break;
+            }
+
+            // 支持会话的渠道把相关通知作为首条消息的回复发送
+            let thread_key = thread_key
+                .as_deref()
+                .filter(|_| notifier.supports_threads());
+            let root = thread_key.and_then(|key| self.threads().root(&channel, key));
+            let image = notification.image.as_deref().map(Image::parse);
+            let started = Instant::now();
+            let result = notifier
+                .send_card_in_thread_blocking(
+                    &notification.title,
+                    &final_content,
+                    &self.color(notifier.as_ref(), &channel, notification),
+                    actions.to_vec(),
+                    image.as_ref(),
+                    root.as_deref(),
+                )
+                .map(|(response, root)| {
+                    if let Some((key, root)) = thread_key.zip(root) {
+                        self.threads().touch(&channel, key, root);
+                    }
+                    response
+                });
+            self.metrics().record_send(
+                &channel,
+                notification.level_name(),
+                if result.is_ok() { "success" } else { "failure" },
+                started.elapsed(),
+            );
+
+            match result {
+                Ok(val) => {
+                    debug!(channel = %channel, "delivered");
+                    self.circuit_breaker().record_success(&channel);
+                    let mut hop = json!({"channel": channel, "success": true, "response": val});
+                    // 记录消息ID，供 `update` 编辑已发送的消息
+                    if let Some(message_id) = notifier.message_id(&val) {
+                        hop["message_id"] = json!(message_id);
+                    }
+                    if native_attachments && !attachments.is_empty() {
+                        hop["attachments"] = attachments
+                            .iter()
+                            .map(|a| self.send_attachment(notifier.as_ref(), &channel, a))
+                            .collect();
+                    }
+                    hops.push(hop);
+                    delivered_via = Some(channel);
+                    break;
+                }
+                Err(e) => {
+                    info!(channel = %channel, "delivery failed: {}", e);
+                    // 本机离线不是渠道的问题，进入离线队列时不计入熔断
+                    let offline =
+                        self.config.offline.enabled && matches!(e, NotifierError::Unreachable(_));
+                    if !offline {
+                        self.circuit_breaker().record_failure(&channel);
+                    }
+                    hops.push(json!({
+                        "channel": channel,
+                        "success": false,
+                        "error": redact::text(&e.to_string()),
+                        "error_kind": e.kind(),
+                        "retryable": e.is_retryable()
+                    }));
+                }
+            }
+        }
+
+        // 单个渠道保持原有结果格式，未配置的渠道不出现在结果中
+        if single && hops.len() == 1 {
+            let mut hop = hops.pop()?;
+            if hop["skipped"] == "not_configured" {
+                return None;
+            }
+            hop.as_object_mut()?.remove("channel");
+            return Some(hop);
+        }
+
+        let retryable = hops.iter().any(|h| h["retryable"] == true);
+        Some(json!({
+            "success": delivered_via.is_some(),
+            "delivered_via": delivered_via,
+            "retryable": retryable,
+            "hops": hops
+        }))
+    }
+}
@@ -0,0 +1,353 @@
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// 渠道支持的markdown方言；通知内容按通用markdown子集书写，发送前转换
+///
+/// 通用子集：`#` 标题、`**粗体**`、`*斜体*`/`_斜体_`、`~~删除线~~`、`` `代码` ``、
+/// `[文字](链接)`、`-`/`*` 列表、``` 代码块和 `| a | b |` 表格
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Dialect {
+    /// 完整markdown，原样发送（Server酱、PushPlus）
+    #[default]
+    Markdown,
+    /// Teams MessageCard：无标题和删除线，单个换行不生效
+    Teams,
+    /// 飞书卡片 lark_md：无标题
+    Lark,
+    /// 企业微信markdown：无斜体、删除线、列表和代码块
+    Wecom,
+    /// 纯文本，去掉所有标记
+    Plain,
+}
+
+struct Inline {
+    bold: Regex,
+    italic: Regex,
+    strike: Regex,
+    code: Regex,
+    link: Regex,
+}
+
+fn inline() -> &'static Inline {
+    static INLINE: OnceLock<Inline> = OnceLock::new();
+    INLINE.get_or_init(|| Inline {
+        bold: Regex::new(r"\*\*([^*\n]+)\*\*|__([^_\n]+)__").expect("bold regex"),
+        italic: Regex::new(r"\*([^*\s][^*\n]*)\*|\b_([^_\s][^_\n]*)_\b").expect("italic regex"),
+        strike: Regex::new(r"~~([^~\n]+)~~").expect("strike regex"),
+        code: Regex::new(r"`([^`\n]+)`").expect("code regex"),
+        link: Regex::new(r"\[([^\]\n]+)\]\(([^)\s]+)\)").expect("link regex"),
+    })
+}
+
+/// 取第一个匹配到的捕获组（交替分支各有一个组）
+fn group<'a>(caps: &Captures<'a>) -> &'a str {
+    caps.iter()
+        .skip(1)
+        .flatten()
+        .next()
+        .map_or("", |m| m.as_str())
+}
+
+/// 两列表格的一行；Teams和飞书卡片显示为原生的键值表
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fact {
+    pub name: String,
+    pub value: String,
+}
+
+/// 表格行的单元格，不是表格行时为None
+fn table_cells(line: &str) -> Option<Vec<&str>> {
+    let inner = line.trim().strip_prefix('|')?.strip_suffix('|')?;
+    Some(inner.split('|').map(str::trim).collect())
+}
+
+/// 表头下的分隔行，例如 `|---|:--:|`
+fn is_separator(cells: &[&str]) -> bool {
+    cells.iter().all(|cell| {
+        let dashes = cell.trim_matches(':');
+        !dashes.is_empty() && dashes.chars().all(|c| c == '-')
+    })
+}
+
+/// 内容中的表格块：起止行号（不含结束行）和列数。表格至少有表头和分隔行
+fn tables(lines: &[&str]) -> Vec<(usize, usize, usize)> {
+    let mut tables = Vec::new();
+    let mut in_code_block = false;
+    let mut i = 0;
+    while i < lines.len() {
+        if lines[i].trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+        let header = table_cells(lines[i]).filter(|_| !in_code_block);
+        let separator = lines.get(i + 1).and_then(|line| table_cells(line));
+        match (header, separator) {
+            (Some(header), Some(separator))
+                if header.len() == separator.len() && is_separator(&separator) =>
+            {
+                let start = i;
+                i += 2;
+                while i < lines.len() && table_cells(lines[i]).is_some() {
+                    i += 1;
+                }
+                tables.push((start, i, header.len()));
+            }
+            _ => i += 1,
+        }
+    }
+    tables
+}
+
+/// 取出内容中的两列表格（不含表头），返回其余内容和表格各行
+pub fn split_facts(content: &str) -> (String, Vec<Fact>) {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut facts = Vec::new();
+    let mut skip = vec![false; lines.len()];
+    for (start, end, columns) in tables(&lines) {
+        if columns != 2 {
+            continue;
+        }
+        for (index, line) in lines.iter().enumerate().take(end).skip(start) {
+            skip[index] = true;
+            if index >= start + 2 {
+                let cells = table_cells(line).unwrap_or_default();
+                facts.push(Fact {
+                    name: cells.first().unwrap_or(&"").to_string(),
+                    value: cells.get(1).unwrap_or(&"").to_string(),
+                });
+            }
+        }
+    }
+    if facts.is_empty() {
+        return (content.to_string(), facts);
+    }
+    // 去掉表格后留下的连续空行合并为一个
+    let mut rest: Vec<&str> = Vec::new();
+    for (line, skip) in lines.iter().zip(skip) {
+        let blank_run = line.trim().is_empty() && rest.last().is_some_and(|l| l.trim().is_empty());
+        if !skip && !blank_run {
+            rest.push(line);
+        }
+    }
+    (rest.join("\n").trim().to_string(), facts)
+}
+
+/// 生成表头为空的两列表格，Teams和飞书显示为键值表；值中的竖线会拆开单元格，换成 `/`
+pub fn facts_table<N: AsRef<str>>(rows: &[(N, String)]) -> String {
+    let mut table = vec!["| | |".to_string(), "|---|---|".to_string()];
+    table.extend(
+        rows.iter()
+            .map(|(name, value)| format!("| {} | {} |", name.as_ref(), value.replace('|', "/"))),
+    );
+    table.join("\n")
+}
+
+/// 去掉粗体标记，整段加粗前使用以免嵌套
+pub fn strip_bold(text: &str) -> String {
+    inline()
+        .bold
+        .replace_all(text, |caps: &Captures| group(caps).to_string())
+        .into_owned()
+}
+
+/// 不能显示表格的方言把表格行展开：两列为 `**名称:** 值`，多列用 ` · ` 连接
+fn flatten_row(cells: &[&str], columns: usize, dialect: Dialect) -> String {
+    let cells: Vec<String> = cells
+        .iter()
+        .map(|cell| render_inline(cell, dialect))
+        .collect();
+    match (columns, dialect) {
+        (2, Dialect::Plain) => format!("{}: {}", cells[0], cells[1]),
+        (2, _) => format!("**{}:** {}", strip_bold(&cells[0]), cells[1]),
+        _ => cells.join(" · "),
+    }
+}
+
+/// 把通用markdown子集转换为指定方言
+pub fn render(content: &str, dialect: Dialect) -> String {
+    if dialect == Dialect::Markdown {
+        return content.to_string();
+    }
+
+    let source: Vec<&str> = content.lines().collect();
+    let mut table_at = vec![None; source.len()];
+    for (start, end, columns) in tables(&source) {
+        for row in table_at.iter_mut().take(end).skip(start) {
+            *row = Some((start, columns));
+        }
+    }
+
+    // 第二项表示该行是列表项或表格行
+    let mut lines: Vec<(String, bool)> = Vec::new();
+    let mut in_code_block = false;
+    for (index, line) in source.iter().enumerate() {
+        if let Some((start, columns)) = table_at[index] {
+            let cells = table_cells(line).unwrap_or_default();
+            // 卡片渠道保留两列表格，发送时转为键值表
+            let keep = columns == 2 && matches!(dialect, Dialect::Teams | Dialect::Lark);
+            if keep {
+                let cells: Vec<String> = cells
+                    .iter()
+                    .map(|cell| render_inline(cell, dialect))
+                    .collect();
+                lines.push((format!("| {} |", cells.join(" | ")), true));
+            } else if index == start + 1 || (index == start && columns == 2) {
+                // 展开时去掉分隔行；两列表格是键值表，表头不显示
+                continue;
+            } else {
+                lines.push((flatten_row(&cells, columns, dialect), true));
+            }
+            continue;
+        }
+        let line = *line;
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+            // 飞书卡片支持代码块，其余方言去掉围栏
+            if dialect == Dialect::Lark {
+                lines.push((line.to_string(), false));
+            }
+            continue;
+        }
+        if in_code_block {
+            lines.push((code_line(line, dialect), false));
+        } else {
+            let is_list = list_item(line).is_some();
+            lines.push((render_line(line, dialect), is_list));
+        }
+    }
+
+    if dialect != Dialect::Teams {
+        return lines
+            .into_iter()
+            .map(|(line, _)| line)
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+
+    // Teams中单个换行会被忽略：段落之间用空行分隔，连续的列表项和表格行保持单个换行
+    let mut out = String::new();
+    let mut previous_list = false;
+    // 空行已由段落分隔代替
+    for (i, (line, is_list)) in lines
+        .into_iter()
+        .filter(|(line, _)| !line.trim().is_empty())
+        .enumerate()
+    {
+        if i > 0 {
+            out.push_str(if is_list && previous_list {
+                "\n"
+            } else {
+                "\n\n"
+            });
+        }
+        out.push_str(&line);
+        previous_list = is_list;
+    }
+    out
+}
+
+fn code_line(line: &str, dialect: Dialect) -> String {
+    match dialect {
+        Dialect::Lark | Dialect::Plain => line.to_string(),
+        _ if line.trim().is_empty() => String::new(),
+        _ => format!("`{}`", line.replace('`', "'")),
+    }
+}
+
+/// 列表项的缩进和正文
+fn list_item(line: &str) -> Option<(&str, &str)> {
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+    ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|marker| trimmed.strip_prefix(marker))
+        .map(|rest| (indent, rest))
+}
+
+fn render_line(line: &str, dialect: Dialect) -> String {
+    let trimmed = line.trim_start();
+
+    // 标题：企业微信原样保留，其余方言改为粗体或纯文本
+    let hashes = trimmed.chars().take_while(|&c| c == '#').count();
+    if (1..=6).contains(&hashes) && trimmed[hashes..].starts_with(' ') {
+        let text = render_inline(trimmed[hashes..].trim(), dialect);
+        return match dialect {
+            Dialect::Wecom => format!("{} {}", &trimmed[..hashes], text),
+            Dialect::Plain => text,
+            // 整行加粗，行内的粗体标记去掉以免嵌套
+            _ => format!("**{}**", strip_bold(&text)),
+        };
+    }
+
+    if let Some((indent, rest)) = list_item(line) {
+        let text = render_inline(rest, dialect);
+        return match dialect {
+            Dialect::Wecom | Dialect::Plain => format!("{}• {}", indent, text),
+            _ => format!("{}- {}", indent, text),
+        };
+    }
+
+    render_inline(line, dialect)
+}
+
+fn render_inline(text: &str, dialect: Dialect) -> String {
+    let re = inline();
+    let mut text = text.to_string();
+
+    let (keep_italic, keep_strike, keep_code) = match dialect {
+        Dialect::Markdown => return text,
+        Dialect::Teams => (true, false, true),
+        Dialect::Lark => (true, true, true),
+        Dialect::Wecom => (false, false, true),
+        Dialect::Plain => (false, false, false),
+    };
+
+    if !keep_code {
+        text = re.code.replace_all(&text, "$1").into_owned();
+    }
+    if !keep_strike {
+        text = re.strike.replace_all(&text, "$1").into_owned();
+    }
+    if dialect == Dialect::Plain {
+        text = re
+            .link
+            .replace_all(&text, |caps: &Captures| {
+                if caps[1] == caps[2] {
+                    caps[2].to_string()
+                } else {
+                    format!("{} ({})", &caps[1], &caps[2])
+                }
+            })
+            .into_owned();
+        text = re
+            .bold
+            .replace_all(&text, |caps: &Captures| group(caps).to_string())
+            .into_owned();
+    }
+    if !keep_italic {
+        // 先保护粗体，避免 `**` 被当作两个斜体标记
+        let bold: Vec<String> = re
+            .bold
+            .find_iter(&text)
+            .map(|m| m.as_str().to_string())
+            .collect();
+        let mut protected = re.bold.replace_all(&text, "\u{0}").into_owned();
+        protected = re
+            .italic
+            .replace_all(&protected, |caps: &Captures| group(caps).to_string())
+            .into_owned();
+        let mut bold = bold.into_iter();
+        text = protected
+            .split('\u{0}')
+            .enumerate()
+            .fold(String::new(), |mut out, (i, part)| {
+                if i > 0 {
+                    out.push_str(&bold.next().unwrap_or_default());
+                }
+                out.push_str(part);
+                out
+            });
+    }
+    text
+}
@@ -0,0 +1,254 @@
+use crate::git;
+use crate::manager::NotificationManager;
+use crate::notification::Notification;
+use crate::redact;
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use tracing::{debug, error};
+
+/// 通过MCP工具发送的通知的默认事件名
+pub const MCP_NOTIFICATION: &str = "mcp_notification";
+
+/// 客户端没有给出协议版本时使用
+const PROTOCOL_VERSION: &str = "2025-06-18";
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+const INSTRUCTIONS: &str = "Use send_notification to reach the user through their configured \
+    channels (Teams, Feishu, WeChat, ...) when they need to know something now: a long task \
+    finished, you are blocked on a decision, or something failed. Do not use it for routine \
+    progress.";
+
+/// JSON-RPC错误
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// 在 `input` 和 `output` 上运行MCP服务（每行一条JSON-RPC消息），直到输入结束；
+/// 输出只写协议消息，日志写到stderr
+pub fn serve<R: BufRead, W: Write>(
+    manager: &mut NotificationManager,
+    input: R,
+    mut output: W,
+) -> Result<()> {
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => handle(manager, &message),
+            Err(e) => Some(error_response(
+                Value::Null,
+                RpcError::new(PARSE_ERROR, e.to_string()),
+            )),
+        };
+        if let Some(response) = response {
+            writeln!(output, "{}", response)?;
+            output.flush()?;
+        }
+    }
+    Ok(())
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {"code": error.code, "message": error.message},
+    })
+}
+
+/// 处理一条消息；通知（没有id）和响应不回复
+fn handle(manager: &mut NotificationManager, message: &Value) -> Option<Value> {
+    let Some(method) = message["method"].as_str() else {
+        return match message.get("id") {
+            // 客户端发来的响应，本服务不发请求，忽略
+            Some(_) if message.get("result").is_some() || message.get("error").is_some() => None,
+            id => Some(error_response(
+                id.cloned().unwrap_or(Value::Null),
+                RpcError::new(INVALID_REQUEST, "Invalid request"),
+            )),
+        };
+    };
+    let Some(id) = message.get("id").cloned() else {
+        debug!(method, "ignoring notification");
+        return None;
+    };
+    let params = &message["params"];
+    let result = match method {
+        "initialize" => Ok(json!({
+            "protocolVersion": params["protocolVersion"].as_str().unwrap_or(PROTOCOL_VERSION),
+            "capabilities": {"tools": {}},
+            "serverInfo": {"name": "claude-notifier", "version": env!("CARGO_PKG_VERSION")},
+            "instructions": INSTRUCTIONS,
+        })),
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({"tools": tools()})),
+        "tools/call" => call_tool(manager, params),
+        _ => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("Method not found: {}", method),
+        )),
+    };
+    Some(match result {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err(error) => error_response(id, error),
+    })
+}
+
+fn tools() -> Value {
+    json!([
+        {
+            "name": "send_notification",
+            "title": "Send notification",
+            "description": "Notify the user through the channels configured for claude-notifier. \
+                Routing, quiet hours and deduplication apply as for any other notification. \
+                Returns the delivery result of each channel.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "title": {"type": "string", "description": "Short headline"},
+                    "content": {"type": "string", "description": "Message body (Markdown)"},
+                    "level": {
+                        "type": "string",
+                        "description": "info, success, warning, critical or a custom level (default: info)",
+                    },
+                    "event": {
+                        "type": "string",
+                        "description": format!("Event type used for routing (default: {})", MCP_NOTIFICATION),
+                    },
+                    "channels": {
+                        "type": "array",
+                        "items": {"type": "string"},
+                        "description": "Send only to these channels instead of the routed ones (see list_channels)",
+                    },
+                },
+                "required": ["title", "content"],
+            },
+        },
+        {
+            "name": "list_channels",
+            "title": "List notification channels",
+            "description": "List the configured notification channels and whether each is ready to send.",
+            "inputSchema": {"type": "object", "properties": {}},
+        },
+    ])
+}
+
+/// 工具执行结果；`is_error` 为true时由模型自行处理，不作为协议错误
+fn tool_result(text: String, is_error: bool) -> Value {
+    json!({
+        "content": [{"type": "text", "text": text}],
+        "isError": is_error,
+    })
+}
+
+fn call_tool(manager: &mut NotificationManager, params: &Value) -> Result<Value, RpcError> {
+    let arguments = &params["arguments"];
+    match params["name"].as_str() {
+        Some("send_notification") => {
+            let arg = |name: &str| arguments[name].as_str().map(str::trim);
+            let (Some(title), Some(content)) = (arg("title"), arg("content")) else {
+                return Err(RpcError::new(
+                    INVALID_PARAMS,
+                    "send_notification requires 'title' and 'content'",
+                ));
+            };
+            let channels = match &arguments["channels"] {
+                Value::Null => None,
+                Value::Array(items) => Some(
+                    items
+                        .iter()
+                        .filter_map(|item| item.as_str().map(String::from))
+                        .collect(),
+                ),
+                _ => {
+                    return Err(RpcError::new(
+                        INVALID_PARAMS,
+                        "'channels' must be an array of channel names",
+                    ))
+                }
+            };
+            let event = arg("event")
+                .filter(|event| !event.is_empty())
+                .unwrap_or(MCP_NOTIFICATION);
+            let level = arg("level").unwrap_or("info");
+            Ok(send(manager, event, title, content, level, channels))
+        }
+        Some("list_channels") => Ok(tool_result(list_channels(manager), false)),
+        Some(name) => Err(RpcError::new(
+            INVALID_PARAMS,
+            format!("Unknown tool: {}", name),
+        )),
+        None => Err(RpcError::new(INVALID_PARAMS, "Missing tool name")),
+    }
+}
+
+fn send(
+    manager: &mut NotificationManager,
+    event: &str,
+    title: &str,
+    content: &str,
+    level: &str,
+    channels: Option<Vec<String>>,
+) -> Value {
+    let (level, custom_level) = match manager.config().level(level) {
+        Ok(level) => level,
+        Err(e) => return tool_result(redact::text(&format!("{:#}", e)), true),
+    };
+    let mut notification = Notification::new(event, title, content, level);
+    notification.custom_level = custom_level;
+    // 服务在项目目录中启动，和 `send` 一样附上仓库状态
+    let context = manager.config().git.as_ref().and_then(|config| {
+        let dir = std::env::current_dir().ok()?;
+        git::context(&dir, config)
+    });
+    if let Some(context) = context {
+        context.apply(&mut notification);
+    }
+
+    match manager.send_notification(&notification, channels, false) {
+        Ok(results) => {
+            // 被静默或去重不算失败
+            let failed = !results.contains_key("status")
+                && results
+                    .get("summary")
+                    .is_some_and(|summary| summary["succeeded"].as_u64() == Some(0));
+            tool_result(pretty(&results), failed)
+        }
+        Err(e) => {
+            error!("Failed to send notification: {:#}", e);
+            tool_result(format!("Failed to send notification: {:#}", e), true)
+        }
+    }
+}
+
+fn pretty(results: &HashMap<String, Value>) -> String {
+    serde_json::to_string_pretty(results).unwrap_or_default()
+}
+
+/// 配置中的所有渠道，未启用或配置不完整的标为未就绪
+fn list_channels(manager: &NotificationManager) -> String {
+    let list: Vec<Value> = manager
+        .channels()
+        .into_iter()
+        .map(|(name, ready)| json!({"name": name, "ready": ready}))
+        .collect();
+    serde_json::to_string_pretty(&list).unwrap_or_default()
+}
@@ -0,0 +1,235 @@
+use crate::config::Config;
+use crate::digest::DigestStore;
+use crate::escalation::EscalationStore;
+use crate::schedule::ScheduleStore;
+use crate::store;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const STATE_FILE: &str = "metrics.json";
+
+/// 请求耗时直方图的桶（秒）
+const LATENCY_BUCKETS: [f64; 8] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Prometheus指标设置
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct MetricsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// node_exporter textfile collector输出文件，每次发送后更新
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub textfile: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+struct Histogram {
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        self.buckets.resize(LATENCY_BUCKETS.len(), 0);
+        for (bucket, le) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= le {
+                *bucket += 1;
+            }
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+
+    fn merge(&mut self, other: &Histogram) {
+        self.buckets.resize(LATENCY_BUCKETS.len(), 0);
+        for (bucket, add) in self.buckets.iter_mut().zip(&other.buckets) {
+            *bucket += add;
+        }
+        self.sum += other.sum;
+        self.count += other.count;
+    }
+}
+
+/// 累计指标；一次性CLI调用的增量合并进持久化文件，保证计数器单调递增
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct Metrics {
+    /// 键为 (channel, level, outcome)
+    sends: BTreeMap<String, u64>,
+    /// 按渠道的请求耗时
+    latency: BTreeMap<String, Histogram>,
+    /// 按渠道的重发次数
+    resends: BTreeMap<String, u64>,
+}
+
+impl Metrics {
+    pub fn record_send(&mut self, channel: &str, level: &str, outcome: &str, elapsed: Duration) {
+        *self
+            .sends
+            .entry(format!("{}|{}|{}", channel, level, outcome))
+            .or_default() += 1;
+        if outcome != "skipped" {
+            self.latency
+                .entry(channel.to_string())
+                .or_default()
+                .observe(elapsed.as_secs_f64());
+        }
+    }
+
+    pub fn record_resend(&mut self, channel: &str) {
+        *self.resends.entry(channel.to_string()).or_default() += 1;
+    }
+
+    fn is_empty(&self) -> bool {
+        self.sends.is_empty() && self.latency.is_empty() && self.resends.is_empty()
+    }
+
+    /// 把本进程的增量合并到持久化文件，并更新textfile输出
+    pub fn flush(&mut self, config: &MetricsConfig) -> Result<()> {
+        if !config.enabled || self.is_empty() {
+            return Ok(());
+        }
+
+        let mut total: Metrics = store::load(STATE_FILE)?;
+        let delta = std::mem::take(self);
+        for (key, count) in delta.sends {
+            *total.sends.entry(key).or_default() += count;
+        }
+        for (channel, histogram) in delta.latency {
+            total.latency.entry(channel).or_default().merge(&histogram);
+        }
+        for (channel, count) in delta.resends {
+            *total.resends.entry(channel).or_default() += count;
+        }
+        store::save(STATE_FILE, &total)?;
+
+        if let Some(path) = &config.textfile {
+            let path = Config::data_dir()?.join(path);
+            // 同目录临时文件再重命名，collector不会读到半个文件
+            let tmp = path.with_extension("prom.tmp");
+            fs::write(&tmp, render()?)?;
+            fs::rename(&tmp, &path)?;
+        }
+        Ok(())
+    }
+}
+
+/// 以Prometheus文本格式输出累计指标和当前队列长度
+pub fn render() -> Result<String> {
+    let metrics: Metrics = store::load(STATE_FILE)?;
+    let mut out = String::new();
+
+    out.push_str(
+        "# HELP claude_notifier_sends_total Delivery attempts by channel, level and outcome.\n",
+    );
+    out.push_str("# TYPE claude_notifier_sends_total counter\n");
+    for (key, count) in &metrics.sends {
+        let mut parts = key.splitn(3, '|');
+        let (channel, level, outcome) = (
+            parts.next().unwrap_or_default(),
+            parts.next().unwrap_or_default(),
+            parts.next().unwrap_or_default(),
+        );
+        let _ = writeln!(
+            out,
+            "claude_notifier_sends_total{{channel=\"{}\",level=\"{}\",outcome=\"{}\"}} {}",
+            channel, level, outcome, count
+        );
+    }
+
+    out.push_str("# HELP claude_notifier_request_duration_seconds Delivery latency by channel.\n");
+    out.push_str("# TYPE claude_notifier_request_duration_seconds histogram\n");
+    for (channel, histogram) in &metrics.latency {
+        for (le, count) in LATENCY_BUCKETS.iter().zip(&histogram.buckets) {
+            let _ = writeln!(
+                out,
+                "claude_notifier_request_duration_seconds_bucket{{channel=\"{}\",le=\"{}\"}} {}",
+                channel, le, count
+            );
+        }
+        let _ = writeln!(
+            out,
+            "claude_notifier_request_duration_seconds_bucket{{channel=\"{}\",le=\"+Inf\"}} {}",
+            channel, histogram.count
+        );
+        let _ = writeln!(
+            out,
+            "claude_notifier_request_duration_seconds_sum{{channel=\"{}\"}} {}",
+            channel, histogram.sum
+        );
+        let _ = writeln!(
+            out,
+            "claude_notifier_request_duration_seconds_count{{channel=\"{}\"}} {}",
+            channel, histogram.count
+        );
+    }
+
+    out.push_str(
+        "# HELP claude_notifier_resends_total Manual re-sends of failed deliveries by channel.\n",
+    );
+    out.push_str("# TYPE claude_notifier_resends_total counter\n");
+    for (channel, count) in &metrics.resends {
+        let _ = writeln!(
+            out,
+            "claude_notifier_resends_total{{channel=\"{}\"}} {}",
+            channel, count
+        );
+    }
+
+    out.push_str("# HELP claude_notifier_queue_depth Notifications waiting to be sent.\n");
+    out.push_str("# TYPE claude_notifier_queue_depth gauge\n");
+    for (queue, depth) in [
+        ("scheduled", ScheduleStore::load()?.pending().len()),
+        ("digest", DigestStore::load()?.len()),
+        ("escalation", EscalationStore::load()?.pending().len()),
+    ] {
+        let _ = writeln!(
+            out,
+            "claude_notifier_queue_depth{{queue=\"{}\"}} {}",
+            queue, depth
+        );
+    }
+
+    Ok(out)
+}
+
+/// 在后台线程中提供 `GET /metrics`
+pub fn serve(addr: &str) -> Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let mut request_line = String::new();
+            if BufReader::new(&stream)
+                .read_line(&mut request_line)
+                .is_err()
+            {
+                continue;
+            }
+
+            let response = if request_line.starts_with("GET /metrics") {
+                match render() {
+                    Ok(body) => format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    ),
+                    Err(e) => format!(
+                        "HTTP/1.1 500 Internal Server Error\r\nConnection: close\r\n\r\n{:#}",
+                        e
+                    ),
+                }
+            } else {
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    .to_string()
+            };
+            let _ = (&stream).write_all(response.as_bytes());
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use crate::error::NotifierError;
+use anyhow::Result;
+use serde_json::{json, Value};
+
+/// 当前配置格式版本，格式有不兼容变化时加一并在 `MIGRATIONS` 中追加升级步骤
+pub const CONFIG_VERSION: u32 = 1;
+
+/// 升级步骤：第 i 项把版本 i 升级到 i+1，返回变更说明
+type Migration = fn(&mut Value) -> Vec<String>;
+
+const MIGRATIONS: [Migration; CONFIG_VERSION as usize] = [v0_to_v1];
+
+/// 没有 `version` 字段的配置视为版本0（引入版本号之前的格式）
+pub fn version_of(value: &Value) -> u32 {
+    value
+        .get("version")
+        .and_then(Value::as_u64)
+        .map_or(0, |v| v as u32)
+}
+
+/// 把配置升级到当前版本，返回变更说明；已是当前版本时为空
+pub fn migrate(value: &mut Value) -> Result<Vec<String>> {
+    let version = version_of(value);
+    if version > CONFIG_VERSION {
+        return Err(NotifierError::ConfigError(format!(
+            "Configuration version {} is newer than supported version {}; upgrade claude-notifier",
+            version, CONFIG_VERSION
+        ))
+        .into());
+    }
+
+    let mut changes = Vec::new();
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        for change in migration(value) {
+            changes.push(format!("v{} -> v{}: {}", from, from + 1, change));
+        }
+        if let Some(map) = value.as_object_mut() {
+            // 版本号放在最前面
+            map.shift_remove("version");
+            map.shift_insert(0, "version".to_string(), json!(from + 1));
+        }
+    }
+    Ok(changes)
+}
+
+/// 版本0到1：格式不变，只记录版本号
+fn v0_to_v1(_value: &mut Value) -> Vec<String> {
+    vec!["add \"version\" field".to_string()]
+}
@@ -0,0 +1,214 @@
+use crate::level::CustomLevel;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// 通知级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Info,
+    Success,
+    Warning,
+    Critical,
+}
+
+impl Level {
+    pub const ALL: [Level; 4] = [Level::Info, Level::Success, Level::Warning, Level::Critical];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Level::Info => "info",
+            Level::Success => "success",
+            Level::Warning => "warning",
+            Level::Critical => "critical",
+        }
+    }
+
+    /// 卡片主题色（十六进制，不含#）
+    pub fn color(&self) -> &'static str {
+        match self {
+            Level::Info => "0078D4",
+            Level::Success => "28A745",
+            Level::Warning => "FFA500",
+            Level::Critical => "DC3545",
+        }
+    }
+
+    pub fn emoji(&self) -> &'static str {
+        match self {
+            Level::Info => "ℹ️",
+            Level::Success => "✅",
+            Level::Warning => "⚠️",
+            Level::Critical => "🚨",
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Level {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Level::ALL
+            .into_iter()
+            .find(|level| level.as_str().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Unknown level '{}' (expected one of: info, success, warning, critical)",
+                    s
+                )
+            })
+    }
+}
+
+/// 卡片按钮：值为http(s)网址时打开链接，否则点击后把值发回回调服务
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CardAction {
+    pub label: String,
+    pub value: String,
+}
+
+impl CardAction {
+    pub fn is_link(&self) -> bool {
+        self.value.starts_with("http://") || self.value.starts_with("https://")
+    }
+}
+
+impl FromStr for CardAction {
+    type Err = anyhow::Error;
+
+    /// `LABEL=VALUE`，例如 `Approve=approve` 或 `Logs=https://ci.example.com/123`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((label, value)) if !label.trim().is_empty() && !value.trim().is_empty() => {
+                Ok(Self {
+                    label: label.trim().to_string(),
+                    value: value.trim().to_string(),
+                })
+            }
+            _ => Err(anyhow::anyhow!(
+                "Invalid action '{}' (expected LABEL=VALUE, e.g. Approve=approve)",
+                s
+            )),
+        }
+    }
+}
+
+/// 贯穿整个发送流程的通知
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    pub event: String,
+    pub title: String,
+    pub content: String,
+    pub level: Level,
+    /// hook传入的原始JSON，作为模板上下文
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<serde_json::Value>,
+    /// 调用方指定的去重键，替代按字段组合的键
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dedup_key: Option<String>,
+    /// 附带的图片，网址或本地文件路径
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// 附件文件的绝对路径
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<String>,
+    /// 需要@的用户，对应配置中 `users` 的键
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mentions: Vec<String>,
+    /// 会话键，相同键的通知作为同一会话的回复发送
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread_key: Option<String>,
+    /// 卡片按钮
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub actions: Vec<CardAction>,
+    /// 自定义级别，`level` 为其基础级别
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_level: Option<CustomLevel>,
+}
+
+impl Notification {
+    pub fn new(
+        event: impl Into<String>,
+        title: impl Into<String>,
+        content: impl Into<String>,
+        level: Level,
+    ) -> Self {
+        Self {
+            event: event.into(),
+            title: title.into(),
+            content: content.into(),
+            level,
+            payload: None,
+            dedup_key: None,
+            image: None,
+            attachments: Vec::new(),
+            mentions: Vec::new(),
+            thread_key: None,
+            actions: Vec::new(),
+            custom_level: None,
+        }
+    }
+
+    /// 级别名，自定义级别时为其名字
+    pub fn level_name(&self) -> &str {
+        match &self.custom_level {
+            Some(custom) => &custom.name,
+            None => self.level.as_str(),
+        }
+    }
+
+    pub fn emoji(&self) -> &str {
+        self.custom_level
+            .as_ref()
+            .and_then(|custom| custom.config.emoji.as_deref())
+            .unwrap_or_else(|| self.level.emoji())
+    }
+
+    /// 是否无视静默时段
+    pub fn bypasses_quiet_hours(&self) -> bool {
+        self.custom_level
+            .as_ref()
+            .and_then(|custom| custom.config.bypass_quiet_hours)
+            .unwrap_or(self.level == Level::Critical)
+    }
+
+    /// 是否@值班人员（没有值班人员时@所有人）
+    pub fn mentions_on_call(&self) -> bool {
+        self.custom_level
+            .as_ref()
+            .and_then(|custom| custom.config.mention_on_call)
+            .unwrap_or(self.level == Level::Critical)
+    }
+
+    /// 正文末尾追加一段说明的副本
+    pub fn with_note(&self, note: &str) -> Notification {
+        let mut notification = self.clone();
+        if notification.content.is_empty() {
+            notification.content = note.to_string();
+        } else {
+            notification.content = format!("{}\n\n{}", notification.content, note);
+        }
+        notification
+    }
+}
+
+/// 生成短通知ID（12位十六进制）
+pub fn new_id() -> String {
+    use std::hash::{BuildHasher, Hasher};
+
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_i64(
+        chrono::Local::now()
+            .timestamp_nanos_opt()
+            .unwrap_or_default(),
+    );
+    hasher.write_u32(std::process::id());
+    format!("{:012x}", hasher.finish() & 0xffff_ffff_ffff)
+}
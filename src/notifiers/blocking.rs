@@ -0,0 +1,118 @@
+use super::{Action, Image, Notifier};
+use crate::error::Result;
+use serde_json::Value;
+use std::path::Path;
+use std::sync::OnceLock;
+use tokio::runtime::Runtime;
+
+/// 同步调用外观 - 保留原同步接口，供CLI及尚未迁移到异步的调用方使用
+///
+/// 对所有 [`Notifier`] 自动实现。不要在异步上下文中调用，否则会阻塞执行器线程；
+/// 异步代码应直接 `.await` [`Notifier`] 的方法。
+pub trait BlockingNotifier {
+    fn send_text_blocking(&self, text: &str) -> Result<Value>;
+    fn send_card_blocking(
+        &self,
+        title: &str,
+        content: &str,
+        color: &str,
+        actions: Vec<Action>,
+    ) -> Result<Value>;
+    fn send_card_with_image_blocking(
+        &self,
+        title: &str,
+        content: &str,
+        color: &str,
+        actions: Vec<Action>,
+        image: &Image,
+    ) -> Result<Value>;
+    fn send_card_in_thread_blocking(
+        &self,
+        title: &str,
+        content: &str,
+        color: &str,
+        actions: Vec<Action>,
+        image: Option<&Image>,
+        root: Option<&str>,
+    ) -> Result<(Value, Option<String>)>;
+    fn update_card_blocking(
+        &self,
+        message_id: &str,
+        title: &str,
+        content: &str,
+        color: &str,
+        actions: Vec<Action>,
+    ) -> Result<Value>;
+    fn reactions_blocking(&self, message_id: &str) -> Result<Vec<String>>;
+    fn send_attachment_blocking(&self, path: &Path) -> Result<Value>;
+}
+
+impl<T: Notifier + ?Sized> BlockingNotifier for T {
+    fn send_text_blocking(&self, text: &str) -> Result<Value> {
+        runtime().block_on(self.send_text(text))
+    }
+
+    fn send_card_blocking(
+        &self,
+        title: &str,
+        content: &str,
+        color: &str,
+        actions: Vec<Action>,
+    ) -> Result<Value> {
+        runtime().block_on(self.send_card(title, content, color, actions))
+    }
+
+    fn send_card_with_image_blocking(
+        &self,
+        title: &str,
+        content: &str,
+        color: &str,
+        actions: Vec<Action>,
+        image: &Image,
+    ) -> Result<Value> {
+        runtime().block_on(self.send_card_with_image(title, content, color, actions, image))
+    }
+
+    fn send_card_in_thread_blocking(
+        &self,
+        title: &str,
+        content: &str,
+        color: &str,
+        actions: Vec<Action>,
+        image: Option<&Image>,
+        root: Option<&str>,
+    ) -> Result<(Value, Option<String>)> {
+        runtime().block_on(self.send_card_in_thread(title, content, color, actions, image, root))
+    }
+
+    fn update_card_blocking(
+        &self,
+        message_id: &str,
+        title: &str,
+        content: &str,
+        color: &str,
+        actions: Vec<Action>,
+    ) -> Result<Value> {
+        runtime().block_on(self.update_card(message_id, title, content, color, actions))
+    }
+
+    fn reactions_blocking(&self, message_id: &str) -> Result<Vec<String>> {
+        runtime().block_on(self.reactions(message_id))
+    }
+
+    fn send_attachment_blocking(&self, path: &Path) -> Result<Value> {
+        runtime().block_on(self.send_attachment(path))
+    }
+}
+
+/// 同步外观共用的运行时，首次使用时创建
+pub fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()
+            .expect("Failed to build tokio runtime")
+    })
+}
@@ -0,0 +1,149 @@
+use super::{card_payload, text_payload, Action, Notifier};
+use crate::config::UserIdentity;
+use crate::error::{NotifierError, Result};
+use crate::markdown::Dialect;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// 外部命令通知器 - 将通知JSON写入用户配置的可执行程序的stdin，退出码0视为成功
+pub struct ExecNotifier {
+    command: String,
+    args: Vec<String>,
+    timeout: Duration,
+    max_content_bytes: Option<usize>,
+    markdown: Dialect,
+    remote: bool,
+}
+
+impl ExecNotifier {
+    pub fn new(command: String, args: Vec<String>, timeout_secs: u64) -> Self {
+        Self {
+            command,
+            args,
+            timeout: Duration::from_secs(timeout_secs),
+            max_content_bytes: None,
+            markdown: Dialect::Markdown,
+            remote: false,
+        }
+    }
+
+    /// 命令接收的markdown方言，例如短信网关用 `plain`
+    pub fn with_markdown(mut self, dialect: Dialect) -> Self {
+        self.markdown = dialect;
+        self
+    }
+
+    /// 命令需要联网，例如短信网关
+    pub fn with_remote(mut self, remote: bool) -> Self {
+        self.remote = remote;
+        self
+    }
+
+    /// 限制消息内容长度，例如短信网关
+    pub fn with_max_content_bytes(mut self, max: Option<usize>) -> Self {
+        self.max_content_bytes = max;
+        self
+    }
+
+    async fn run(&self, payload: Value) -> Result<Value> {
+        let mut child = Command::new(&self.command)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| {
+                NotifierError::ChannelUnavailable(format!(
+                    "Failed to spawn {}: {}",
+                    self.command, e
+                ))
+            })?;
+
+        // 写入stdin与读取输出同时进行并一起计时：命令不读stdin或先写满stdout时，
+        // 超过管道缓冲的内容也不会一直卡住
+        let stdin = child.stdin.take();
+        let input = payload.to_string();
+        let write = async move {
+            if let Some(mut stdin) = stdin {
+                // 命令可能不读取stdin就退出，以退出码为准
+                if let Err(e) = stdin.write_all(input.as_bytes()).await {
+                    if e.kind() != std::io::ErrorKind::BrokenPipe {
+                        return Err(NotifierError::ChannelUnavailable(e.to_string()));
+                    }
+                }
+            }
+            Ok(())
+        };
+
+        let writer = tokio::spawn(write);
+        let abort = writer.abort_handle();
+
+        // 超时后丢弃future，kill_on_drop负责结束子进程
+        let finished = tokio::time::timeout(self.timeout, async {
+            let output = child.wait_with_output().await;
+            (writer.await, output)
+        })
+        .await;
+        let Ok((written, output)) = finished else {
+            abort.abort();
+            return Err(NotifierError::Timeout);
+        };
+        written.map_err(|e| NotifierError::ChannelUnavailable(e.to_string()))??;
+        let output = output.map_err(|e| NotifierError::ChannelUnavailable(e.to_string()))?;
+
+        if output.status.success() {
+            Ok(json!({
+                "exit_code": 0,
+                "stdout": String::from_utf8_lossy(&output.stdout).trim()
+            }))
+        } else {
+            // 被信号终止时没有退出码，记为-1
+            Err(NotifierError::ProviderRejected {
+                code: output.status.code().map_or(-1, i64::from),
+                msg: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            })
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for ExecNotifier {
+    async fn send_text(&self, text: &str) -> Result<Value> {
+        self.run(text_payload(text)).await
+    }
+
+    async fn send_card(
+        &self,
+        title: &str,
+        content: &str,
+        color: &str,
+        actions: Vec<Action>,
+    ) -> Result<Value> {
+        self.run(card_payload(title, content, color, actions)).await
+    }
+
+    /// 企业微信方言用userid（`<@userid>`），其余用手机号，由命令自行处理
+    fn mention(&self, _name: &str, user: &UserIdentity) -> Option<String> {
+        match self.markdown {
+            Dialect::Wecom => user.wecom.as_ref().map(|id| format!("<@{}>", id)),
+            _ => user.phone.as_ref().map(|phone| format!("@{}", phone)),
+        }
+    }
+
+    fn max_content_bytes(&self) -> Option<usize> {
+        self.max_content_bytes
+    }
+
+    fn markdown_dialect(&self) -> Dialect {
+        self.markdown
+    }
+
+    fn is_local(&self) -> bool {
+        !self.remote
+    }
+}
@@ -1,53 +1,475 @@
-use super::{send_request, Action, Notifier};
-use anyhow::Result;
+use super::{exchange, json_body, send_request, Action, Image, Notifier};
+use crate::config::UserIdentity;
+use crate::error::{NotifierError, Result};
+use crate::http::HttpClient;
+use crate::markdown::{self, Dialect};
+use crate::notification::Level;
+use async_trait::async_trait;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use reqwest::Method;
 use serde_json::{json, Value};
+use sha2::Sha256;
+use std::path::Path;
+use tracing::warn;
+
+/// 自定义机器人请求体上限20KB，给卡片的其余部分留出余量
+const MAX_CONTENT_BYTES: usize = 18 * 1024;
 
 pub struct FeishuNotifier {
     webhook: String,
+    secret: Option<String>,
+    /// 自建应用的 app_id 和 app_secret，上传图片时需要
+    app: Option<(String, String)>,
+    /// 应用机器人所在群，发送文件和会话回复时需要
+    chat_id: Option<String>,
+    client: HttpClient,
 }
 
 impl FeishuNotifier {
     pub fn new(webhook: String, _at_all_on_critical: bool) -> Self {
-        Self { webhook }
+        Self {
+            webhook,
+            secret: None,
+            app: None,
+            chat_id: None,
+            client: HttpClient::default(),
+        }
+    }
+
+    /// 设置签名校验密钥
+    pub fn with_secret(mut self, secret: Option<String>) -> Self {
+        self.secret = secret.filter(|s| !s.is_empty());
+        self
+    }
+
+    /// 使用指定的HTTP客户端（代理、超时、重试等设置）
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// 设置自建应用凭证，两项都有时才生效
+    pub fn with_app(mut self, app_id: Option<String>, app_secret: Option<String>) -> Self {
+        self.app = app_id.zip(app_secret);
+        self
+    }
+
+    /// 设置应用机器人所在群的 chat_id，用于发送文件
+    pub fn with_chat_id(mut self, chat_id: Option<String>) -> Self {
+        self.chat_id = chat_id.filter(|c| !c.is_empty());
+        self
+    }
+
+    /// 开放平台接口地址，与webhook同域（飞书或Lark）
+    fn api_url(&self, path: &str) -> Result<String> {
+        let url = reqwest::Url::parse(&self.webhook)
+            .map_err(|e| NotifierError::ConfigError(format!("Invalid Feishu webhook: {}", e)))?;
+        Ok(format!("{}{}", url.origin().ascii_serialization(), path))
+    }
+
+    /// 获取 tenant_access_token
+    async fn access_token(&self, app_id: &str, app_secret: &str) -> Result<String> {
+        let url = self.api_url("/open-apis/auth/v3/tenant_access_token/internal")?;
+        let body = json!({ "app_id": app_id, "app_secret": app_secret });
+        let send = self.client.inner.post(&url).json(&body).send();
+        let response = json_body(exchange(&url, &body, send).await?)?;
+        check_response(response)?["tenant_access_token"]
+            .as_str()
+            .map(String::from)
+            .ok_or_else(|| NotifierError::Network("no tenant_access_token in response".into()))
+    }
+
+    /// 上传图片，返回卡片中使用的 image_key
+    async fn upload_image(&self, image: &Image) -> Result<String> {
+        let token = self.app_token().await?;
+        let part = reqwest::multipart::Part::bytes(image.bytes(&self.client).await?)
+            .file_name(image.file_name());
+        let form = reqwest::multipart::Form::new()
+            .text("image_type", "message")
+            .part("image", part);
+        let url = self.api_url("/open-apis/im/v1/images")?;
+        let send = self
+            .client
+            .inner
+            .post(&url)
+            .bearer_auth(token)
+            .multipart(form)
+            .send();
+        let request = json!({ "image_type": "message", "image": image.file_name() });
+        let response = json_body(exchange(&url, &request, send).await?)?;
+        check_response(response)?["data"]["image_key"]
+            .as_str()
+            .map(String::from)
+            .ok_or_else(|| NotifierError::Network("no image_key in response".into()))
+    }
+
+    /// 上传文件，返回 file_key
+    async fn upload_file(&self, token: &str, path: &Path) -> Result<String> {
+        let data = std::fs::read(path).map_err(|e| {
+            NotifierError::ConfigError(format!("Cannot read {}: {}", path.display(), e))
+        })?;
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("attachment")
+            .to_string();
+        let request = json!({ "file_type": "stream", "file_name": file_name });
+        let form = reqwest::multipart::Form::new()
+            .text("file_type", "stream")
+            .text("file_name", file_name.clone())
+            .part(
+                "file",
+                reqwest::multipart::Part::bytes(data).file_name(file_name),
+            );
+        let url = self.api_url("/open-apis/im/v1/files")?;
+        let send = self
+            .client
+            .inner
+            .post(&url)
+            .bearer_auth(token)
+            .multipart(form)
+            .send();
+        let response = json_body(exchange(&url, &request, send).await?)?;
+        check_response(response)?["data"]["file_key"]
+            .as_str()
+            .map(String::from)
+            .ok_or_else(|| NotifierError::Network("no file_key in response".into()))
+    }
+
+    /// 配置了应用凭证和群时，卡片以应用机器人身份发送，以便回复和编辑；返回群的 chat_id
+    fn app_bot(&self) -> Option<&str> {
+        self.app.as_ref().and(self.chat_id.as_deref())
+    }
+
+    /// 应用的 tenant_access_token
+    async fn app_token(&self) -> Result<String> {
+        let Some((app_id, app_secret)) = &self.app else {
+            return Err(NotifierError::ConfigError(
+                "app_id and app_secret are required".to_string(),
+            ));
+        };
+        self.access_token(app_id, app_secret).await
+    }
+
+    /// 以应用机器人身份调用消息接口
+    async fn message_request(
+        &self,
+        method: Method,
+        token: &str,
+        path: &str,
+        body: Value,
+    ) -> Result<Value> {
+        let url = self.api_url(path)?;
+        let send = self
+            .client
+            .inner
+            .request(method, &url)
+            .bearer_auth(token)
+            .json(&body)
+            .send();
+        let response = json_body(exchange(&url, &body, send).await?)?;
+        check_response(response)
+    }
+
+    /// 带图片的卡片；上传失败时网址以链接发送，不影响通知本身
+    async fn image_card_request(
+        &self,
+        title: &str,
+        content: &str,
+        color: &str,
+        actions: Vec<Action>,
+        image: &Image,
+    ) -> Value {
+        match self.upload_image(image).await {
+            Ok(image_key) => {
+                let mut data = self.card_request(title, content, color, actions);
+                if let Some(elements) = data["card"]["elements"].as_array_mut() {
+                    elements.insert(
+                        1,
+                        json!({
+                            "tag": "img",
+                            "img_key": image_key,
+                            "alt": { "tag": "plain_text", "content": image.file_name() }
+                        }),
+                    );
+                }
+                data
+            }
+            Err(e) => {
+                if self.app.is_some() {
+                    warn!("Feishu image upload failed: {}", e);
+                }
+                let content = match image {
+                    Image::Url(url) => format!("{}\n\n[{}]({})", content, image.file_name(), url),
+                    Image::File(_) => content.to_string(),
+                };
+                self.card_request(title, &content, color, actions)
+            }
+        }
+    }
+
+    /// 开启签名校验时在请求体中加入 `timestamp` 和 `sign`
+    fn sign(&self, mut data: Value) -> Value {
+        let Some(secret) = &self.secret else {
+            return data;
+        };
+        let timestamp = chrono::Utc::now().timestamp().to_string();
+        // 以 "timestamp\nsecret" 为密钥对空消息做HMAC-SHA256
+        let mac = Hmac::<Sha256>::new_from_slice(format!("{}\n{}", timestamp, secret).as_bytes())
+            .expect("HMAC accepts keys of any length");
+        let sign = base64::engine::general_purpose::STANDARD.encode(mac.finalize().into_bytes());
+        if let Some(map) = data.as_object_mut() {
+            map.insert("timestamp".to_string(), json!(timestamp));
+            map.insert("sign".to_string(), json!(sign));
+        }
+        data
     }
 }
 
+/// 消息接口中的卡片内容；声明为共享卡片，之后才能编辑
+fn app_card(data: &Value) -> String {
+    let mut card = data["card"].clone();
+    card["config"] = json!({ "update_multi": true });
+    card.to_string()
+}
+
+/// 飞书出错时返回HTTP 200和非0的 `code`（旧版接口为 `StatusCode`）
+fn check_response(response: Value) -> Result<Value> {
+    let code = response["code"]
+        .as_i64()
+        .or_else(|| response["StatusCode"].as_i64())
+        .unwrap_or(0);
+    if code == 0 {
+        return Ok(response);
+    }
+    let msg = response["msg"]
+        .as_str()
+        .or_else(|| response["StatusMessage"].as_str())
+        .unwrap_or_default();
+    Err(NotifierError::ProviderRejected {
+        code,
+        msg: msg.to_string(),
+    })
+}
+
+#[async_trait]
 impl Notifier for FeishuNotifier {
-    fn send_text(&self, text: &str) -> Result<Value> {
+    async fn send_text(&self, text: &str) -> Result<Value> {
         let data = json!({
             "msg_type": "text",
             "content": {
                 "text": text
             }
         });
-        send_request(&self.webhook, data)
+        send_request(&self.client, &self.webhook, self.sign(data), check_response).await
+    }
+
+    async fn send_card(
+        &self,
+        title: &str,
+        content: &str,
+        color: &str,
+        actions: Vec<Action>,
+    ) -> Result<Value> {
+        if self.app_bot().is_some() {
+            return Ok(self
+                .send_card_in_thread(title, content, color, actions, None, None)
+                .await?
+                .0);
+        }
+        let data = self.card_request(title, content, color, actions);
+        send_request(&self.client, &self.webhook, self.sign(data), check_response).await
+    }
+
+    async fn send_card_with_image(
+        &self,
+        title: &str,
+        content: &str,
+        color: &str,
+        actions: Vec<Action>,
+        image: &Image,
+    ) -> Result<Value> {
+        if self.app_bot().is_some() {
+            return Ok(self
+                .send_card_in_thread(title, content, color, actions, Some(image), None)
+                .await?
+                .0);
+        }
+        let data = self
+            .image_card_request(title, content, color, actions, image)
+            .await;
+        send_request(&self.client, &self.webhook, self.sign(data), check_response).await
+    }
+
+    fn supports_threads(&self) -> bool {
+        self.app_bot().is_some()
+    }
+
+    /// 自定义机器人不能回复消息，会话中的消息以应用机器人身份发到 `chat_id` 群
+    async fn send_card_in_thread(
+        &self,
+        title: &str,
+        content: &str,
+        color: &str,
+        actions: Vec<Action>,
+        image: Option<&Image>,
+        root: Option<&str>,
+    ) -> Result<(Value, Option<String>)> {
+        let Some(chat_id) = self.app_bot() else {
+            let response = match image {
+                Some(image) => {
+                    self.send_card_with_image(title, content, color, actions, image)
+                        .await?
+                }
+                None => self.send_card(title, content, color, actions).await?,
+            };
+            return Ok((response, None));
+        };
+        let data = match image {
+            Some(image) => {
+                self.image_card_request(title, content, color, actions, image)
+                    .await
+            }
+            None => self.card_request(title, content, color, actions),
+        };
+        let card = app_card(&data);
+        let token = self.app_token().await?;
+
+        if let Some(root) = root {
+            let reply = json!({
+                "msg_type": "interactive",
+                "content": card,
+                "reply_in_thread": true
+            });
+            let path = format!("/open-apis/im/v1/messages/{}/reply", root);
+            match self
+                .message_request(Method::POST, &token, &path, reply)
+                .await
+            {
+                Ok(response) => return Ok((response, Some(root.to_string()))),
+                // 首条消息被撤回等情况下发起新会话
+                Err(e) => warn!("Feishu reply failed, starting a new thread: {}", e),
+            }
+        }
+
+        let message = json!({
+            "receive_id": chat_id,
+            "msg_type": "interactive",
+            "content": card
+        });
+        let response = self
+            .message_request(
+                Method::POST,
+                &token,
+                "/open-apis/im/v1/messages?receive_id_type=chat_id",
+                message,
+            )
+            .await?;
+        let message_id = self.message_id(&response);
+        Ok((response, message_id))
+    }
+
+    fn message_id(&self, response: &Value) -> Option<String> {
+        response["data"]["message_id"].as_str().map(String::from)
     }
 
-    fn send_card(
+    /// 只能编辑应用机器人发送的卡片
+    async fn update_card(
         &self,
+        message_id: &str,
         title: &str,
         content: &str,
         color: &str,
         actions: Vec<Action>,
     ) -> Result<Value> {
-        let mut elements = vec![json!({
-            "tag": "markdown",
-            "content": content
-        })];
+        if self.app.is_none() {
+            return Err(NotifierError::ConfigError(
+                "app_id and app_secret are required to edit messages".to_string(),
+            ));
+        }
+        let data = self.card_request(title, content, color, actions);
+        let token = self.app_token().await?;
+        self.message_request(
+            Method::PATCH,
+            &token,
+            &format!("/open-apis/im/v1/messages/{}", message_id),
+            json!({ "content": app_card(&data) }),
+        )
+        .await
+    }
+
+    async fn reactions(&self, message_id: &str) -> Result<Vec<String>> {
+        let token = self.app_token().await?;
+        let url = self.api_url(&format!(
+            "/open-apis/im/v1/messages/{}/reactions",
+            message_id
+        ))?;
+        let send = self
+            .client
+            .inner
+            .get(&url)
+            .bearer_auth(token)
+            .query(&[("page_size", "50")])
+            .send();
+        let response = json_body(exchange(&url, &Value::Null, send).await?)?;
+        Ok(check_response(response)?["data"]["items"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|item| item["reaction_type"]["emoji_type"].as_str())
+            .map(String::from)
+            .collect())
+    }
+
+    fn card_request(&self, title: &str, content: &str, color: &str, actions: Vec<Action>) -> Value {
+        let (content, facts) = markdown::split_facts(content);
+        let mut elements = Vec::new();
+        if !content.is_empty() || facts.is_empty() {
+            elements.push(json!({
+                "tag": "markdown",
+                "content": content
+            }));
+        }
+        // 键值表以两列字段显示
+        if !facts.is_empty() {
+            let fields: Vec<Value> = facts
+                .into_iter()
+                .map(|fact| {
+                    json!({
+                        "is_short": true,
+                        "text": {
+                            "tag": "lark_md",
+                            "content": format!(
+                                "**{}**\n{}",
+                                markdown::strip_bold(&fact.name),
+                                fact.value
+                            )
+                        }
+                    })
+                })
+                .collect();
+            elements.push(json!({ "tag": "div", "fields": fields }));
+        }
 
         if !actions.is_empty() {
             let action_elements: Vec<Value> = actions
                 .into_iter()
                 .map(|action| {
-                    json!({
+                    let mut button = json!({
                         "tag": "button",
                         "text": {
                             "tag": "plain_text",
                             "content": action.text
                         },
-                        "url": action.url,
                         "type": "default"
-                    })
+                    });
+                    // 回调按钮的点击发到应用配置的请求地址
+                    match action.callback {
+                        Some(callback) => button["value"] = callback,
+                        None => button["url"] = json!(action.url),
+                    }
+                    button
                 })
                 .collect();
 
@@ -57,7 +479,7 @@ impl Notifier for FeishuNotifier {
             }));
         }
 
-        let data = json!({
+        json!({
             "msg_type": "interactive",
             "card": {
                 "header": {
@@ -69,8 +491,57 @@ impl Notifier for FeishuNotifier {
                 },
                 "elements": elements
             }
+        })
+    }
+
+    /// 飞书标题栏只接受颜色模板名
+    fn level_color(&self, level: Level) -> &'static str {
+        match level {
+            Level::Info => "blue",
+            Level::Success => "green",
+            Level::Warning => "orange",
+            Level::Critical => "red",
+        }
+    }
+
+    fn mention(&self, _name: &str, user: &UserIdentity) -> Option<String> {
+        user.feishu
+            .as_ref()
+            .map(|open_id| format!("<at id={}></at>", open_id))
+    }
+
+    fn supports_attachments(&self) -> bool {
+        self.app_bot().is_some()
+    }
+
+    /// 自定义机器人不能发送文件，以应用机器人身份发到 `chat_id` 群
+    async fn send_attachment(&self, path: &Path) -> Result<Value> {
+        let Some(chat_id) = self.app_bot() else {
+            return Err(NotifierError::ConfigError(
+                "app_id, app_secret and chat_id are required to upload files".to_string(),
+            ));
+        };
+        let token = self.app_token().await?;
+        let file_key = self.upload_file(&token, path).await?;
+        let message = json!({
+            "receive_id": chat_id,
+            "msg_type": "file",
+            "content": json!({ "file_key": file_key }).to_string()
         });
+        self.message_request(
+            Method::POST,
+            &token,
+            "/open-apis/im/v1/messages?receive_id_type=chat_id",
+            message,
+        )
+        .await
+    }
+
+    fn max_content_bytes(&self) -> Option<usize> {
+        Some(MAX_CONTENT_BYTES)
+    }
 
-        send_request(&self.webhook, data)
+    fn markdown_dialect(&self) -> Dialect {
+        Dialect::Lark
     }
 }
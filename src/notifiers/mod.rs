@@ -1,39 +1,476 @@
+pub mod blocking;
+pub mod exec;
 pub mod feishu;
 pub mod teams;
+#[cfg(feature = "wasm-plugins")]
+pub mod wasm;
 pub mod wechat;
 
-use anyhow::Result;
-use serde_json::Value;
+use crate::audit;
+use crate::config::UserIdentity;
+use crate::error::{NotifierError, Result};
+use crate::http::HttpClient;
+use crate::i18n;
+use crate::markdown::Dialect;
+use crate::notification::Level;
+use crate::redact;
+use crate::replay::{self, Mode, Outcome};
+use async_trait::async_trait;
+use base64::Engine;
+use serde_json::{json, Value};
+use std::borrow::Cow;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tracing::{debug, info, info_span, warn, Instrument};
 
+pub use blocking::BlockingNotifier;
+
+/// 通知渠道 - 异步接口，同步调用方使用 [`BlockingNotifier`]
+#[async_trait]
 pub trait Notifier: Send + Sync {
-    fn send_text(&self, text: &str) -> Result<Value>;
-    fn send_card(
+    async fn send_text(&self, text: &str) -> Result<Value>;
+    async fn send_card(
         &self,
         title: &str,
         content: &str,
         color: &str,
         actions: Vec<Action>,
     ) -> Result<Value>;
+
+    /// 发送带图片的卡片；默认把图片网址以markdown图片附在内容后，本地文件无法内嵌时忽略
+    async fn send_card_with_image(
+        &self,
+        title: &str,
+        content: &str,
+        color: &str,
+        actions: Vec<Action>,
+        image: &Image,
+    ) -> Result<Value> {
+        let content = match image {
+            Image::Url(url) => format!("{}\n\n![image]({})", content, url),
+            Image::File(path) => {
+                warn!(file = %path.display(), "channel cannot embed local images, sending without");
+                content.to_string()
+            }
+        };
+        self.send_card(title, &content, color, actions).await
+    }
+
+    /// 能否把相关通知作为回复归入同一会话
+    fn supports_threads(&self) -> bool {
+        false
+    }
+
+    /// 在会话中发送卡片：`root` 为会话首条消息的ID，None时发起新会话。
+    /// 返回响应和此后回复所用的首条消息ID；不支持会话的渠道照常发送
+    async fn send_card_in_thread(
+        &self,
+        title: &str,
+        content: &str,
+        color: &str,
+        actions: Vec<Action>,
+        image: Option<&Image>,
+        _root: Option<&str>,
+    ) -> Result<(Value, Option<String>)> {
+        let response = match image {
+            Some(image) => {
+                self.send_card_with_image(title, content, color, actions, image)
+                    .await?
+            }
+            None => self.send_card(title, content, color, actions).await?,
+        };
+        Ok((response, None))
+    }
+
+    /// 从发送响应中取出消息ID，供之后编辑消息；不支持编辑的渠道为None
+    fn message_id(&self, _response: &Value) -> Option<String> {
+        None
+    }
+
+    /// 编辑已发送的卡片
+    async fn update_card(
+        &self,
+        _message_id: &str,
+        _title: &str,
+        _content: &str,
+        _color: &str,
+        _actions: Vec<Action>,
+    ) -> Result<Value> {
+        Err(NotifierError::ChannelUnavailable(
+            "channel cannot edit sent messages".to_string(),
+        ))
+    }
+
+    /// 已发送消息上的表情回复（表情类型），用于确认通知
+    async fn reactions(&self, _message_id: &str) -> Result<Vec<String>> {
+        Err(NotifierError::ChannelUnavailable(
+            "channel cannot read reactions".to_string(),
+        ))
+    }
+
+    /// 不需要联网的渠道（例如本机命令），联网探测失败时照常发送
+    fn is_local(&self) -> bool {
+        false
+    }
+
+    /// 能否直接上传文件；不能的渠道在消息中附上上传目标的链接
+    fn supports_attachments(&self) -> bool {
+        false
+    }
+
+    /// 在消息中@用户的标记；没有该渠道身份或渠道不支持时为None，改为显示 `@name`
+    fn mention(&self, _name: &str, _user: &UserIdentity) -> Option<String> {
+        None
+    }
+
+    /// 直接上传并发送文件
+    async fn send_attachment(&self, path: &Path) -> Result<Value> {
+        Err(NotifierError::ChannelUnavailable(format!(
+            "channel cannot upload {}",
+            path.display()
+        )))
+    }
+
+    /// 发送卡片时的请求体，供dry-run预览；默认为插件渠道使用的通用卡片JSON
+    fn card_request(&self, title: &str, content: &str, color: &str, actions: Vec<Action>) -> Value {
+        card_payload(title, content, color, actions)
+    }
+
+    /// 消息内容的字节数上限，超出时截断；None表示不限制
+    fn max_content_bytes(&self) -> Option<usize> {
+        None
+    }
+
+    /// 级别的默认卡片颜色，可被配置中的 `colors` 覆盖
+    fn level_color(&self, level: Level) -> &'static str {
+        level.color()
+    }
+
+    /// 渠道的markdown方言，发送前把内容转换过去
+    fn markdown_dialect(&self) -> Dialect {
+        Dialect::Markdown
+    }
+}
+
+/// 把内容截断到 `max_bytes` 字节以内（按字符边界），并注明省略的字符数
+pub fn truncate(content: &str, max_bytes: usize) -> Cow<'_, str> {
+    if content.len() <= max_bytes {
+        return Cow::Borrowed(content);
+    }
+    let total_chars = content.chars().count();
+    // 标记本身也占用长度，省略数变化时标记长度会变，按最长的情况预留
+    let marker_len = truncation_marker(total_chars).len();
+    // 放不下标记时只截断内容，保证不超过上限
+    let Some(mut end) = max_bytes.checked_sub(marker_len) else {
+        return Cow::Borrowed(&content[..char_floor(content, max_bytes)]);
+    };
+    end = char_floor(content, end);
+    let kept = &content[..end];
+    let omitted = total_chars - kept.chars().count();
+    Cow::Owned(format!("{}{}", kept, truncation_marker(omitted)))
+}
+
+/// 不超过 `index` 的最近字符边界
+fn char_floor(content: &str, mut index: usize) -> usize {
+    while !content.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+fn truncation_marker(omitted: usize) -> String {
+    format!(
+        "\n\n{}",
+        i18n::fill(i18n::strings().truncated, &[("omitted", &omitted)])
+    )
 }
 
 #[derive(Debug, Clone)]
 pub struct Action {
     pub text: String,
     pub url: String,
+    /// 回调值：设置时按钮把它POST到 `url`（飞书发到应用配置的回调地址），而不是打开链接
+    pub callback: Option<Value>,
+}
+
+/// 通知附带的图片：http(s)网址或本地文件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Image {
+    Url(String),
+    File(PathBuf),
+}
+
+impl Image {
+    pub fn parse(s: &str) -> Self {
+        if s.starts_with("http://") || s.starts_with("https://") {
+            Image::Url(s.to_string())
+        } else {
+            Image::File(PathBuf::from(s))
+        }
+    }
+
+    /// 文件名，上传时使用
+    pub fn file_name(&self) -> String {
+        let name = match self {
+            Image::Url(url) => url
+                .split(['?', '#'])
+                .next()
+                .unwrap_or(url)
+                .rsplit('/')
+                .next(),
+            Image::File(path) => path.file_name().and_then(|n| n.to_str()),
+        };
+        name.filter(|n| !n.is_empty())
+            .unwrap_or("image.png")
+            .to_string()
+    }
+
+    /// 读取图片内容，网址用渠道的HTTP客户端下载
+    pub async fn bytes(&self, client: &HttpClient) -> Result<Vec<u8>> {
+        match self {
+            Image::Url(url) => {
+                let response = client.inner.get(url).send().await?;
+                let status = response.status();
+                if !status.is_success() {
+                    return Err(NotifierError::HttpStatus {
+                        status: status.as_u16(),
+                        body: format!("downloading image {}", url),
+                    });
+                }
+                Ok(response.bytes().await?.to_vec())
+            }
+            Image::File(path) => read_image(path),
+        }
+    }
+
+    /// 本地文件编码为data URI，网址原样返回
+    pub fn data_uri(&self) -> Result<String> {
+        match self {
+            Image::Url(url) => Ok(url.clone()),
+            Image::File(path) => {
+                let data = read_image(path)?;
+                Ok(format!(
+                    "data:{};base64,{}",
+                    image_mime(path),
+                    base64::engine::general_purpose::STANDARD.encode(data)
+                ))
+            }
+        }
+    }
 }
 
-pub fn send_request(webhook: &str, data: Value) -> Result<Value> {
-    let client = reqwest::blocking::Client::new();
-    let response = client
+fn read_image(path: &Path) -> Result<Vec<u8>> {
+    std::fs::read(path).map_err(|e| {
+        NotifierError::ConfigError(format!("Cannot read image {}: {}", path.display(), e))
+    })
+}
+
+fn image_mime(path: &Path) -> &'static str {
+    match path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase)
+        .as_deref()
+    {
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("bmp") => "image/bmp",
+        _ => "image/png",
+    }
+}
+
+/// 检查服务商的响应体：部分服务商出错时仍返回HTTP 200，错误码在响应体中
+pub type ResponseCheck = fn(Value) -> Result<Value>;
+
+/// 不检查响应体
+pub fn accept_any(response: Value) -> Result<Value> {
+    Ok(response)
+}
+
+pub async fn send_request(
+    client: &HttpClient,
+    webhook: &str,
+    data: Value,
+    check: ResponseCheck,
+) -> Result<Value> {
+    let started = Instant::now();
+    let span = info_span!("http_request", url = %redact::mask_url(webhook));
+    let mut attempt = 0;
+    let result = loop {
+        let result = post_json(&client.inner, webhook, &data)
+            .instrument(span.clone())
+            .await
+            .and_then(check);
+        match result {
+            Err(e) if e.is_retryable() && attempt < client.retries => {
+                let retry_after = match e {
+                    NotifierError::RateLimited { retry_after } => retry_after,
+                    _ => None,
+                };
+                let delay = HttpClient::backoff(attempt, retry_after);
+                attempt += 1;
+                span.in_scope(|| {
+                    info!(
+                        attempt,
+                        delay_ms = delay.as_millis() as u64,
+                        error_kind = e.kind(),
+                        "request failed, retrying: {}",
+                        e
+                    )
+                });
+                tokio::time::sleep(delay).await;
+            }
+            result => break result,
+        }
+    };
+    let elapsed_ms = started.elapsed().as_millis();
+    span.in_scope(|| match &result {
+        Ok(_) => debug!(elapsed_ms, "request succeeded"),
+        Err(e) => info!(elapsed_ms, error_kind = e.kind(), "request failed: {}", e),
+    });
+    audit::record(webhook, &data, result.as_ref(), elapsed_ms);
+    result
+}
+
+async fn post_json(client: &reqwest::Client, webhook: &str, data: &Value) -> Result<Value> {
+    let send = client
         .post(webhook)
-        .json(&data)
+        .json(data)
         .header("Content-Type", "application/json")
-        .timeout(std::time::Duration::from_secs(10))
-        .send()?;
+        .send();
+    interpret(exchange(webhook, data, send).await?)
+}
 
-    if response.status().is_success() {
-        Ok(response.json()?)
+/// 发出请求并读取响应，不解释状态码。`--record` 时把 `request`（请求内容的JSON形式）
+/// 和响应写入fixture，`--replay` 时不发出 `send` 而返回录制的响应
+pub(crate) async fn exchange(
+    url: &str,
+    request: &Value,
+    send: impl Future<Output = reqwest::Result<reqwest::Response>>,
+) -> Result<Outcome> {
+    match replay::mode() {
+        Some(Mode::Replay(dir)) => replay::replay(dir, url),
+        Some(Mode::Record(dir)) => {
+            let result = fetch(send).await;
+            let outcome = match &result {
+                Ok(outcome) => outcome.clone(),
+                Err(e) => Outcome::from_error(e),
+            };
+            if let Err(e) = replay::record(dir, url, request, &outcome) {
+                warn!("Failed to record the request: {}", e);
+            }
+            result
+        }
+        None => fetch(send).await,
+    }
+}
+
+async fn fetch(send: impl Future<Output = reqwest::Result<reqwest::Response>>) -> Result<Outcome> {
+    let response = send.await?;
+    let status = response.status().as_u16();
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok());
+    Ok(Outcome::Response {
+        status,
+        retry_after,
+        body: response.text().await?,
+    })
+}
+
+/// 开放平台接口的响应体，不看状态码（错误码在响应体中）
+pub(crate) fn json_body(outcome: Outcome) -> Result<Value> {
+    match outcome {
+        Outcome::Response { body, .. } => serde_json::from_str(&body)
+            .map_err(|e| NotifierError::Network(format!("error decoding response body: {}", e))),
+        Outcome::Error { error, message } => Err(replay::error(&error, message)),
+    }
+}
+
+/// 按状态码把响应转为发送结果
+fn interpret(outcome: Outcome) -> Result<Value> {
+    let (status, retry_after, body) = match outcome {
+        Outcome::Response {
+            status,
+            retry_after,
+            body,
+        } => (status, retry_after, body),
+        Outcome::Error { error, message } => return Err(replay::error(&error, message)),
+    };
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS.as_u16() {
+        return Err(NotifierError::RateLimited { retry_after });
+    }
+    if (200..300).contains(&status) {
+        // Teams等渠道返回纯文本而非JSON
+        Ok(serde_json::from_str(&body).unwrap_or(Value::String(body)))
     } else {
-        Err(anyhow::anyhow!("Request failed: {}", response.status()))
+        Err(NotifierError::HttpStatus { status, body })
+    }
+}
+
+/// 插件类渠道（exec、wasm）使用的通用卡片JSON
+pub fn card_payload(title: &str, content: &str, color: &str, actions: Vec<Action>) -> Value {
+    let actions: Vec<Value> = actions
+        .into_iter()
+        .map(|action| match action.callback {
+            Some(callback) => json!({"text": action.text, "url": action.url, "callback": callback}),
+            None => json!({"text": action.text, "url": action.url}),
+        })
+        .collect();
+
+    json!({
+        "type": "card",
+        "title": title,
+        "content": content,
+        "color": color,
+        "actions": actions
+    })
+}
+
+pub fn text_payload(text: &str) -> Value {
+    json!({
+        "type": "text",
+        "text": text
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::truncate;
+
+    #[test]
+    fn truncate_keeps_short_content() {
+        assert_eq!(truncate("hello", 5), "hello");
+    }
+
+    #[test]
+    fn truncate_appends_marker_within_limit() {
+        let content = "a".repeat(1000);
+        let truncated = truncate(&content, 200);
+        assert!(truncated.len() <= 200);
+        assert!(truncated.starts_with("aaa"));
+        // 末尾是省略标记
+        assert!(!truncated.ends_with('a'));
+    }
+
+    #[test]
+    fn truncate_drops_marker_when_it_does_not_fit() {
+        let content = "a".repeat(1000);
+        assert_eq!(truncate(&content, 10), "a".repeat(10));
+        assert_eq!(truncate(&content, 0), "");
+    }
+
+    #[test]
+    fn truncate_stops_at_char_boundary() {
+        let content = "通知".repeat(100);
+        for max in 0..64 {
+            let truncated = truncate(&content, max);
+            assert!(truncated.len() <= max);
+        }
+        assert_eq!(truncate(&content, 7), "通知");
     }
 }
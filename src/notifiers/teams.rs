@@ -1,32 +1,97 @@
-use super::{send_request, Action, Notifier};
-use anyhow::Result;
+use super::{send_request, Action, Image, Notifier};
+use crate::config::UserIdentity;
+use crate::error::{NotifierError, Result};
+use crate::http::HttpClient;
+use crate::markdown::{self, Dialect};
+use async_trait::async_trait;
 use serde_json::{json, Value};
+use tracing::warn;
+
+/// Teams消息总大小上限约28KB，给卡片的其余部分留出余量
+const MAX_CONTENT_BYTES: usize = 26 * 1024;
+
+/// 以data URI内嵌本地图片的上限，更大的图片需使用网址
+const MAX_INLINE_IMAGE_BYTES: usize = 20 * 1024;
 
 pub struct TeamsNotifier {
     webhook: String,
+    client: HttpClient,
 }
 
 impl TeamsNotifier {
     pub fn new(webhook: String) -> Self {
-        Self { webhook }
+        Self {
+            webhook,
+            client: HttpClient::default(),
+        }
     }
+
+    /// 使用指定的HTTP客户端（代理、超时、重试等设置）
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        self.client = client;
+        self
+    }
+}
+
+/// 旧版Teams连接器在下游出错时仍返回HTTP 200，响应体为
+/// "Webhook message delivery failed with error: ... returned HTTP error 413 ..."
+fn check_response(response: Value) -> Result<Value> {
+    let Some(text) = response.as_str().filter(|t| t.contains("delivery failed")) else {
+        return Ok(response);
+    };
+    let code = text
+        .split("HTTP error ")
+        .nth(1)
+        .and_then(|rest| rest.split(|c: char| !c.is_ascii_digit()).next())
+        .and_then(|code| code.parse().ok())
+        .unwrap_or(-1);
+    Err(NotifierError::ProviderRejected {
+        code,
+        msg: text.to_string(),
+    })
 }
 
+#[async_trait]
 impl Notifier for TeamsNotifier {
-    fn send_text(&self, text: &str) -> Result<Value> {
+    async fn send_text(&self, text: &str) -> Result<Value> {
         let data = json!({
             "text": text
         });
-        send_request(&self.webhook, data)
+        send_request(&self.client, &self.webhook, data, check_response).await
+    }
+
+    async fn send_card(
+        &self,
+        title: &str,
+        content: &str,
+        color: &str,
+        actions: Vec<Action>,
+    ) -> Result<Value> {
+        let card = self.card_request(title, content, color, actions);
+        send_request(&self.client, &self.webhook, card, check_response).await
     }
 
-    fn send_card(
+    async fn send_card_with_image(
         &self,
         title: &str,
         content: &str,
         color: &str,
         actions: Vec<Action>,
+        image: &Image,
     ) -> Result<Value> {
+        let mut card = self.card_request(title, content, color, actions);
+        match image.data_uri() {
+            Ok(uri) if uri.len() <= MAX_INLINE_IMAGE_BYTES => {
+                card["sections"][0]["heroImage"] = json!({ "image": uri });
+            }
+            Ok(_) => warn!("image is too large to embed in a Teams card, use a URL instead"),
+            Err(e) => warn!("{}", e),
+        }
+        send_request(&self.client, &self.webhook, card, check_response).await
+    }
+
+    fn card_request(&self, title: &str, content: &str, color: &str, actions: Vec<Action>) -> Value {
+        let (content, facts) = markdown::split_facts(content);
         let mut card = json!({
             "@type": "MessageCard",
             "@context": "http://schema.org/extensions",
@@ -38,22 +103,52 @@ impl Notifier for TeamsNotifier {
                 "markdown": true
             }]
         });
+        if !facts.is_empty() {
+            card["sections"][0]["facts"] = facts
+                .into_iter()
+                .map(|fact| json!({ "name": fact.name, "value": fact.value }))
+                .collect();
+        }
 
         if !actions.is_empty() {
             let mut potential_actions = Vec::new();
             for action in actions {
-                potential_actions.push(json!({
-                    "@type": "OpenUri",
-                    "name": action.text,
-                    "targets": [{
-                        "os": "default",
-                        "uri": action.url
-                    }]
-                }));
+                potential_actions.push(match action.callback {
+                    Some(callback) => json!({
+                        "@type": "HttpPOST",
+                        "name": action.text,
+                        "target": action.url,
+                        "body": callback.to_string(),
+                        "bodyContentType": "application/json"
+                    }),
+                    None => json!({
+                        "@type": "OpenUri",
+                        "name": action.text,
+                        "targets": [{
+                            "os": "default",
+                            "uri": action.url
+                        }]
+                    }),
+                });
             }
             card["potentialAction"] = json!(potential_actions);
         }
 
-        send_request(&self.webhook, card)
+        card
+    }
+
+    /// MessageCard不支持@提及实体，只能显示为指向用户邮箱的链接，不会触发提醒
+    fn mention(&self, name: &str, user: &UserIdentity) -> Option<String> {
+        user.teams
+            .as_ref()
+            .map(|upn| format!("[@{}](mailto:{})", name, upn))
+    }
+
+    fn max_content_bytes(&self) -> Option<usize> {
+        Some(MAX_CONTENT_BYTES)
+    }
+
+    fn markdown_dialect(&self) -> Dialect {
+        Dialect::Teams
     }
 }
@@ -0,0 +1,226 @@
+use super::{accept_any, card_payload, send_request, text_payload, Action, Notifier};
+use crate::error::{NotifierError, Result};
+use crate::http::HttpClient;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+use wasmtime::{Caller, Engine, Linker, Module, Store, Trap};
+
+/// 插件单次调用的时间上限，超时后中断插件
+const CALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// epoch计时的间隔，调用的截止时间按此取整
+const EPOCH_TICK: Duration = Duration::from_millis(100);
+
+/// WASM插件通知器
+///
+/// 插件ABI（核心WASM模块，无需component model）：
+/// - 导出 `memory`
+/// - 导出 `alloc(len: i32) -> i32`：分配len字节，返回指针
+/// - 导出 `send_card(ptr: i32, len: i32) -> i32`：参数为UTF-8 JSON通知（与exec渠道相同格式），返回0表示成功
+/// - 可导入 `env.http_post(url_ptr, url_len, body_ptr, body_len) -> i32`：由宿主发送JSON POST，成功返回0
+/// - 可导入 `env.log(ptr, len)`：输出到stderr
+pub struct WasmNotifier {
+    name: String,
+    path: PathBuf,
+    client: HttpClient,
+    /// 编译结果，首次发送时编译后复用
+    module: OnceLock<std::result::Result<Module, String>>,
+}
+
+struct HostState {
+    name: String,
+    client: HttpClient,
+}
+
+/// 所有插件共用的引擎，后台线程推进epoch，超过截止时间的调用会被中断
+fn engine() -> Result<&'static Engine> {
+    static ENGINE: OnceLock<std::result::Result<Engine, String>> = OnceLock::new();
+    ENGINE
+        .get_or_init(|| {
+            let mut config = wasmtime::Config::new();
+            config.epoch_interruption(true);
+            let engine = Engine::new(&config).map_err(|e| format!("{:#}", e))?;
+            let ticker = engine.clone();
+            std::thread::spawn(move || loop {
+                std::thread::sleep(EPOCH_TICK);
+                ticker.increment_epoch();
+            });
+            Ok(engine)
+        })
+        .as_ref()
+        .map_err(|e| NotifierError::ConfigError(format!("Cannot start the WASM engine: {}", e)))
+}
+
+impl WasmNotifier {
+    pub fn new(name: String, path: PathBuf) -> Self {
+        Self {
+            name,
+            path,
+            client: HttpClient::default(),
+            module: OnceLock::new(),
+        }
+    }
+
+    /// 插件 `http_post` 使用的HTTP客户端（代理、超时、重试等设置）
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        self.client = client;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 首次路由到插件时才编译，避免拖慢每次启动；插件缺失或无效属于配置错误
+    fn module(&self) -> Result<Module> {
+        let engine = engine()?;
+        self.module
+            .get_or_init(|| Module::from_file(engine, &self.path).map_err(|e| format!("{:#}", e)))
+            .clone()
+            .map_err(|e| {
+                NotifierError::ConfigError(format!(
+                    "Cannot load plugin {} ({}): {}",
+                    self.name,
+                    self.path.display(),
+                    e
+                ))
+            })
+    }
+
+    /// wasmtime为同步接口，放到阻塞线程池中执行
+    async fn call(&self, payload: Value) -> Result<Value> {
+        let module = self.module()?;
+        let state = HostState {
+            name: self.name.clone(),
+            client: self.client.clone(),
+        };
+        let code =
+            tokio::task::spawn_blocking(move || Self::call_blocking(&module, state, payload))
+                .await
+                .map_err(|e| NotifierError::ChannelUnavailable(e.to_string()))?
+                .map_err(|e| match e.downcast_ref::<Trap>() {
+                    Some(Trap::Interrupt) => NotifierError::Timeout,
+                    _ => {
+                        NotifierError::ChannelUnavailable(format!("Plugin {}: {:#}", self.name, e))
+                    }
+                })?;
+
+        if code == 0 {
+            Ok(json!({"plugin": self.name, "code": code}))
+        } else {
+            Err(NotifierError::ProviderRejected {
+                code: i64::from(code),
+                msg: format!("Plugin {} returned error code {}", self.name, code),
+            })
+        }
+    }
+
+    /// 返回插件 `send_card` 的返回码；超过 [`CALL_TIMEOUT`] 时以 `Trap::Interrupt` 中断
+    fn call_blocking(module: &Module, state: HostState, payload: Value) -> anyhow::Result<i32> {
+        let engine = module.engine();
+        let mut linker: Linker<HostState> = Linker::new(engine);
+        linker.func_wrap(
+            "env",
+            "http_post",
+            |mut caller: Caller<'_, HostState>,
+             url_ptr: i32,
+             url_len: i32,
+             body_ptr: i32,
+             body_len: i32|
+             -> i32 {
+                let url = read_string(&mut caller, url_ptr, url_len);
+                let body = read_string(&mut caller, body_ptr, body_len);
+                let (Some(url), Some(body)) = (url, body) else {
+                    return -1;
+                };
+                let Ok(data) = serde_json::from_str::<Value>(&body) else {
+                    return -1;
+                };
+                let client = caller.data().client.clone();
+                let handle = tokio::runtime::Handle::current();
+                match handle.block_on(send_request(&client, &url, data, accept_any)) {
+                    Ok(_) => 0,
+                    Err(_) => -1,
+                }
+            },
+        )?;
+        linker.func_wrap(
+            "env",
+            "log",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+                if let Some(message) = read_string(&mut caller, ptr, len) {
+                    tracing::info!(plugin = %caller.data().name, "{}", message);
+                }
+            },
+        )?;
+
+        let name = state.name.clone();
+        let mut store = Store::new(engine, state);
+        let ticks = CALL_TIMEOUT.as_millis() / EPOCH_TICK.as_millis();
+        store.set_epoch_deadline(ticks as u64);
+        let instance = linker.instantiate(&mut store, module)?;
+
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| anyhow::anyhow!("Plugin {} does not export memory", name))?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+        let send_card = instance.get_typed_func::<(i32, i32), i32>(&mut store, "send_card")?;
+
+        let input = serde_json::to_vec(&payload)?;
+        let len = i32::try_from(input.len())?;
+        let ptr = alloc.call(&mut store, len)?;
+        memory.write(&mut store, ptr as usize, &input)?;
+
+        send_card.call(&mut store, (ptr, len))
+    }
+}
+
+fn read_string(caller: &mut Caller<'_, HostState>, ptr: i32, len: i32) -> Option<String> {
+    let memory = caller.get_export("memory")?.into_memory()?;
+    let data = memory.data(&caller);
+    let start = usize::try_from(ptr).ok()?;
+    let end = start.checked_add(usize::try_from(len).ok()?)?;
+    let bytes = data.get(start..end)?;
+    String::from_utf8(bytes.to_vec()).ok()
+}
+
+/// 扫描插件目录，按文件名（不含扩展名）作为渠道名
+pub fn discover(dir: &Path) -> Vec<WasmNotifier> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut plugins: Vec<WasmNotifier> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "wasm"))
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_str()?.to_string();
+            Some(WasmNotifier::new(name, path))
+        })
+        .collect();
+    plugins.sort_by(|a, b| a.name.cmp(&b.name));
+    plugins
+}
+
+#[async_trait]
+impl Notifier for WasmNotifier {
+    async fn send_text(&self, text: &str) -> Result<Value> {
+        self.call(text_payload(text)).await
+    }
+
+    async fn send_card(
+        &self,
+        title: &str,
+        content: &str,
+        color: &str,
+        actions: Vec<Action>,
+    ) -> Result<Value> {
+        self.call(card_payload(title, content, color, actions))
+            .await
+    }
+}
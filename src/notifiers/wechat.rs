@@ -1,7 +1,15 @@
-use super::{send_request, Action, Notifier};
-use anyhow::Result;
+use super::{send_request, Action, Notifier, ResponseCheck};
+use crate::error::{NotifierError, Result};
+use crate::http::HttpClient;
+use crate::i18n;
+use async_trait::async_trait;
 use serde_json::{json, Value};
 
+/// Server酱 `desp` 上限32KB
+const SERVERCHAN_MAX_CONTENT_BYTES: usize = 30 * 1024;
+/// PushPlus内容上限约20KB
+const PUSHPLUS_MAX_CONTENT_BYTES: usize = 18 * 1024;
+
 /// 微信推送通知器 - 支持Server酱和PushPlus
 pub enum WechatService {
     ServerChan { key: String },
@@ -10,82 +18,140 @@ pub enum WechatService {
 
 pub struct WechatNotifier {
     service: WechatService,
+    client: HttpClient,
 }
 
 impl WechatNotifier {
+    fn card_url(&self) -> String {
+        match &self.service {
+            WechatService::ServerChan { key } => format!("https://sctapi.ftqq.com/{}.send", key),
+            WechatService::PushPlus { .. } => "http://www.pushplus.plus/send".to_string(),
+        }
+    }
+
+    fn check(&self) -> ResponseCheck {
+        match &self.service {
+            WechatService::ServerChan { .. } => check_serverchan,
+            WechatService::PushPlus { .. } => check_pushplus,
+        }
+    }
+
     pub fn new_serverchan(key: String) -> Self {
         Self {
             service: WechatService::ServerChan { key },
+            client: HttpClient::default(),
         }
     }
 
     pub fn new_pushplus(token: String) -> Self {
         Self {
             service: WechatService::PushPlus { token },
+            client: HttpClient::default(),
         }
     }
+
+    /// 使用指定的HTTP客户端（代理、超时、重试等设置）
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        self.client = client;
+        self
+    }
+}
+
+/// Server酱成功时 `code` 为0，错误信息在 `message`
+fn check_serverchan(response: Value) -> Result<Value> {
+    check_code(response, 0, "message")
+}
+
+/// PushPlus成功时 `code` 为200，错误信息在 `msg`
+fn check_pushplus(response: Value) -> Result<Value> {
+    check_code(response, 200, "msg")
+}
+
+fn check_code(response: Value, ok: i64, msg_field: &str) -> Result<Value> {
+    match response["code"].as_i64() {
+        Some(code) if code != ok => Err(NotifierError::ProviderRejected {
+            code,
+            msg: response[msg_field].as_str().unwrap_or_default().to_string(),
+        }),
+        _ => Ok(response),
+    }
 }
 
+#[async_trait]
 impl Notifier for WechatNotifier {
-    fn send_text(&self, text: &str) -> Result<Value> {
+    async fn send_text(&self, text: &str) -> Result<Value> {
         match &self.service {
             WechatService::ServerChan { key } => {
                 let url = format!("https://sctapi.ftqq.com/{}.send", key);
                 let data = json!({
-                    "title": "通知",
+                    "title": i18n::strings().default_title,
                     "desp": text
                 });
-                send_request(&url, data)
+                send_request(&self.client, &url, data, check_serverchan).await
             }
             WechatService::PushPlus { token } => {
                 let url = "http://www.pushplus.plus/send";
                 let data = json!({
                     "token": token,
-                    "title": "通知",
+                    "title": i18n::strings().default_title,
                     "content": text,
                     "template": "txt"
                 });
-                send_request(url, data)
+                send_request(&self.client, url, data, check_pushplus).await
             }
         }
     }
 
-    fn send_card(
+    async fn send_card(
         &self,
         title: &str,
         content: &str,
-        _color: &str,
+        color: &str,
         actions: Vec<Action>,
     ) -> Result<Value> {
+        let data = self.card_request(title, content, color, actions);
+        send_request(&self.client, &self.card_url(), data, self.check()).await
+    }
+
+    fn card_request(
+        &self,
+        title: &str,
+        content: &str,
+        _color: &str,
+        actions: Vec<Action>,
+    ) -> Value {
         let mut formatted_content = content.to_string();
 
-        // 添加操作链接
-        if !actions.is_empty() {
+        // 添加操作链接；回调按钮无法在推送消息中使用
+        let links: Vec<Action> = actions
+            .into_iter()
+            .filter(|action| action.callback.is_none())
+            .collect();
+        if !links.is_empty() {
             formatted_content.push_str("\n\n---\n");
-            for action in actions {
+            for action in links {
                 formatted_content.push_str(&format!("[{}]({})\n", action.text, action.url));
             }
         }
 
         match &self.service {
-            WechatService::ServerChan { key } => {
-                let url = format!("https://sctapi.ftqq.com/{}.send", key);
-                let data = json!({
-                    "title": title,
-                    "desp": formatted_content
-                });
-                send_request(&url, data)
-            }
-            WechatService::PushPlus { token } => {
-                let url = "http://www.pushplus.plus/send";
-                let data = json!({
-                    "token": token,
-                    "title": title,
-                    "content": formatted_content,
-                    "template": "markdown"
-                });
-                send_request(url, data)
-            }
+            WechatService::ServerChan { .. } => json!({
+                "title": title,
+                "desp": formatted_content
+            }),
+            WechatService::PushPlus { token } => json!({
+                "token": token,
+                "title": title,
+                "content": formatted_content,
+                "template": "markdown"
+            }),
         }
     }
+
+    fn max_content_bytes(&self) -> Option<usize> {
+        Some(match self.service {
+            WechatService::ServerChan { .. } => SERVERCHAN_MAX_CONTENT_BYTES,
+            WechatService::PushPlus { .. } => PUSHPLUS_MAX_CONTENT_BYTES,
+        })
+    }
 }
@@ -0,0 +1,171 @@
+use crate::i18n;
+use crate::notification::Notification;
+use crate::store;
+use anyhow::Result;
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+
+const STATE_FILE: &str = "offline_queue.json";
+
+/// 离线队列 - 网络不可达（DNS、连接失败）时先保存通知，恢复后按顺序补发
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OfflineConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// 最多保存的通知条数，超出时丢弃最早的
+    #[serde(default = "default_max_queued")]
+    pub max_queued: usize,
+    /// 超过多少小时仍未补发的通知丢弃
+    #[serde(default = "default_max_age_hours")]
+    pub max_age_hours: i64,
+}
+
+impl Default for OfflineConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            max_queued: default_max_queued(),
+            max_age_hours: default_max_age_hours(),
+        }
+    }
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_max_queued() -> usize {
+    200
+}
+
+fn default_max_age_hours() -> i64 {
+    24
+}
+
+/// 等待网络恢复后补发的通知
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QueuedNotification {
+    pub id: String,
+    pub notification: Notification,
+    /// 未能送达的渠道项，备用链保留为 `a -> b` 的形式
+    pub channels: Vec<String>,
+    /// 最近一次发送的历史记录ID，补发记录为它的重发
+    pub history_id: String,
+    pub queued_at: i64,
+}
+
+impl QueuedNotification {
+    /// 补发的通知，正文末尾注明原始时间
+    pub fn notification(&self) -> Notification {
+        let time = Local
+            .timestamp_opt(self.queued_at, 0)
+            .single()
+            .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+        let note = i18n::fill(i18n::strings().queued_offline, &[("time", &time)]);
+        self.notification.with_note(&note)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct OfflineQueue {
+    pending: VecDeque<QueuedNotification>,
+}
+
+impl OfflineQueue {
+    pub fn load() -> Result<Self> {
+        store::load(STATE_FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        store::save(STATE_FILE, self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// 加入队尾，超出上限时返回丢弃的条数
+    pub fn push(&mut self, queued: QueuedNotification, config: &OfflineConfig) -> usize {
+        self.pending.push_back(queued);
+        let excess = self.pending.len().saturating_sub(config.max_queued.max(1));
+        self.pending.drain(..excess);
+        excess
+    }
+
+    /// 丢弃超过保存时长的通知，返回丢弃的条数
+    pub fn expire(&mut self, now: i64, config: &OfflineConfig) -> usize {
+        let before = self.pending.len();
+        self.pending
+            .retain(|q| now - q.queued_at < config.max_age_hours * 3600);
+        before - self.pending.len()
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut QueuedNotification> {
+        self.pending.front_mut()
+    }
+
+    pub fn pop_front(&mut self) -> Option<QueuedNotification> {
+        self.pending.pop_front()
+    }
+}
+
+/// 渠道结果是否因网络不可达而失败：单个渠道的错误或备用链中每个尝试过的渠道都是 `unreachable`
+pub fn is_unreachable(result: &Value) -> bool {
+    if result["success"] != false {
+        return false;
+    }
+    let kinds: Vec<&Value> = match result["hops"].as_array() {
+        Some(hops) => hops
+            .iter()
+            .map(|hop| &hop["error_kind"])
+            .filter(|kind| !kind.is_null())
+            .collect(),
+        None => vec![&result["error_kind"]],
+    };
+    !kinds.is_empty() && kinds.iter().all(|kind| *kind == "unreachable")
+}
+
+/// 结果对应的渠道项：备用链按尝试过的渠道还原
+pub fn channel_spec(primary: &str, result: &Value) -> String {
+    match result["hops"].as_array() {
+        Some(hops) => {
+            let chain: Vec<&str> = hops
+                .iter()
+                .filter_map(|hop| hop["channel"].as_str())
+                .collect();
+            if chain.is_empty() {
+                primary.to_string()
+            } else {
+                chain.join(" -> ")
+            }
+        }
+        None => primary.to_string(),
+    }
+}
+
+/// 把网络不可达的渠道结果改为 `queued`，返回这些渠道项（已排序）
+pub fn mark_queued(queue_id: &str, results: &mut HashMap<String, Value>) -> Vec<String> {
+    let mut channels = Vec::new();
+    for (primary, result) in results.iter_mut() {
+        if !is_unreachable(result) {
+            continue;
+        }
+        channels.push(channel_spec(primary, result));
+        // 备用链的错误在最后一跳中
+        let error = result["error"]
+            .as_str()
+            .or_else(|| result["hops"].as_array()?.last()?["error"].as_str())
+            .unwrap_or_default()
+            .to_string();
+        *result = json!({"status": "queued", "queue_id": queue_id, "error": error});
+    }
+    channels.sort();
+    channels
+}
@@ -0,0 +1,56 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// 默认每周轮换一次
+const DEFAULT_ROTATION_DAYS: u32 = 7;
+
+/// 渠道的值班人员，critical通知时@他们，代替@所有人
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct OnCall {
+    /// 总是@的用户，对应配置中 `users` 的键
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub users: Vec<String>,
+    /// 轮值列表，从 `rotation_start` 起每 `rotation_days` 天换下一人
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rotation: Vec<String>,
+    /// 轮值开始日期（`YYYY-MM-DD`），当天由列表中第一人值班
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotation_start: Option<String>,
+    /// 每人值班天数，默认7
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotation_days: Option<u32>,
+}
+
+impl OnCall {
+    pub fn is_empty(&self) -> bool {
+        self.users.is_empty() && self.rotation.is_empty()
+    }
+
+    /// 指定日期需要@的用户：固定用户加上当前轮值人
+    pub fn current(&self, today: NaiveDate) -> Vec<String> {
+        let mut users = self.users.clone();
+        if let Some(user) = self.on_rotation(today) {
+            if !users.contains(user) {
+                users.push(user.clone());
+            }
+        }
+        users
+    }
+
+    /// 当前轮值人；开始日期缺失或无效时为列表中第一人
+    pub fn on_rotation(&self, today: NaiveDate) -> Option<&String> {
+        if self.rotation.is_empty() {
+            return None;
+        }
+        let start = self
+            .rotation_start
+            .as_deref()
+            .and_then(|s| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok())
+            .unwrap_or(today);
+        let period = i64::from(self.rotation_days.unwrap_or(DEFAULT_ROTATION_DAYS).max(1));
+        // 开始日期之前也按同样的周期倒推
+        let shift = (today - start).num_days().div_euclid(period);
+        let index = shift.rem_euclid(self.rotation.len() as i64) as usize;
+        self.rotation.get(index)
+    }
+}
@@ -0,0 +1,166 @@
+use crate::claude;
+use crate::config::Config;
+use crate::doctor;
+use crate::env;
+use crate::error::NotifierError;
+use crate::escalation::EscalationPolicy;
+use crate::notification::Level;
+use crate::routing::RoutingRule;
+use crate::validate::BUILTIN_CHANNELS;
+use anyhow::Result;
+use serde_json::json;
+use std::str::FromStr;
+
+/// `init --preset` 的路由模板
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// 构建、测试、部署结果，全天发送
+    Ci,
+    /// 本机的Claude Code会话，夜间静默
+    Personal,
+    /// 告警经备用链送达，未确认的critical通知升级
+    Oncall,
+}
+
+impl FromStr for Preset {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "ci" => Ok(Preset::Ci),
+            "personal" => Ok(Preset::Personal),
+            "oncall" | "on-call" => Ok(Preset::Oncall),
+            _ => Err(anyhow::anyhow!(
+                "Unknown preset '{}' (expected one of: ci, personal, oncall)",
+                s
+            )),
+        }
+    }
+}
+
+impl Preset {
+    fn events(&self) -> &'static [&'static str] {
+        match self {
+            Preset::Ci => &[
+                "build_success",
+                "build_failure",
+                "test_failure",
+                "deploy_start",
+                "deploy_success",
+                "deploy_failure",
+                "security_alert",
+            ],
+            Preset::Personal => &[
+                "stop",
+                "notification",
+                claude::PERMISSION_REQUEST,
+                claude::AWAITING_INPUT,
+                claude::AGENT_ERROR,
+                "task_completed",
+            ],
+            Preset::Oncall => &[
+                "alert",
+                "build_failure",
+                "deploy_failure",
+                "security_alert",
+                claude::AGENT_ERROR,
+            ],
+        }
+    }
+
+    /// 用预设替换 `config` 的路由、静默时段和升级策略，发往所有已启用的渠道
+    /// （没有时发往内置渠道，配置渠道后即生效）
+    pub fn apply(&self, config: &mut Config) {
+        let channels = targets(config);
+        // 备用链：前一个渠道失败时才发往下一个
+        let chain = vec![channels.join(" -> ")];
+        let routes = match self {
+            Preset::Oncall => chain.clone(),
+            _ => channels.clone(),
+        };
+        config.notifications = self
+            .events()
+            .iter()
+            .map(|event| (event.to_string(), routes.clone()))
+            .collect();
+        config.rules.clear();
+        config.escalations.clear();
+
+        match self {
+            Preset::Ci => {
+                // 流水线随时在跑，结果不因夜间而延后
+                config.quiet_hours.enabled = false;
+            }
+            Preset::Personal => {
+                config.quiet_hours.enabled = true;
+                config.quiet_hours.start = "22:00".to_string();
+                config.quiet_hours.end = "08:00".to_string();
+            }
+            Preset::Oncall => {
+                // critical本身不受静默时段限制
+                config.quiet_hours.enabled = true;
+                config.rules.push(RoutingRule {
+                    min_level: Some(Level::Critical),
+                    channels: chain,
+                    ..Default::default()
+                });
+                config.escalations.push(EscalationPolicy {
+                    event: None,
+                    min_level: Level::Critical,
+                    after_minutes: 5,
+                    channels: channels.clone(),
+                    repeat: 1,
+                    then: Vec::new(),
+                    ack_reactions: Vec::new(),
+                });
+            }
+        }
+    }
+}
+
+fn targets(config: &Config) -> Vec<String> {
+    let enabled = doctor::enabled_channels(config);
+    if enabled.is_empty() {
+        BUILTIN_CHANNELS.iter().map(|c| c.to_string()).collect()
+    } else {
+        enabled
+    }
+}
+
+/// 默认事件只发往 `channels`；原本的渠道都不在其中时发往所有 `channels`
+pub fn route_to(config: &mut Config, channels: &[String]) {
+    for targets in config.notifications.values_mut() {
+        targets.retain(|target| channels.contains(target));
+        if targets.is_empty() {
+            *targets = channels.to_vec();
+        }
+    }
+}
+
+/// 把 `CLAUDE_NOTIFIER_*` 变量（例如 `CLAUDE_NOTIFIER_FEISHU_WEBHOOK`）写入配置，
+/// 和运行时的环境变量覆盖规则相同；变量中没有任何渠道时报错
+pub fn from_env(
+    config: &Config,
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> Result<Config> {
+    let mut value = serde_json::to_value(config)?;
+    env::apply_overrides(&mut value, vars);
+    // 只给出key时默认为Server酱
+    if let Some(wechat) = value["channels"]["wechat"].as_object_mut() {
+        wechat.entry("service").or_insert(json!("serverchan"));
+    }
+    let config = Config::from_value(value, |_| {}).map_err(|e| {
+        let path = e.path().to_string();
+        NotifierError::ConfigError(format!("{}: {}", path, e.into_inner()))
+    })?;
+    if doctor::enabled_channels(&config).is_empty() {
+        return Err(NotifierError::ConfigError(format!(
+            "No channel is configured by the environment (set e.g. {}FEISHU_WEBHOOK, {}TEAMS_WEBHOOK or {}WECHAT_KEY)",
+            env::OVERRIDE_PREFIX,
+            env::OVERRIDE_PREFIX,
+            env::OVERRIDE_PREFIX
+        ))
+        .into());
+    }
+    Ok(config)
+}
@@ -0,0 +1,75 @@
+use crate::routing::glob_match;
+use crate::transcript::Usage;
+use serde::{Deserialize, Serialize};
+
+/// 模型单价（美元/百万token）
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ModelPrice {
+    /// 模型名通配符，例如 `claude-sonnet-4*`
+    pub model: String,
+    pub input: f64,
+    pub output: f64,
+    /// 写入缓存，默认为输入单价的1.25倍
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_write: Option<f64>,
+    /// 读取缓存，默认为输入单价的0.1倍
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read: Option<f64>,
+}
+
+impl ModelPrice {
+    fn builtin(model: &str, input: f64, output: f64) -> Self {
+        Self {
+            model: model.to_string(),
+            input,
+            output,
+            cache_write: None,
+            cache_read: None,
+        }
+    }
+
+    /// 按用量估算的费用（美元）
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        (usage.input_tokens as f64 * self.input
+            + usage.output_tokens as f64 * self.output
+            + usage.cache_creation_input_tokens as f64 * self.cache_write()
+            + usage.cache_read_input_tokens as f64 * self.cache_read())
+            / 1_000_000.0
+    }
+
+    pub fn cache_write(&self) -> f64 {
+        self.cache_write.unwrap_or(self.input * 1.25)
+    }
+
+    pub fn cache_read(&self) -> f64 {
+        self.cache_read.unwrap_or(self.input * 0.1)
+    }
+}
+
+/// 内置价目表，按顺序匹配，较具体的模式在前
+fn builtin() -> Vec<ModelPrice> {
+    vec![
+        ModelPrice::builtin("claude-opus-4-5*", 5.0, 25.0),
+        ModelPrice::builtin("claude-opus-4*", 15.0, 75.0),
+        ModelPrice::builtin("claude-3-opus*", 15.0, 75.0),
+        ModelPrice::builtin("claude-sonnet-4*", 3.0, 15.0),
+        ModelPrice::builtin("claude-3-7-sonnet*", 3.0, 15.0),
+        ModelPrice::builtin("claude-3-5-sonnet*", 3.0, 15.0),
+        ModelPrice::builtin("claude-haiku-4*", 1.0, 5.0),
+        ModelPrice::builtin("claude-3-5-haiku*", 0.8, 4.0),
+        ModelPrice::builtin("claude-3-haiku*", 0.25, 1.25),
+    ]
+}
+
+/// 模型的单价：先查配置的 `pricing`，再查内置价目表
+pub fn price(configured: &[ModelPrice], model: &str) -> Option<ModelPrice> {
+    configured
+        .iter()
+        .find(|price| glob_match(&price.model, model))
+        .cloned()
+        .or_else(|| {
+            builtin()
+                .into_iter()
+                .find(|price| glob_match(&price.model, model))
+        })
+}
@@ -0,0 +1,135 @@
+use crate::config::{Config, WechatServiceType};
+use crate::http::{HttpConfig, DIRECT};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// 最近一次探测的时间和结果
+static LAST: Mutex<Option<(Instant, bool)>> = Mutex::new(None);
+
+/// 联网探测 - 发送前先试连，离线时远程渠道立即按不可达处理，不必逐个等待超时
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProbeConfig {
+    /// 探测的 `host:port`，为空时使用各远程渠道webhook（或所配代理）的地址
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<String>,
+    /// 探测超时（毫秒），任一地址在此时间内连上即为在线
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// 探测结果的缓存时间（秒），守护进程和批量发送时不必每条都探测
+    #[serde(default = "default_cache_secs")]
+    pub cache_secs: u64,
+    /// 离线时额外发送的本地渠道，例如弹出桌面通知或写入文件的exec渠道
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback: Vec<String>,
+}
+
+fn default_timeout_ms() -> u64 {
+    500
+}
+
+fn default_cache_secs() -> u64 {
+    30
+}
+
+/// 未配置 `targets` 时探测的地址：已启用的远程渠道的主机，配置了代理时为代理
+pub fn targets(config: &Config) -> Vec<String> {
+    if let Some(probe) = &config.probe {
+        if !probe.targets.is_empty() {
+            return probe.targets.clone();
+        }
+    }
+
+    let channels = &config.channels;
+    let mut endpoints: Vec<(String, HttpConfig)> = Vec::new();
+    if let Some(teams) = channels.teams.as_ref().filter(|c| c.enabled) {
+        endpoints.push((teams.webhook.clone(), teams.http.or(&config.http)));
+    }
+    if let Some(feishu) = channels.feishu.as_ref().filter(|c| c.enabled) {
+        endpoints.push((feishu.webhook.clone(), feishu.http.or(&config.http)));
+    }
+    if let Some(wechat) = channels.wechat.as_ref().filter(|c| c.enabled) {
+        let url = match wechat.service {
+            WechatServiceType::ServerChan => "https://sctapi.ftqq.com",
+            WechatServiceType::PushPlus => "https://www.pushplus.plus",
+        };
+        endpoints.push((url.to_string(), wechat.http.or(&config.http)));
+    }
+
+    let mut targets: Vec<String> = Vec::new();
+    for (url, http) in endpoints {
+        let url = match http.proxy.as_deref() {
+            Some(proxy) if proxy != DIRECT => proxy.to_string(),
+            _ => url,
+        };
+        if let Some(target) = address(&url) {
+            if !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
+    }
+    targets
+}
+
+/// URL的 `host:port`
+fn address(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    Some(format!(
+        "{}:{}",
+        url.host_str()?,
+        url.port_or_known_default()?
+    ))
+}
+
+/// 是否在线：任一地址能在超时内建立TCP连接；没有可探测的地址时视为在线
+pub fn is_online(config: &ProbeConfig, targets: &[String]) -> bool {
+    if targets.is_empty() {
+        return true;
+    }
+    let mut last = LAST.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((at, online)) = *last {
+        if at.elapsed() < Duration::from_secs(config.cache_secs) {
+            return online;
+        }
+    }
+
+    let timeout = Duration::from_millis(config.timeout_ms.max(1));
+    let started = Instant::now();
+    let (sender, receiver) = mpsc::channel();
+    // DNS解析没有超时设置，放在线程中，超时后不再等待
+    for target in targets {
+        let (sender, target) = (sender.clone(), target.clone());
+        thread::spawn(move || {
+            let connected = target.to_socket_addrs().is_ok_and(|addrs| {
+                addrs
+                    .into_iter()
+                    .any(|addr| TcpStream::connect_timeout(&addr, timeout).is_ok())
+            });
+            let _ = sender.send(connected);
+        });
+    }
+    drop(sender);
+
+    let mut online = false;
+    while let Some(remaining) = timeout.checked_sub(started.elapsed()) {
+        match receiver.recv_timeout(remaining) {
+            Ok(true) => {
+                online = true;
+                break;
+            }
+            Ok(false) => continue,
+            Err(_) => break,
+        }
+    }
+    debug!(
+        online,
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "connectivity probe"
+    );
+    *last = Some((Instant::now(), online));
+    online
+}
@@ -0,0 +1,134 @@
+use crate::i18n;
+use crate::routing::glob_match;
+use crate::store;
+use anyhow::Result;
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+const STATE_FILE: &str = "progress.json";
+
+/// 进度模式：同一会话中反复出现的完成事件合并为一条消息，之后每次就地更新
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProgressConfig {
+    /// 合并的事件名通配符
+    #[serde(default = "default_events")]
+    pub events: Vec<String>,
+    /// 消息中最多列出的完成项，更早的只计数
+    #[serde(default = "default_max_items")]
+    pub max_items: usize,
+    /// 会话空闲超过多少分钟后另起一条消息
+    #[serde(default = "default_idle_minutes")]
+    pub idle_minutes: i64,
+}
+
+fn default_events() -> Vec<String> {
+    vec!["subagent_stop".to_string(), "task_completed".to_string()]
+}
+
+fn default_max_items() -> usize {
+    10
+}
+
+fn default_idle_minutes() -> i64 {
+    60
+}
+
+impl ProgressConfig {
+    pub fn matches(&self, event: &str) -> bool {
+        self.events.iter().any(|pattern| glob_match(pattern, event))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct ProgressItem {
+    at: i64,
+    text: String,
+}
+
+/// 一条进度消息
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProgressEntry {
+    /// 各渠道可编辑的消息ID
+    pub message_ids: BTreeMap<String, String>,
+    items: Vec<ProgressItem>,
+    last_at: i64,
+}
+
+impl ProgressEntry {
+    pub fn count(&self) -> usize {
+        self.items.len()
+    }
+
+    /// 按完成时间列出最近的完成项
+    pub fn content(&self, max_items: usize) -> String {
+        let skipped = self.items.len().saturating_sub(max_items);
+        let mut lines = Vec::new();
+        if skipped > 0 {
+            lines.push(i18n::fill(
+                i18n::strings().progress_earlier,
+                &[("count", &skipped)],
+            ));
+        }
+        for item in &self.items[skipped..] {
+            let time = Local
+                .timestamp_opt(item.at, 0)
+                .single()
+                .map(|t| t.format("%H:%M").to_string())
+                .unwrap_or_default();
+            lines.push(format!("- {} {}", time, item.text));
+        }
+        lines.join("\n")
+    }
+}
+
+/// 各会话进度消息的记录，跨进程持久化
+pub struct ProgressStore {
+    idle_secs: i64,
+    entries: HashMap<String, ProgressEntry>,
+}
+
+impl ProgressStore {
+    pub fn load(config: &ProgressConfig) -> Result<Self> {
+        Ok(Self {
+            idle_secs: config.idle_minutes * 60,
+            entries: store::load(STATE_FILE)?,
+        })
+    }
+
+    /// 未过期的进度消息
+    pub fn get(&self, key: &str) -> Option<&ProgressEntry> {
+        let now = Local::now().timestamp();
+        self.entries
+            .get(key)
+            .filter(|entry| now - entry.last_at < self.idle_secs)
+    }
+
+    /// 追加一个完成项；没有未过期的消息时新建记录，`message_ids` 为空表示尚未发送
+    pub fn push(&mut self, key: &str, text: String) -> &mut ProgressEntry {
+        let now = Local::now().timestamp();
+        if self.get(key).is_none() {
+            self.entries.insert(
+                key.to_string(),
+                ProgressEntry {
+                    message_ids: BTreeMap::new(),
+                    items: Vec::new(),
+                    last_at: now,
+                },
+            );
+        }
+        let entry = self.entries.get_mut(key).expect("entry was just inserted");
+        entry.items.push(ProgressItem { at: now, text });
+        entry.last_at = now;
+        entry
+    }
+
+    /// 保存时丢弃已过期的记录
+    pub fn save(&mut self) -> Result<()> {
+        let now = Local::now().timestamp();
+        let idle_secs = self.idle_secs;
+        self.entries
+            .retain(|_, entry| now - entry.last_at < idle_secs);
+        store::save(STATE_FILE, &self.entries)
+    }
+}
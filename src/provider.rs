@@ -0,0 +1,182 @@
+use crate::config::{Config, WechatServiceType};
+use crate::error::NotifierError;
+use reqwest::Url;
+use tracing::debug;
+
+/// 飞书（Lark）自定义机器人webhook的路径前缀
+const FEISHU_HOOK_PATH: &str = "/open-apis/bot/v2/hook/";
+
+/// 飞书开放平台的主机
+const FEISHU_HOSTS: [&str; 2] = ["open.feishu.cn", "open.larksuite.com"];
+
+/// 各服务商的主机后缀及对应的渠道，webhook填错渠道时给出提示
+const PROVIDER_HOSTS: [(&str, &str, &str); 7] = [
+    ("feishu.cn", "Feishu", "feishu"),
+    ("larksuite.com", "Lark", "feishu"),
+    ("webhook.office.com", "Teams", "teams"),
+    ("logic.azure.com", "Teams Workflows", "teams"),
+    ("api.powerplatform.com", "Teams Workflows", "teams"),
+    ("ftqq.com", "ServerChan", "wechat"),
+    ("qyapi.weixin.qq.com", "WeCom", ""),
+];
+
+/// 格式检查的结论：`Error` 时不可能发送成功，`Warning` 时少见但可能可用（例如经中转服务转发）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Finding {
+    Error(String),
+    Warning(String),
+}
+
+/// 解析 `channel` 渠道的http(s)地址，`provider` 为提示中的服务商名
+fn parse(url: &str, channel: &str, provider: &str) -> Result<Url, Finding> {
+    let parsed = match Url::parse(url.trim()) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") && parsed.has_host() => parsed,
+        Ok(_) => return Err(Finding::Error("Webhook must be an http(s) URL".to_string())),
+        Err(e) => return Err(Finding::Error(format!("Malformed webhook URL: {}", e))),
+    };
+    let host = parsed.host_str().unwrap_or_default();
+    if let Some((_, other, _)) = PROVIDER_HOSTS
+        .iter()
+        .find(|(suffix, _, owner)| is_host(host, suffix) && *owner != channel)
+    {
+        return Err(Finding::Error(format!(
+            "This is a {} URL, not a {} webhook",
+            other, provider
+        )));
+    }
+    Ok(parsed)
+}
+
+/// `host` 为 `suffix` 或其子域名
+fn is_host(host: &str, suffix: &str) -> bool {
+    host == suffix || host.ends_with(&format!(".{}", suffix))
+}
+
+/// Teams：Incoming Webhook（`*.webhook.office.com/webhookb2/...`）或Workflows（带 `sig` 签名参数）
+pub fn teams_webhook(url: &str) -> Option<Finding> {
+    let url = match parse(url, "teams", "Teams") {
+        Ok(url) => url,
+        Err(finding) => return Some(finding),
+    };
+    let host = url.host_str().unwrap_or_default();
+    if is_host(host, "webhook.office.com")
+        || host == "outlook.office.com"
+        || host == "outlook.office365.com"
+    {
+        if !url.path().contains("/webhook") {
+            return Some(Finding::Error(
+                "Incoming webhook URLs contain /webhookb2/ in the path; copy the whole URL from the connector"
+                    .to_string(),
+            ));
+        }
+    } else if is_host(host, "logic.azure.com") || is_host(host, "api.powerplatform.com") {
+        if !url.query_pairs().any(|(name, _)| name == "sig") {
+            return Some(Finding::Error(
+                "Workflows URL is missing the sig parameter; copy the whole URL from the workflow trigger"
+                    .to_string(),
+            ));
+        }
+    } else {
+        return Some(Finding::Warning(format!(
+            "{} is not a Microsoft Teams host (expected *.webhook.office.com or a Workflows URL); fine for a relay",
+            host
+        )));
+    }
+    None
+}
+
+/// 飞书：`https://open.feishu.cn/open-apis/bot/v2/hook/<token>`
+pub fn feishu_webhook(url: &str) -> Option<Finding> {
+    let url = match parse(url, "feishu", "Feishu") {
+        Ok(url) => url,
+        Err(finding) => return Some(finding),
+    };
+    let token = url
+        .path()
+        .strip_prefix(FEISHU_HOOK_PATH)
+        .unwrap_or_default();
+    if token.is_empty() || token.contains('/') {
+        return Some(Finding::Error(format!(
+            "Feishu bot webhooks look like https://{}{}<token>; copy it from the bot settings",
+            FEISHU_HOSTS[0], FEISHU_HOOK_PATH
+        )));
+    }
+    let host = url.host_str().unwrap_or_default();
+    if !FEISHU_HOSTS.contains(&host) {
+        return Some(Finding::Warning(format!(
+            "{} is not a Feishu host (expected {}); fine for a relay",
+            host,
+            FEISHU_HOSTS.join(" or ")
+        )));
+    }
+    None
+}
+
+/// Server酱的SendKey（`SCT` 开头）或PushPlus的token（32位字母数字）
+pub fn wechat_key(service: &WechatServiceType, key: &str) -> Option<Finding> {
+    if key.contains("://") {
+        return Some(Finding::Error(
+            "Expected the key itself, not a URL".to_string(),
+        ));
+    }
+    let alphanumeric = key.chars().all(|c| c.is_ascii_alphanumeric());
+    match service {
+        WechatServiceType::ServerChan if !(key.starts_with("SCT") && alphanumeric) => {
+            Some(Finding::Error(
+                "ServerChan SendKeys start with SCT and contain only letters and digits (see sct.ftqq.com)"
+                    .to_string(),
+            ))
+        }
+        WechatServiceType::PushPlus if !alphanumeric => Some(Finding::Error(
+            "PushPlus tokens contain only letters and digits".to_string(),
+        )),
+        WechatServiceType::PushPlus if key.len() != 32 => Some(Finding::Warning(
+            "PushPlus tokens are usually 32 characters long".to_string(),
+        )),
+        _ => None,
+    }
+}
+
+/// 检查已启用渠道的webhook和key，返回 (字段路径, 结论)；空值由渠道初始化跳过，不在此检查
+pub fn check(config: &Config) -> Vec<(&'static str, Finding)> {
+    let channels = &config.channels;
+    let mut findings = Vec::new();
+    if let Some(teams) = channels.teams.as_ref().filter(|c| c.enabled) {
+        if !teams.webhook.is_empty() {
+            findings.extend(teams_webhook(&teams.webhook).map(|f| ("channels.teams.webhook", f)));
+        }
+    }
+    if let Some(feishu) = channels.feishu.as_ref().filter(|c| c.enabled) {
+        if !feishu.webhook.is_empty() {
+            findings
+                .extend(feishu_webhook(&feishu.webhook).map(|f| ("channels.feishu.webhook", f)));
+        }
+    }
+    if let Some(wechat) = channels.wechat.as_ref().filter(|c| c.enabled) {
+        if !wechat.key.is_empty() {
+            findings.extend(
+                wechat_key(&wechat.service, &wechat.key).map(|f| ("channels.wechat.key", f)),
+            );
+        }
+    }
+    findings
+}
+
+/// 创建渠道前调用：有错误时返回配置错误，警告只记录日志（中转服务等情况下是正常的）
+pub fn verify(config: &Config) -> anyhow::Result<()> {
+    let mut errors = Vec::new();
+    for (path, finding) in check(config) {
+        match finding {
+            Finding::Error(message) => errors.push(format!("{}: {}", path, message)),
+            Finding::Warning(message) => debug!(key = path, "{}", message),
+        }
+    }
+    if errors.is_empty() {
+        return Ok(());
+    }
+    Err(NotifierError::ConfigError(format!(
+        "{} (see `claude-notifier config validate`)",
+        errors.join("; ")
+    ))
+    .into())
+}
@@ -0,0 +1,259 @@
+use crate::config::Config;
+use crate::notification::Notification;
+use crate::routing::glob_match;
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use tracing::warn;
+
+/// 静默时段 - 时段内非critical通知不发送
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QuietHours {
+    pub enabled: bool,
+    pub start: String,
+    pub end: String,
+    /// 按渠道设置的静默时段，覆盖全局设置
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub channels: HashMap<String, QuietWindow>,
+    /// 不受静默时段限制的事件通配符（任何级别）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exempt_events: Vec<String>,
+    /// 静默时段内总是不发送的事件通配符（包括critical）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suppress_events: Vec<String>,
+    /// 全天静默的星期，例如 `["sat", "sun"]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub days: Vec<String>,
+    /// 全天静默的日期（`YYYY-MM-DD`）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub holidays: Vec<String>,
+    /// 节假日iCal文件，相对路径基于配置目录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub holiday_calendar: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QuietWindow {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    pub start: String,
+    pub end: String,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl QuietHours {
+    /// 通知是否受静默时段限制：豁免事件优先，其次强制静默事件，否则critical不受限制
+    pub fn applies_to(&self, notification: &Notification) -> bool {
+        let matches = |patterns: &[String]| {
+            patterns
+                .iter()
+                .any(|pattern| glob_match(pattern, &notification.event))
+        };
+        if matches(&self.exempt_events) {
+            return false;
+        }
+        matches(&self.suppress_events) || !notification.bypasses_quiet_hours()
+    }
+
+    /// 渠道当前是否处于静默时段
+    pub fn is_quiet(&self, channel: &str, now: &DateTime<Local>) -> bool {
+        let today = now.date_naive();
+        let time = now.format("%H:%M").to_string();
+        let (enabled, start, end) = match self.channels.get(channel) {
+            Some(window) => (window.enabled, &window.start, &window.end),
+            None => (self.enabled, &self.start, &self.end),
+        };
+        enabled && (self.is_quiet_day(today) || in_window(start, end, &time))
+    }
+
+    /// 是否为全天静默的星期或节假日
+    fn is_quiet_day(&self, date: NaiveDate) -> bool {
+        let weekday = date.weekday();
+        if self
+            .days
+            .iter()
+            .any(|day| day.parse::<Weekday>().is_ok_and(|d| d == weekday))
+        {
+            return true;
+        }
+
+        let formatted = date.format("%Y-%m-%d").to_string();
+        if self.holidays.contains(&formatted) {
+            return true;
+        }
+
+        match &self.holiday_calendar {
+            Some(path) => match calendar_dates(path) {
+                Ok(dates) => dates.contains(&date),
+                Err(e) => {
+                    warn!("Failed to read holiday calendar {}: {:#}", path, e);
+                    false
+                }
+            },
+            None => false,
+        }
+    }
+}
+
+/// 读取iCal文件中所有全天事件覆盖的日期
+fn calendar_dates(path: &str) -> Result<Vec<NaiveDate>> {
+    let path = Config::config_dir()?.join(path);
+    Ok(parse_calendar(&fs::read_to_string(&path)?))
+}
+
+/// iCal内容中所有全天事件覆盖的日期（DTEND不含当天）
+fn parse_calendar(content: &str) -> Vec<NaiveDate> {
+    let parse = |line: &str| {
+        let value = line.rsplit(':').next()?.trim();
+        NaiveDate::parse_from_str(value.get(..8)?, "%Y%m%d").ok()
+    };
+
+    let mut dates = Vec::new();
+    let mut start = None;
+    let mut end = None;
+    for line in content.lines() {
+        if line.starts_with("BEGIN:VEVENT") {
+            start = None;
+            end = None;
+        } else if line.starts_with("DTSTART") {
+            start = parse(line);
+        } else if line.starts_with("DTEND") {
+            end = parse(line);
+        } else if line.starts_with("END:VEVENT") {
+            if let Some(start) = start {
+                let end = end
+                    .filter(|end| *end > start)
+                    .unwrap_or(start + Duration::days(1));
+                dates.extend(start.iter_days().take_while(|day| *day < end));
+            }
+        }
+    }
+    dates
+}
+
+/// `HH:MM` 格式的时段，start大于end时跨越午夜
+fn in_window(start: &str, end: &str, now: &str) -> bool {
+    if start < end {
+        now >= start && now <= end
+    } else {
+        now >= start || now <= end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notification::Level;
+    use chrono::TimeZone;
+
+    fn quiet(start: &str, end: &str) -> QuietHours {
+        QuietHours {
+            enabled: true,
+            start: start.to_string(),
+            end: end.to_string(),
+            channels: HashMap::new(),
+            exempt_events: Vec::new(),
+            suppress_events: Vec::new(),
+            days: Vec::new(),
+            holidays: Vec::new(),
+            holiday_calendar: None,
+        }
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        // 2024-06-03为周一
+        Local
+            .with_ymd_and_hms(2024, 6, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn window_can_cross_midnight() {
+        assert!(in_window("22:00", "08:00", "23:30"));
+        assert!(in_window("22:00", "08:00", "07:59"));
+        assert!(!in_window("22:00", "08:00", "12:00"));
+        assert!(in_window("12:00", "14:00", "13:00"));
+        assert!(!in_window("12:00", "14:00", "14:01"));
+    }
+
+    #[test]
+    fn channel_window_overrides_global() {
+        let mut config = quiet("22:00", "08:00");
+        config.channels.insert(
+            "wechat".to_string(),
+            QuietWindow {
+                enabled: true,
+                start: "12:00".to_string(),
+                end: "13:00".to_string(),
+            },
+        );
+        config.channels.insert(
+            "teams".to_string(),
+            QuietWindow {
+                enabled: false,
+                start: "00:00".to_string(),
+                end: "23:59".to_string(),
+            },
+        );
+        let noon = at(3, 12, 30);
+        let night = at(3, 23, 0);
+        assert!(config.is_quiet("wechat", &noon));
+        assert!(!config.is_quiet("wechat", &night));
+        assert!(!config.is_quiet("feishu", &noon));
+        assert!(config.is_quiet("feishu", &night));
+        assert!(!config.is_quiet("teams", &night));
+    }
+
+    #[test]
+    fn exempt_and_suppressed_events() {
+        let mut config = quiet("22:00", "08:00");
+        config.exempt_events = vec!["deploy_*".to_string()];
+        config.suppress_events = vec!["nightly_*".to_string()];
+        let notify = |event: &str, level: Level| Notification::new(event, "t", "c", level);
+
+        assert!(config.applies_to(&notify("build", Level::Info)));
+        assert!(!config.applies_to(&notify("build", Level::Critical)));
+        assert!(!config.applies_to(&notify("deploy_prod", Level::Info)));
+        assert!(config.applies_to(&notify("nightly_report", Level::Critical)));
+    }
+
+    #[test]
+    fn weekdays_and_holidays_are_quiet_all_day() {
+        let mut config = quiet("22:00", "08:00");
+        config.days = vec!["sat".to_string(), "Sunday".to_string()];
+        config.holidays = vec!["2024-06-10".to_string()];
+        assert!(config.is_quiet("teams", &at(8, 12, 0)));
+        assert!(config.is_quiet("teams", &at(9, 12, 0)));
+        assert!(config.is_quiet("teams", &at(10, 12, 0)));
+        assert!(!config.is_quiet("teams", &at(11, 12, 0)));
+    }
+
+    #[test]
+    fn calendar_events_cover_start_to_exclusive_end() {
+        let content = "BEGIN:VCALENDAR\r\n\
+            BEGIN:VEVENT\r\n\
+            DTSTART;VALUE=DATE:20241001\r\n\
+            DTEND;VALUE=DATE:20241004\r\n\
+            SUMMARY:National Day\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            DTSTART;VALUE=DATE:20250101\r\n\
+            END:VEVENT\r\n\
+            END:VCALENDAR\r\n";
+        let date = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+        assert_eq!(
+            parse_calendar(content),
+            [
+                date(10, 1),
+                date(10, 2),
+                date(10, 3),
+                NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()
+            ]
+        );
+    }
+}
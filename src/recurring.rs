@@ -0,0 +1,95 @@
+use crate::error::NotifierError;
+use crate::notification::Level;
+use crate::store;
+use anyhow::Result;
+use chrono::{Local, TimeZone};
+use cron::Schedule;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+const STATE_FILE: &str = "recurring.json";
+
+/// 周期性通知 - 按cron表达式由守护进程（或 run-scheduled）发送
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RecurringJob {
+    pub name: String,
+    /// 标准5段cron表达式（分 时 日 月 周），也接受带秒的6/7段格式
+    pub cron: String,
+    pub event: String,
+    pub title: String,
+    /// 内容，可配合 `templates/<event>.tera` 生成
+    #[serde(default)]
+    pub content: String,
+    #[serde(default = "default_level")]
+    pub level: Level,
+    /// 不设置时按路由规则发送
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channels: Option<Vec<String>>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_level() -> Level {
+    Level::Info
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl RecurringJob {
+    pub fn schedule(&self) -> Result<Schedule> {
+        // cron库要求秒字段，5段表达式补上秒
+        let expression = if self.cron.split_whitespace().count() == 5 {
+            format!("0 {}", self.cron)
+        } else {
+            self.cron.clone()
+        };
+        Schedule::from_str(&expression).map_err(|e| {
+            NotifierError::ConfigError(format!(
+                "Invalid cron expression '{}' for recurring '{}': {}",
+                self.cron, self.name, e
+            ))
+            .into()
+        })
+    }
+
+    /// 某个时间点之后的下一次触发时间
+    pub fn next_after(&self, ts: i64) -> Result<Option<i64>> {
+        let Some(after) = Local.timestamp_opt(ts, 0).single() else {
+            return Ok(None);
+        };
+        Ok(self.schedule()?.after(&after).next().map(|t| t.timestamp()))
+    }
+}
+
+/// 各周期任务上次触发的时间
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RecurringStore {
+    last_run: HashMap<String, i64>,
+}
+
+impl RecurringStore {
+    pub fn load() -> Result<Self> {
+        store::load(STATE_FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        store::save(STATE_FILE, self)
+    }
+
+    pub fn last_run(&self, name: &str) -> Option<i64> {
+        self.last_run.get(name).copied()
+    }
+
+    pub fn set_last_run(&mut self, name: &str, at: i64) {
+        self.last_run.insert(name.to_string(), at);
+    }
+
+    /// 清理已从配置中删除的任务
+    pub fn retain(&mut self, jobs: &[RecurringJob]) {
+        self.last_run
+            .retain(|name, _| jobs.iter().any(|job| &job.name == name));
+    }
+}
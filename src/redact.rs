@@ -0,0 +1,217 @@
+use crate::config::Config;
+use serde_json::{json, Value};
+use std::io::{self, Write};
+use std::sync::RwLock;
+use tracing_subscriber::fmt::MakeWriter;
+
+/// 敏感字段名（小写包含即视为敏感）
+const SENSITIVE_KEYS: [&str; 7] = [
+    "token",
+    "key",
+    "secret",
+    "password",
+    "sign",
+    "webhook",
+    "authorization",
+];
+
+/// 短于该长度的配置值不按原文替换，避免误伤普通文字
+const MIN_SECRET_LEN: usize = 8;
+
+/// URL在文字中的结束字符
+const URL_END: [char; 10] = ['"', '\'', '`', '<', '>', '(', ')', '[', ']', ','];
+
+/// 配置中的密钥及其打码形式，较长的在前
+static SECRETS: RwLock<Vec<(String, String)>> = RwLock::new(Vec::new());
+
+/// 记录配置中的webhook、key和token，之后 `text` 按原文替换；加载配置时调用
+pub fn register(config: &Config) {
+    let channels = &config.channels;
+    let mut values: Vec<&str> = Vec::new();
+    if let Some(teams) = &channels.teams {
+        values.push(&teams.webhook);
+    }
+    if let Some(feishu) = &channels.feishu {
+        values.push(&feishu.webhook);
+        values.extend(feishu.secret.as_deref());
+        values.extend(feishu.app_secret.as_deref());
+    }
+    if let Some(wechat) = &channels.wechat {
+        values.push(&wechat.key);
+    }
+    if let Some(callbacks) = &config.callbacks {
+        values.push(&callbacks.secret);
+        values.extend(callbacks.feishu_token.as_deref());
+    }
+    if let Some(grpc) = &config.grpc {
+        values.extend(grpc.token.as_deref());
+    }
+    if let Some(upload) = &config.upload {
+        values.push(&upload.url);
+        values.extend(
+            upload
+                .headers
+                .iter()
+                .filter(|(name, _)| is_sensitive(name))
+                .map(|(_, value)| value.as_str()),
+        );
+    }
+
+    let mut secrets: Vec<(String, String)> = values
+        .into_iter()
+        .filter(|value| value.chars().count() >= MIN_SECRET_LEN)
+        .map(|value| {
+            let masked = if value.contains("://") {
+                mask_url(value)
+            } else {
+                mask_secret(value)
+            };
+            (value.to_string(), masked)
+        })
+        .collect();
+    secrets.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+    secrets.dedup();
+    if let Ok(mut current) = SECRETS.write() {
+        *current = secrets;
+    }
+}
+
+/// 只保留scheme和主机名；较长的路径段（webhook token、key）和查询参数值打码
+pub fn mask_url(url: &str) -> String {
+    let (base, query) = url.split_once('?').unwrap_or((url, ""));
+    let (scheme, rest) = base.split_once("://").unwrap_or(("", base));
+    let mut segments = rest.split('/');
+    let host = segments.next().unwrap_or_default();
+
+    let mut masked = if scheme.is_empty() {
+        host.to_string()
+    } else {
+        format!("{}://{}", scheme, host)
+    };
+    for segment in segments {
+        masked.push('/');
+        if segment.chars().count() < 16 {
+            masked.push_str(segment);
+        } else {
+            masked.push_str(&mask_secret(segment));
+        }
+    }
+
+    if !query.is_empty() {
+        let params: Vec<String> = query
+            .split('&')
+            .map(|param| match param.split_once('=') {
+                Some((name, _)) => format!("{}=***", name),
+                None => param.to_string(),
+            })
+            .collect();
+        masked.push('?');
+        masked.push_str(&params.join("&"));
+    }
+    masked
+}
+
+/// 只保留前4个字符，例如Server酱的SendKey显示为 `SCT1***`
+pub fn mask_secret(secret: &str) -> String {
+    let prefix: String = secret.chars().take(4).collect();
+    format!("{}***", prefix)
+}
+
+fn is_sensitive(key: &str) -> bool {
+    let lower = key.to_lowercase();
+    SENSITIVE_KEYS.iter().any(|s| lower.contains(s))
+}
+
+/// 递归打码敏感字段的值
+pub fn json(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| {
+                    if is_sensitive(k) && !v.is_null() {
+                        (k.clone(), json!("***"))
+                    } else {
+                        (k.clone(), json(v))
+                    }
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(json).collect()),
+        other => other.clone(),
+    }
+}
+
+/// 打码文字中的已知密钥和所有http(s)地址，用于错误信息和日志
+/// （reqwest的错误信息中带有完整URL，服务商的响应可能原样返回key）
+pub fn text(text: &str) -> String {
+    let mut text = text.to_string();
+    if let Ok(secrets) = SECRETS.read() {
+        for (secret, masked) in secrets.iter() {
+            if text.contains(secret.as_str()) {
+                text = text.replace(secret.as_str(), masked);
+            }
+        }
+    }
+    if !text.contains("://") {
+        return text;
+    }
+
+    let mut masked = String::with_capacity(text.len());
+    let mut rest = text.as_str();
+    while let Some(start) = find_url(rest) {
+        masked.push_str(&rest[..start]);
+        let url = &rest[start..];
+        let end = url
+            .find(|c: char| c.is_whitespace() || URL_END.contains(&c))
+            .unwrap_or(url.len());
+        masked.push_str(&mask_url(&url[..end]));
+        rest = &url[end..];
+    }
+    masked.push_str(rest);
+    masked
+}
+
+fn find_url(text: &str) -> Option<usize> {
+    match (text.find("https://"), text.find("http://")) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// 打码JSON中所有字符串值里的密钥和地址，字段名不变；用于发送结果和历史记录
+pub fn strings(value: &mut Value) {
+    match value {
+        Value::String(s) => *s = text(s),
+        Value::Array(items) => items.iter_mut().for_each(strings),
+        Value::Object(map) => map.values_mut().for_each(strings),
+        _ => {}
+    }
+}
+
+/// 日志输出前经过 `text` 打码的writer
+pub struct Writer<M>(pub M);
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for Writer<M> {
+    type Writer = Redacted<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        Redacted(self.0.make_writer())
+    }
+}
+
+/// tracing每条日志一次写入，按整条打码
+pub struct Redacted<W>(W);
+
+impl<W: Write> Write for Redacted<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match std::str::from_utf8(buf) {
+            Ok(line) => self.0.write_all(text(line).as_bytes())?,
+            Err(_) => self.0.write_all(buf)?,
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
@@ -0,0 +1,167 @@
+use crate::error::{NotifierError, Result};
+use crate::redact;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+/// webhook请求的录制或回放（`--record`/`--replay`），供离线的集成测试使用
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mode {
+    /// 正常发送，并把请求和响应追加到目录中的fixture
+    Record(PathBuf),
+    /// 不联网，按顺序返回目录中录制的响应
+    Replay(PathBuf),
+}
+
+static MODE: OnceLock<Mode> = OnceLock::new();
+
+/// 回放时各fixture已使用的条数；录制时串行读写同一文件
+static CURSORS: Mutex<Option<HashMap<PathBuf, usize>>> = Mutex::new(None);
+
+/// 整个进程使用录制或回放模式，在发送前调用
+pub fn set_mode(mode: Mode) -> anyhow::Result<()> {
+    match &mode {
+        Mode::Record(dir) => fs::create_dir_all(dir)?,
+        Mode::Replay(dir) if !dir.is_dir() => {
+            return Err(NotifierError::ConfigError(format!(
+                "Replay directory {} does not exist",
+                dir.display()
+            ))
+            .into())
+        }
+        Mode::Replay(_) => {}
+    }
+    let _ = MODE.set(mode);
+    Ok(())
+}
+
+pub fn mode() -> Option<&'static Mode> {
+    MODE.get()
+}
+
+/// 服务商的HTTP响应（或传输错误），录制和回放的单位
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Outcome {
+    Response {
+        status: u16,
+        /// 429响应的 `Retry-After`（秒）
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        retry_after: Option<u64>,
+        body: String,
+    },
+    /// 超时、连接失败等未收到响应的情况
+    Error { error: String, message: String },
+}
+
+impl Outcome {
+    /// 传输错误转为录制的形式
+    pub fn from_error(e: &NotifierError) -> Self {
+        let message = match e {
+            NotifierError::ChannelUnavailable(message)
+            | NotifierError::Unreachable(message)
+            | NotifierError::Network(message) => message.clone(),
+            other => other.to_string(),
+        };
+        Outcome::Error {
+            error: e.kind().to_string(),
+            message,
+        }
+    }
+}
+
+/// 录制的传输错误还原为发送错误
+pub fn error(kind: &str, message: String) -> NotifierError {
+    match kind {
+        "timeout" => NotifierError::Timeout,
+        "channel_unavailable" => NotifierError::ChannelUnavailable(message),
+        "unreachable" => NotifierError::Unreachable(message),
+        _ => NotifierError::Network(message),
+    }
+}
+
+/// fixture中的一次请求，地址和敏感字段已打码
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Exchange {
+    url: String,
+    request: Value,
+    response: Outcome,
+}
+
+/// 同一webhook的请求录制在同一文件中，文件名为主机名加完整地址的摘要（不含token原文）
+fn fixture(dir: &Path, url: &str) -> PathBuf {
+    let host = reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(String::from))
+        .unwrap_or_else(|| "webhook".to_string());
+    let digest = Sha256::digest(url.as_bytes());
+    let hash: String = digest[..6].iter().map(|b| format!("{:02x}", b)).collect();
+    dir.join(format!("{}-{}.json", host, hash))
+}
+
+fn read(path: &Path) -> Result<Vec<Exchange>> {
+    let content = fs::read_to_string(path).map_err(|e| {
+        NotifierError::ConfigError(format!("Cannot read {}: {}", path.display(), e))
+    })?;
+    serde_json::from_str(&content).map_err(|e| {
+        NotifierError::ConfigError(format!("Invalid fixture {}: {}", path.display(), e))
+    })
+}
+
+/// 录制模式下把请求和结果追加到fixture；写入失败只影响录制
+pub fn record(dir: &Path, url: &str, request: &Value, outcome: &Outcome) -> Result<()> {
+    let path = fixture(dir, url);
+    let _guard = CURSORS.lock().unwrap_or_else(|e| e.into_inner());
+    let mut exchanges = if path.exists() {
+        read(&path)?
+    } else {
+        Vec::new()
+    };
+    let response = match outcome {
+        Outcome::Error { error, message } => Outcome::Error {
+            error: error.clone(),
+            message: redact::text(message),
+        },
+        response => response.clone(),
+    };
+    exchanges.push(Exchange {
+        url: redact::mask_url(url),
+        request: redact::json(request),
+        response,
+    });
+    let content = serde_json::to_string_pretty(&exchanges)
+        .map(|content| content + "\n")
+        .map_err(|e| NotifierError::ConfigError(e.to_string()))?;
+    fs::write(&path, content)
+        .map_err(|e| NotifierError::ConfigError(format!("Cannot write {}: {}", path.display(), e)))
+}
+
+/// 回放下一条录制的结果；录制的用完后重复最后一条
+pub fn replay(dir: &Path, url: &str) -> Result<Outcome> {
+    let path = fixture(dir, url);
+    if !path.exists() {
+        return Err(NotifierError::ConfigError(format!(
+            "No recording for {} in {} (record it with --record)",
+            redact::mask_url(url),
+            dir.display()
+        )));
+    }
+    let exchanges = read(&path)?;
+    let mut cursors = CURSORS.lock().unwrap_or_else(|e| e.into_inner());
+    let cursor = cursors
+        .get_or_insert_with(HashMap::new)
+        .entry(path.clone())
+        .or_insert(0);
+    let Some(exchange) = exchanges.get(*cursor).or_else(|| exchanges.last()).cloned() else {
+        return Err(NotifierError::ConfigError(format!(
+            "{} has no recorded requests",
+            path.display()
+        )));
+    };
+    *cursor += 1;
+    Ok(exchange.response)
+}
@@ -0,0 +1,248 @@
+use crate::config::{Config, RoutingMode};
+use crate::error::NotifierError;
+use crate::notification::{Level, Notification};
+use anyhow::Result;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+/// 路由规则 - 所有设置的条件都满足时命中
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RoutingRule {
+    /// 事件名通配符，支持 `*` 和 `?`，例如 `deploy_*`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<String>,
+    /// 最低级别，例如 `warning` 匹配 warning 和 critical
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_level: Option<Level>,
+    /// 级别名（包括自定义级别），匹配其中任一个
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub levels: Vec<String>,
+    /// 内容正则
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+
+    pub channels: Vec<String>,
+    /// 内联内容模板（Tera语法），例如 `{{ emoji }} {{ content }}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// 是否@所有人（仅飞书），不设置时critical级别@所有人
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mention_all: Option<bool>,
+}
+
+/// 路由结果
+#[derive(Debug, Clone, Default)]
+pub struct Route {
+    pub channels: Vec<String>,
+    pub template: Option<String>,
+    pub mention_all: Option<bool>,
+}
+
+impl RoutingRule {
+    pub fn matches(&self, notification: &Notification) -> Result<bool> {
+        if let Some(pattern) = &self.event {
+            if !glob_match(pattern, &notification.event) {
+                return Ok(false);
+            }
+        }
+
+        if let Some(min_level) = self.min_level {
+            if notification.level < min_level {
+                return Ok(false);
+            }
+        }
+
+        if !self.levels.is_empty()
+            && !self
+                .levels
+                .iter()
+                .any(|level| level == notification.level_name())
+        {
+            return Ok(false);
+        }
+
+        if let Some(pattern) = &self.content {
+            let re = Regex::new(pattern).map_err(|e| {
+                NotifierError::ConfigError(format!("Invalid content regex '{}': {}", pattern, e))
+            })?;
+            if !re.is_match(&notification.content) {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+/// 根据规则确定发送渠道，没有规则命中时退回 `notifications` 映射
+pub fn resolve(config: &Config, notification: &Notification) -> Result<Route> {
+    let mut route = Route::default();
+    let mut matched = false;
+
+    for (index, rule) in config.rules.iter().enumerate() {
+        if !rule.matches(notification)? {
+            continue;
+        }
+        matched = true;
+        debug!(rule = index, channels = ?rule.channels, "routing rule matched");
+
+        for channel in &rule.channels {
+            if !route.channels.contains(channel) {
+                route.channels.push(channel.clone());
+            }
+        }
+        // 累积模式下模板和@设置以先命中的规则为准
+        if route.template.is_none() {
+            route.template = rule.template.clone();
+        }
+        if route.mention_all.is_none() {
+            route.mention_all = rule.mention_all;
+        }
+
+        if config.routing_mode == RoutingMode::FirstMatch {
+            break;
+        }
+    }
+
+    if !matched {
+        route.channels = config
+            .notifications
+            .get(&notification.event)
+            .cloned()
+            .unwrap_or_default();
+        debug!(channels = ?route.channels, "no routing rule matched, using notifications map");
+    }
+
+    Ok(route)
+}
+
+/// 事件是否有专门的路由：`notifications` 中有该事件，或有规则的事件通配符匹配它
+pub fn is_routed(config: &Config, event: &str) -> bool {
+    config.notifications.contains_key(event)
+        || config.rules.iter().any(|rule| {
+            rule.event
+                .as_deref()
+                .is_some_and(|pattern| glob_match(pattern, event))
+        })
+}
+
+/// 解析备用链 "feishu -> wechat -> sms"，单个渠道名返回只有一项的链
+pub fn parse_chain(spec: &str) -> Vec<String> {
+    spec.split("->")
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty())
+        .collect()
+}
+
+/// 简单通配符匹配：`*` 匹配任意串，`?` 匹配单个字符
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((sp, st)) = star {
+            // 回溯：让上一个 * 多匹配一个字符
+            p = sp + 1;
+            t = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(event: &str, channels: &[&str]) -> RoutingRule {
+        RoutingRule {
+            event: Some(event.to_string()),
+            channels: channels.iter().map(|c| c.to_string()).collect(),
+            ..RoutingRule::default()
+        }
+    }
+
+    #[test]
+    fn glob_matches_stars_and_question_marks() {
+        assert!(glob_match("deploy_*", "deploy_prod"));
+        assert!(glob_match("deploy_*", "deploy_"));
+        assert!(glob_match("*_failure", "build_failure"));
+        assert!(glob_match("a*b*c", "axxbyybzzc"));
+        assert!(glob_match("build_?", "build_1"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("deploy_*", "predeploy_prod"));
+        assert!(!glob_match("build_?", "build_12"));
+        assert!(!glob_match("a*b", "acbd"));
+    }
+
+    #[test]
+    fn rule_checks_level_and_content() {
+        let notification = Notification::new("ci", "t", "tests failed", Level::Warning);
+        let mut rule = rule("ci", &["teams"]);
+        rule.min_level = Some(Level::Warning);
+        assert!(rule.matches(&notification).unwrap());
+        rule.min_level = Some(Level::Critical);
+        assert!(!rule.matches(&notification).unwrap());
+
+        rule.min_level = None;
+        rule.content = Some("fail(ed|ure)".to_string());
+        assert!(rule.matches(&notification).unwrap());
+        rule.content = Some("[".to_string());
+        assert!(rule.matches(&notification).is_err());
+    }
+
+    #[test]
+    fn first_match_uses_one_rule_and_accumulate_merges() {
+        let mut config = Config {
+            rules: vec![
+                rule("deploy_*", &["teams"]),
+                rule("*", &["feishu", "teams"]),
+            ],
+            ..Config::default()
+        };
+        let notification = Notification::new("deploy_prod", "t", "c", Level::Info);
+
+        let route = resolve(&config, &notification).unwrap();
+        assert_eq!(route.channels, ["teams"]);
+
+        config.routing_mode = RoutingMode::Accumulate;
+        let route = resolve(&config, &notification).unwrap();
+        assert_eq!(route.channels, ["teams", "feishu"]);
+    }
+
+    #[test]
+    fn unmatched_events_fall_back_to_notifications_map() {
+        let config = Config {
+            rules: vec![rule("deploy_*", &["teams"])],
+            ..Config::default()
+        };
+        let notification = Notification::new("daily_report", "t", "c", Level::Info);
+        let route = resolve(&config, &notification).unwrap();
+        assert_eq!(route.channels, ["feishu"]);
+        assert!(is_routed(&config, "deploy_staging"));
+        assert!(!is_routed(&config, "unknown"));
+    }
+
+    #[test]
+    fn chain_splits_on_arrows() {
+        assert_eq!(
+            parse_chain("feishu -> wechat->sms"),
+            ["feishu", "wechat", "sms"]
+        );
+        assert_eq!(parse_chain("teams"), ["teams"]);
+        assert_eq!(parse_chain(" -> teams -> "), ["teams"]);
+    }
+}
@@ -0,0 +1,59 @@
+use crate::notification::Notification;
+use crate::store;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+const STATE_FILE: &str = "scheduled.json";
+
+/// 等待定时发送的通知
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScheduledNotification {
+    pub id: String,
+    pub notification: Notification,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channels: Option<Vec<String>>,
+    #[serde(default)]
+    pub force: bool,
+    pub created_at: i64,
+    pub due_at: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ScheduleStore {
+    pending: Vec<ScheduledNotification>,
+}
+
+impl ScheduleStore {
+    pub fn load() -> Result<Self> {
+        store::load(STATE_FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        store::save(STATE_FILE, self)
+    }
+
+    pub fn add(&mut self, scheduled: ScheduledNotification) {
+        self.pending.push(scheduled);
+        self.pending.sort_by_key(|s| s.due_at);
+    }
+
+    /// 取消定时发送，返回是否存在该ID
+    pub fn cancel(&mut self, id: &str) -> bool {
+        let before = self.pending.len();
+        self.pending.retain(|s| s.id != id);
+        self.pending.len() != before
+    }
+
+    /// 取出所有已到期的通知（按到期时间排序）
+    pub fn take_due(&mut self, now: i64) -> Vec<ScheduledNotification> {
+        let (due, pending) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|s| s.due_at <= now);
+        self.pending = pending;
+        due
+    }
+
+    pub fn pending(&self) -> &[ScheduledNotification] {
+        &self.pending
+    }
+}
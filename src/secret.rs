@@ -0,0 +1,176 @@
+use crate::error::NotifierError;
+use anyhow::Result;
+use serde_json::Value;
+use std::process::Command;
+
+/// 配置中引用密钥环条目的前缀，例如 `"webhook": "keyring:teams-prod"`
+pub const KEYRING_PREFIX: &str = "keyring:";
+
+/// 密钥环中的服务名，别名作为用户名
+#[cfg(feature = "keyring")]
+const SERVICE: &str = "claude-notifier";
+
+#[cfg(feature = "keyring")]
+fn entry(alias: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(SERVICE, alias).map_err(|e| keyring_error(alias, e))
+}
+
+#[cfg(feature = "keyring")]
+fn keyring_error(alias: &str, e: keyring::Error) -> anyhow::Error {
+    NotifierError::ConfigError(format!("Keyring entry '{}': {}", alias, e)).into()
+}
+
+#[cfg(not(feature = "keyring"))]
+fn unsupported() -> anyhow::Error {
+    NotifierError::ConfigError(
+        "Keyring support is not enabled (rebuild with --features keyring)".to_string(),
+    )
+    .into()
+}
+
+/// 读取密钥环中的值
+pub fn get(alias: &str) -> Result<String> {
+    #[cfg(feature = "keyring")]
+    {
+        entry(alias)?
+            .get_password()
+            .map_err(|e| keyring_error(alias, e))
+    }
+    #[cfg(not(feature = "keyring"))]
+    {
+        let _ = alias;
+        Err(unsupported())
+    }
+}
+
+/// 写入（或覆盖）密钥环中的值
+pub fn set(alias: &str, value: &str) -> Result<()> {
+    #[cfg(feature = "keyring")]
+    {
+        entry(alias)?
+            .set_password(value)
+            .map_err(|e| keyring_error(alias, e))
+    }
+    #[cfg(not(feature = "keyring"))]
+    {
+        let _ = (alias, value);
+        Err(unsupported())
+    }
+}
+
+/// 删除密钥环中的值
+pub fn delete(alias: &str) -> Result<()> {
+    #[cfg(feature = "keyring")]
+    {
+        entry(alias)?
+            .delete_credential()
+            .map_err(|e| keyring_error(alias, e))
+    }
+    #[cfg(not(feature = "keyring"))]
+    {
+        let _ = alias;
+        Err(unsupported())
+    }
+}
+
+/// 执行外部命令（Vault、1Password、pass等），标准输出去掉末尾换行后作为值
+pub fn run_command(command: &str) -> Result<String> {
+    #[cfg(windows)]
+    let output = Command::new("cmd").args(["/C", command]).output();
+    #[cfg(not(windows))]
+    let output = Command::new("sh").args(["-c", command]).output();
+
+    let output = output.map_err(|e| {
+        NotifierError::ConfigError(format!(
+            "Secret command '{}' failed to start: {}",
+            command, e
+        ))
+    })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let mut msg = format!("Secret command '{}' exited with {}", command, output.status);
+        if !stderr.trim().is_empty() {
+            msg = format!("{}: {}", msg, stderr.trim());
+        }
+        return Err(NotifierError::ConfigError(msg).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end_matches(['\r', '\n'])
+        .to_string())
+}
+
+/// 只含 `command` 键的对象（密钥命令）中的命令
+pub fn secret_command(value: &Value) -> Option<&str> {
+    match value {
+        Value::Object(map) if map.len() == 1 => map.get("command")?.as_str(),
+        _ => None,
+    }
+}
+
+/// exec渠道（`channels.exec.<name>`）本身可能只含 `command` 键，它不是密钥命令
+pub fn is_exec_channel(path: &str) -> bool {
+    path.strip_prefix("channels.exec.")
+        .is_some_and(|name| !name.contains('.'))
+}
+
+/// 解析配置中的密钥引用：`keyring:<alias>` 字符串替换为密钥环中的值，
+/// 只含 `command` 键的对象（`{"command": "vault kv get ..."}`）替换为命令输出
+pub fn resolve(value: &mut Value) -> Result<()> {
+    resolve_at(value, "")
+}
+
+fn resolve_at(value: &mut Value, path: &str) -> Result<()> {
+    if let Some(command) = secret_command(value).filter(|_| !is_exec_channel(path)) {
+        *value = Value::String(run_command(command)?);
+        return Ok(());
+    }
+    match value {
+        Value::String(s) => {
+            if let Some(alias) = s.strip_prefix(KEYRING_PREFIX) {
+                *s = get(alias)?;
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                resolve_at(item, path)?;
+            }
+        }
+        Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                resolve_at(item, &path)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn exec_channels_are_not_secret_commands() {
+        let mut value = json!({
+            "channels": {
+                "exec": { "sms": { "command": "send-sms" } },
+                "teams": { "webhook": { "command": "echo https://example.com/hook" } }
+            }
+        });
+        resolve(&mut value).unwrap();
+        assert_eq!(
+            value["channels"]["exec"]["sms"],
+            json!({ "command": "send-sms" })
+        );
+        assert_eq!(
+            value["channels"]["teams"]["webhook"],
+            "https://example.com/hook"
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// 外部工具的严重程度刻度
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Scale {
+    /// syslog 0–7 或名称（emerg … debug）
+    Syslog,
+    /// Sentry 事件级别（fatal、error、warning、info、debug）
+    Sentry,
+    /// GitHub check run / workflow 的 conclusion
+    Github,
+    /// 进程退出码，0为成功
+    ExitCode,
+}
+
+impl Scale {
+    pub fn level(self, value: &str) -> Option<&'static str> {
+        let level = match self {
+            Scale::Syslog => match value {
+                "0" | "1" | "2" | "3" | "emerg" | "panic" | "alert" | "crit" | "err" | "error" => {
+                    "critical"
+                }
+                "4" | "warn" | "warning" => "warning",
+                "5" | "6" | "7" | "notice" | "info" | "debug" => "info",
+                _ => return None,
+            },
+            Scale::Sentry => match value {
+                "fatal" | "error" => "critical",
+                "warning" => "warning",
+                "info" | "debug" => "info",
+                _ => return None,
+            },
+            Scale::Github => match value {
+                "success" => "success",
+                "failure" | "timed_out" | "startup_failure" => "critical",
+                "cancelled" | "action_required" | "stale" => "warning",
+                "neutral" | "skipped" => "info",
+                _ => return None,
+            },
+            Scale::ExitCode => match value.parse::<i64>().ok()? {
+                0 => "success",
+                _ => "critical",
+            },
+        };
+        Some(level)
+    }
+}
+
+/// 把hook payload中的外部严重程度映射为通知级别
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SeverityMapping {
+    /// 严重程度字段的JSON Pointer，例如 `/level` 或 `/check_run/conclusion`
+    pub field: String,
+    /// 内置刻度
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<Scale>,
+    /// 自定义映射（值 → 级别名），优先于内置刻度；值不区分大小写
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub map: HashMap<String, String>,
+    /// 字段存在但没有对应项时的级别
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+}
+
+impl SeverityMapping {
+    /// payload中有该字段时返回映射到的级别名
+    pub fn level(&self, payload: &Value) -> Option<String> {
+        let value = match payload.pointer(&self.field)? {
+            Value::String(s) => s.trim().to_lowercase(),
+            Value::Number(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            _ => return None,
+        };
+        self.map
+            .iter()
+            .find(|(key, _)| key.to_lowercase() == value)
+            .map(|(_, level)| level.clone())
+            .or_else(|| {
+                self.scale
+                    .and_then(|scale| scale.level(&value))
+                    .map(String::from)
+            })
+            .or_else(|| self.default.clone())
+    }
+}
+
+/// 按顺序使用第一个命中的映射
+pub fn level(mappings: &[SeverityMapping], payload: &Value) -> Option<String> {
+    mappings.iter().find_map(|mapping| mapping.level(payload))
+}
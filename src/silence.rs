@@ -0,0 +1,126 @@
+use crate::notification::{new_id, Notification};
+use crate::routing::glob_match;
+use crate::store;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+const STATE_FILE: &str = "silences.json";
+
+/// 手动设置的临时静音（`silence` 命令），例如计划维护期间
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Silence {
+    pub id: String,
+    /// 事件名通配符，为空时所有事件
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<String>,
+    /// 渠道名通配符，为空时所有渠道
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    /// 去重键或事件名（`snooze` 命令），只静音这一个问题
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    pub created_at: i64,
+    pub until: i64,
+}
+
+impl Silence {
+    pub fn new(event: Option<String>, channel: Option<String>, now: i64, until: i64) -> Self {
+        Self {
+            id: new_id(),
+            event,
+            channel,
+            key: None,
+            created_at: now,
+            until,
+        }
+    }
+
+    /// 暂停某个去重键（或事件名）的通知
+    pub fn snooze(key: String, now: i64, until: i64) -> Self {
+        Self {
+            key: Some(key),
+            ..Self::new(None, None, now, until)
+        }
+    }
+
+    pub fn is_active(&self, now: i64) -> bool {
+        now < self.until
+    }
+
+    pub fn matches(&self, notification: &Notification) -> bool {
+        self.event
+            .as_ref()
+            .is_none_or(|pattern| glob_match(pattern, &notification.event))
+            && self.key.as_ref().is_none_or(|key| {
+                notification.dedup_key.as_ref() == Some(key) || notification.event == *key
+            })
+    }
+
+    /// 是否静音该渠道；没有指定渠道时静音整条通知
+    pub fn covers(&self, channel: &str) -> bool {
+        self.channel
+            .as_ref()
+            .is_none_or(|pattern| glob_match(pattern, channel))
+    }
+
+    /// 静音范围的说明，例如 `event deploy_* on wechat`
+    pub fn scope(&self) -> String {
+        if let Some(key) = &self.key {
+            return format!("snoozed {}", key);
+        }
+        match (&self.event, &self.channel) {
+            (None, None) => "all notifications".to_string(),
+            (Some(event), None) => format!("event {}", event),
+            (None, Some(channel)) => format!("channel {}", channel),
+            (Some(event), Some(channel)) => format!("event {} on {}", event, channel),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SilenceStore {
+    silences: Vec<Silence>,
+}
+
+impl SilenceStore {
+    pub fn load() -> Result<Self> {
+        store::load(STATE_FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        store::save(STATE_FILE, self)
+    }
+
+    /// 添加静音，同时清理已过期的
+    pub fn add(&mut self, silence: Silence) {
+        let now = silence.created_at;
+        self.silences.retain(|s| s.is_active(now));
+        self.silences.push(silence);
+    }
+
+    /// 生效中的静音，按结束时间排序
+    pub fn active(&self, now: i64) -> Vec<&Silence> {
+        let mut active: Vec<&Silence> = self.silences.iter().filter(|s| s.is_active(now)).collect();
+        active.sort_by_key(|s| s.until);
+        active
+    }
+
+    /// 对该通知生效的静音
+    pub fn matching(&self, notification: &Notification, now: i64) -> Vec<Silence> {
+        self.active(now)
+            .into_iter()
+            .filter(|s| s.matches(notification))
+            .cloned()
+            .collect()
+    }
+
+    /// 删除指定ID的静音，未指定时全部删除；返回删除的条数
+    pub fn clear(&mut self, id: Option<&str>) -> usize {
+        let before = self.silences.len();
+        match id {
+            Some(id) => self.silences.retain(|s| s.id != id),
+            None => self.silences.clear(),
+        }
+        before - self.silences.len()
+    }
+}
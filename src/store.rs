@@ -0,0 +1,44 @@
+use crate::config::Config;
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+
+/// 状态文件的独占锁，释放（drop）时解锁
+pub struct Lock {
+    _file: fs::File,
+}
+
+/// 对状态文件加独占锁（同目录下的 `.<name>.lock`），其他进程的 `lock` 会等待；
+/// 并发的hook读取-修改-写入同一状态时用它包住整个过程，避免互相覆盖
+pub fn lock(name: &str) -> Result<Lock> {
+    let dir = Config::data_dir()?;
+    fs::create_dir_all(&dir)?;
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(dir.join(format!(".{}.lock", name)))?;
+    file.lock()?;
+    Ok(Lock { _file: file })
+}
+
+/// 读取数据目录下的JSON状态文件，文件不存在或损坏时返回默认值
+pub fn load<T: DeserializeOwned + Default>(name: &str) -> Result<T> {
+    let path = Config::data_dir()?.join(name);
+    Ok(fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default())
+}
+
+/// 写入JSON状态文件（先写临时文件再重命名，避免并发调用读到半个文件）
+pub fn save<T: Serialize>(name: &str, value: &T) -> Result<()> {
+    let dir = Config::data_dir()?;
+    fs::create_dir_all(&dir)?;
+
+    let tmp = dir.join(format!(".{}.{}.tmp", name, std::process::id()));
+    fs::write(&tmp, serde_json::to_string_pretty(value)?)?;
+    fs::rename(&tmp, dir.join(name))?;
+    Ok(())
+}
@@ -0,0 +1,234 @@
+use crate::i18n;
+use crate::notification::{Level, Notification};
+use crate::store;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+const STATE_FILE: &str = "storm.json";
+
+/// 告警风暴 - 全局每分钟的通知超过 `max_per_minute` 时只发送紧急通知，
+/// 其余的计数后定期汇总，速率回落后自动恢复
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StormConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 所有事件合计每分钟超过多少条时进入风暴模式
+    #[serde(default = "default_max_per_minute")]
+    pub max_per_minute: usize,
+    /// 风暴期间每隔多少分钟发送一次被抑制的条数
+    #[serde(default = "default_summary_minutes")]
+    pub summary_minutes: i64,
+}
+
+impl Default for StormConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_per_minute: default_max_per_minute(),
+            summary_minutes: default_summary_minutes(),
+        }
+    }
+}
+
+fn default_max_per_minute() -> usize {
+    30
+}
+
+fn default_summary_minutes() -> i64 {
+    10
+}
+
+/// 本次检查后风暴模式的变化
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StormState {
+    Normal,
+    Started,
+    Active,
+    Ended,
+}
+
+impl StormState {
+    /// 是否处于风暴模式，非紧急通知不发送
+    pub fn is_storming(self) -> bool {
+        matches!(self, StormState::Started | StormState::Active)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct StormStore {
+    /// 最近一分钟的到达时间
+    arrivals: Vec<i64>,
+    /// 进入风暴模式的时间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    active_since: Option<i64>,
+    /// 本次汇总的起始时间
+    #[serde(default)]
+    period_start: i64,
+    /// 本次汇总内各事件被抑制的条数
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    suppressed: BTreeMap<String, usize>,
+    /// 被抑制的通知原本的渠道，汇总发往这些渠道
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    channels: BTreeSet<String>,
+}
+
+impl StormStore {
+    /// 持有期间完成读取、更新和写入，并发的hook不会漏记到达
+    pub fn lock() -> Result<store::Lock> {
+        store::lock(STATE_FILE)
+    }
+
+    pub fn load() -> Result<Self> {
+        store::load(STATE_FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        store::save(STATE_FILE, self)
+    }
+
+    /// 没有风暴也没有待汇总的条数
+    pub fn is_idle(&self) -> bool {
+        self.active_since.is_none() && self.suppressed.is_empty()
+    }
+
+    /// 按最近一分钟的速率更新风暴模式，`arrival` 时先记录一次到达
+    pub fn tick(&mut self, config: &StormConfig, now: i64, arrival: bool) -> StormState {
+        self.arrivals.retain(|at| now - at < 60);
+        if arrival {
+            self.arrivals.push(now);
+        }
+        let storming = self.arrivals.len() > config.max_per_minute;
+        match (self.active_since.is_some(), storming) {
+            (false, true) => {
+                self.active_since = Some(now);
+                self.period_start = now;
+                StormState::Started
+            }
+            (true, false) => {
+                self.active_since = None;
+                StormState::Ended
+            }
+            (true, true) => StormState::Active,
+            (false, false) => StormState::Normal,
+        }
+    }
+
+    /// 记录一条被抑制的通知
+    pub fn suppress(&mut self, notification: &Notification, channels: &[String]) {
+        *self
+            .suppressed
+            .entry(notification.event.clone())
+            .or_default() += 1;
+        self.channels.extend(channels.iter().cloned());
+    }
+
+    /// 到了汇总时间或风暴已结束时，取出汇总通知和发送渠道
+    pub fn take_summary(
+        &mut self,
+        config: &StormConfig,
+        now: i64,
+        ended: bool,
+    ) -> Option<(Notification, Vec<String>)> {
+        let due = ended || now - self.period_start >= config.summary_minutes * 60;
+        if self.suppressed.is_empty() || !due {
+            return None;
+        }
+        let strings = i18n::strings();
+        let count: usize = self.suppressed.values().sum();
+        let minutes = ((now - self.period_start + 30) / 60).max(1);
+        let mut lines: Vec<String> = std::mem::take(&mut self.suppressed)
+            .into_iter()
+            .map(|(event, count)| format!("- `{}` × {}", event, count))
+            .collect();
+        if ended {
+            lines.push(String::new());
+            lines.push(strings.storm_ended.to_string());
+        }
+        let notification = Notification::new(
+            "storm",
+            i18n::fill(
+                strings.storm_summary,
+                &[("count", &count), ("minutes", &minutes)],
+            ),
+            lines.join("\n"),
+            Level::Warning,
+        );
+        self.period_start = now;
+        Some((
+            notification,
+            std::mem::take(&mut self.channels).into_iter().collect(),
+        ))
+    }
+}
+
+/// 进入风暴模式时的提示
+pub fn started(config: &StormConfig) -> Notification {
+    Notification::new(
+        "storm",
+        i18n::fill(
+            i18n::strings().storm_started,
+            &[("max", &config.max_per_minute)],
+        ),
+        "",
+        Level::Warning,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> StormConfig {
+        StormConfig {
+            enabled: true,
+            max_per_minute: 3,
+            summary_minutes: 10,
+        }
+    }
+
+    #[test]
+    fn tick_follows_rate_over_last_minute() {
+        let config = config();
+        let mut store = StormStore::default();
+        for at in 0..3 {
+            assert_eq!(store.tick(&config, at, true), StormState::Normal);
+        }
+        assert_eq!(store.tick(&config, 3, true), StormState::Started);
+        assert_eq!(store.tick(&config, 4, true), StormState::Active);
+        // 没有新的到达时只按速率判断
+        assert_eq!(store.tick(&config, 59, false), StormState::Active);
+        assert_eq!(store.tick(&config, 62, false), StormState::Ended);
+        assert_eq!(store.tick(&config, 63, false), StormState::Normal);
+        assert!(store.is_idle());
+    }
+
+    #[test]
+    fn summary_counts_suppressed_events_per_period() {
+        let config = config();
+        let mut store = StormStore::default();
+        for at in 0..4 {
+            store.tick(&config, at, true);
+        }
+        let teams = vec!["teams".to_string()];
+        let feishu = vec!["feishu".to_string()];
+        store.suppress(&Notification::new("stop", "t", "c", Level::Info), &teams);
+        store.suppress(&Notification::new("stop", "t", "c", Level::Info), &teams);
+        store.suppress(&Notification::new("ci", "t", "c", Level::Warning), &feishu);
+        assert!(!store.is_idle());
+
+        assert!(store.take_summary(&config, 602, false).is_none());
+        let (summary, channels) = store.take_summary(&config, 603, false).unwrap();
+        assert_eq!(summary.event, "storm");
+        assert_eq!(summary.content, "- `ci` × 1\n- `stop` × 2");
+        assert_eq!(channels, ["feishu", "teams"]);
+
+        // 新的汇总周期从上次汇总开始，风暴结束时立即汇总
+        store.suppress(&Notification::new("stop", "t", "c", Level::Info), &teams);
+        assert!(store.take_summary(&config, 900, false).is_none());
+        let (summary, channels) = store.take_summary(&config, 900, true).unwrap();
+        assert!(summary.content.starts_with("- `stop` × 1\n\n"));
+        assert_eq!(channels, ["teams"]);
+        assert!(store.take_summary(&config, 900, true).is_none());
+    }
+}
@@ -0,0 +1,115 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// 在systemd下运行时日志写入journald（标准错误），不需要颜色和时间戳
+pub fn under_journald() -> bool {
+    std::env::var_os("JOURNAL_STREAM").is_some()
+}
+
+/// 向systemd报告状态（`READY=1`、`RELOADING=1` 等）；不是由systemd以 `Type=notify` 启动时什么也不做
+#[cfg(unix)]
+pub fn notify(state: &str) -> Result<()> {
+    use std::os::unix::net::UnixDatagram;
+
+    let Some(path) = std::env::var_os("NOTIFY_SOCKET") else {
+        return Ok(());
+    };
+    let socket = UnixDatagram::unbound()?;
+    let path = path.to_string_lossy();
+    // `@` 开头的是Linux的抽象命名空间
+    match path.strip_prefix('@') {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes())?;
+            socket.send_to_addr(state.as_bytes(), &addr)?;
+        }
+        _ => {
+            socket.send_to(state.as_bytes(), path.as_ref())?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn notify(_state: &str) -> Result<()> {
+    Ok(())
+}
+
+/// 用户单元的名称，每个配置档各一个
+pub fn unit_name(profile: Option<&str>) -> String {
+    match profile {
+        Some(profile) => format!("claude-notifier-{}.service", profile),
+        None => "claude-notifier.service".to_string(),
+    }
+}
+
+/// ExecStart中的参数：含空白或引号时加双引号，`%` 和 `$` 需要转义
+fn quote(arg: &str) -> String {
+    let escaped = arg
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('%', "%%")
+        .replace('$', "$$");
+    if escaped.is_empty() || escaped.contains(char::is_whitespace) || escaped != arg {
+        format!("\"{}\"", escaped)
+    } else {
+        escaped
+    }
+}
+
+/// 运行 `args` 的用户单元；SIGHUP重新加载配置
+pub fn unit(args: &[String]) -> String {
+    let command: Vec<String> = args.iter().map(|arg| quote(arg)).collect();
+    format!(
+        "[Unit]\n\
+         Description=Claude Notifier daemon (scheduled sends, escalations, digests)\n\
+         \n\
+         [Service]\n\
+         Type=notify\n\
+         ExecStart={}\n\
+         ExecReload=/bin/kill -HUP $MAINPID\n\
+         Restart=on-failure\n\
+         RestartSec=10\n\
+         \n\
+         [Install]\n\
+         WantedBy=default.target\n",
+        command.join(" ")
+    )
+}
+
+fn unit_dir() -> Result<PathBuf> {
+    let config = dirs::config_dir().context("Cannot find the user configuration directory")?;
+    Ok(config.join("systemd").join("user"))
+}
+
+fn systemctl(args: &[&str]) -> Result<()> {
+    let status = Command::new("systemctl")
+        .arg("--user")
+        .args(args)
+        .status()
+        .context("Failed to run systemctl")?;
+    if !status.success() {
+        anyhow::bail!("systemctl --user {} failed ({})", args.join(" "), status);
+    }
+    Ok(())
+}
+
+/// 写入用户单元，重新加载并启用、启动
+pub fn install(name: &str, content: &str) -> Result<PathBuf> {
+    let path = unit_dir()?.join(name);
+    write_unit(&path, content)?;
+    systemctl(&["daemon-reload"])
+        .and_then(|_| systemctl(&["enable", "--now", name]))
+        .with_context(|| format!("Wrote {}, but could not start it", path.display()))?;
+    Ok(path)
+}
+
+fn write_unit(path: &Path, content: &str) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    std::fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
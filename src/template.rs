@@ -0,0 +1,81 @@
+use crate::config::Config;
+use crate::error::NotifierError;
+use crate::notification::Notification;
+use anyhow::Result;
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+use tera::{Context, Tera};
+
+/// 消息模板（Tera语法）
+///
+/// 查找顺序：`templates/<event>.<channel>.tera` → `templates/<event>.tera` → 路由规则中的内联模板。
+/// 都没有时直接使用原始内容。
+pub struct Templates {
+    dir: PathBuf,
+}
+
+impl Templates {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    pub fn load() -> Result<Self> {
+        Ok(Self::new(Config::config_dir()?.join("templates")))
+    }
+
+    /// 渲染发往指定渠道的内容
+    pub fn render(
+        &self,
+        notification: &Notification,
+        inline: Option<&str>,
+        channel: &str,
+    ) -> Result<String> {
+        let candidates = [
+            self.dir
+                .join(format!("{}.{}.tera", notification.event, channel)),
+            self.dir.join(format!("{}.tera", notification.event)),
+        ];
+
+        let template = match candidates.iter().find(|path| path.is_file()) {
+            Some(path) => fs::read_to_string(path)?,
+            None => match inline {
+                Some(inline) => inline.to_string(),
+                None => return Ok(notification.content.clone()),
+            },
+        };
+
+        let context = Self::context(notification, channel)?;
+        Tera::one_off(&template, &context, false).map_err(|e| {
+            // tera的错误信息在source链中
+            let mut message = e.to_string();
+            let mut source = std::error::Error::source(&e);
+            while let Some(cause) = source {
+                message = format!("{}: {}", message, cause);
+                source = cause.source();
+            }
+            NotifierError::ConfigError(format!("Template error: {}", message)).into()
+        })
+    }
+
+    /// 模板上下文：hook原始payload的顶层字段，加上通知字段和完整的 `payload`
+    fn context(notification: &Notification, channel: &str) -> Result<Context> {
+        let mut context = match &notification.payload {
+            Some(payload @ Value::Object(_)) => Context::from_value(payload.clone())?,
+            _ => Context::new(),
+        };
+
+        context.insert("event", &notification.event);
+        context.insert("title", &notification.title);
+        context.insert("content", &notification.content);
+        context.insert("level", notification.level_name());
+        context.insert("emoji", notification.emoji());
+        context.insert("color", notification.level.color());
+        context.insert("channel", channel);
+        context.insert(
+            "payload",
+            notification.payload.as_ref().unwrap_or(&Value::Null),
+        );
+        Ok(context)
+    }
+}
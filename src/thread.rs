@@ -0,0 +1,98 @@
+use crate::notification::Notification;
+use crate::store;
+use anyhow::Result;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const STATE_FILE: &str = "threads.json";
+
+/// 会话设置 - 相关通知作为首条消息的回复发送
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ThreadingConfig {
+    /// 没有指定会话键时按事件名归入会话
+    #[serde(default)]
+    pub by_event: bool,
+    /// 会话空闲超过多少小时后发起新会话
+    #[serde(default = "default_idle_hours")]
+    pub idle_hours: i64,
+}
+
+impl Default for ThreadingConfig {
+    fn default() -> Self {
+        Self {
+            by_event: false,
+            idle_hours: default_idle_hours(),
+        }
+    }
+}
+
+fn default_idle_hours() -> i64 {
+    24
+}
+
+impl ThreadingConfig {
+    /// 通知所属会话的键，None表示不归入会话
+    pub fn key(&self, notification: &Notification) -> Option<String> {
+        notification
+            .thread_key
+            .clone()
+            .or_else(|| self.by_event.then(|| notification.event.clone()))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct ThreadEntry {
+    /// 会话首条消息的ID
+    message_id: String,
+    /// 最后一次发送的时间（Unix时间戳）
+    last_at: i64,
+}
+
+/// 各渠道会话首条消息的记录，跨进程持久化
+pub struct ThreadStore {
+    idle_secs: i64,
+    threads: HashMap<String, ThreadEntry>,
+}
+
+impl ThreadStore {
+    pub fn load(config: &ThreadingConfig) -> Result<Self> {
+        Ok(Self {
+            idle_secs: config.idle_hours * 3600,
+            threads: store::load(STATE_FILE)?,
+        })
+    }
+
+    fn entry_key(channel: &str, key: &str) -> String {
+        format!("{}/{}", channel, key)
+    }
+
+    /// 未过期的会话首条消息ID
+    pub fn root(&self, channel: &str, key: &str) -> Option<String> {
+        let now = Local::now().timestamp();
+        self.threads
+            .get(&Self::entry_key(channel, key))
+            .filter(|entry| now - entry.last_at < self.idle_secs)
+            .map(|entry| entry.message_id.clone())
+    }
+
+    /// 记录会话首条消息并刷新活跃时间
+    pub fn touch(&mut self, channel: &str, key: &str, message_id: String) {
+        self.threads.insert(
+            Self::entry_key(channel, key),
+            ThreadEntry {
+                message_id,
+                last_at: Local::now().timestamp(),
+            },
+        );
+    }
+
+    /// 保存时丢弃已过期的会话
+    pub fn save(&mut self) -> Result<()> {
+        let now = Local::now().timestamp();
+        let idle_secs = self.idle_secs;
+        self.threads
+            .retain(|_, entry| now - entry.last_at < idle_secs);
+        store::save(STATE_FILE, &self.threads)
+    }
+}
@@ -0,0 +1,116 @@
+use crate::notification::Notification;
+use crate::routing::glob_match;
+use crate::store;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const STATE_FILE: &str = "throttle.json";
+
+/// 按事件限流 - 每个渠道在窗口内最多发送 `max` 条，超出的计数并在下一条中注明，
+/// 防止CI反复失败时刷屏
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ThrottleRule {
+    /// 事件名通配符，匹配的事件共用同一限额
+    pub event: String,
+    /// 每个渠道在窗口内最多发送的条数
+    pub max: usize,
+    #[serde(default = "default_window_minutes")]
+    pub window_minutes: i64,
+}
+
+fn default_window_minutes() -> i64 {
+    60
+}
+
+impl ThrottleRule {
+    pub fn matches(&self, notification: &Notification) -> bool {
+        glob_match(&self.event, &notification.event)
+    }
+}
+
+/// 规则在某个渠道上的发送记录
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Counter {
+    /// 窗口内已发送的时间戳
+    sent: Vec<i64>,
+    /// 上次发送后被限流的条数
+    #[serde(default)]
+    suppressed: usize,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ThrottleStore {
+    /// 键为 `事件通配符 渠道`
+    counters: HashMap<String, Counter>,
+}
+
+impl ThrottleStore {
+    /// 持有期间完成读取、计数和写入，并发的hook不会超出限额
+    pub fn lock() -> Result<store::Lock> {
+        store::lock(STATE_FILE)
+    }
+
+    pub fn load() -> Result<Self> {
+        store::load(STATE_FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        store::save(STATE_FILE, self)
+    }
+
+    /// 记录一次发往 `channel` 的通知：超出限额时返回 `None`，否则返回此前被限流的条数
+    pub fn admit(&mut self, rule: &ThrottleRule, channel: &str, now: i64) -> Option<usize> {
+        let counter = self
+            .counters
+            .entry(format!("{} {}", rule.event, channel))
+            .or_default();
+        counter
+            .sent
+            .retain(|at| now - at < rule.window_minutes * 60);
+        if counter.sent.len() >= rule.max {
+            counter.suppressed += 1;
+            return None;
+        }
+        counter.sent.push(now);
+        Some(std::mem::take(&mut counter.suppressed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notification::Level;
+
+    fn rule() -> ThrottleRule {
+        ThrottleRule {
+            event: "ci_*".to_string(),
+            max: 2,
+            window_minutes: 10,
+        }
+    }
+
+    #[test]
+    fn rule_matches_event_glob() {
+        let rule = rule();
+        assert!(rule.matches(&Notification::new("ci_failure", "t", "c", Level::Critical)));
+        assert!(!rule.matches(&Notification::new("stop", "t", "c", Level::Info)));
+    }
+
+    #[test]
+    fn admits_up_to_max_and_reports_suppressed_count() {
+        let rule = rule();
+        let mut store = ThrottleStore::default();
+        assert_eq!(store.admit(&rule, "teams", 0), Some(0));
+        assert_eq!(store.admit(&rule, "teams", 10), Some(0));
+        assert_eq!(store.admit(&rule, "teams", 20), None);
+        assert_eq!(store.admit(&rule, "teams", 30), None);
+        // 其他渠道有自己的限额
+        assert_eq!(store.admit(&rule, "feishu", 30), Some(0));
+
+        // 第一条移出窗口后放行，并带上期间被限流的条数
+        assert_eq!(store.admit(&rule, "teams", 600), Some(2));
+        assert_eq!(store.admit(&rule, "teams", 605), None);
+        assert_eq!(store.admit(&rule, "teams", 610), Some(1));
+    }
+}
@@ -0,0 +1,118 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+
+/// 解析时长，例如 `30m`、`2h`、`1d`、`1h30m`、`45s`
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let invalid = || anyhow::anyhow!("Invalid duration '{}' (expected e.g. 30s, 10m, 2h, 1d)", s);
+
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let n: i64 = number.parse().map_err(|_| invalid())?;
+        number.clear();
+        total += match c {
+            's' => Duration::seconds(n),
+            'm' => Duration::minutes(n),
+            'h' => Duration::hours(n),
+            'd' => Duration::days(n),
+            'w' => Duration::weeks(n),
+            _ => return Err(invalid()),
+        };
+    }
+
+    // 纯数字按秒处理；单位后面不能跟多余数字
+    if !number.is_empty() {
+        if total.is_zero() {
+            total = Duration::seconds(number.parse().map_err(|_| invalid())?);
+        } else {
+            return Err(invalid());
+        }
+    }
+
+    if s.is_empty() {
+        return Err(invalid());
+    }
+    Ok(total)
+}
+
+/// 解析本地时间，支持 `2024-06-01T09:00`、`2024-06-01 09:00:00`、RFC 3339、
+/// `2024-06-01`（当天0点）和 `09:00`（今天，已过则为明天）
+pub fn parse_datetime(s: &str) -> Result<DateTime<Local>> {
+    let s = s.trim();
+
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Ok(dt.with_timezone(&Local));
+    }
+
+    for format in [
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%d %H:%M:%S",
+    ] {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(s, format) {
+            return local(naive, s);
+        }
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return local(date.and_time(NaiveTime::MIN), s);
+    }
+
+    for format in ["%H:%M", "%H:%M:%S"] {
+        if let Ok(time) = NaiveTime::parse_from_str(s, format) {
+            let now = Local::now();
+            let today = local(now.date_naive().and_time(time), s)?;
+            return Ok(if today > now {
+                today
+            } else {
+                today + Duration::days(1)
+            });
+        }
+    }
+
+    Err(anyhow::anyhow!(
+        "Invalid time '{}' (expected e.g. 2024-06-01T09:00 or 09:00)",
+        s
+    ))
+}
+
+fn local(naive: NaiveDateTime, original: &str) -> Result<DateTime<Local>> {
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .ok_or_else(|| anyhow::anyhow!("Time '{}' does not exist in the local timezone", original))
+}
+
+/// 解析起始时间：时长表示多久之前（如 `1h`），否则按本地时间解析，返回Unix时间戳
+pub fn parse_since(s: &str) -> Result<i64> {
+    match parse_duration(s) {
+        Ok(duration) => Ok((Local::now() - duration).timestamp()),
+        Err(_) => Ok(parse_datetime(s)?.timestamp()),
+    }
+}
+
+/// 把时长格式化为 `parse_duration` 的写法，例如 `1h23m`、`4m5s`、`45s`
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.num_seconds().max(0);
+    let (h, m, s) = (secs / 3600, secs % 3600 / 60, secs % 60);
+    match (h, m) {
+        (0, 0) => format!("{}s", s),
+        (0, _) => format!("{}m{}s", m, s),
+        _ => format!("{}h{}m", h, m),
+    }
+}
+
+/// 把Unix时间戳格式化为本地时间
+pub fn format_timestamp(ts: i64) -> String {
+    Local
+        .timestamp_opt(ts, 0)
+        .single()
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| ts.to_string())
+}
@@ -0,0 +1,385 @@
+use crate::pricing::{self, ModelPrice};
+use crate::timeutil;
+use anyhow::Result;
+use chrono::{DateTime, Duration, FixedOffset};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::io::{BufRead, BufReader};
+use std::ops::AddAssign;
+use std::path::Path;
+
+/// 摘要中最多列出的文件和命令数
+const MAX_ITEMS: usize = 10;
+/// 单条命令的最大字符数
+const MAX_COMMAND_CHARS: usize = 80;
+/// 统计中单独列出的工具种类数
+const MAX_TOOL_TYPES: usize = 5;
+
+/// 最后一轮对话的摘要：最终回复、改动的文件和执行的命令
+#[derive(Debug, Default, Clone)]
+pub struct TurnSummary {
+    pub last_message: Option<String>,
+    pub files: Vec<String>,
+    pub commands: Vec<String>,
+}
+
+impl TurnSummary {
+    pub fn is_empty(&self) -> bool {
+        self.last_message.is_none() && self.files.is_empty() && self.commands.is_empty()
+    }
+}
+
+/// 逐条读取Claude Code的会话记录（JSONL），写入中途的行或无法解析的行直接跳过
+fn entries(path: &Path) -> Result<impl Iterator<Item = Value>> {
+    let file = std::fs::File::open(path)?;
+    Ok(BufReader::new(file)
+        .lines()
+        .map_while(|line| line.ok())
+        .filter_map(|line| serde_json::from_str(&line).ok()))
+}
+
+/// 汇总会话记录中最后一次用户提问之后的内容
+pub fn summarize(path: &Path) -> Result<TurnSummary> {
+    let mut summary = TurnSummary::default();
+    for entry in entries(path)? {
+        // 子代理的记录不计入主会话
+        if entry["isSidechain"].as_bool() == Some(true) {
+            continue;
+        }
+        match entry["type"].as_str() {
+            Some("user") if is_prompt(&entry["message"]["content"]) => {
+                summary = TurnSummary::default();
+            }
+            Some("assistant") => {
+                let Some(blocks) = entry["message"]["content"].as_array() else {
+                    continue;
+                };
+                for block in blocks {
+                    match block["type"].as_str() {
+                        Some("text") => {
+                            if let Some(text) = block["text"].as_str().map(str::trim) {
+                                if !text.is_empty() {
+                                    summary.last_message = Some(text.to_string());
+                                }
+                            }
+                        }
+                        Some("tool_use") => record_tool(&mut summary, block),
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(summary)
+}
+
+/// 用户输入的提问（而不是工具结果）开始新的一轮
+fn is_prompt(content: &Value) -> bool {
+    match content {
+        Value::String(_) => true,
+        Value::Array(blocks) => blocks
+            .iter()
+            .any(|block| block["type"].as_str() == Some("text")),
+        _ => false,
+    }
+}
+
+fn record_tool(summary: &mut TurnSummary, block: &Value) {
+    let input = &block["input"];
+    match block["name"].as_str() {
+        Some("Edit" | "MultiEdit" | "Write" | "NotebookEdit") => {
+            let path = input["file_path"]
+                .as_str()
+                .or_else(|| input["notebook_path"].as_str());
+            if let Some(path) = path {
+                if !summary.files.iter().any(|f| f == path) {
+                    summary.files.push(path.to_string());
+                }
+            }
+        }
+        Some("Bash") => {
+            if let Some(command) = input["command"].as_str() {
+                summary.commands.push(command.to_string());
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 把摘要排成通知正文；文件路径在 `cwd` 下时显示相对路径
+pub fn format(summary: &TurnSummary, cwd: Option<&str>) -> String {
+    let strings = crate::i18n::strings();
+    let mut sections = Vec::new();
+    if let Some(message) = &summary.last_message {
+        sections.push(message.clone());
+    }
+    let mut changes = Vec::new();
+    if !summary.files.is_empty() {
+        let files = summary.files.iter().map(|file| {
+            let relative = cwd
+                .and_then(|cwd| Path::new(file).strip_prefix(cwd).ok())
+                .map(|p| p.display().to_string());
+            relative.unwrap_or_else(|| file.clone())
+        });
+        changes.push(format!(
+            "**{}** {}",
+            strings.claude_files_changed,
+            list(files, summary.files.len())
+        ));
+    }
+    if !summary.commands.is_empty() {
+        let commands = summary
+            .commands
+            .iter()
+            .map(|command| short_command(command));
+        changes.push(format!(
+            "**{}** {}",
+            strings.claude_commands_run,
+            list(commands, summary.commands.len())
+        ));
+    }
+    if !changes.is_empty() {
+        sections.push(changes.join("\n"));
+    }
+    sections.join("\n\n")
+}
+
+fn list(items: impl Iterator<Item = String>, total: usize) -> String {
+    let mut shown: Vec<String> = items
+        .take(MAX_ITEMS)
+        .map(|item| format!("`{}`", item))
+        .collect();
+    if total > MAX_ITEMS {
+        shown.push(crate::i18n::fill(
+            crate::i18n::strings().claude_more,
+            &[("count", &(total - MAX_ITEMS))],
+        ));
+    }
+    shown.join(", ")
+}
+
+/// 只保留命令的第一行，过长时截断
+fn short_command(command: &str) -> String {
+    let first = command.trim().lines().next().unwrap_or_default();
+    let multiline = command.trim().lines().nth(1).is_some();
+    if first.chars().count() > MAX_COMMAND_CHARS {
+        let kept: String = first.chars().take(MAX_COMMAND_CHARS).collect();
+        format!("{}…", kept)
+    } else if multiline {
+        format!("{} …", first)
+    } else {
+        first.to_string()
+    }
+}
+
+/// 一次API调用或累计的token用量，字段与API的 `usage` 相同
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+pub struct Usage {
+    #[serde(default)]
+    pub input_tokens: u64,
+    #[serde(default)]
+    pub output_tokens: u64,
+    #[serde(default)]
+    pub cache_creation_input_tokens: u64,
+    #[serde(default)]
+    pub cache_read_input_tokens: u64,
+}
+
+impl AddAssign for Usage {
+    fn add_assign(&mut self, other: Self) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_input_tokens += other.cache_creation_input_tokens;
+        self.cache_read_input_tokens += other.cache_read_input_tokens;
+    }
+}
+
+impl Usage {
+    /// 输入中命中缓存的比例
+    pub fn cache_hit_rate(&self) -> Option<f64> {
+        let total =
+            self.input_tokens + self.cache_creation_input_tokens + self.cache_read_input_tokens;
+        (total > 0).then(|| self.cache_read_input_tokens as f64 / total as f64)
+    }
+}
+
+/// 整个会话（包括子代理）按模型累计的用量
+pub fn usage(path: &Path) -> Result<BTreeMap<String, Usage>> {
+    let mut by_model: BTreeMap<String, Usage> = BTreeMap::new();
+    // 一条回复的每个内容块单独成行，用量重复出现，按消息ID只计一次
+    let mut seen = HashSet::new();
+    for entry in entries(path)? {
+        if entry["type"].as_str() != Some("assistant") {
+            continue;
+        }
+        let message = &entry["message"];
+        let model = message["model"].as_str().unwrap_or_default();
+        // 本地生成的消息（`<synthetic>`）没有调用API
+        if model.is_empty() || model.starts_with('<') {
+            continue;
+        }
+        if let Some(id) = message["id"].as_str() {
+            if !seen.insert(id.to_string()) {
+                continue;
+            }
+        }
+        let Ok(usage) = Usage::deserialize(&message["usage"]) else {
+            continue;
+        };
+        *by_model.entry(model.to_string()).or_default() += usage;
+    }
+    Ok(by_model)
+}
+
+/// 用量和估算费用；没有单价的模型不计入费用
+pub fn format_usage(by_model: &BTreeMap<String, Usage>, prices: &[ModelPrice]) -> String {
+    let strings = crate::i18n::strings();
+    let mut total = Usage::default();
+    let mut cost = 0.0;
+    let mut costs = Vec::new();
+    let mut unpriced = Vec::new();
+    for (model, usage) in by_model {
+        total += *usage;
+        match pricing::price(prices, model) {
+            Some(price) => {
+                let model_cost = price.cost(usage);
+                cost += model_cost;
+                costs.push(format!("{} ${:.2}", model, model_cost));
+            }
+            None => unpriced.push(model.as_str()),
+        }
+    }
+
+    let mut lines = vec![format!(
+        "**{}** {}",
+        strings.usage_tokens,
+        crate::i18n::fill(
+            strings.usage_breakdown,
+            &[
+                ("input", &compact(total.input_tokens)),
+                ("output", &compact(total.output_tokens)),
+                ("cache_read", &compact(total.cache_read_input_tokens)),
+                ("cache_write", &compact(total.cache_creation_input_tokens)),
+            ],
+        )
+    )];
+    if let Some(rate) = total.cache_hit_rate() {
+        lines.push(format!(
+            "**{}** {:.0}%",
+            strings.usage_cache_hits,
+            rate * 100.0
+        ));
+    }
+    if !costs.is_empty() {
+        // 有多个模型或部分模型没有单价时列出各模型的费用
+        let detail = if costs.len() > 1 || !unpriced.is_empty() {
+            format!(" ({})", costs.join(", "))
+        } else {
+            String::new()
+        };
+        lines.push(format!(
+            "**{}** ~${:.2}{}",
+            strings.usage_cost, cost, detail
+        ));
+    }
+    if !unpriced.is_empty() {
+        lines.push(crate::i18n::fill(
+            strings.usage_unpriced,
+            &[("models", &unpriced.join(", "))],
+        ));
+    }
+    lines.join("\n")
+}
+
+/// 整个会话的统计：时长、各工具的调用次数、编辑过的文件和执行的命令数
+#[derive(Debug, Default, Clone)]
+pub struct SessionStats {
+    pub started_at: Option<DateTime<FixedOffset>>,
+    pub ended_at: Option<DateTime<FixedOffset>>,
+    pub tool_calls: BTreeMap<String, usize>,
+    pub files_edited: BTreeSet<String>,
+    pub commands: usize,
+}
+
+impl SessionStats {
+    pub fn duration(&self) -> Option<Duration> {
+        Some(self.ended_at? - self.started_at?)
+    }
+}
+
+/// 统计整个会话，包括子代理
+pub fn stats(path: &Path) -> Result<SessionStats> {
+    let mut stats = SessionStats::default();
+    for entry in entries(path)? {
+        if let Some(at) = entry["timestamp"]
+            .as_str()
+            .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+        {
+            stats.started_at.get_or_insert(at);
+            stats.ended_at = Some(at);
+        }
+        if entry["type"].as_str() != Some("assistant") {
+            continue;
+        }
+        let blocks = entry["message"]["content"].as_array().into_iter().flatten();
+        for block in blocks.filter(|block| block["type"].as_str() == Some("tool_use")) {
+            let Some(name) = block["name"].as_str() else {
+                continue;
+            };
+            *stats.tool_calls.entry(name.to_string()).or_default() += 1;
+            let mut summary = TurnSummary::default();
+            record_tool(&mut summary, block);
+            stats.files_edited.extend(summary.files);
+            stats.commands += summary.commands.len();
+        }
+    }
+    Ok(stats)
+}
+
+/// 会话统计的两列表格，卡片渠道显示为键值表
+pub fn format_stats(stats: &SessionStats) -> String {
+    let strings = crate::i18n::strings();
+    let mut rows = Vec::new();
+    if let Some(duration) = stats.duration() {
+        rows.push((strings.stats_duration, timeutil::format_duration(duration)));
+    }
+    let total: usize = stats.tool_calls.values().sum();
+    if total > 0 {
+        let mut by_count: Vec<(&String, &usize)> = stats.tool_calls.iter().collect();
+        by_count.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let mut top: Vec<String> = by_count
+            .iter()
+            .take(MAX_TOOL_TYPES)
+            .map(|(name, count)| format!("{} {}", name, count))
+            .collect();
+        if by_count.len() > MAX_TOOL_TYPES {
+            let others: usize = by_count[MAX_TOOL_TYPES..].iter().map(|(_, c)| **c).sum();
+            top.push(crate::i18n::fill(
+                strings.stats_others,
+                &[("count", &others)],
+            ));
+        }
+        rows.push((
+            strings.stats_tool_calls,
+            format!("{} ({})", total, top.join(", ")),
+        ));
+    }
+    rows.push((
+        strings.stats_files_edited,
+        stats.files_edited.len().to_string(),
+    ));
+    rows.push((strings.stats_commands, stats.commands.to_string()));
+
+    crate::markdown::facts_table(&rows)
+}
+
+/// 12345 → 12.3k，1234567 → 1.2M
+fn compact(n: u64) -> String {
+    match n {
+        0..=999 => n.to_string(),
+        1_000..=999_999 => format!("{:.1}k", n as f64 / 1_000.0),
+        _ => format!("{:.1}M", n as f64 / 1_000_000.0),
+    }
+}
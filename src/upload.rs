@@ -0,0 +1,95 @@
+use crate::error::{NotifierError, Result};
+use crate::http::HttpClient;
+use crate::notification::new_id;
+use crate::notifiers::exchange;
+use crate::replay::{self, Outcome};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// 上传方式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum UploadMethod {
+    /// multipart表单上传（粘贴服务，如 0x0.st）
+    #[default]
+    Post,
+    /// 请求体为文件内容（S3预签名地址、WebDAV等）
+    Put,
+}
+
+/// 附件上传目标，不能直接上传文件的渠道在消息中附上链接
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UploadConfig {
+    /// 上传地址，`{name}` 替换为带随机前缀的文件名
+    pub url: String,
+    #[serde(default)]
+    pub method: UploadMethod,
+    /// multipart表单中文件字段名
+    #[serde(default = "default_field")]
+    pub field: String,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+    /// 链接模板，`{name}` 同上；不设置时从响应中取
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+    /// 响应为JSON时链接所在的JSON Pointer（如 `/data/url`）；不设置时整个响应体即为链接
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_field: Option<String>,
+}
+
+fn default_field() -> String {
+    "file".to_string()
+}
+
+impl UploadConfig {
+    /// 上传文件并返回可分享的链接
+    pub async fn upload(&self, client: &HttpClient, path: &Path) -> Result<String> {
+        let data = std::fs::read(path).map_err(|e| {
+            NotifierError::ConfigError(format!("Cannot read {}: {}", path.display(), e))
+        })?;
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("attachment");
+        // 随机前缀避免同名文件互相覆盖
+        let name = format!("{}-{}", new_id(), file_name);
+        let url = self.url.replace("{name}", &name);
+
+        let mut request = match self.method {
+            UploadMethod::Post => {
+                let part = reqwest::multipart::Part::bytes(data).file_name(file_name.to_string());
+                client
+                    .inner
+                    .post(&url)
+                    .multipart(reqwest::multipart::Form::new().part(self.field.clone(), part))
+            }
+            UploadMethod::Put => client.inner.put(&url).body(data),
+        };
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+
+        let summary = json!({ "file": name });
+        let (status, body) = match exchange(&url, &summary, request.send()).await? {
+            Outcome::Response { status, body, .. } => (status, body),
+            Outcome::Error { error, message } => return Err(replay::error(&error, message)),
+        };
+        if !(200..300).contains(&status) {
+            return Err(NotifierError::HttpStatus { status, body });
+        }
+
+        if let Some(link) = &self.link {
+            return Ok(link.replace("{name}", &name));
+        }
+        let link = match &self.link_field {
+            Some(pointer) => serde_json::from_str::<Value>(&body)
+                .ok()
+                .and_then(|v| v.pointer(pointer).and_then(Value::as_str).map(String::from)),
+            None => Some(body.trim().to_string()),
+        };
+        link.filter(|l| !l.is_empty())
+            .ok_or_else(|| NotifierError::Network(format!("No link in upload response: {}", body)))
+    }
+}
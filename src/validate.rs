@@ -0,0 +1,938 @@
+use crate::color::FEISHU_TEMPLATES;
+use crate::config::{Config, ConfigFormat};
+use crate::env;
+use crate::error::NotifierError;
+use crate::extract;
+use crate::git;
+use crate::http::{self, HttpConfig};
+use crate::migrate;
+use crate::notification::Level;
+use crate::oncall::OnCall;
+use crate::provider::{self, Finding};
+use crate::routing;
+use crate::secret::{is_exec_channel, secret_command, KEYRING_PREFIX};
+use anyhow::Result;
+use chrono::{NaiveDate, NaiveTime, Weekday};
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+pub const BUILTIN_CHANNELS: [&str; 3] = ["teams", "feishu", "wechat"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        })
+    }
+}
+
+/// 一条校验结果，`path` 为点分字段路径，`line` 为在配置文件中大致的行号
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.severity)?;
+        if !self.path.is_empty() {
+            write!(f, "{}", self.path)?;
+            if let Some(line) = self.line {
+                write!(f, " (line {})", line)?;
+            }
+            write!(f, ": ")?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+struct Report<'a> {
+    content: &'a str,
+    /// 来自密钥环或外部命令的字段，运行前无法检查其值
+    secret_paths: HashSet<String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Report<'_> {
+    fn push(&mut self, severity: Severity, path: impl Into<String>, message: impl Into<String>) {
+        let path = path.into();
+        self.diagnostics.push(Diagnostic {
+            severity,
+            line: line_of(self.content, &path),
+            path,
+            message: message.into(),
+        });
+    }
+
+    fn error(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.push(Severity::Error, path, message);
+    }
+
+    fn warning(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.push(Severity::Warning, path, message);
+    }
+
+    fn finding(&mut self, path: &str, finding: Option<Finding>) {
+        match finding {
+            Some(Finding::Error(message)) => self.error(path, message),
+            Some(Finding::Warning(message)) => self.warning(path, message),
+            None => {}
+        }
+    }
+}
+
+/// 检查配置文件：语法、未知键、缺少的字段、webhook地址、引用的渠道、静默时段等
+pub fn validate(path: &Path) -> Result<Vec<Diagnostic>> {
+    if !path.exists() {
+        return Ok(vec![Diagnostic {
+            severity: Severity::Warning,
+            path: String::new(),
+            line: None,
+            message: format!(
+                "{} does not exist; defaults and environment overrides are used",
+                path.display()
+            ),
+        }]);
+    }
+
+    let content = Config::read_file(path)?;
+    let format = ConfigFormat::from_path(path).unwrap_or(ConfigFormat::Json);
+    Ok(validate_content(&content, format))
+}
+
+/// 检查指定格式的配置内容
+pub fn validate_content(content: &str, format: ConfigFormat) -> Vec<Diagnostic> {
+    let mut report = Report {
+        content,
+        secret_paths: HashSet::new(),
+        diagnostics: Vec::new(),
+    };
+
+    let mut value = match format.parse(content) {
+        Ok(value) => value,
+        Err(e) => {
+            report.error("", format!("Syntax error: {}", e));
+            return report.diagnostics;
+        }
+    };
+
+    if let Err(e) = migrate::migrate(&mut value) {
+        report.error("version", format!("{:#}", e));
+    }
+    if let Err(e) = env::expand_vars(&mut value) {
+        report.error("", format!("{:#}", e));
+    }
+    env::apply_overrides(&mut value, std::env::vars());
+    mark_secrets(&mut value, String::new(), &mut report.secret_paths);
+
+    let mut unknown = Vec::new();
+    let config = match Config::from_value(value, |path| unknown.push(path)) {
+        Ok(config) => Some(config),
+        Err(e) => {
+            let path = e.path().to_string();
+            let path = if path == "." { String::new() } else { path };
+            report.error(path, e.into_inner().to_string());
+            None
+        }
+    };
+    for path in unknown {
+        // serde_ignored 用 `?` 表示Option层级
+        let path = path
+            .split('.')
+            .filter(|s| *s != "?")
+            .collect::<Vec<_>>()
+            .join(".");
+        report.error(path, "Unknown key (typo?); it is ignored");
+    }
+
+    if let Some(config) = config {
+        check_channels(&config, &mut report);
+        check_references(&config, &mut report);
+        check_quiet_hours(&config, &mut report);
+        check_rules(&config, &mut report);
+        check_upload(&config, &mut report);
+        check_callbacks(&config, &mut report);
+        check_grpc(&config, &mut report);
+        check_approvals(&config, &mut report);
+        for (index, price) in config.pricing.iter().enumerate() {
+            let prices = [price.input, price.output]
+                .into_iter()
+                .chain(price.cache_write)
+                .chain(price.cache_read);
+            if prices.into_iter().any(|p| !p.is_finite() || p < 0.0) {
+                report.error(
+                    format!("pricing[{}]", index),
+                    "Prices must be non-negative numbers (USD per million tokens)",
+                );
+            }
+        }
+        if let Some(progress) = &config.progress {
+            if progress.max_items == 0 {
+                report.error("progress.max_items", "Must be at least 1");
+            }
+            if progress.idle_minutes <= 0 {
+                report.error(
+                    "progress.idle_minutes",
+                    "Must be a positive number of minutes",
+                );
+            }
+        }
+        if let Some(link) = config.git.as_ref().and_then(|git| git.link.as_deref()) {
+            check_git_link(link, &mut report);
+        }
+        check_levels(&config, &mut report);
+        check_colors(&config, &mut report);
+        check_severity(&config, &mut report);
+        check_extract(&config, &mut report);
+        check_users(&config, &mut report);
+        check_on_call(&config, &mut report);
+        if config.threading.idle_hours <= 0 {
+            report.error("threading.idle_hours", "Must be a positive number of hours");
+        }
+        if config.burst.window_secs <= 0 {
+            report.error("burst.window_secs", "Must be a positive number of seconds");
+        }
+        if config.burst.max_wait_secs < config.burst.window_secs {
+            report.warning(
+                "burst.max_wait_secs",
+                "Shorter than window_secs; collapsed notifications are sent before the burst can end",
+            );
+        }
+        if config.storm.max_per_minute == 0 {
+            report.error("storm.max_per_minute", "Must be at least 1");
+        }
+        if config.storm.summary_minutes <= 0 {
+            report.error(
+                "storm.summary_minutes",
+                "Must be a positive number of minutes",
+            );
+        }
+        for (index, rule) in config.throttle.iter().enumerate() {
+            if rule.window_minutes <= 0 {
+                report.error(
+                    format!("throttle[{}].window_minutes", index),
+                    "Must be a positive number of minutes",
+                );
+            }
+            if rule.max == 0 {
+                report.warning(
+                    format!("throttle[{}].max", index),
+                    "Matching notifications are never sent (except with --force)",
+                );
+            }
+        }
+        for (index, policy) in config.escalations.iter().enumerate() {
+            if policy.after_minutes <= 0 {
+                report.error(
+                    format!("escalations[{}].after_minutes", index),
+                    "Must be a positive number of minutes",
+                );
+            }
+        }
+    }
+
+    report.diagnostics
+}
+
+/// 检查后以 `format` 保存（位置同 `Config::save_as`），有错误时不写入；返回文件路径和警告
+pub fn save_checked(config: &Config, format: ConfigFormat) -> Result<(PathBuf, Vec<Diagnostic>)> {
+    let (path, format) = Config::target(format)?;
+    let diagnostics = validate_content(&format.serialize(config)?, format);
+    let errors: Vec<String> = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .map(|d| d.to_string())
+        .collect();
+    if !errors.is_empty() {
+        return Err(NotifierError::ConfigError(format!(
+            "The configuration is invalid; nothing was written:\n{}",
+            errors.join("\n")
+        ))
+        .into());
+    }
+    config.save_as(format)?;
+    Ok((path, diagnostics))
+}
+
+/// 把 `{"command": ...}` 替换为占位字符串并记录路径，校验时不执行命令
+fn mark_secrets(value: &mut Value, path: String, secrets: &mut HashSet<String>) {
+    if secret_command(value).is_some() && !is_exec_channel(&path) {
+        *value = Value::String(String::new());
+        secrets.insert(path);
+        return;
+    }
+    match value {
+        Value::String(s) if s.starts_with(KEYRING_PREFIX) => {
+            secrets.insert(path);
+        }
+        Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                mark_secrets(item, join(&path, key), secrets);
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                mark_secrets(item, format!("{}[{}]", path, index), secrets);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn check_webhook(report: &mut Report, path: &str, url: &str, check: fn(&str) -> Option<Finding>) {
+    if report.secret_paths.contains(path) {
+        return;
+    }
+    if url.is_empty() {
+        report.warning(path, "Webhook is empty; the channel is skipped");
+        return;
+    }
+    report.finding(path, check(url));
+}
+
+fn check_http(report: &mut Report, path: &str, http: &HttpConfig) {
+    check_certs(report, path, http);
+    for (key, secs) in [
+        ("timeout_secs", http.timeout_secs),
+        ("connect_timeout_secs", http.connect_timeout_secs),
+    ] {
+        if let Some(secs) = secs.filter(|s| !(s.is_finite() && *s > 0.0)) {
+            report.error(
+                format!("{}.{}", path, key),
+                format!("Timeout must be a positive number of seconds, got {}", secs),
+            );
+        }
+    }
+    let Some(proxy) = http.proxy.as_deref() else {
+        return;
+    };
+    let path = format!("{}.proxy", path);
+    if proxy == http::DIRECT || report.secret_paths.contains(&path) {
+        return;
+    }
+    match reqwest::Url::parse(proxy) {
+        Ok(url)
+            if matches!(url.scheme(), "http" | "https" | "socks5" | "socks5h")
+                && url.has_host() => {}
+        _ => report.error(
+            path,
+            format!(
+                "Invalid proxy '{}' (expected http://, https://, socks5:// or \"direct\")",
+                proxy
+            ),
+        ),
+    }
+}
+
+fn check_certs(report: &mut Report, path: &str, http: &HttpConfig) {
+    if http.client_cert.is_some() != http.client_key.is_some() {
+        report.error(
+            format!("{}.client_cert", path),
+            "client_cert and client_key must be set together",
+        );
+    }
+    let Ok(dir) = Config::config_dir() else {
+        return;
+    };
+    for (key, file) in [
+        ("ca_cert", &http.ca_cert),
+        ("client_cert", &http.client_cert),
+        ("client_key", &http.client_key),
+    ] {
+        if let Some(file) = file.as_ref().filter(|f| !dir.join(f).is_file()) {
+            report.error(
+                format!("{}.{}", path, key),
+                format!("File {} does not exist", dir.join(file).display()),
+            );
+        }
+    }
+}
+
+fn check_channels(config: &Config, report: &mut Report) {
+    let channels = &config.channels;
+    check_http(report, "http", &config.http);
+    if let Some(teams) = channels.teams.as_ref().filter(|c| c.enabled) {
+        check_webhook(
+            report,
+            "channels.teams.webhook",
+            &teams.webhook,
+            provider::teams_webhook,
+        );
+        check_http(report, "channels.teams.http", &teams.http);
+    }
+    if let Some(feishu) = channels.feishu.as_ref().filter(|c| c.enabled) {
+        check_webhook(
+            report,
+            "channels.feishu.webhook",
+            &feishu.webhook,
+            provider::feishu_webhook,
+        );
+        if feishu.app_id.is_some() != feishu.app_secret.is_some() {
+            report.error(
+                "channels.feishu.app_id",
+                "app_id and app_secret must be set together",
+            );
+        }
+        if feishu.chat_id.is_some() && feishu.app_id.is_none() {
+            report.warning(
+                "channels.feishu.chat_id",
+                "chat_id is only used together with app_id and app_secret",
+            );
+        }
+        check_http(report, "channels.feishu.http", &feishu.http);
+    }
+    if let Some(wechat) = channels.wechat.as_ref().filter(|c| c.enabled) {
+        check_http(report, "channels.wechat.http", &wechat.http);
+        let path = "channels.wechat.key";
+        if !report.secret_paths.contains(path) {
+            if wechat.key.is_empty() {
+                report.warning(path, "Key is empty; the channel is skipped");
+            } else {
+                report.finding(path, provider::wechat_key(&wechat.service, &wechat.key));
+            }
+        }
+    }
+    for (name, exec) in &channels.exec {
+        let path = format!("channels.exec.{}.command", name);
+        if exec.enabled && exec.command.is_empty() && !report.secret_paths.contains(&path) {
+            report.warning(path, "Command is empty; the channel is skipped");
+        }
+    }
+}
+
+/// 已配置的渠道名及是否启用
+pub fn configured_channels(config: &Config) -> Vec<(String, bool)> {
+    let channels = &config.channels;
+    let mut configured = Vec::new();
+    if let Some(teams) = &channels.teams {
+        configured.push(("teams".to_string(), teams.enabled));
+    }
+    if let Some(feishu) = &channels.feishu {
+        configured.push(("feishu".to_string(), feishu.enabled));
+    }
+    if let Some(wechat) = &channels.wechat {
+        configured.push(("wechat".to_string(), wechat.enabled));
+    }
+    for (name, exec) in &channels.exec {
+        configured.push((name.clone(), exec.enabled));
+    }
+    #[cfg(feature = "wasm-plugins")]
+    if let Ok(dir) = Config::config_dir() {
+        for plugin in crate::notifiers::wasm::discover(&dir.join("plugins")) {
+            configured.push((plugin.name().to_string(), true));
+        }
+    }
+    configured
+}
+
+fn check_references(config: &Config, report: &mut Report) {
+    let configured = configured_channels(config);
+    let check = |report: &mut Report, path: String, spec: &str| {
+        for channel in routing::parse_chain(spec) {
+            match configured.iter().find(|(name, _)| *name == channel) {
+                // 内置渠道未配置只是不发送，其他名称多半是拼写错误
+                None if BUILTIN_CHANNELS.contains(&channel.as_str()) => report.warning(
+                    path.clone(),
+                    format!("Channel '{}' is not configured", channel),
+                ),
+                None => report.error(
+                    path.clone(),
+                    format!("Channel '{}' is not configured", channel),
+                ),
+                Some((_, false)) => {
+                    report.warning(path.clone(), format!("Channel '{}' is disabled", channel))
+                }
+                Some(_) => {}
+            }
+        }
+    };
+
+    let mut events: Vec<_> = config.notifications.iter().collect();
+    events.sort();
+    for (event, channels) in events {
+        for (index, spec) in channels.iter().enumerate() {
+            check(report, format!("notifications.{}[{}]", event, index), spec);
+        }
+    }
+    for (rule, item) in config.rules.iter().enumerate() {
+        for (index, spec) in item.channels.iter().enumerate() {
+            check(report, format!("rules[{}].channels[{}]", rule, index), spec);
+        }
+    }
+    for (policy, item) in config.escalations.iter().enumerate() {
+        for (index, spec) in item.channels.iter().enumerate() {
+            check(
+                report,
+                format!("escalations[{}].channels[{}]", policy, index),
+                spec,
+            );
+        }
+        for (index, spec) in item.then.iter().enumerate() {
+            check(
+                report,
+                format!("escalations[{}].then[{}]", policy, index),
+                spec,
+            );
+        }
+    }
+    for (job, item) in config.recurring.iter().enumerate() {
+        for (index, spec) in item.channels.iter().flatten().enumerate() {
+            check(
+                report,
+                format!("recurring[{}].channels[{}]", job, index),
+                spec,
+            );
+        }
+    }
+    for channel in config.quiet_hours.channels.keys() {
+        check(report, format!("quiet_hours.channels.{}", channel), channel);
+    }
+    for (index, channel) in config.probe.iter().flat_map(|p| &p.fallback).enumerate() {
+        let path = format!("probe.fallback[{}]", index);
+        check(report, path.clone(), channel);
+        // 离线时只有本地命令渠道能送达
+        let local = config
+            .channels
+            .exec
+            .get(channel)
+            .is_some_and(|exec| !exec.remote);
+        if !local && configured.iter().any(|(name, _)| name == channel) {
+            report.warning(
+                path,
+                format!(
+                    "Channel '{}' needs the network and cannot deliver while offline",
+                    channel
+                ),
+            );
+        }
+    }
+}
+
+fn check_time(report: &mut Report, path: String, time: &str) {
+    // 比较时按字符串比较，必须是补零的 HH:MM
+    if time.len() != 5 || NaiveTime::parse_from_str(time, "%H:%M").is_err() {
+        report.error(
+            path,
+            format!("Invalid time '{}' (expected HH:MM, e.g. 08:00)", time),
+        );
+    }
+}
+
+fn check_upload(config: &Config, report: &mut Report) {
+    let Some(upload) = &config.upload else {
+        return;
+    };
+    if !report.secret_paths.contains("upload.url") {
+        let url = upload.url.replace("{name}", "name");
+        match reqwest::Url::parse(&url) {
+            Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => {}
+            _ => report.error("upload.url", format!("Invalid upload URL '{}'", upload.url)),
+        }
+    }
+    if let Some(pointer) = upload.link_field.as_deref().filter(|p| !p.starts_with('/')) {
+        report.error(
+            "upload.link_field",
+            format!("'{}' is not a JSON Pointer (e.g. /data/url)", pointer),
+        );
+    }
+}
+
+fn check_callbacks(config: &Config, report: &mut Report) {
+    let Some(callbacks) = &config.callbacks else {
+        return;
+    };
+    if callbacks.secret.is_empty() {
+        report.error(
+            "callbacks.secret",
+            "Must not be empty; it signs button callbacks",
+        );
+    }
+    if callbacks.listen.parse::<SocketAddr>().is_err() {
+        report.error(
+            "callbacks.listen",
+            format!(
+                "Invalid address '{}' (expected IP:PORT, e.g. 127.0.0.1:8787)",
+                callbacks.listen
+            ),
+        );
+    }
+    match &callbacks.public_url {
+        Some(url) => match reqwest::Url::parse(url) {
+            Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => {}
+            _ => report.error("callbacks.public_url", format!("Invalid URL '{}'", url)),
+        },
+        None => report.warning(
+            "callbacks",
+            "No public_url; Teams buttons cannot post clicks back",
+        ),
+    }
+}
+
+fn check_grpc(config: &Config, report: &mut Report) {
+    let Some(grpc) = &config.grpc else {
+        return;
+    };
+    if grpc.listen.parse::<SocketAddr>().is_err() {
+        report.error(
+            "grpc.listen",
+            format!(
+                "Invalid address '{}' (expected IP:PORT, e.g. 127.0.0.1:50051)",
+                grpc.listen
+            ),
+        );
+    }
+    if grpc.token.as_deref() == Some("") {
+        report.error("grpc.token", "Must not be empty; omit it to disable auth");
+    }
+    if !cfg!(feature = "grpc") {
+        report.warning(
+            "grpc",
+            "This build has no gRPC support (rebuild with --features grpc)",
+        );
+    }
+}
+
+fn check_approvals(config: &Config, report: &mut Report) {
+    let Some(approvals) = &config.approvals else {
+        return;
+    };
+    if config.callbacks.is_none() {
+        report.error(
+            "approvals",
+            "Needs `callbacks`; the Approve/Deny buttons are answered by `serve`",
+        );
+    }
+    if approvals.timeout_secs == 0 {
+        report.error(
+            "approvals.timeout_secs",
+            "Must be a positive number of seconds",
+        );
+    } else if approvals.timeout_secs >= 60 {
+        report.warning(
+            "approvals.timeout_secs",
+            "Make sure the hook's `timeout` in the Claude Code settings is longer (default 60 seconds)",
+        );
+    }
+}
+
+/// 内置级别或配置中的自定义级别
+fn is_level(config: &Config, name: &str) -> bool {
+    config.level(name).is_ok()
+}
+
+fn check_levels(config: &Config, report: &mut Report) {
+    for name in config.levels.keys() {
+        if name.parse::<Level>().is_ok() {
+            report.error(
+                format!("levels.{}", name),
+                "A custom level cannot reuse a built-in level name",
+            );
+        }
+    }
+    for (index, rule) in config.rules.iter().enumerate() {
+        for (position, level) in rule.levels.iter().enumerate() {
+            if !is_level(config, level) {
+                report.error(
+                    format!("rules[{}].levels[{}]", index, position),
+                    format!("Level '{}' is not defined", level),
+                );
+            }
+        }
+    }
+}
+
+fn check_git_link(link: &str, report: &mut Report) {
+    if !link.starts_with("http://") && !link.starts_with("https://") {
+        report.error("git.link", format!("'{}' is not an http(s) URL", link));
+    }
+    let mut rest = link;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 1..start + end];
+        if !git::PLACEHOLDERS.contains(&name) {
+            report.error(
+                "git.link",
+                format!(
+                    "Unknown placeholder {{{}}} (expected one of: {})",
+                    name,
+                    git::PLACEHOLDERS.join(", ")
+                ),
+            );
+        }
+        rest = &rest[start + end..];
+    }
+}
+
+fn check_extract(config: &Config, report: &mut Report) {
+    for (index, mapping) in config.extract.iter().enumerate() {
+        for (field, expr) in mapping.expressions() {
+            if let Err(e) = extract::check(expr) {
+                report.error(format!("extract[{}].{}", index, field), format!("{:#}", e));
+            }
+        }
+    }
+}
+
+fn check_severity(config: &Config, report: &mut Report) {
+    for (index, mapping) in config.severity.iter().enumerate() {
+        let path = format!("severity[{}]", index);
+        if !mapping.field.starts_with('/') {
+            report.error(
+                format!("{}.field", path),
+                format!("'{}' is not a JSON Pointer (e.g. /level)", mapping.field),
+            );
+        }
+        if mapping.scale.is_none() && mapping.map.is_empty() {
+            report.error(&path, "Needs a `scale` or a `map`");
+        }
+        let mut levels: Vec<(String, &String)> = mapping
+            .map
+            .iter()
+            .map(|(value, level)| (format!("{}.map.{}", path, value), level))
+            .collect();
+        levels.sort();
+        levels.extend(
+            mapping
+                .default
+                .iter()
+                .map(|level| (format!("{}.default", path), level)),
+        );
+        for (path, level) in levels {
+            if !is_level(config, level) {
+                report.error(path, format!("Level '{}' is not defined", level));
+            }
+        }
+    }
+}
+
+fn check_colors(config: &Config, report: &mut Report) {
+    let colors = &config.colors;
+    let is_hex = |color: &str| color.len() == 6 && color.chars().all(|c| c.is_ascii_hexdigit());
+    let configured = configured_channels(config);
+
+    let mut levels: Vec<_> = colors.levels.iter().collect();
+    levels.sort();
+    for (level, color) in levels {
+        let path = format!("colors.levels.{}", level);
+        if !is_level(config, level) {
+            report.warning(&path, format!("Level '{}' is not defined", level));
+        }
+        if !is_hex(color) {
+            report.error(
+                &path,
+                format!("'{}' is not a hex color (e.g. DC3545)", color),
+            );
+        } else if config.channels.feishu.is_some() && colors.get("feishu", level) == Some(color) {
+            // 飞书只接受模板名，通用的十六进制颜色对它无效
+            report.warning(
+                &path,
+                format!(
+                    "Feishu ignores hex colors; set colors.channels.feishu.{} to a template name",
+                    level
+                ),
+            );
+        }
+    }
+
+    let mut channels: Vec<_> = colors.channels.iter().collect();
+    channels.sort_by_key(|(channel, _)| channel.as_str());
+    for (channel, overrides) in channels {
+        if !configured.iter().any(|(name, _)| name == channel) {
+            report.warning(
+                format!("colors.channels.{}", channel),
+                format!("Channel '{}' is not configured", channel),
+            );
+        }
+        let mut overrides: Vec<_> = overrides.iter().collect();
+        overrides.sort();
+        for (level, color) in overrides {
+            let path = format!("colors.channels.{}.{}", channel, level);
+            if !is_level(config, level) {
+                report.warning(&path, format!("Level '{}' is not defined", level));
+            }
+            match channel.as_str() {
+                "feishu" if !FEISHU_TEMPLATES.contains(&color.as_str()) => report.error(
+                    &path,
+                    format!(
+                        "'{}' is not a Feishu template (one of {})",
+                        color,
+                        FEISHU_TEMPLATES.join(", ")
+                    ),
+                ),
+                "teams" if !is_hex(color) => report.error(
+                    &path,
+                    format!("'{}' is not a hex color (e.g. DC3545)", color),
+                ),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn check_users(config: &Config, report: &mut Report) {
+    for (name, user) in &config.users {
+        let path = format!("users.{}", name);
+        if user.is_empty() {
+            report.warning(
+                &path,
+                "User has no platform identities and is shown as plain text",
+            );
+        }
+        if let Some(open_id) = user.feishu.as_deref().filter(|id| !id.starts_with("ou_")) {
+            report.warning(
+                format!("{}.feishu", path),
+                format!("'{}' does not look like an open_id (ou_...)", open_id),
+            );
+        }
+    }
+}
+
+fn check_on_call(config: &Config, report: &mut Report) {
+    let channels = &config.channels;
+    let mut on_calls: Vec<(String, &OnCall)> = Vec::new();
+    if let Some(teams) = &channels.teams {
+        on_calls.push(("channels.teams.on_call".to_string(), &teams.on_call));
+    }
+    if let Some(feishu) = &channels.feishu {
+        on_calls.push(("channels.feishu.on_call".to_string(), &feishu.on_call));
+    }
+    for (name, exec) in &channels.exec {
+        on_calls.push((format!("channels.exec.{}.on_call", name), &exec.on_call));
+    }
+
+    for (path, on_call) in on_calls {
+        for (key, names) in [("users", &on_call.users), ("rotation", &on_call.rotation)] {
+            for (index, name) in names.iter().enumerate() {
+                if !config.users.contains_key(name) {
+                    report.warning(
+                        format!("{}.{}[{}]", path, key, index),
+                        format!(
+                            "'{}' is not defined under users and is shown as plain text",
+                            name
+                        ),
+                    );
+                }
+            }
+        }
+        match &on_call.rotation_start {
+            Some(date) if NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() => report.error(
+                format!("{}.rotation_start", path),
+                format!("Invalid date '{}' (expected YYYY-MM-DD)", date),
+            ),
+            None if on_call.rotation.len() > 1 => report.warning(
+                format!("{}.rotation_start", path),
+                "No rotation_start; the first user in the rotation is always on call",
+            ),
+            _ => {}
+        }
+        if on_call.rotation_days == Some(0) {
+            report.error(format!("{}.rotation_days", path), "Must be at least 1");
+        }
+    }
+}
+
+fn check_quiet_hours(config: &Config, report: &mut Report) {
+    let quiet = &config.quiet_hours;
+    check_time(report, "quiet_hours.start".to_string(), &quiet.start);
+    check_time(report, "quiet_hours.end".to_string(), &quiet.end);
+    for (channel, window) in &quiet.channels {
+        check_time(
+            report,
+            format!("quiet_hours.channels.{}.start", channel),
+            &window.start,
+        );
+        check_time(
+            report,
+            format!("quiet_hours.channels.{}.end", channel),
+            &window.end,
+        );
+    }
+    for (index, day) in quiet.days.iter().enumerate() {
+        if day.parse::<Weekday>().is_err() {
+            report.error(
+                format!("quiet_hours.days[{}]", index),
+                format!("Invalid weekday '{}' (expected e.g. sat, sunday)", day),
+            );
+        }
+    }
+    for (index, date) in quiet.holidays.iter().enumerate() {
+        if NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+            report.error(
+                format!("quiet_hours.holidays[{}]", index),
+                format!("Invalid date '{}' (expected YYYY-MM-DD)", date),
+            );
+        }
+    }
+    if let Some(calendar) = &quiet.holiday_calendar {
+        let exists = Config::config_dir().is_ok_and(|dir| dir.join(calendar).exists());
+        if !exists {
+            report.warning(
+                "quiet_hours.holiday_calendar",
+                format!("Calendar file '{}' not found", calendar),
+            );
+        }
+    }
+}
+
+fn check_rules(config: &Config, report: &mut Report) {
+    for (index, rule) in config.rules.iter().enumerate() {
+        if let Some(pattern) = &rule.content {
+            if let Err(e) = Regex::new(pattern) {
+                report.error(
+                    format!("rules[{}].content", index),
+                    format!("Invalid regex: {}", e),
+                );
+            }
+        }
+    }
+    for (index, job) in config.recurring.iter().enumerate() {
+        if let Err(e) = job.schedule() {
+            report.error(format!("recurring[{}].cron", index), format!("{:#}", e));
+        }
+    }
+}
+
+/// 按字段路径在配置文件中依次查找各级键，返回最后找到的行号（从1开始）
+fn line_of(content: &str, path: &str) -> Option<usize> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut found = None;
+    let mut from = 0;
+    for key in path
+        .split(['.', '[', ']'])
+        .filter(|key| !key.is_empty() && key.parse::<usize>().is_err())
+    {
+        let pattern = Regex::new(&format!(r"(^|[^\w-]){}([^\w-]|$)", regex::escape(key))).ok()?;
+        if let Some(offset) = lines[from..].iter().position(|line| pattern.is_match(line)) {
+            from += offset;
+            found = Some(from + 1);
+        }
+    }
+    found
+}
@@ -0,0 +1,85 @@
+use crate::i18n;
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// 一条通知中最多列出的匹配行
+const MAX_LINES: usize = 20;
+
+/// 跟踪文件新追加的行，文件被截断或轮转后从头读取新文件
+pub struct FileTail {
+    path: PathBuf,
+    offset: u64,
+    /// 尚未以换行结束的末尾部分
+    partial: Vec<u8>,
+    identity: Option<u64>,
+}
+
+impl FileTail {
+    /// `from_start` 为false时跳过已有内容，只读取之后追加的行
+    pub fn open(path: &Path, from_start: bool) -> Result<Self> {
+        let metadata = std::fs::metadata(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            offset: if from_start { 0 } else { metadata.len() },
+            partial: Vec::new(),
+            identity: identity(&metadata),
+        })
+    }
+
+    /// 上次读取后新增的完整行；文件暂时不存在（轮转中）时返回空
+    pub fn read_lines(&mut self) -> Result<Vec<String>> {
+        let Ok(mut file) = File::open(&self.path) else {
+            return Ok(Vec::new());
+        };
+        let metadata = file.metadata()?;
+        let identity = identity(&metadata);
+        if identity != self.identity || metadata.len() < self.offset {
+            self.identity = identity;
+            self.offset = 0;
+            self.partial.clear();
+        }
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut buf = Vec::new();
+        let read = file.read_to_end(&mut buf)?;
+        self.offset += read as u64;
+        self.partial.extend_from_slice(&buf);
+
+        let Some(end) = self.partial.iter().rposition(|b| *b == b'\n') else {
+            return Ok(Vec::new());
+        };
+        let complete: Vec<u8> = self.partial.drain(..=end).collect();
+        Ok(String::from_utf8_lossy(&complete)
+            .lines()
+            .map(|line| line.trim_end_matches('\r').to_string())
+            .collect())
+    }
+}
+
+/// 区分轮转后的新文件
+#[cfg(unix)]
+fn identity(metadata: &std::fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.ino())
+}
+
+#[cfg(not(unix))]
+fn identity(_metadata: &std::fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// 匹配行作为代码块，超出的只计数
+pub fn content(lines: &[String]) -> String {
+    let shown = &lines[..lines.len().min(MAX_LINES)];
+    let mut content = format!("```\n{}\n```", shown.join("\n"));
+    if lines.len() > shown.len() {
+        content.push('\n');
+        content.push_str(&i18n::fill(
+            i18n::strings().claude_more,
+            &[("count", &(lines.len() - shown.len()))],
+        ));
+    }
+    content
+}
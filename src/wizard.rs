@@ -0,0 +1,329 @@
+use crate::config::{
+    Config, ConfigFormat, FeishuConfig, TeamConfig, WechatConfig, WechatServiceType,
+};
+use crate::manager::NotificationManager;
+use crate::preset;
+use crate::provider::{self, Finding};
+use crate::validate;
+use anyhow::Result;
+use chrono::NaiveTime;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+/// 向导可以设置的渠道
+const CHANNELS: [&str; 3] = ["teams", "feishu", "wechat"];
+
+/// 测试失败后的选择
+enum OnFailure {
+    Retry,
+    Keep,
+    Skip,
+}
+
+/// 逐行提问的交互式配置向导
+pub struct Wizard<R, W> {
+    input: R,
+    output: W,
+}
+
+impl<R: BufRead, W: Write> Wizard<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Self { input, output }
+    }
+
+    /// 提问并读取一行，空行时使用默认值
+    fn ask(&mut self, question: &str, default: Option<&str>) -> Result<String> {
+        match default {
+            Some(default) => write!(self.output, "{} [{}]: ", question, default)?,
+            None => write!(self.output, "{}: ", question)?,
+        }
+        self.output.flush()?;
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 {
+            writeln!(self.output)?;
+            anyhow::bail!("Input ended before the setup was complete; nothing was written");
+        }
+        let answer = line.trim();
+        Ok(match (answer.is_empty(), default) {
+            (true, Some(default)) => default.to_string(),
+            _ => answer.to_string(),
+        })
+    }
+
+    /// 必须回答的问题，`check` 不通过时说明原因并重新提问
+    fn ask_valid(
+        &mut self,
+        question: &str,
+        default: Option<&str>,
+        check: impl Fn(&str) -> Result<(), String>,
+    ) -> Result<String> {
+        loop {
+            let answer = self.ask(question, default)?;
+            match check(&answer) {
+                Ok(()) => return Ok(answer),
+                Err(reason) => writeln!(self.output, "  {}", reason)?,
+            }
+        }
+    }
+
+    fn confirm(&mut self, question: &str, default: bool) -> Result<bool> {
+        let hint = if default { "Y/n" } else { "y/N" };
+        loop {
+            match self
+                .ask(&format!("{} [{}]", question, hint), None)?
+                .to_ascii_lowercase()
+                .as_str()
+            {
+                "" => return Ok(default),
+                "y" | "yes" => return Ok(true),
+                "n" | "no" => return Ok(false),
+                _ => writeln!(self.output, "  Please answer y or n")?,
+            }
+        }
+    }
+
+    /// 选择要设置的渠道，至少一个
+    fn ask_channels(&mut self) -> Result<Vec<String>> {
+        let answer = self.ask_valid(
+            "Channels to set up (teams, feishu, wechat; comma-separated)",
+            None,
+            |answer| {
+                let names: Vec<&str> = split_list(answer);
+                if names.is_empty() {
+                    return Err("Choose at least one channel".to_string());
+                }
+                match names.iter().find(|name| !CHANNELS.contains(name)) {
+                    Some(name) => Err(format!(
+                        "Unknown channel '{}' (expected teams, feishu or wechat)",
+                        name
+                    )),
+                    None => Ok(()),
+                }
+            },
+        )?;
+        let mut channels: Vec<String> = Vec::new();
+        for name in split_list(&answer) {
+            if !channels.iter().any(|c| c == name) {
+                channels.push(name.to_string());
+            }
+        }
+        Ok(channels)
+    }
+
+    /// 输入渠道的webhook或key并写入配置
+    fn ask_channel(&mut self, config: &mut Config, channel: &str) -> Result<()> {
+        let channels = &mut config.channels;
+        match channel {
+            "teams" => {
+                let webhook = self.ask_valid("Teams webhook URL", None, |answer| {
+                    check(provider::teams_webhook(answer))
+                })?;
+                channels.teams = Some(TeamConfig {
+                    enabled: true,
+                    webhook,
+                    http: Default::default(),
+                    default_channel: String::new(),
+                    on_call: Default::default(),
+                });
+            }
+            "feishu" => {
+                let webhook = self.ask_valid("Feishu webhook URL", None, |answer| {
+                    check(provider::feishu_webhook(answer))
+                })?;
+                let secret = self.ask(
+                    "Feishu signing secret (leave empty if signing is off)",
+                    None,
+                )?;
+                channels.feishu = Some(FeishuConfig {
+                    enabled: true,
+                    webhook,
+                    http: Default::default(),
+                    secret: Some(secret).filter(|s| !s.is_empty()),
+                    app_id: None,
+                    app_secret: None,
+                    chat_id: None,
+                    at_all_on_critical: false,
+                    on_call: Default::default(),
+                });
+            }
+            _ => {
+                let service = self.ask_valid(
+                    "WeChat service (serverchan, pushplus)",
+                    Some("serverchan"),
+                    |answer| match answer {
+                        "serverchan" | "pushplus" => Ok(()),
+                        _ => Err("Expected serverchan or pushplus".to_string()),
+                    },
+                )?;
+                let (service, question) = match service.as_str() {
+                    "pushplus" => (WechatServiceType::PushPlus, "PushPlus token"),
+                    _ => (WechatServiceType::ServerChan, "Server酱 SendKey"),
+                };
+                let key = self.ask_valid(question, None, |answer| {
+                    if answer.is_empty() {
+                        Err("The key is required".to_string())
+                    } else {
+                        check(provider::wechat_key(&service, answer))
+                    }
+                })?;
+                channels.wechat = Some(WechatConfig {
+                    enabled: true,
+                    service,
+                    key,
+                    http: Default::default(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// 向刚输入的渠道发送测试消息，返回是否送达
+    fn verify(&mut self, config: &Config, channel: &str) -> Result<bool> {
+        write!(self.output, "Sending a test message to {}... ", channel)?;
+        self.output.flush()?;
+        let manager = NotificationManager::with_config(config.clone())?;
+        let results = manager.test_channels(Some(&[channel.to_string()]), false);
+        let result = results.get(channel).cloned().unwrap_or_default();
+        if result["success"] == true {
+            match result["elapsed_ms"].as_u64() {
+                Some(ms) => writeln!(self.output, "delivered in {} ms", ms)?,
+                None => writeln!(self.output, "delivered")?,
+            }
+            return Ok(true);
+        }
+        writeln!(self.output, "failed")?;
+        writeln!(
+            self.output,
+            "  {}",
+            result["error"].as_str().unwrap_or("not delivered")
+        )?;
+        Ok(false)
+    }
+
+    fn on_failure(&mut self, channel: &str) -> Result<OnFailure> {
+        let answer = self.ask_valid(
+            &format!("Re-enter, keep or skip {}? (retry, keep, skip)", channel),
+            Some("retry"),
+            |answer| match answer {
+                "retry" | "keep" | "skip" => Ok(()),
+                _ => Err("Expected retry, keep or skip".to_string()),
+            },
+        )?;
+        Ok(match answer.as_str() {
+            "keep" => OnFailure::Keep,
+            "skip" => OnFailure::Skip,
+            _ => OnFailure::Retry,
+        })
+    }
+
+    fn ask_quiet_hours(&mut self, config: &mut Config) -> Result<()> {
+        let quiet_hours = &mut config.quiet_hours;
+        quiet_hours.enabled = self.confirm(
+            "Hold back non-critical notifications at night (quiet hours)?",
+            true,
+        )?;
+        if !quiet_hours.enabled {
+            return Ok(());
+        }
+        let start = self.ask_valid("Quiet hours start", Some(&quiet_hours.start), check_time)?;
+        let end = self.ask_valid("Quiet hours end", Some(&quiet_hours.end), |answer| {
+            check_time(answer)?;
+            if answer == start {
+                Err("End must differ from start".to_string())
+            } else {
+                Ok(())
+            }
+        })?;
+        quiet_hours.start = start;
+        quiet_hours.end = end;
+        Ok(())
+    }
+
+    /// 运行向导，写入配置并返回文件路径；配置文件已存在且用户不覆盖时返回None
+    pub fn run(&mut self, format: ConfigFormat) -> Result<Option<PathBuf>> {
+        writeln!(
+            self.output,
+            "Setting up claude-notifier. Press Enter to accept the value in [brackets]."
+        )?;
+        let (existing, _) = Config::target(format)?;
+        if existing.exists() {
+            let question = format!(
+                "{} already exists. Replace it with a new configuration?",
+                existing.display()
+            );
+            if !self.confirm(&question, false)? {
+                writeln!(self.output, "Nothing was changed.")?;
+                return Ok(None);
+            }
+        }
+
+        let mut config = Config::default();
+        let mut kept = Vec::new();
+        for channel in self.ask_channels()? {
+            writeln!(self.output)?;
+            loop {
+                self.ask_channel(&mut config, &channel)?;
+                if self.verify(&config, &channel)? {
+                    kept.push(channel.clone());
+                    break;
+                }
+                match self.on_failure(&channel)? {
+                    OnFailure::Retry => continue,
+                    OnFailure::Keep => kept.push(channel.clone()),
+                    OnFailure::Skip => remove_channel(&mut config, &channel),
+                }
+                break;
+            }
+        }
+        if kept.is_empty() {
+            anyhow::bail!("No channel was set up; nothing was written");
+        }
+        preset::route_to(&mut config, &kept);
+
+        writeln!(self.output)?;
+        self.ask_quiet_hours(&mut config)?;
+
+        let (path, warnings) = validate::save_checked(&config, format)?;
+        for warning in &warnings {
+            writeln!(self.output, "{}", warning)?;
+        }
+        writeln!(self.output)?;
+        writeln!(self.output, "Configuration written to {}", path.display())?;
+        writeln!(
+            self.output,
+            "Run `claude-notifier doctor` to check the Claude Code hooks and the rest of the setup."
+        )?;
+        Ok(Some(path))
+    }
+}
+
+fn split_list(answer: &str) -> Vec<&str> {
+    answer
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// 格式错误时重新输入；少见的主机（中转服务）只是警告，照常接受
+fn check(finding: Option<Finding>) -> Result<(), String> {
+    match finding {
+        Some(Finding::Error(message)) => Err(message),
+        _ => Ok(()),
+    }
+}
+
+fn check_time(answer: &str) -> Result<(), String> {
+    NaiveTime::parse_from_str(answer, "%H:%M")
+        .map(|_| ())
+        .map_err(|_| "Expected a time such as 22:00".to_string())
+}
+
+fn remove_channel(config: &mut Config, channel: &str) {
+    let channels = &mut config.channels;
+    match channel {
+        "teams" => channels.teams = None,
+        "feishu" => channels.feishu = None,
+        _ => channels.wechat = None,
+    }
+}
@@ -0,0 +1,163 @@
+//! 各渠道对录制响应的处理，使用 `tests/fixtures/replay` 中的fixture，不联网
+
+use claude_notifier::error::NotifierError;
+use claude_notifier::notifiers::exec::ExecNotifier;
+use claude_notifier::notifiers::feishu::FeishuNotifier;
+use claude_notifier::notifiers::teams::TeamsNotifier;
+use claude_notifier::notifiers::wechat::WechatNotifier;
+use claude_notifier::notifiers::BlockingNotifier;
+use claude_notifier::replay::{self, Mode};
+use std::path::PathBuf;
+use std::sync::Once;
+
+const TEAMS: &str = "https://example.webhook.office.com/webhookb2/";
+const FEISHU: &str = "https://open.feishu.cn/open-apis/bot/v2/hook/";
+
+/// 回放模式对整个进程生效；审计日志写到临时目录，不碰真实的数据目录
+fn setup() {
+    static SETUP: Once = Once::new();
+    SETUP.call_once(|| {
+        let home = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("replay-home");
+        std::env::set_var("HOME", &home);
+        std::env::set_var("XDG_STATE_HOME", home.join("state"));
+        let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/replay");
+        replay::set_mode(Mode::Replay(fixtures)).unwrap();
+    });
+}
+
+#[test]
+fn teams_accepts_plain_text_response() {
+    setup();
+    let notifier = TeamsNotifier::new(format!("{}ok", TEAMS));
+    assert!(notifier.send_text_blocking("hello").is_ok());
+}
+
+#[test]
+fn teams_rejects_failed_delivery_with_http_200() {
+    setup();
+    let notifier = TeamsNotifier::new(format!("{}delivery-failed", TEAMS));
+    let err = notifier
+        .send_card_blocking("title", "hello", "0078D4", vec![])
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        NotifierError::ProviderRejected { code: 413, .. }
+    ));
+}
+
+#[test]
+fn teams_reports_retry_after_when_rate_limited() {
+    setup();
+    let notifier = TeamsNotifier::new(format!("{}rate-limited", TEAMS));
+    let err = notifier.send_text_blocking("hello").unwrap_err();
+    assert!(matches!(
+        err,
+        NotifierError::RateLimited {
+            retry_after: Some(30)
+        }
+    ));
+}
+
+#[test]
+fn teams_replays_transport_errors() {
+    setup();
+    let notifier = TeamsNotifier::new(format!("{}timeout", TEAMS));
+    let err = notifier.send_text_blocking("hello").unwrap_err();
+    assert!(matches!(err, NotifierError::Timeout));
+}
+
+#[test]
+fn feishu_accepts_code_zero() {
+    setup();
+    let notifier = FeishuNotifier::new(format!("{}ok", FEISHU), false);
+    let response = notifier.send_text_blocking("hello").unwrap();
+    assert_eq!(response["code"], 0);
+}
+
+#[test]
+fn feishu_rejects_nonzero_code() {
+    setup();
+    let notifier = FeishuNotifier::new(format!("{}bad-sign", FEISHU), false)
+        .with_secret(Some("secret".to_string()));
+    let err = notifier
+        .send_card_blocking("title", "hello", "red", vec![])
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        NotifierError::ProviderRejected { code: 19021, .. }
+    ));
+}
+
+#[test]
+fn feishu_reports_http_status() {
+    setup();
+    let notifier = FeishuNotifier::new(format!("{}server-error", FEISHU), false);
+    let err = notifier.send_text_blocking("hello").unwrap_err();
+    assert!(matches!(err, NotifierError::HttpStatus { status: 502, .. }));
+}
+
+#[test]
+fn serverchan_checks_response_code() {
+    setup();
+    let ok = WechatNotifier::new_serverchan("SCT0000".to_string());
+    assert!(ok.send_text_blocking("hello").is_ok());
+
+    let rejected = WechatNotifier::new_serverchan("SCT9999".to_string());
+    let err = rejected
+        .send_card_blocking("title", "hello", "red", vec![])
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        NotifierError::ProviderRejected { code: 40001, .. }
+    ));
+}
+
+#[test]
+fn pushplus_checks_response_code() {
+    setup();
+    // 同一地址的录制按顺序回放：先成功，再被拒绝
+    let notifier = WechatNotifier::new_pushplus("token".to_string());
+    assert!(notifier.send_text_blocking("hello").is_ok());
+    let err = notifier.send_text_blocking("hello").unwrap_err();
+    assert!(matches!(
+        err,
+        NotifierError::ProviderRejected { code: 900, .. }
+    ));
+}
+
+#[cfg(unix)]
+#[test]
+fn exec_reads_payload_from_stdin() {
+    let notifier = ExecNotifier::new("cat".to_string(), vec![], 5);
+    let response = notifier
+        .send_card_blocking("title", "hello", "red", vec![])
+        .unwrap();
+    assert_eq!(
+        response["stdout"].as_str().map(|s| s.contains("hello")),
+        Some(true)
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn exec_fails_on_nonzero_exit() {
+    let notifier = ExecNotifier::new(
+        "sh".to_string(),
+        vec!["-c".to_string(), "cat >/dev/null; exit 3".to_string()],
+        5,
+    );
+    let err = notifier.send_text_blocking("hello").unwrap_err();
+    assert!(matches!(
+        err,
+        NotifierError::ProviderRejected { code: 3, .. }
+    ));
+}
+
+#[cfg(unix)]
+#[test]
+fn exec_times_out_when_command_ignores_large_stdin() {
+    let notifier = ExecNotifier::new("sleep".to_string(), vec!["30".to_string()], 1);
+    let content = "a".repeat(300 * 1024);
+    let err = notifier.send_text_blocking(&content).unwrap_err();
+    assert!(matches!(err, NotifierError::Timeout));
+}